@@ -0,0 +1,90 @@
+use bson::{doc, oid::ObjectId, Document};
+use std::collections::{HashMap, HashSet};
+
+// 同一 IP / 设备签到的不同听众数达到该值即视为可疑
+const SHARED_SOURCE_THRESHOLD: usize = 3;
+// 同一来源两次签到间隔小于该值（毫秒）视为可疑
+const RAPID_CHECKIN_MS: i64 = 5_000;
+
+pub struct Flag {
+    pub la_id: ObjectId,
+    pub reasons: Vec<String>,
+}
+
+// 基于规则的签到异常检测：输入某场演讲的全部 LA 记录，返回需要标记的记录及原因
+pub fn detect(records: &[Document]) -> Vec<Flag> {
+    let mut reasons: HashMap<ObjectId, HashSet<String>> = HashMap::new();
+
+    // 规则一：同一 IP 或设备为多个听众签到
+    for (field, label) in [("client_ip", "shared_ip"), ("device_id", "shared_device")] {
+        let mut groups: HashMap<&str, Vec<&Document>> = HashMap::new();
+        for r in records {
+            if let Ok(v) = r.get_str(field) {
+                if !v.is_empty() {
+                    groups.entry(v).or_default().push(r);
+                }
+            }
+        }
+        for (source, group) in groups {
+            let audiences: HashSet<ObjectId> = group
+                .iter()
+                .filter_map(|r| r.get_object_id("audience_id").ok())
+                .collect();
+            if audiences.len() >= SHARED_SOURCE_THRESHOLD {
+                for r in group {
+                    if let Ok(id) = r.get_object_id("_id") {
+                        reasons.entry(id).or_default().insert(format!(
+                            "{}: {} 个听众使用同一来源 {}",
+                            label,
+                            audiences.len(),
+                            source
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    // 规则二：同一 IP 下签到时间间隔过短
+    let mut by_ip: HashMap<&str, Vec<(i64, ObjectId)>> = HashMap::new();
+    for r in records {
+        let (Ok(ip), Ok(at), Ok(id)) =
+            (r.get_str("client_ip"), r.get_i64("checked_in_at"), r.get_object_id("_id"))
+        else {
+            continue;
+        };
+        if !ip.is_empty() {
+            by_ip.entry(ip).or_default().push((at, id));
+        }
+    }
+    for list in by_ip.values_mut() {
+        list.sort_by_key(|(at, _)| *at);
+        for pair in list.windows(2) {
+            let gap = pair[1].0 - pair[0].0;
+            if gap < RAPID_CHECKIN_MS {
+                for (_, id) in pair {
+                    reasons
+                        .entry(*id)
+                        .or_default()
+                        .insert(format!("rapid_checkin: 同一来源 {} 毫秒内连续签到", gap));
+                }
+            }
+        }
+    }
+
+    reasons
+        .into_iter()
+        .map(|(la_id, set)| {
+            let mut reasons: Vec<String> = set.into_iter().collect();
+            reasons.sort();
+            Flag { la_id, reasons }
+        })
+        .collect()
+}
+
+pub fn mark_update(flag: &Flag) -> (Document, Document) {
+    (
+        doc! { "_id": flag.la_id },
+        doc! { "$set": { "suspect": true, "suspect_reasons": &flag.reasons } },
+    )
+}
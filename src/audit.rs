@@ -0,0 +1,28 @@
+use bson::{doc, oid::ObjectId, Document};
+use chrono::Utc;
+use mongodb::Client;
+use std::sync::Arc;
+
+use crate::db::audit_collection;
+
+// 审计日志：记录敏感操作的操作者、对象与请求 ID，供合规审查导出。
+// 写入失败只打印日志，不影响业务请求本身
+pub async fn record(
+    client: &Arc<Client>,
+    actor_id: Option<ObjectId>,
+    action: &str,
+    target: &str,
+    details: Document,
+) {
+    let entry = doc! {
+        "at": Utc::now().timestamp_millis(),
+        "actor_id": actor_id,
+        "action": action,
+        "target": target,
+        "request_id": crate::request_id::current(),
+        "details": details,
+    };
+    if let Err(e) = audit_collection(client).insert_one(entry, None).await {
+        eprintln!("写入审计日志失败 {} {}: {}", action, target, e);
+    }
+}
@@ -0,0 +1,562 @@
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Request, State},
+    http::{header, request::Parts, HeaderMap, HeaderValue, Method},
+    middleware::Next,
+    response::{IntoResponse, Json, Response},
+};
+use bson::{doc, oid::ObjectId};
+use chrono::Utc;
+use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use mongodb::Client;
+use once_cell::sync::Lazy;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use crate::config;
+use crate::db::{lecture_collection, lecture_role_collection, session_collection, user_collection};
+use crate::quota::{hash_key, ApiKeyOwner};
+use crate::error::AppError;
+
+// 访问令牌短时有效，过期后凭刷新令牌换新
+const DEFAULT_TOKEN_TTL_SECS: i64 = 15 * 60;
+const REFRESH_TTL_DAYS: i64 = 30;
+
+// 会话 Cookie（HttpOnly）与 CSRF 令牌：Cookie 认证时非只读请求须在请求头回传 CSRF 令牌，
+// CSRF Cookie 不设 HttpOnly，供同源页面脚本读取
+pub const SESSION_COOKIE: &str = "rm_session";
+pub const CSRF_COOKIE: &str = "rm_csrf";
+pub const CSRF_HEADER: &str = "x-csrf-token";
+
+// 用户角色取值，与 users.role 字段一致
+pub const ROLE_ORGANIZER: i32 = 1;
+pub const ROLE_SPEAKER: i32 = 2;
+pub const ROLE_AUDIENCE: i32 = 3;
+
+// JWT 密钥：取配置中的 jwt_secret；未配置时随机生成（重启后需重新登录，prod 环境启动时已拒绝）
+static SECRET: Lazy<Vec<u8>> = Lazy::new(|| match config::get().jwt_secret.as_str() {
+    s if !s.is_empty() => s.as_bytes().to_vec(),
+    _ => {
+        println!("警告: 未配置 JWT_SECRET，使用随机密钥，重启后已签发的令牌将失效");
+        rand::thread_rng().gen::<[u8; 32]>().to_vec()
+    }
+});
+
+fn token_ttl_secs() -> i64 {
+    std::env::var("JWT_TTL_SECONDS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|t: &i64| *t > 0)
+        .unwrap_or(DEFAULT_TOKEN_TTL_SECS)
+}
+
+#[derive(Serialize, Deserialize)]
+struct Claims {
+    // 用户 ObjectId 的 hex
+    sub: String,
+    role: i32,
+    // 会话 id，注销或吊销会话后令牌立即失效
+    sid: String,
+    iat: i64,
+    exp: i64,
+}
+
+// 已认证用户，由 require_auth 写入请求扩展，handler 通过提取器获取
+#[derive(Clone, Debug)]
+pub struct AuthUser {
+    pub id: ObjectId,
+    pub role: i32,
+    // 通过 API key 认证时没有会话
+    pub session_id: Option<ObjectId>,
+}
+
+impl AuthUser {
+    pub fn id_hex(&self) -> String {
+        self.id.to_hex()
+    }
+
+    // 代表某个用户执行操作时，须为其本人
+    pub fn ensure_self(&self, user_id: &str) -> Result<(), AppError> {
+        if self.id_hex() != user_id {
+            return Err(AppError::Forbidden("无权代表其他用户操作".to_string()));
+        }
+        Ok(())
+    }
+}
+
+// 签发访问令牌，返回 (token, 过期时间戳秒)
+fn issue_token(user_id: ObjectId, role: i32, session_id: ObjectId) -> Result<(String, i64), AppError> {
+    let now = Utc::now().timestamp();
+    let claims = Claims {
+        sub: user_id.to_hex(),
+        role,
+        sid: session_id.to_hex(),
+        iat: now,
+        exp: now + token_ttl_secs(),
+    };
+    let token = encode(&Header::default(), &claims, &EncodingKey::from_secret(&SECRET))
+        .map_err(|_| AppError::Internal("签发令牌失败".to_string()))?;
+    Ok((token, claims.exp))
+}
+
+fn new_refresh_token() -> String {
+    format!("rmr_{}", hex::encode(rand::thread_rng().gen::<[u8; 32]>()))
+}
+
+fn random_token() -> String {
+    hex::encode(rand::thread_rng().gen::<[u8; 32]>())
+}
+
+// 本地 http 调试时可用 cookie_secure = false 去掉 Secure 属性
+fn cookie_secure() -> bool {
+    config::get().cookie_secure
+}
+
+fn set_cookie(name: &str, value: &str, http_only: bool, max_age_secs: i64) -> HeaderValue {
+    let mut cookie = format!("{}={}; Path=/; Max-Age={}; SameSite=Strict", name, value, max_age_secs);
+    if http_only {
+        cookie.push_str("; HttpOnly");
+    }
+    if cookie_secure() {
+        cookie.push_str("; Secure");
+    }
+    HeaderValue::from_str(&cookie).expect("cookie 值只含十六进制字符")
+}
+
+// 登录结果：响应体为令牌信息，选择 Cookie 模式时附带 Set-Cookie
+pub struct LoginResponse {
+    pub body: serde_json::Value,
+    cookies: Vec<HeaderValue>,
+}
+
+impl IntoResponse for LoginResponse {
+    fn into_response(self) -> Response {
+        let mut resp = Json(self.body).into_response();
+        for cookie in self.cookies {
+            resp.headers_mut().append(header::SET_COOKIE, cookie);
+        }
+        resp
+    }
+}
+
+// 注销时清除 Cookie
+pub fn clear_cookies() -> [(header::HeaderName, HeaderValue); 2] {
+    [
+        (header::SET_COOKIE, set_cookie(SESSION_COOKIE, "", true, 0)),
+        (header::SET_COOKIE, set_cookie(CSRF_COOKIE, "", false, 0)),
+    ]
+}
+
+// 登录时创建会话，返回访问令牌与刷新令牌（刷新令牌只在此时明文返回，库中存摘要）；
+// with_cookie 为 true 时另外签发会话 Cookie 与 CSRF 令牌，同样只存摘要
+pub async fn start_session(
+    client: &Arc<Client>,
+    user_id: ObjectId,
+    role: i32,
+    user_agent: &str,
+    ip: &str,
+    with_cookie: bool,
+) -> Result<LoginResponse, AppError> {
+    let now = Utc::now();
+    let session_id = ObjectId::new();
+    let refresh_token = new_refresh_token();
+    let mut session = doc! {
+        "_id": session_id,
+        "user_id": user_id,
+        "refresh_hash": hash_key(&refresh_token),
+        "user_agent": user_agent,
+        "ip": ip,
+        "created_at": now.timestamp_millis(),
+        "last_used_at": now.timestamp_millis(),
+        "expires_at": (now + chrono::Duration::days(REFRESH_TTL_DAYS)).timestamp_millis(),
+        "revoked": false,
+    };
+    let cookie_tokens = with_cookie.then(|| (random_token(), random_token()));
+    if let Some((cookie_token, csrf_token)) = &cookie_tokens {
+        session.insert("cookie_hash", hash_key(cookie_token));
+        session.insert("csrf_hash", hash_key(csrf_token));
+    }
+    session_collection(client)
+        .insert_one(session, None)
+        .await
+        .map_err(|_| AppError::Internal("创建会话失败".to_string()))?;
+
+    let mut body = token_pair(user_id, role, session_id, refresh_token)?;
+    let mut cookies = Vec::new();
+    if let Some((cookie_token, csrf_token)) = cookie_tokens {
+        let max_age = REFRESH_TTL_DAYS * 86_400;
+        cookies.push(set_cookie(SESSION_COOKIE, &cookie_token, true, max_age));
+        cookies.push(set_cookie(CSRF_COOKIE, &csrf_token, false, max_age));
+        body["csrf_token"] = csrf_token.into();
+    }
+    Ok(LoginResponse { body, cookies })
+}
+
+fn token_pair(user_id: ObjectId, role: i32, session_id: ObjectId, refresh_token: String) -> Result<serde_json::Value, AppError> {
+    let (token, expires_at) = issue_token(user_id, role, session_id)?;
+    Ok(serde_json::json!({
+        "token": token,
+        "token_type": "Bearer",
+        "expires_at": expires_at,
+        "refresh_token": refresh_token,
+        "session_id": session_id.to_hex(),
+    }))
+}
+
+// 用刷新令牌换取新的令牌对；刷新令牌每次使用后轮换，旧值失效
+pub async fn refresh_session(client: &Arc<Client>, refresh_token: &str) -> Result<serde_json::Value, AppError> {
+    let now = Utc::now().timestamp_millis();
+    let next_token = new_refresh_token();
+    let session = session_collection(client)
+        .find_one_and_update(
+            doc! {
+                "refresh_hash": hash_key(refresh_token.trim()),
+                "revoked": false,
+                "expires_at": { "$gt": now },
+            },
+            doc! { "$set": { "refresh_hash": hash_key(&next_token), "last_used_at": now } },
+            None,
+        )
+        .await
+        .map_err(|_| AppError::Internal("数据库错误".to_string()))?
+        .ok_or(AppError::Unauthorized("刷新令牌无效或已过期".to_string()))?;
+    let session_id = session.get_object_id("_id").map_err(|_| AppError::Internal("会话数据异常".to_string()))?;
+    let user_id = session.get_object_id("user_id").map_err(|_| AppError::Internal("会话数据异常".to_string()))?;
+    // 角色以数据库为准，角色变更后刷新即生效
+    let user = user_collection(client)
+        .find_one(doc! { "_id": user_id }, None)
+        .await
+        .map_err(|_| AppError::Internal("数据库错误".to_string()))?
+        .ok_or(AppError::Unauthorized("用户不存在".to_string()))?;
+    token_pair(user_id, user.get_i32("role").unwrap_or(0), session_id, next_token)
+}
+
+// 吊销会话，只能操作本人的会话；返回是否确有会话被吊销
+pub async fn revoke_session(client: &Arc<Client>, user_id: ObjectId, session_id: ObjectId) -> Result<bool, AppError> {
+    let result = session_collection(client)
+        .update_one(
+            doc! { "_id": session_id, "user_id": user_id, "revoked": false },
+            doc! { "$set": { "revoked": true, "revoked_at": Utc::now().timestamp_millis() } },
+            None,
+        )
+        .await
+        .map_err(|_| AppError::Internal("数据库错误".to_string()))?;
+    Ok(result.modified_count > 0)
+}
+
+// 重置密码等场景下吊销该用户的全部会话
+pub async fn revoke_all_sessions(client: &Arc<Client>, user_id: ObjectId) -> Result<u64, AppError> {
+    let result = session_collection(client)
+        .update_many(
+            doc! { "user_id": user_id, "revoked": false },
+            doc! { "$set": { "revoked": true, "revoked_at": Utc::now().timestamp_millis() } },
+            None,
+        )
+        .await
+        .map_err(|_| AppError::Internal("数据库错误".to_string()))?;
+    Ok(result.modified_count)
+}
+
+// 校验签名与有效期，并确认所属会话未被吊销
+pub async fn verify_token(client: &Arc<Client>, token: &str) -> Option<AuthUser> {
+    let data = decode::<Claims>(token, &DecodingKey::from_secret(&SECRET), &Validation::default()).ok()?;
+    let session_id = ObjectId::parse_str(&data.claims.sid).ok()?;
+    session_collection(client)
+        .find_one(doc! { "_id": session_id, "revoked": false }, None)
+        .await
+        .ok()??;
+    Some(AuthUser {
+        id: ObjectId::parse_str(&data.claims.sub).ok()?,
+        role: data.claims.role,
+        session_id: Some(session_id),
+    })
+}
+
+fn cookie_value<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(k, _)| *k == name)
+        .map(|(_, v)| v.trim())
+        .filter(|v| !v.is_empty())
+}
+
+// Cookie 认证：会话有效即通过；非只读请求还须携带与 Cookie 一致且与会话匹配的 CSRF 令牌。
+// 未携带会话 Cookie 返回 Ok(None)，交由调用方按未登录处理
+async fn cookie_user(client: &Arc<Client>, headers: &HeaderMap, method: &Method) -> Result<Option<AuthUser>, AppError> {
+    let Some(cookie_token) = cookie_value(headers, SESSION_COOKIE) else { return Ok(None) };
+    let now = Utc::now().timestamp_millis();
+    let Some(session) = session_collection(client)
+        .find_one(doc! { "cookie_hash": hash_key(cookie_token), "revoked": false, "expires_at": { "$gt": now } }, None)
+        .await?
+    else {
+        return Ok(None);
+    };
+
+    if !matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS) {
+        let header_token = headers.get(CSRF_HEADER).and_then(|v| v.to_str().ok()).unwrap_or("");
+        let valid = !header_token.is_empty()
+            && cookie_value(headers, CSRF_COOKIE) == Some(header_token)
+            && session.get_str("csrf_hash").ok() == Some(hash_key(header_token).as_str());
+        if !valid {
+            return Err(AppError::Forbidden("CSRF 校验失败".to_string()));
+        }
+    }
+
+    let (Ok(session_id), Ok(user_id)) = (session.get_object_id("_id"), session.get_object_id("user_id")) else {
+        return Ok(None);
+    };
+    // Cookie 会话不携带角色，以数据库为准
+    let Some(user) = user_collection(client).find_one(doc! { "_id": user_id }, None).await? else {
+        return Ok(None);
+    };
+    Ok(Some(AuthUser { id: user_id, role: user.get_i32("role").unwrap_or(0), session_id: Some(session_id) }))
+}
+
+fn bearer_token(parts: &axum::http::HeaderMap) -> Option<&str> {
+    parts
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(|t| t.trim())
+}
+
+// 校验 Authorization: Bearer <jwt>；携带登录后创建的有效 API key 的请求以 key 所有者身份通过
+// （所有者由服务端取自创建时的会话）；两者都没有时尝试会话 Cookie
+pub async fn require_auth(State(client): State<Arc<Client>>, mut req: Request, next: Next) -> Response {
+    let user = match bearer_token(req.headers()) {
+        Some(token) => verify_token(&client, token).await,
+        None => match req.extensions().get::<ApiKeyOwner>() {
+            Some(owner) => api_key_user(&client, &owner.0).await,
+            None => match cookie_user(&client, req.headers(), req.method()).await {
+                Ok(user) => user,
+                Err(e) => return e.into_response(),
+            },
+        },
+    };
+    match user {
+        Some(user) => {
+            req.extensions_mut().insert(user);
+            next.run(req).await
+        }
+        None => AppError::Unauthorized("未登录或登录已过期".to_string()).into_response(),
+    }
+}
+
+async fn api_key_user(client: &Arc<Client>, owner_id: &str) -> Option<AuthUser> {
+    let id = ObjectId::parse_str(owner_id).ok()?;
+    let user = user_collection(client).find_one(doc! { "_id": id }, None).await.ok()??;
+    Some(AuthUser { id, role: user.get_i32("role").unwrap_or(0), session_id: None })
+}
+
+// ==================== 单场演讲角色 ====================
+
+// 组织者为某一场演讲授予特定用户的角色，只在该场演讲内生效；
+// 演讲的组织者与讲者天然拥有全部单场角色
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LectureRole {
+    // 讨论区管理员：可删除讨论
+    Moderator,
+    // 考勤员：可为其他听众登记到场
+    AttendanceTaker,
+    // 记录员：演讲进行中可追加现场记录
+    NoteTaker,
+}
+
+impl LectureRole {
+    pub const ALL: [LectureRole; 3] = [LectureRole::Moderator, LectureRole::AttendanceTaker, LectureRole::NoteTaker];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LectureRole::Moderator => "moderator",
+            LectureRole::AttendanceTaker => "attendance_taker",
+            LectureRole::NoteTaker => "note_taker",
+        }
+    }
+
+    pub fn parse(raw: &str) -> Result<LectureRole, AppError> {
+        LectureRole::ALL
+            .into_iter()
+            .find(|r| r.as_str() == raw.trim())
+            .ok_or_else(|| {
+                let names: Vec<&str> = LectureRole::ALL.iter().map(|r| r.as_str()).collect();
+                AppError::BadRequest(format!("role 仅支持 {}", names.join("、")))
+            })
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            LectureRole::Moderator => "讨论管理员",
+            LectureRole::AttendanceTaker => "考勤员",
+            LectureRole::NoteTaker => "记录员",
+        }
+    }
+}
+
+// 要求当前用户在该演讲中具有指定角色（组织者、讲者或被授予者），通过时返回演讲文档
+pub async fn require_lecture_role(
+    client: &Arc<Client>,
+    lecture_oid: ObjectId,
+    user: &AuthUser,
+    role: LectureRole,
+) -> Result<bson::Document, AppError> {
+    let lecture = lecture_collection(client)
+        .find_one(doc! { "_id": lecture_oid }, None)
+        .await?
+        .ok_or(AppError::NotFound("Lecture not found".to_string()))?;
+    let me = user.id_hex();
+    if lecture.get_str("organizer_id").ok() == Some(me.as_str()) || lecture.get_str("speaker_id").ok() == Some(me.as_str()) {
+        return Ok(lecture);
+    }
+    let granted = lecture_role_collection(client)
+        .find_one(doc! { "lecture_id": lecture_oid, "user_id": user.id, "role": role.as_str() }, None)
+        .await?
+        .is_some();
+    if !granted {
+        return Err(AppError::Forbidden(format!("需要本场演讲的{}权限", role.label())));
+    }
+    Ok(lecture)
+}
+
+// 角色集合标记类型，配合 RequireRole 在 handler 签名中声明所需角色
+pub trait RoleSet: Send + Sync + 'static {
+    const ROLES: &'static [i32];
+    const NAME: &'static str;
+}
+
+pub struct Organizer;
+pub struct Speaker;
+pub struct Audience;
+// 演讲的主持方：组织者或讲者
+pub struct Host;
+// 任一已分配角色的用户
+pub struct AnyRole;
+
+impl RoleSet for Organizer {
+    const ROLES: &'static [i32] = &[ROLE_ORGANIZER];
+    const NAME: &'static str = "组织者";
+}
+
+impl RoleSet for Speaker {
+    const ROLES: &'static [i32] = &[ROLE_SPEAKER];
+    const NAME: &'static str = "讲者";
+}
+
+impl RoleSet for Audience {
+    const ROLES: &'static [i32] = &[ROLE_AUDIENCE];
+    const NAME: &'static str = "听众";
+}
+
+impl RoleSet for Host {
+    const ROLES: &'static [i32] = &[ROLE_ORGANIZER, ROLE_SPEAKER];
+    const NAME: &'static str = "组织者或讲者";
+}
+
+impl RoleSet for AnyRole {
+    const ROLES: &'static [i32] = &[ROLE_ORGANIZER, ROLE_SPEAKER, ROLE_AUDIENCE];
+    const NAME: &'static str = "组织者、讲者或听众";
+}
+
+// 要求当前用户具有指定角色，否则返回 403；例如 RequireRole<Organizer>
+pub struct RequireRole<R: RoleSet>(pub AuthUser, PhantomData<R>);
+
+impl<R: RoleSet> std::ops::Deref for RequireRole<R> {
+    type Target = AuthUser;
+
+    fn deref(&self) -> &AuthUser {
+        &self.0
+    }
+}
+
+#[async_trait]
+impl<R: RoleSet> FromRequestParts<Arc<Client>> for RequireRole<R> {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &Arc<Client>) -> Result<Self, Self::Rejection> {
+        let user = AuthUser::from_request_parts(parts, state).await?;
+        if !R::ROLES.contains(&user.role) {
+            return Err(AppError::Forbidden(format!("仅{}可执行该操作", R::NAME)));
+        }
+        Ok(RequireRole(user, PhantomData))
+    }
+}
+
+#[async_trait]
+impl FromRequestParts<Arc<Client>> for AuthUser {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &Arc<Client>) -> Result<Self, Self::Rejection> {
+        if let Some(user) = parts.extensions.get::<AuthUser>() {
+            return Ok(user.clone());
+        }
+        let unauthorized = || AppError::Unauthorized("未登录或登录已过期".to_string());
+        match bearer_token(&parts.headers) {
+            Some(token) => verify_token(state, token).await.ok_or_else(unauthorized),
+            None => cookie_user(state, &parts.headers, &parts.method).await?.ok_or_else(unauthorized),
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use axum::http::StatusCode;
+
+    // 只构造客户端不建立连接：鉴权在访问数据库之前就会拒绝
+    pub(crate) async fn offline_client() -> Arc<Client> {
+        Arc::new(Client::with_uri_str("mongodb://127.0.0.1:27017").await.unwrap())
+    }
+
+    pub(crate) fn user(role: i32) -> AuthUser {
+        AuthUser { id: ObjectId::new(), role, session_id: Some(ObjectId::new()) }
+    }
+
+    async fn extract<R: RoleSet>(user: Option<AuthUser>) -> Result<AuthUser, AppError> {
+        let (mut parts, _) = Request::new(axum::body::Body::empty()).into_parts();
+        if let Some(user) = user {
+            parts.extensions.insert(user);
+        }
+        RequireRole::<R>::from_request_parts(&mut parts, &offline_client().await)
+            .await
+            .map(|r| r.0)
+    }
+
+    #[tokio::test]
+    async fn missing_credentials_are_unauthorized() {
+        let err = extract::<AnyRole>(None).await.unwrap_err();
+        assert_eq!(err.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn wrong_role_is_forbidden() {
+        for (role, allowed) in [(ROLE_ORGANIZER, false), (ROLE_SPEAKER, false), (ROLE_AUDIENCE, true)] {
+            assert_eq!(extract::<Audience>(Some(user(role))).await.is_ok(), allowed);
+        }
+        let err = extract::<Organizer>(Some(user(ROLE_AUDIENCE))).await.unwrap_err();
+        assert_eq!(err.status(), StatusCode::FORBIDDEN);
+        let err = extract::<Host>(Some(user(ROLE_AUDIENCE))).await.unwrap_err();
+        assert_eq!(err.status(), StatusCode::FORBIDDEN);
+        assert!(extract::<Host>(Some(user(ROLE_SPEAKER))).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn any_role_requires_an_assigned_role() {
+        for role in [ROLE_ORGANIZER, ROLE_SPEAKER, ROLE_AUDIENCE] {
+            assert!(extract::<AnyRole>(Some(user(role))).await.is_ok());
+        }
+        let err = extract::<AnyRole>(Some(user(0))).await.unwrap_err();
+        assert_eq!(err.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[test]
+    fn ensure_self_rejects_other_users() {
+        let me = user(ROLE_AUDIENCE);
+        assert!(me.ensure_self(&me.id_hex()).is_ok());
+        assert_eq!(me.ensure_self(&ObjectId::new().to_hex()).unwrap_err().status(), StatusCode::FORBIDDEN);
+    }
+}
@@ -0,0 +1,150 @@
+use axum::{
+    extract::Request,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use bson::doc;
+use mongodb::error::{Error, ErrorKind};
+use mongodb::event::command::{CommandEventHandler, CommandFailedEvent, CommandSucceededEvent};
+use mongodb::event::sdam::{
+    SdamEventHandler, ServerHeartbeatFailedEvent, ServerHeartbeatSucceededEvent,
+};
+use mongodb::Client;
+use once_cell::sync::Lazy;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::db;
+use crate::error::AppError;
+
+// 数据库熔断器：连续失败达到阈值后熔断，熔断期间所有依赖数据库的请求直接返回 503，
+// 由后台探测任务定期 ping，成功后自动恢复。失败/成功信号来自驱动的命令事件与心跳事件，
+// 因此无需改动各处的集合操作
+const DEFAULT_THRESHOLD: u32 = 3;
+const DEFAULT_PROBE_SECS: u64 = 5;
+
+// 熔断期间仍需可用的路径：静态资源、首页跳转、服务器时间以及运维接口
+const EXEMPT_PREFIXES: &[&str] = &["/static", "/time", "/admin/maintenance", "/admin/db_health", "/admin/selfcheck"];
+
+struct State {
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    last_error: Option<String>,
+}
+
+static STATE: Lazy<Mutex<State>> = Lazy::new(|| {
+    Mutex::new(State { consecutive_failures: 0, opened_at: None, last_error: None })
+});
+
+fn threshold() -> u32 {
+    std::env::var("DB_BREAKER_THRESHOLD")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|n| *n > 0)
+        .unwrap_or(DEFAULT_THRESHOLD)
+}
+
+pub fn probe_interval() -> Duration {
+    let secs = std::env::var("DB_BREAKER_PROBE_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|n| *n > 0)
+        .unwrap_or(DEFAULT_PROBE_SECS);
+    Duration::from_secs(secs)
+}
+
+pub fn is_open() -> bool {
+    STATE.lock().unwrap().opened_at.is_some()
+}
+
+pub fn record_success() {
+    let mut state = STATE.lock().unwrap();
+    if let Some(opened_at) = state.opened_at.take() {
+        println!("[db_breaker] 数据库已恢复，熔断关闭（持续 {} 秒）", opened_at.elapsed().as_secs());
+    }
+    state.consecutive_failures = 0;
+    state.last_error = None;
+}
+
+pub fn record_failure(reason: String) {
+    let mut state = STATE.lock().unwrap();
+    state.consecutive_failures += 1;
+    if state.opened_at.is_none() && state.consecutive_failures >= threshold() {
+        println!("[db_breaker] 连续 {} 次数据库失败，熔断开启: {}", state.consecutive_failures, reason);
+        state.opened_at = Some(Instant::now());
+    }
+    state.last_error = Some(reason);
+}
+
+pub fn status() -> serde_json::Value {
+    let state = STATE.lock().unwrap();
+    serde_json::json!({
+        "state": if state.opened_at.is_some() { "open" } else { "closed" },
+        "consecutive_failures": state.consecutive_failures,
+        "open_for_secs": state.opened_at.map(|t| t.elapsed().as_secs()),
+        "last_error": state.last_error,
+    })
+}
+
+// 只有连接类错误才说明数据库不可用；重复键、校验失败等业务错误不计入
+fn is_outage(error: &Error) -> bool {
+    matches!(
+        *error.kind,
+        ErrorKind::Io(_) | ErrorKind::ServerSelection { .. } | ErrorKind::ConnectionPoolCleared { .. }
+    )
+}
+
+// ==================== 驱动事件 ====================
+
+pub struct HealthMonitor;
+
+impl CommandEventHandler for HealthMonitor {
+    fn handle_command_succeeded_event(&self, _event: CommandSucceededEvent) {
+        record_success();
+    }
+
+    fn handle_command_failed_event(&self, event: CommandFailedEvent) {
+        if is_outage(&event.failure) {
+            record_failure(format!("{} 命令失败: {}", event.command_name, event.failure));
+        }
+    }
+}
+
+impl SdamEventHandler for HealthMonitor {
+    fn handle_server_heartbeat_succeeded_event(&self, _event: ServerHeartbeatSucceededEvent) {
+        record_success();
+    }
+
+    fn handle_server_heartbeat_failed_event(&self, event: ServerHeartbeatFailedEvent) {
+        record_failure(format!("心跳失败 {}: {}", event.server_address, event.failure));
+    }
+}
+
+// ==================== 探测与拦截 ====================
+
+// 熔断期间定期 ping，成功即关闭熔断；未熔断时不做任何事
+pub async fn probe(client: Arc<Client>) -> Result<String, String> {
+    if !is_open() {
+        return Ok(String::new());
+    }
+    match db::database(&client).run_command(doc! { "ping": 1 }, None).await {
+        Ok(_) => {
+            record_success();
+            Ok("探测成功".to_string())
+        }
+        Err(e) => Err(format!("探测失败: {}", e)),
+    }
+}
+
+pub async fn guard(req: Request, next: Next) -> Response {
+    let path = req.uri().path();
+    let exempt = path == "/" || EXEMPT_PREFIXES.iter().any(|p| path.starts_with(p));
+    if !exempt && is_open() {
+        return (
+            [("Retry-After", probe_interval().as_secs().to_string())],
+            AppError::Unavailable("数据库暂时不可用，请稍后再试".to_string()),
+        )
+            .into_response();
+    }
+    next.run(req).await
+}
@@ -0,0 +1,31 @@
+use axum::http::HeaderMap;
+use std::net::SocketAddr;
+
+// 客户端 IP：优先取反向代理写入的 X-Forwarded-For / X-Real-IP，否则用 TCP 对端地址
+pub fn client_ip(headers: &HeaderMap, peer: Option<SocketAddr>) -> String {
+    let forwarded = headers
+        .get("x-forwarded-for")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split(',').next())
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty());
+    let real_ip = headers
+        .get("x-real-ip")
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty());
+    forwarded
+        .or(real_ip)
+        .or_else(|| peer.map(|p| p.ip().to_string()))
+        .unwrap_or_default()
+}
+
+// 设备标识：前端可通过 X-Device-Id 上报，否则退化为 User-Agent
+pub fn device_id(headers: &HeaderMap) -> String {
+    headers
+        .get("x-device-id")
+        .or_else(|| headers.get("user-agent"))
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.chars().take(256).collect())
+        .unwrap_or_default()
+}
@@ -0,0 +1,368 @@
+use axum::http::HeaderValue;
+use once_cell::sync::OnceCell;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::Path;
+
+use crate::timefmt::{parse_timezone, Locale};
+
+// 运行配置：先读取可选的 TOML 文件（CONFIG_FILE 指定，默认 ./config.toml，不存在则跳过），
+// 再用同名大写环境变量覆盖，最后统一校验；校验失败时拒绝启动
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub mongo_uri: String,
+    // 数据库账号，优先于 mongo_uri 中的用户名密码；建议使用只授予本库读写与建索引权限的专用账号
+    pub mongo_username: String,
+    pub mongo_password: String,
+    // 账号所在的认证库，为空时沿用 mongo_uri 中的 authSource（默认 admin）
+    pub mongo_auth_source: String,
+    // 强制使用 TLS；mongodb+srv:// 地址（如 Atlas）默认已启用。可另行指定 CA 证书与客户端证书
+    pub mongo_tls: bool,
+    pub mongo_tls_ca_file: String,
+    pub mongo_tls_cert_key_file: String,
+    pub db_name: String,
+    // 运行环境（dev / test / staging / prod），设置后实际库名为 db_name_环境，
+    // 共用一个集群时测试与预发不会写进生产库；为空则直接使用 db_name
+    pub environment: String,
+    pub bind_addr: String,
+    pub static_dir: String,
+    // 头像等公开上传文件，对外以 /static/uploads/ 访问
+    pub upload_dir: String,
+    // 课件文件，仅通过签名链接下载
+    pub material_dir: String,
+    // 组织数据导出生成的压缩包，仅通过签名链接下载
+    pub export_dir: String,
+    // 允许跨域的来源；为空表示允许所有来源（开发环境）
+    pub cors_origins: Vec<String>,
+    // 用户未设置偏好时，导出与邮件中时间展示使用的时区（IANA 名称）与语言
+    pub default_timezone: String,
+    pub default_locale: String,
+    // 同一用户同类通知在窗口期内合并为一条摘要（秒），0 表示不合并；可按通知类型单独配置
+    pub notify_digest_window_secs: u64,
+    pub notify_digest_windows: HashMap<String, u64>,
+    // 按演讲码签到的宽限时间（分钟）：开始前与结束后各放宽这么久
+    pub checkin_grace_minutes: u64,
+    // 可选的 Redis 地址；配置后限流计数存放在 Redis 中，多个副本共享额度，为空时按进程计数
+    pub redis_url: String,
+    // 按路由前缀限流，取最长匹配的前缀；未匹配的路由不限流
+    pub rate_limits: HashMap<String, RateLimitRule>,
+    // 后台任务按名称单独配置：开关、执行间隔及任务专用参数
+    pub jobs: HashMap<String, JobConfig>,
+    // 登录令牌与签名链接的密钥；prod 环境必须配置，其他环境为空时每次启动随机生成
+    pub jwt_secret: String,
+    pub signing_secret: String,
+    // 管理接口令牌（X-Admin-Token），为空时管理接口不可用
+    pub admin_token: String,
+    // 本地 http 调试时可设为 false 去掉 Cookie 的 Secure 属性
+    pub cookie_secure: bool,
+    // API key 默认每日调用额度，可被单个 key 的 daily_quota 覆盖
+    pub api_key_daily_quota: i64,
+    // 邮件、日历等对外链接使用的站点地址
+    pub public_base_url: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct JobConfig {
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+    pub interval_secs: Option<u64>,
+    // 其余整数键作为任务参数，如 lead_minutes、after_days
+    #[serde(flatten)]
+    pub params: HashMap<String, i64>,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+// 每个客户端在 window_secs 秒内最多 limit 次请求
+#[derive(Debug, Clone, Deserialize)]
+pub struct RateLimitRule {
+    pub limit: u64,
+    pub window_secs: u64,
+}
+
+fn default_rate_limits() -> HashMap<String, RateLimitRule> {
+    HashMap::from([
+        ("/user/login".to_string(), RateLimitRule { limit: 10, window_secs: 60 }),
+        ("/user/register".to_string(), RateLimitRule { limit: 5, window_secs: 60 }),
+        ("/user/magic_link".to_string(), RateLimitRule { limit: 5, window_secs: 300 }),
+    ])
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            mongo_uri: "mongodb://localhost:27017".to_string(),
+            mongo_username: String::new(),
+            mongo_password: String::new(),
+            mongo_auth_source: String::new(),
+            mongo_tls: false,
+            mongo_tls_ca_file: String::new(),
+            mongo_tls_cert_key_file: String::new(),
+            db_name: "rust_meeting".to_string(),
+            environment: String::new(),
+            bind_addr: "127.0.0.1:8000".to_string(),
+            static_dir: "static".to_string(),
+            upload_dir: "static/uploads".to_string(),
+            material_dir: "uploads/materials".to_string(),
+            export_dir: "uploads/exports".to_string(),
+            cors_origins: Vec::new(),
+            default_timezone: "Asia/Shanghai".to_string(),
+            default_locale: "zh-CN".to_string(),
+            notify_digest_window_secs: 300,
+            notify_digest_windows: HashMap::new(),
+            checkin_grace_minutes: 15,
+            redis_url: String::new(),
+            rate_limits: default_rate_limits(),
+            jobs: HashMap::new(),
+            jwt_secret: String::new(),
+            signing_secret: String::new(),
+            admin_token: String::new(),
+            cookie_secure: true,
+            api_key_daily_quota: 1000,
+            public_base_url: "http://127.0.0.1:8000".to_string(),
+        }
+    }
+}
+
+static CONFIG: OnceCell<Config> = OnceCell::new();
+
+const DEFAULT_CONFIG_FILE: &str = "config.toml";
+
+pub const ENVIRONMENTS: [&str; 4] = ["dev", "test", "staging", "prod"];
+
+fn env_override(target: &mut String, key: &str) {
+    if let Ok(v) = std::env::var(key) {
+        if !v.trim().is_empty() {
+            *target = v.trim().to_string();
+        }
+    }
+}
+
+fn load_file() -> Result<Config, String> {
+    let (path, explicit) = match std::env::var("CONFIG_FILE") {
+        Ok(p) if !p.trim().is_empty() => (p.trim().to_string(), true),
+        _ => (DEFAULT_CONFIG_FILE.to_string(), false),
+    };
+    if !Path::new(&path).exists() {
+        // 显式指定的配置文件必须存在，默认文件缺失则全部使用默认值
+        return if explicit {
+            Err(format!("配置文件 {} 不存在", path))
+        } else {
+            Ok(Config::default())
+        };
+    }
+    let text = std::fs::read_to_string(&path).map_err(|e| format!("读取配置文件 {} 失败: {}", path, e))?;
+    toml::from_str(&text).map_err(|e| format!("解析配置文件 {} 失败: {}", path, e))
+}
+
+impl Config {
+    pub fn load() -> Result<Config, String> {
+        let mut cfg = load_file()?;
+        env_override(&mut cfg.mongo_uri, "MONGO_URI");
+        env_override(&mut cfg.mongo_username, "MONGO_USERNAME");
+        env_override(&mut cfg.mongo_password, "MONGO_PASSWORD");
+        env_override(&mut cfg.mongo_auth_source, "MONGO_AUTH_SOURCE");
+        env_override(&mut cfg.mongo_tls_ca_file, "MONGO_TLS_CA_FILE");
+        env_override(&mut cfg.mongo_tls_cert_key_file, "MONGO_TLS_CERT_KEY_FILE");
+        if let Ok(v) = std::env::var("MONGO_TLS") {
+            cfg.mongo_tls = v.trim().parse().map_err(|_| format!("MONGO_TLS 无效: {:?}（应为 true 或 false）", v))?;
+        }
+        env_override(&mut cfg.db_name, "DB_NAME");
+        env_override(&mut cfg.environment, "ENVIRONMENT");
+        env_override(&mut cfg.bind_addr, "BIND_ADDR");
+        env_override(&mut cfg.static_dir, "STATIC_DIR");
+        env_override(&mut cfg.upload_dir, "UPLOAD_DIR");
+        env_override(&mut cfg.material_dir, "MATERIAL_DIR");
+        env_override(&mut cfg.export_dir, "EXPORT_DIR");
+        env_override(&mut cfg.default_timezone, "DEFAULT_TIMEZONE");
+        env_override(&mut cfg.default_locale, "DEFAULT_LOCALE");
+        env_override(&mut cfg.redis_url, "REDIS_URL");
+        env_override(&mut cfg.jwt_secret, "JWT_SECRET");
+        env_override(&mut cfg.signing_secret, "SIGNING_SECRET");
+        env_override(&mut cfg.admin_token, "ADMIN_TOKEN");
+        env_override(&mut cfg.public_base_url, "PUBLIC_BASE_URL");
+        if let Ok(v) = std::env::var("COOKIE_SECURE") {
+            cfg.cookie_secure = !matches!(v.trim(), "false" | "0");
+        }
+        if let Ok(v) = std::env::var("API_KEY_DAILY_QUOTA") {
+            cfg.api_key_daily_quota = v
+                .trim()
+                .parse()
+                .map_err(|_| format!("API_KEY_DAILY_QUOTA 无效: {:?}", v))?;
+        }
+        if let Ok(v) = std::env::var("NOTIFY_DIGEST_WINDOW_SECS") {
+            cfg.notify_digest_window_secs = v
+                .trim()
+                .parse()
+                .map_err(|_| format!("NOTIFY_DIGEST_WINDOW_SECS 无效: {:?}", v))?;
+        }
+        if let Ok(v) = std::env::var("CHECKIN_GRACE_MINUTES") {
+            cfg.checkin_grace_minutes = v
+                .trim()
+                .parse()
+                .map_err(|_| format!("CHECKIN_GRACE_MINUTES 无效: {:?}", v))?;
+        }
+        if let Ok(v) = std::env::var("CORS_ORIGINS") {
+            cfg.cors_origins = v
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect();
+        }
+        cfg.validate()?;
+        Ok(cfg)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if !self.mongo_uri.starts_with("mongodb://") && !self.mongo_uri.starts_with("mongodb+srv://") {
+            return Err("mongo_uri 必须以 mongodb:// 或 mongodb+srv:// 开头".to_string());
+        }
+        if self.mongo_username.is_empty() != self.mongo_password.is_empty() {
+            return Err("mongo_username 与 mongo_password 需同时配置".to_string());
+        }
+        // 账号只在一处配置，避免 mongo_uri 与 mongo_username 不一致时不清楚用了哪个
+        let authority = self.mongo_uri.split("://").nth(1).unwrap_or("").split('/').next().unwrap_or("");
+        if !self.mongo_username.is_empty() && authority.contains('@') {
+            return Err("mongo_uri 中已包含账号，不能再配置 mongo_username".to_string());
+        }
+        for (key, path) in [("mongo_tls_ca_file", &self.mongo_tls_ca_file), ("mongo_tls_cert_key_file", &self.mongo_tls_cert_key_file)] {
+            if !path.is_empty() && !Path::new(path).is_file() {
+                return Err(format!("{} 文件不存在: {}", key, path));
+            }
+        }
+        if !self.environment.is_empty() && !ENVIRONMENTS.contains(&self.environment.as_str()) {
+            return Err(format!("environment 无效: {:?}（支持 {}）", self.environment, ENVIRONMENTS.join("、")));
+        }
+        // db_name 已带其他环境的后缀时拒绝启动，避免如 environment=dev 却连上 rust_meeting_prod
+        let foreign = ENVIRONMENTS
+            .iter()
+            .filter(|_| !self.environment.is_empty())
+            .find(|e| **e != self.environment && self.db_name.ends_with(&format!("_{}", e)));
+        if let Some(other) = foreign {
+            return Err(format!("db_name {:?} 属于 {} 环境，与 environment={} 不一致", self.db_name, other, self.environment));
+        }
+        // MongoDB 数据库名不能包含 /\. "$ 且不超过 64 字节
+        let name = self.database_name();
+        if self.db_name.is_empty() || name.len() > 64 || name.contains(['/', '\\', '.', ' ', '"', '$']) {
+            return Err(format!("db_name 无效: {:?}", name));
+        }
+        self.bind_addr
+            .parse::<SocketAddr>()
+            .map_err(|_| format!("bind_addr 无效: {:?}（应形如 127.0.0.1:8000）", self.bind_addr))?;
+        if !Path::new(&self.static_dir).is_dir() {
+            return Err(format!("static_dir 目录不存在: {}", self.static_dir));
+        }
+        if self.upload_dir.is_empty() || self.material_dir.is_empty() || self.export_dir.is_empty() {
+            return Err("upload_dir、material_dir 与 export_dir 不能为空".to_string());
+        }
+        if parse_timezone(&self.default_timezone).is_none() {
+            return Err(format!("default_timezone 无效: {:?}（应为 IANA 时区名，如 Asia/Shanghai）", self.default_timezone));
+        }
+        if Locale::parse(&self.default_locale).is_none() {
+            return Err(format!("default_locale 无效: {:?}（支持 zh-CN、en-US）", self.default_locale));
+        }
+        if self.checkin_grace_minutes > 720 {
+            return Err("checkin_grace_minutes 不能超过 720".to_string());
+        }
+        if !self.redis_url.is_empty()
+            && !self.redis_url.starts_with("redis://")
+            && !self.redis_url.starts_with("rediss://")
+        {
+            return Err("redis_url 必须以 redis:// 或 rediss:// 开头".to_string());
+        }
+        for (prefix, rule) in &self.rate_limits {
+            if !prefix.starts_with('/') {
+                return Err(format!("rate_limits 的路由前缀需以 / 开头: {:?}", prefix));
+            }
+            if rule.limit == 0 || rule.window_secs == 0 {
+                return Err(format!("rate_limits.{:?} 的 limit 与 window_secs 必须大于 0", prefix));
+            }
+        }
+        for (name, job) in &self.jobs {
+            if job.interval_secs == Some(0) {
+                return Err(format!("jobs.{}.interval_secs 必须大于 0", name));
+            }
+        }
+        if self.environment == "prod" {
+            for (key, value) in [("jwt_secret", &self.jwt_secret), ("signing_secret", &self.signing_secret)] {
+                if value.trim().is_empty() {
+                    return Err(format!("prod 环境必须配置 {}", key));
+                }
+            }
+        }
+        if self.api_key_daily_quota <= 0 {
+            return Err("api_key_daily_quota 必须大于 0".to_string());
+        }
+        if !self.public_base_url.starts_with("http://") && !self.public_base_url.starts_with("https://") {
+            return Err(format!("public_base_url 无效: {:?}（应以 http:// 或 https:// 开头）", self.public_base_url));
+        }
+        for origin in &self.cors_origins {
+            let valid = (origin.starts_with("http://") || origin.starts_with("https://"))
+                && HeaderValue::from_str(origin).is_ok();
+            if !valid {
+                return Err(format!("cors_origins 中的来源无效: {:?}", origin));
+            }
+        }
+        Ok(())
+    }
+
+    // 实际使用的库名：db_name 加环境后缀，已带同一后缀时不重复添加
+    pub fn database_name(&self) -> String {
+        let suffix = format!("_{}", self.environment);
+        if self.environment.is_empty() || self.db_name.ends_with(&suffix) {
+            self.db_name.clone()
+        } else {
+            format!("{}{}", self.db_name, suffix)
+        }
+    }
+
+    pub fn notify_digest_window(&self, kind: &str) -> u64 {
+        self.notify_digest_windows.get(kind).copied().unwrap_or(self.notify_digest_window_secs)
+    }
+
+    // 按路径段匹配，/user/login 不会命中 /user/loginx
+    pub fn rate_limit_for(&self, path: &str) -> Option<(&str, &RateLimitRule)> {
+        self.rate_limits
+            .iter()
+            .filter(|(prefix, _)| {
+                let prefix = prefix.trim_end_matches('/');
+                path == prefix || path.starts_with(&format!("{}/", prefix)) || prefix.is_empty()
+            })
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(prefix, rule)| (prefix.as_str(), rule))
+    }
+
+    pub fn job(&self, name: &str) -> Option<&JobConfig> {
+        self.jobs.get(name)
+    }
+
+    // 任务参数，未配置时使用默认值
+    pub fn job_param(&self, name: &str, key: &str, default: i64) -> i64 {
+        self.job(name).and_then(|j| j.params.get(key).copied()).unwrap_or(default)
+    }
+
+    pub fn bind_addr(&self) -> SocketAddr {
+        self.bind_addr.parse().expect("bind_addr 已在启动时校验")
+    }
+
+    pub fn cors_origin_values(&self) -> Vec<HeaderValue> {
+        self.cors_origins
+            .iter()
+            .filter_map(|o| HeaderValue::from_str(o).ok())
+            .collect()
+    }
+}
+
+// 启动时调用一次；之后各模块通过 config::get() 读取
+pub fn init() -> Result<&'static Config, String> {
+    let cfg = Config::load()?;
+    Ok(CONFIG.get_or_init(|| cfg))
+}
+
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(|| Config::load().expect("配置无效"))
+}
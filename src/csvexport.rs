@@ -0,0 +1,44 @@
+use axum::{
+    body::{Body, Bytes},
+    http::header,
+    response::{IntoResponse, Response},
+};
+use futures_util::{stream, Stream, StreamExt};
+
+// CSV 流式导出：先写 UTF-8 BOM（Excel 打开中文不乱码）与表头，之后逐行编码写出，
+// 内存占用与导出行数无关
+
+fn encode(row: &[String]) -> Bytes {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    // 写入内存缓冲不会失败
+    let _ = writer.write_record(row);
+    Bytes::from(writer.into_inner().unwrap_or_default())
+}
+
+// 一次性生成完整文件，用于打包进压缩包等非流式场景
+pub fn to_bytes(header_row: &[&str], rows: &[Vec<String>]) -> Vec<u8> {
+    let mut out = b"\xEF\xBB\xBF".to_vec();
+    out.extend_from_slice(&encode(&header_row.iter().map(|h| h.to_string()).collect::<Vec<_>>()));
+    for row in rows {
+        out.extend_from_slice(&encode(row));
+    }
+    out
+}
+
+pub fn response<S>(filename: &str, header_row: &[&str], rows: S) -> Response
+where
+    S: Stream<Item = mongodb::error::Result<Vec<String>>> + Send + 'static,
+{
+    let mut head = b"\xEF\xBB\xBF".to_vec();
+    head.extend_from_slice(&encode(&header_row.iter().map(|h| h.to_string()).collect::<Vec<_>>()));
+    let body = stream::once(async move { Ok::<_, mongodb::error::Error>(Bytes::from(head)) })
+        .chain(rows.map(|row| row.map(|r| encode(&r))));
+    (
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename)),
+        ],
+        Body::from_stream(body),
+    )
+        .into_response()
+}
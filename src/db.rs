@@ -1,47 +1,375 @@
-use mongodb::{Client, Collection};
-use once_cell::sync::Lazy;
-use bson::Document;
+use mongodb::{
+    error::{Error, ErrorKind, WriteFailure},
+    options::{ClientOptions, Credential, IndexOptions, Tls, TlsOptions},
+    Client, Collection, Database, IndexModel,
+};
+use bson::{doc, Document};
 use std::sync::Arc;
+use std::time::Duration;
 
-pub async fn get_db() -> Arc<Client> {
-    let client = Arc::new(
-        Client::with_uri_str("mongodb://localhost:27017")
-            .await
-            .expect("Failed to connect to MongoDB"),
-    );
-    client
+use crate::breaker::HealthMonitor;
+
+// 数据库不可用时尽快失败，而不是让每个请求都挂到驱动默认的 30 秒超时
+fn env_millis(key: &str, default: u64) -> Duration {
+    let ms = std::env::var(key).ok().and_then(|v| v.parse().ok()).unwrap_or(default);
+    Duration::from_millis(ms)
 }
 
-pub static CLIENT: Lazy<Arc<Client>> = Lazy::new(|| {
-    let rt = tokio::runtime::Handle::current();
-    Arc::new(
-        rt.block_on(Client::with_uri_str("mongodb://localhost:27017"))
-            .expect("Failed to connect to MongoDB"),
-    )
-});
+// 配置中的账号与 TLS 设置叠加到 mongo_uri 解析出的选项上
+fn apply_auth(options: &mut ClientOptions) {
+    let cfg = crate::config::get();
+    if !cfg.mongo_username.is_empty() {
+        let mut credential = options.credential.take().unwrap_or_default();
+        credential.username = Some(cfg.mongo_username.clone());
+        credential.password = Some(cfg.mongo_password.clone());
+        options.credential = Some(credential);
+    }
+    if !cfg.mongo_auth_source.is_empty() {
+        let credential = options.credential.get_or_insert_with(Credential::default);
+        credential.source = Some(cfg.mongo_auth_source.clone());
+    }
+    if cfg.mongo_tls || !cfg.mongo_tls_ca_file.is_empty() || !cfg.mongo_tls_cert_key_file.is_empty() {
+        let mut tls = match options.tls.take() {
+            Some(Tls::Enabled(tls)) => tls,
+            _ => TlsOptions::default(),
+        };
+        if !cfg.mongo_tls_ca_file.is_empty() {
+            tls.ca_file_path = Some(cfg.mongo_tls_ca_file.clone().into());
+        }
+        if !cfg.mongo_tls_cert_key_file.is_empty() {
+            tls.cert_key_file_path = Some(cfg.mongo_tls_cert_key_file.clone().into());
+        }
+        options.tls = Some(Tls::Enabled(tls));
+    }
+}
+
+pub async fn get_db() -> Arc<Client> {
+    let mut options = ClientOptions::parse_async(&crate::config::get().mongo_uri)
+        .await
+        .expect("Failed to parse MongoDB options");
+    apply_auth(&mut options);
+    options.server_selection_timeout = Some(env_millis("MONGO_SERVER_SELECTION_TIMEOUT_MS", 3000));
+    options.connect_timeout = Some(env_millis("MONGO_CONNECT_TIMEOUT_MS", 3000));
+    options.heartbeat_freq = Some(env_millis("MONGO_HEARTBEAT_MS", 5000));
+    options.command_event_handler = Some(Arc::new(HealthMonitor));
+    options.sdam_event_handler = Some(Arc::new(HealthMonitor));
+
+    Arc::new(Client::with_options(options).expect("Failed to connect to MongoDB"))
+}
 
-pub const DB_NAME: &str = "rust_meeting";
+pub fn database(client: &Arc<Client>) -> Database {
+    client.database(&crate::config::get().database_name())
+}
 
 pub fn user_collection(client: &Arc<Client>) -> Collection<Document> {
-    client.database(DB_NAME).collection("users")
+    database(client).collection("users")
 }
 
 pub fn lecture_collection(client: &Arc<Client>) -> Collection<Document> {
-    client.database(DB_NAME).collection("lecture")
+    database(client).collection("lecture")
 }
 
 pub fn invitation_collection(client: &Arc<Client>) -> Collection<Document> {
-    client.database(DB_NAME).collection("invitation")
+    database(client).collection("invitation")
 }
 
 pub fn feedback_collection(client: &Arc<Client>) -> Collection<Document> {
-    client.database(DB_NAME).collection("feedback")
+    database(client).collection("feedback")
 }
 
 pub fn la_collection(client: &Arc<Client>) -> Collection<Document> {
-    client.database(DB_NAME).collection("la")
+    database(client).collection("la")
 }
 
 pub fn discussion_collection(client: &Arc<Client>) -> Collection<Document> {
-    client.database(DB_NAME).collection("discussion")
-}
\ No newline at end of file
+    database(client).collection("discussion")
+}
+pub fn organization_collection(client: &Arc<Client>) -> Collection<Document> {
+    database(client).collection("organization")
+}
+
+pub fn api_key_collection(client: &Arc<Client>) -> Collection<Document> {
+    database(client).collection("api_keys")
+}
+
+pub fn api_usage_collection(client: &Arc<Client>) -> Collection<Document> {
+    database(client).collection("api_usage")
+}
+
+pub fn material_collection(client: &Arc<Client>) -> Collection<Document> {
+    database(client).collection("material")
+}
+
+pub fn notification_collection(client: &Arc<Client>) -> Collection<Document> {
+    database(client).collection("notifications")
+}
+
+pub fn announcement_collection(client: &Arc<Client>) -> Collection<Document> {
+    database(client).collection("lecture_announcements")
+}
+
+pub fn faq_collection(client: &Arc<Client>) -> Collection<Document> {
+    database(client).collection("lecture_faq")
+}
+
+pub fn session_collection(client: &Arc<Client>) -> Collection<Document> {
+    database(client).collection("sessions")
+}
+
+pub fn password_reset_collection(client: &Arc<Client>) -> Collection<Document> {
+    database(client).collection("password_resets")
+}
+
+pub fn magic_link_collection(client: &Arc<Client>) -> Collection<Document> {
+    database(client).collection("magic_links")
+}
+
+pub fn lecture_role_collection(client: &Arc<Client>) -> Collection<Document> {
+    database(client).collection("lecture_roles")
+}
+
+pub fn audit_collection(client: &Arc<Client>) -> Collection<Document> {
+    database(client).collection("audit_log")
+}
+
+pub fn waitlist_collection(client: &Arc<Client>) -> Collection<Document> {
+    database(client).collection("waitlist")
+}
+
+pub fn lecture_note_collection(client: &Arc<Client>) -> Collection<Document> {
+    database(client).collection("lecture_notes")
+}
+
+pub fn transcript_collection(client: &Arc<Client>) -> Collection<Document> {
+    database(client).collection("transcript_entries")
+}
+
+pub fn reaction_collection(client: &Arc<Client>) -> Collection<Document> {
+    database(client).collection("discussion_reactions")
+}
+
+pub fn invitation_template_collection(client: &Arc<Client>) -> Collection<Document> {
+    database(client).collection("invitation_templates")
+}
+
+pub fn banner_collection(client: &Arc<Client>) -> Collection<Document> {
+    database(client).collection("site_announcements")
+}
+
+pub fn org_export_collection(client: &Arc<Client>) -> Collection<Document> {
+    database(client).collection("org_exports")
+}
+
+// 应用读写的全部集合，用于启动时核对账号权限
+fn app_collections(client: &Arc<Client>) -> Vec<Collection<Document>> {
+    vec![
+        user_collection(client),
+        lecture_collection(client),
+        invitation_collection(client),
+        feedback_collection(client),
+        la_collection(client),
+        discussion_collection(client),
+        organization_collection(client),
+        api_key_collection(client),
+        api_usage_collection(client),
+        material_collection(client),
+        notification_collection(client),
+        announcement_collection(client),
+        faq_collection(client),
+        session_collection(client),
+        password_reset_collection(client),
+        magic_link_collection(client),
+        lecture_role_collection(client),
+        audit_collection(client),
+        waitlist_collection(client),
+        lecture_note_collection(client),
+        transcript_collection(client),
+        reaction_collection(client),
+        invitation_template_collection(client),
+        banner_collection(client),
+        org_export_collection(client),
+    ]
+}
+
+const READ_WRITE_ACTIONS: [&str; 4] = ["find", "insert", "update", "remove"];
+const INDEX_ACTIONS: [&str; 2] = ["createIndex", "listIndexes"];
+
+// 权限条目是否覆盖本库的某个集合：anyResource、db 或 collection 为空串表示任意
+fn privilege_covers(resource: &Document, db_name: &str, coll: &str) -> bool {
+    if resource.get_bool("anyResource").unwrap_or(false) {
+        return true;
+    }
+    let db_ok = matches!(resource.get_str("db"), Ok(d) if d.is_empty() || d == db_name);
+    let coll_ok = matches!(resource.get_str("collection"), Ok(c) if c.is_empty() || c == coll);
+    db_ok && coll_ok
+}
+
+// 当前账号缺少的权限，形如 "lecture: insert, update"。连接未经账号认证（如本地未开启认证）时
+// 无从判断，返回 None
+pub async fn missing_privileges(client: &Arc<Client>) -> Result<Option<Vec<String>>, Error> {
+    let status = database(client)
+        .run_command(doc! { "connectionStatus": 1, "showPrivileges": true }, None)
+        .await?;
+    let auth_info = status.get_document("authInfo").cloned().unwrap_or_default();
+    if auth_info.get_array("authenticatedUsers").map(|u| u.is_empty()).unwrap_or(true) {
+        return Ok(None);
+    }
+    let privileges: Vec<Document> = auth_info
+        .get_array("authenticatedUserPrivileges")
+        .map(|p| p.iter().filter_map(|b| b.as_document().cloned()).collect())
+        .unwrap_or_default();
+    let db_name = crate::config::get().database_name();
+    let indexed: Vec<String> = index_plan(client).iter().map(|(c, _)| c.name().to_string()).collect();
+
+    let mut missing = Vec::new();
+    for coll in app_collections(client) {
+        let name = coll.name();
+        let granted: Vec<&str> = privileges
+            .iter()
+            .filter(|p| p.get_document("resource").is_ok_and(|r| privilege_covers(r, &db_name, name)))
+            .flat_map(|p| p.get_array("actions").into_iter().flatten().filter_map(|a| a.as_str()))
+            .collect();
+        let needs_index = indexed.iter().any(|c| c == name);
+        let lacking: Vec<&str> = READ_WRITE_ACTIONS
+            .iter()
+            .chain(INDEX_ACTIONS.iter().filter(|_| needs_index))
+            .filter(|action| !granted.contains(action))
+            .copied()
+            .collect();
+        if !lacking.is_empty() {
+            missing.push(format!("{}: {}", name, lacking.join(", ")));
+        }
+    }
+    Ok(Some(missing))
+}
+
+// 启动时核对数据库账号：认证失败或缺少权限时返回错误，由调用方拒绝启动；
+// 数据库暂时连不上时只记录日志，服务照常启动，由熔断器在数据库恢复后放行
+pub async fn verify_access(client: &Arc<Client>) -> Result<(), String> {
+    match missing_privileges(client).await {
+        Ok(None) => {
+            println!("数据库连接未使用账号认证，跳过权限检查");
+            Ok(())
+        }
+        Ok(Some(missing)) if missing.is_empty() => Ok(()),
+        Ok(Some(missing)) => Err(format!(
+            "数据库账号在 {} 库缺少以下权限（集合: 操作）: {}",
+            crate::config::get().database_name(),
+            missing.join("; ")
+        )),
+        Err(e) if matches!(&*e.kind, ErrorKind::Authentication { .. }) => Err(format!(
+            "数据库认证失败，请检查 mongo_username、mongo_password 与 mongo_auth_source: {}",
+            e
+        )),
+        Err(e) => {
+            println!("暂时无法检查数据库权限: {}", e);
+            Ok(())
+        }
+    }
+}
+
+fn index(keys: Document, name: &str) -> IndexModel {
+    IndexModel::builder()
+        .keys(keys)
+        .options(IndexOptions::builder().name(name.to_string()).build())
+        .build()
+}
+
+fn unique_index(keys: Document, name: &str) -> IndexModel {
+    IndexModel::builder()
+        .keys(keys)
+        .options(IndexOptions::builder().unique(true).name(name.to_string()).build())
+        .build()
+}
+
+const DUPLICATE_KEY: i32 = 11000;
+
+// 写入违反唯一索引时返回冲突的索引名（如 uniq_email），其他错误返回 None。
+// 服务端错误信息形如 "E11000 duplicate key error collection: db.users index: uniq_email dup key: {...}"
+pub fn duplicate_key_index(error: &Error) -> Option<String> {
+    let ErrorKind::Write(WriteFailure::WriteError(e)) = &*error.kind else { return None };
+    if e.code != DUPLICATE_KEY {
+        return None;
+    }
+    let name = e.message.split("index: ").nth(1)?.split_whitespace().next()?;
+    Some(name.to_string())
+}
+
+// insert_many 等批量写入中是否有文档违反唯一索引
+pub fn bulk_has_duplicate(error: &Error) -> bool {
+    let ErrorKind::BulkWrite(failure) = &*error.kind else { return false };
+    failure.write_errors.as_ref().is_some_and(|errors| errors.iter().any(|e| e.code == DUPLICATE_KEY))
+}
+
+// 业务依赖的唯一索引及常用查询索引
+fn index_plan(client: &Arc<Client>) -> Vec<(Collection<Document>, IndexModel)> {
+    vec![
+        (user_collection(client), unique_index(doc! { "email": 1 }, "uniq_email")),
+        (user_collection(client), unique_index(doc! { "username": 1 }, "uniq_username")),
+        (lecture_collection(client), unique_index(doc! { "lecturecode": 1 }, "uniq_lecturecode")),
+        (la_collection(client), unique_index(doc! { "lecture_id": 1, "audience_id": 1 }, "uniq_lecture_audience")),
+        (feedback_collection(client), unique_index(doc! { "lecture_id": 1, "user_id": 1 }, "uniq_lecture_user")),
+        (waitlist_collection(client), unique_index(doc! { "lecture_id": 1, "audience_id": 1 }, "uniq_waitlist_audience")),
+        (lecture_role_collection(client), unique_index(doc! { "lecture_id": 1, "user_id": 1, "role": 1 }, "uniq_lecture_role")),
+        (lecture_note_collection(client), unique_index(doc! { "lecture_id": 1, "revision": 1 }, "uniq_lecture_revision")),
+        (reaction_collection(client), unique_index(doc! { "discussion_id": 1, "user_id": 1, "kind": 1 }, "uniq_discussion_reaction")),
+        (invitation_template_collection(client), unique_index(doc! { "organizer_id": 1, "name": 1 }, "uniq_organizer_template")),
+        // 演讲检索常用条件
+        (lecture_collection(client), index(doc! { "status": 1, "start_time": 1 }, "status_start_time")),
+        (audit_collection(client), index(doc! { "at": 1 }, "at")),
+        // 标签订阅：按标签找演讲、按标签找订阅者
+        (lecture_collection(client), index(doc! { "tags": 1 }, "tags")),
+        (user_collection(client), index(doc! { "subscribed_tags": 1 }, "subscribed_tags")),
+        // 讨论串：按根消息取整串回复
+        (discussion_collection(client), index(doc! { "lecture_id": 1, "thread_id": 1 }, "lecture_thread")),
+        // 通知合并时按用户、类型查找窗口内的未读通知
+        (notification_collection(client), index(doc! { "user_id": 1, "kind": 1, "read": 1, "created_at": -1 }, "user_kind_unread")),
+        // 反馈提醒任务按离场时间查找最近离场的听众
+        (la_collection(client), index(doc! { "left_at": 1 }, "left_at")),
+        // 现场记录按演讲取出后按时间排列
+        (transcript_collection(client), index(doc! { "lecture_id": 1, "created_at": 1 }, "lecture_created")),
+        // 全站公告按生效时间段查询
+        (banner_collection(client), index(doc! { "ends_at": 1, "starts_at": 1 }, "ends_starts")),
+        // 导出任务按状态取最早提交的
+        (org_export_collection(client), index(doc! { "status": 1, "created_at": 1 }, "status_created")),
+    ]
+}
+
+fn index_name(index: &IndexModel) -> String {
+    index.options.as_ref().and_then(|o| o.name.clone()).unwrap_or_default()
+}
+
+// 启动时创建索引，并发下由数据库兜底防止重复注册、重复报名与重复反馈。
+// 已存在同名索引时为空操作；已有重复数据会导致对应索引创建失败，需先清理数据
+pub async fn init_indexes(client: &Arc<Client>) -> Result<(), String> {
+    let mut errors = Vec::new();
+    for (coll, index) in index_plan(client) {
+        let name = index_name(&index);
+        if let Err(e) = coll.create_index(index, None).await {
+            errors.push(format!("{}.{}: {}", coll.name(), name, e));
+        }
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors.join("; "))
+    }
+}
+
+// 尚未建立的索引，形如 users.uniq_email；集合尚不存在时其索引全部计为缺失
+pub async fn missing_indexes(client: &Arc<Client>) -> Result<Vec<String>, Error> {
+    const NAMESPACE_NOT_FOUND: i32 = 26;
+    let mut missing = Vec::new();
+    for (coll, index) in index_plan(client) {
+        let existing = match coll.list_index_names().await {
+            Ok(names) => names,
+            Err(e) if matches!(&*e.kind, ErrorKind::Command(c) if c.code == NAMESPACE_NOT_FOUND) => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        let name = index_name(&index);
+        if !existing.contains(&name) {
+            missing.push(format!("{}.{}", coll.name(), name));
+        }
+    }
+    Ok(missing)
+}
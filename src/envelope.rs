@@ -0,0 +1,108 @@
+use axum::{
+    body::{to_bytes, Body},
+    extract::Request,
+    http::{header, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+
+use crate::error::code_for;
+
+// 客户端通过该请求头选择响应格式：1 为统一信封，0 为原始格式；未携带时按 RESPONSE_ENVELOPE 环境变量
+pub const ENVELOPE_HEADER: &str = "x-response-envelope";
+// 信封模式只处理不超过该大小的 JSON / 文本响应，文件下载等保持原样
+const MAX_ENVELOPE_BODY: usize = 16 * 1024 * 1024;
+
+// 分页信息：列表接口写入响应扩展，信封模式下放入 meta.pagination
+#[derive(Clone, Debug)]
+pub struct Pagination {
+    pub page: u64,
+    pub limit: u64,
+    pub total: u64,
+}
+
+impl Pagination {
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "page": self.page,
+            "limit": self.limit,
+            "total": self.total,
+            "has_more": self.page * self.limit < self.total,
+            "next_page": (self.page * self.limit < self.total).then_some(self.page + 1),
+        })
+    }
+}
+
+fn enabled_by_default() -> bool {
+    matches!(
+        std::env::var("RESPONSE_ENVELOPE").as_deref(),
+        Ok("1") | Ok("true")
+    )
+}
+
+fn wants_envelope(req: &Request) -> bool {
+    match req.headers().get(ENVELOPE_HEADER).and_then(|v| v.to_str().ok()) {
+        Some(v) => matches!(v.trim(), "1" | "true"),
+        None => enabled_by_default(),
+    }
+}
+
+pub async fn wrap(req: Request, next: Next) -> Response {
+    if !wants_envelope(&req) {
+        return next.run(req).await;
+    }
+    let resp = next.run(req).await;
+
+    let content_type = resp
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("")
+        .to_string();
+    let is_json = content_type.starts_with("application/json");
+    let is_text = content_type.starts_with("text/plain");
+    if !is_json && !is_text {
+        return resp;
+    }
+
+    let (mut parts, body) = resp.into_parts();
+    let Ok(bytes) = to_bytes(body, MAX_ENVELOPE_BODY).await else {
+        return Response::from_parts(parts, Body::empty());
+    };
+    let payload: serde_json::Value = if is_json {
+        serde_json::from_slice(&bytes).unwrap_or(serde_json::Value::Null)
+    } else {
+        serde_json::Value::String(String::from_utf8_lossy(&bytes).into_owned())
+    };
+
+    let pagination = parts.extensions.get::<Pagination>().cloned();
+    let mut meta = serde_json::json!({ "request_id": crate::request_id::current() });
+    let envelope = if parts.status.is_success() {
+        let data = match (&pagination, payload) {
+            // 分页接口的列表放在 data，分页字段移入 meta
+            (Some(p), serde_json::Value::Object(mut obj)) if obj.contains_key("items") => {
+                meta["pagination"] = p.to_json();
+                obj.remove("items").unwrap_or_default()
+            }
+            (Some(p), other) => {
+                meta["pagination"] = p.to_json();
+                other
+            }
+            (None, other) => other,
+        };
+        serde_json::json!({ "data": data, "error": null, "meta": meta })
+    } else {
+        // 已是结构化错误（含 error 字段）时沿用，否则把原始信息包装为 message
+        let error = match payload {
+            serde_json::Value::Object(mut obj) if obj.contains_key("error") => obj.remove("error").unwrap_or_default(),
+            serde_json::Value::String(message) => serde_json::json!({ "code": code_for(parts.status), "message": message, "details": null }),
+            other => serde_json::json!({ "code": code_for(parts.status), "message": "请求失败", "details": other }),
+        };
+        serde_json::json!({ "data": null, "error": error, "meta": meta })
+    };
+
+    let body = serde_json::to_vec(&envelope).unwrap_or_default();
+    parts.headers.insert(header::CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(body))
+}
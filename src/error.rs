@@ -0,0 +1,106 @@
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+};
+
+// 统一错误类型：所有接口失败时均返回 { "error": { "code", "message", "details" } }，
+// code 为稳定的机器可读标识，message 为给用户看的中文提示，details 为可选的补充信息
+#[derive(Debug, thiserror::Error)]
+pub enum AppError {
+    #[error("{0}")]
+    BadRequest(String),
+    #[error("{0}")]
+    Unauthorized(String),
+    #[error("{0}")]
+    Forbidden(String),
+    #[error("{0}")]
+    NotFound(String),
+    #[error("{0}")]
+    Conflict(String),
+    #[error("{0}")]
+    Gone(String),
+    #[error("{0}")]
+    TooManyRequests(String),
+    #[error("{0}")]
+    Unavailable(String),
+    #[error("{0}")]
+    Internal(String),
+    #[error("数据库错误: {0}")]
+    Database(#[from] mongodb::error::Error),
+    #[error("{message}")]
+    Detailed {
+        status: StatusCode,
+        message: String,
+        details: serde_json::Value,
+    },
+}
+
+impl AppError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            AppError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            AppError::Forbidden(_) => StatusCode::FORBIDDEN,
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::Conflict(_) => StatusCode::CONFLICT,
+            AppError::Gone(_) => StatusCode::GONE,
+            AppError::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
+            AppError::Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            // 重试后仍未恢复的瞬时故障（网络中断、主节点切换）提示客户端稍后再试
+            AppError::Database(e) if crate::retry::is_transient(e) => StatusCode::SERVICE_UNAVAILABLE,
+            AppError::Internal(_) | AppError::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            AppError::Detailed { status, .. } => *status,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::Database(e) if !crate::retry::is_transient(e) => "database_error",
+            _ => code_for(self.status()),
+        }
+    }
+
+    // 附带结构化补充信息（如出错的字段名），状态码与 code 保持不变
+    pub fn with_details(self, details: serde_json::Value) -> AppError {
+        AppError::Detailed { status: self.status(), message: self.to_string(), details }
+    }
+}
+
+// 状态码对应的错误标识；框架自身产生的错误（如请求体解析失败）在信封中也沿用同一套标识
+pub fn code_for(status: StatusCode) -> &'static str {
+    match status {
+        StatusCode::BAD_REQUEST => "bad_request",
+        StatusCode::UNAUTHORIZED => "unauthorized",
+        StatusCode::FORBIDDEN => "forbidden",
+        StatusCode::NOT_FOUND => "not_found",
+        StatusCode::CONFLICT => "conflict",
+        StatusCode::GONE => "gone",
+        StatusCode::TOO_MANY_REQUESTS => "rate_limited",
+        StatusCode::SERVICE_UNAVAILABLE => "service_unavailable",
+        StatusCode::UNPROCESSABLE_ENTITY => "unprocessable_entity",
+        StatusCode::UNSUPPORTED_MEDIA_TYPE => "unsupported_media_type",
+        StatusCode::PAYLOAD_TOO_LARGE => "payload_too_large",
+        _ => "internal_error",
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        let code = self.code();
+        let (message, details) = match self {
+            // 数据库原始错误只写日志，不透传给客户端
+            AppError::Database(e) => {
+                println!("[{}] 数据库错误: {}", crate::request_id::current().unwrap_or_default(), e);
+                let message = if status == StatusCode::SERVICE_UNAVAILABLE { "数据库暂时不可用，请稍后重试" } else { "数据库错误" };
+                (message.to_string(), serde_json::Value::Null)
+            }
+            AppError::Detailed { message, details, .. } => (message, details),
+            other => (other.to_string(), serde_json::Value::Null),
+        };
+        let body = serde_json::json!({
+            "error": { "code": code, "message": message, "details": details }
+        });
+        (status, Json(body)).into_response()
+    }
+}
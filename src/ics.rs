@@ -0,0 +1,109 @@
+use axum::{
+    http::header,
+    response::{IntoResponse, Response},
+};
+use bson::Document;
+use chrono::{TimeZone, Utc};
+
+use crate::lifecycle::LectureStatus;
+
+// RFC 5545 日历：演讲导出为 VEVENT，时间统一用 UTC，由日历客户端换算为本地时间
+const PRODID: &str = "-//Rust Meeting//Lecture Calendar//ZH";
+
+// TEXT 类型需转义反斜杠、分号、逗号与换行
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+// 内容行超过 75 字节时折行，续行以空格开头；按字符边界切分，避免截断多字节字符
+fn fold(line: &str, out: &mut String) {
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(c);
+        width += c.len_utf8();
+    }
+    out.push_str("\r\n");
+}
+
+fn utc_stamp(ms: i64) -> String {
+    Utc.timestamp_millis_opt(ms)
+        .single()
+        .unwrap_or_default()
+        .format("%Y%m%dT%H%M%SZ")
+        .to_string()
+}
+
+pub fn event(lecture: &Document) -> String {
+    let id = lecture.get_object_id("_id").map(|o| o.to_hex()).unwrap_or_default();
+    let start = lecture.get_i64("start_time").unwrap_or(0);
+    let duration = lecture.get_i32("duration").unwrap_or(0) as i64;
+    let base = &crate::config::get().public_base_url;
+    let mut description = lecture.get_str("description").unwrap_or("").to_string();
+    if let Ok(code) = lecture.get_str("lecturecode") {
+        if !description.is_empty() {
+            description.push_str("\n\n");
+        }
+        description.push_str(&format!("演讲码：{}", code));
+    }
+    let status = match LectureStatus::of(lecture) {
+        LectureStatus::Cancelled => "CANCELLED",
+        LectureStatus::Draft => "TENTATIVE",
+        _ => "CONFIRMED",
+    };
+
+    let lines = [
+        "BEGIN:VEVENT".to_string(),
+        format!("UID:{}@rust-meeting", id),
+        format!("DTSTAMP:{}", utc_stamp(Utc::now().timestamp_millis())),
+        format!("DTSTART:{}", utc_stamp(start)),
+        format!("DTEND:{}", utc_stamp(start + duration * 60_000)),
+        format!("SUMMARY:{}", escape(lecture.get_str("topic").unwrap_or(""))),
+        format!("DESCRIPTION:{}", escape(&description)),
+        format!("URL:{}/static/lecture_detail.html?id={}", base.trim_end_matches('/'), id),
+        format!("STATUS:{}", status),
+        "END:VEVENT".to_string(),
+    ];
+    let mut out = String::new();
+    for line in &lines {
+        fold(line, &mut out);
+    }
+    out
+}
+
+pub fn calendar(name: &str, lectures: &[Document]) -> String {
+    let mut out = String::new();
+    for line in [
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        format!("PRODID:{}", PRODID),
+        "CALSCALE:GREGORIAN".to_string(),
+        "METHOD:PUBLISH".to_string(),
+        format!("X-WR-CALNAME:{}", escape(name)),
+    ] {
+        fold(&line, &mut out);
+    }
+    for lecture in lectures {
+        out.push_str(&event(lecture));
+    }
+    out.push_str("END:VCALENDAR\r\n");
+    out
+}
+
+pub fn response(body: String, filename: &str) -> Response {
+    (
+        [
+            (header::CONTENT_TYPE, "text/calendar; charset=utf-8".to_string()),
+            (header::CONTENT_DISPOSITION, format!("inline; filename=\"{}\"", filename)),
+        ],
+        body,
+    )
+        .into_response()
+}
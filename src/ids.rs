@@ -0,0 +1,46 @@
+use bson::{oid::ObjectId, Bson, Document};
+use chrono::SecondsFormat;
+use crate::error::AppError;
+
+// 统一的 ID 序列化：ObjectId 一律输出为 hex 字符串，顶层 _id 改名为 id
+
+pub fn parse_oid(raw: &str, field: &str) -> Result<ObjectId, AppError> {
+    ObjectId::parse_str(raw.trim()).map_err(|_| {
+        AppError::BadRequest(format!("无效的 {}", field)).with_details(serde_json::json!({ "field": field }))
+    })
+}
+
+// 读取文档中的 ObjectId 字段并转为 hex，缺失时为空字符串
+pub fn oid_hex(doc: &Document, key: &str) -> String {
+    doc.get_object_id(key).map(|o| o.to_hex()).unwrap_or_default()
+}
+
+pub fn bson_to_json(value: Bson) -> serde_json::Value {
+    match value {
+        Bson::ObjectId(oid) => serde_json::Value::String(oid.to_hex()),
+        // 与 chrono::DateTime<Utc> 的 serde 输出保持一致
+        Bson::DateTime(dt) => serde_json::Value::String(
+            dt.to_chrono().to_rfc3339_opts(SecondsFormat::AutoSi, true),
+        ),
+        Bson::Document(doc) => serde_json::Value::Object(
+            doc.into_iter().map(|(k, v)| (k, bson_to_json(v))).collect(),
+        ),
+        Bson::Array(arr) => serde_json::Value::Array(arr.into_iter().map(bson_to_json).collect()),
+        other => other.into_relaxed_extjson(),
+    }
+}
+
+pub fn doc_to_json(mut doc: Document) -> serde_json::Value {
+    let id = doc.remove("_id");
+    let mut v = bson_to_json(Bson::Document(doc));
+    if let (Some(id), Some(obj)) = (id, v.as_object_mut()) {
+        obj.insert("id".to_string(), bson_to_json(id));
+    }
+    v
+}
+
+// 用户文档输出前去掉密码摘要
+pub fn user_to_json(mut doc: Document) -> serde_json::Value {
+    doc.remove("password");
+    doc_to_json(doc)
+}
@@ -0,0 +1,666 @@
+use bson::{doc, oid::ObjectId, Document};
+use chrono::Utc;
+use futures_util::TryStreamExt;
+use mongodb::options::FindOptions;
+use mongodb::Client;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::config;
+use crate::db::{
+    feedback_collection, invitation_collection, la_collection, lecture_collection, material_collection,
+    organization_collection, user_collection,
+};
+use crate::lifecycle::LectureStatus;
+use crate::mailer::MAILER;
+use crate::routes::invitation::{invitee_of, InviteeRole};
+use crate::routes::lecture::wipe_rehearsal;
+use crate::timefmt::UserTime;
+use crate::{audit, breaker, notify, orgexport, report};
+use crate::scheduler::{spawn_every, spawn_job};
+
+const DEFAULT_ARCHIVE_AFTER_DAYS: i64 = 30;
+// 只为最近结束的演讲发送报告，避免首次部署时给历史演讲补发
+const REPORT_LOOKBACK_DAYS: i64 = 7;
+const REPORT_MAX_ATTEMPTS: i32 = 3;
+// 任务参数默认值，可在 config.jobs.<任务名> 中覆盖
+const DEFAULT_REMINDER_LEAD_MINUTES: i64 = 30;
+const DEFAULT_INVITATION_EXPIRE_DAYS: i64 = 14;
+const DEFAULT_AUTO_END_GRACE_MINUTES: i64 = 15;
+const DEFAULT_ORPHAN_MIN_AGE_HOURS: i64 = 24;
+const DEFAULT_FEEDBACK_PROMPT_LOOKBACK_HOURS: i64 = 24;
+// 邀请状态：0 待回应 / 1 已接受 / -1 已拒绝 / -2 已过期 / -3 同一演讲已有其他讲者接受
+pub const INVITATION_EXPIRED: i32 = -2;
+pub const INVITATION_SUPERSEDED: i32 = -3;
+
+pub fn register(client: Arc<Client>) {
+    spawn_job("archive_lectures", Duration::from_secs(3600), client.clone(), archive_past_lectures);
+    spawn_job("lecture_reports", Duration::from_secs(600), client.clone(), send_lecture_reports);
+    spawn_job("lecture_reminders", Duration::from_secs(60), client.clone(), send_lecture_reminders);
+    spawn_job("expire_invitations", Duration::from_secs(3600), client.clone(), expire_invitations);
+    spawn_job("auto_end_lectures", Duration::from_secs(300), client.clone(), auto_end_lectures);
+    spawn_job("clean_orphan_uploads", Duration::from_secs(86_400), client.clone(), clean_orphan_uploads);
+    spawn_job("feedback_prompts", Duration::from_secs(60), client.clone(), send_feedback_prompts);
+    spawn_job("org_exports", Duration::from_secs(10), client.clone(), orgexport::run_pending);
+    // 熔断探测是数据库恢复的唯一途径，不允许停用
+    spawn_every("db_probe", breaker::probe_interval(), client, breaker::probe);
+}
+
+fn default_archive_after_days() -> i64 {
+    std::env::var("ARCHIVE_AFTER_DAYS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_ARCHIVE_AFTER_DAYS)
+}
+
+// 结束时间 = start_time + duration(分钟)
+fn ended_before(cutoff_ms: i64) -> Document {
+    doc! { "$expr": { "$lt": [
+        { "$add": ["$start_time", { "$multiply": [{ "$ifNull": ["$duration", 0] }, 60_000] }] },
+        cutoff_ms,
+    ] } }
+}
+
+async fn archive_matching(client: &Arc<Client>, mut filter: Document, days: i64) -> Result<u64, String> {
+    let now = Utc::now().timestamp_millis();
+    filter.insert("archived", doc! { "$ne": true });
+    // 手动取消归档的演讲不再被自动归档
+    filter.insert("archive_exempt", doc! { "$ne": true });
+    filter.extend(ended_before(now - days * 86_400_000));
+    let result = lecture_collection(client)
+        .update_many(filter, doc! { "$set": { "archived": true, "archived_at": now } }, None)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(result.modified_count)
+}
+
+// 演讲结束 N 天后自动归档；组织可在设置中用 archive_after_days 覆盖，0 表示不自动归档
+pub async fn archive_past_lectures(client: Arc<Client>) -> Result<String, String> {
+    let mut cursor = organization_collection(&client)
+        .find(doc! { "settings.archive_after_days": { "$exists": true } }, None)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut configured = Vec::new();
+    let mut archived = 0;
+    while let Some(org) = cursor.try_next().await.map_err(|e| e.to_string())? {
+        let Ok(oid) = org.get_object_id("_id") else { continue };
+        let org_id = oid.to_hex();
+        let days = org
+            .get_document("settings")
+            .ok()
+            .and_then(|s| s.get_i32("archive_after_days").ok())
+            .unwrap_or(0) as i64;
+        if days > 0 {
+            archived += archive_matching(&client, doc! { "org_id": &org_id }, days).await?;
+        }
+        configured.push(org_id);
+    }
+
+    let default_days = default_archive_after_days();
+    if default_days > 0 {
+        archived += archive_matching(&client, doc! { "org_id": { "$nin": configured } }, default_days).await?;
+    }
+
+    Ok(if archived > 0 { format!("已归档 {} 场演讲", archived) } else { String::new() })
+}
+
+// 组织者可在个人偏好中关闭报告邮件，未设置时默认发送
+fn wants_report_email(user: &Document) -> bool {
+    user.get_document("preferences")
+        .ok()
+        .and_then(|p| p.get_bool("lecture_report_email").ok())
+        .unwrap_or(true)
+}
+
+async fn send_report(client: &Arc<Client>, lecture: &Document) -> Result<&'static str, String> {
+    let organizer = lecture
+        .get_str("organizer_id")
+        .ok()
+        .and_then(|id| ObjectId::parse_str(id).ok())
+        .ok_or("演讲缺少组织者")?;
+    let user = user_collection(client)
+        .find_one(doc! { "_id": organizer }, None)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or("组织者不存在")?;
+    if !wants_report_email(&user) {
+        return Ok("opted_out");
+    }
+    let email = user.get_str("email").map_err(|_| "组织者缺少邮箱")?;
+    let report = report::build(client, lecture).await.map_err(|e| e.to_string())?;
+    let time = UserTime::for_user(&user);
+    MAILER.send(email, &report.subject(), &report.to_text(&time)).await?;
+    Ok("sent")
+}
+
+// 演讲结束（状态为已结束或已过结束时间）后给组织者发送一次汇总报告；
+// 先以 report_sent_at 抢占，发送失败则撤销并计数，超过次数后不再重试
+pub async fn send_lecture_reports(client: Arc<Client>) -> Result<String, String> {
+    let now = Utc::now().timestamp_millis();
+    let coll = lecture_collection(&client);
+    let filter = doc! {
+        "report_sent_at": { "$exists": false },
+        "report_attempts": { "$not": { "$gte": REPORT_MAX_ATTEMPTS } },
+        "start_time": { "$gte": now - REPORT_LOOKBACK_DAYS * 86_400_000 },
+        "status": { "$ne": LectureStatus::Cancelled.as_i32() },
+        "$or": [{ "status": LectureStatus::Ended.as_i32() }, ended_before(now)],
+    };
+    let mut cursor = coll.find(filter, None).await.map_err(|e| e.to_string())?;
+
+    let (mut sent, mut skipped, mut failed) = (0, 0, 0);
+    while let Some(lecture) = cursor.try_next().await.map_err(|e| e.to_string())? {
+        let Ok(oid) = lecture.get_object_id("_id") else { continue };
+        let claimed = coll
+            .update_one(
+                doc! { "_id": oid, "report_sent_at": { "$exists": false } },
+                doc! { "$set": { "report_sent_at": now } },
+                None,
+            )
+            .await
+            .map_err(|e| e.to_string())?;
+        if claimed.modified_count == 0 {
+            continue;
+        }
+        match send_report(&client, &lecture).await {
+            Ok(outcome) => {
+                if outcome == "sent" { sent += 1 } else { skipped += 1 }
+                coll.update_one(doc! { "_id": oid }, doc! { "$set": { "report_status": outcome } }, None)
+                    .await
+                    .map_err(|e| e.to_string())?;
+            }
+            Err(e) => {
+                failed += 1;
+                println!("[job:lecture_reports] 演讲 {} 报告发送失败: {}", oid.to_hex(), e);
+                coll.update_one(
+                    doc! { "_id": oid },
+                    doc! { "$unset": { "report_sent_at": "" }, "$inc": { "report_attempts": 1 } },
+                    None,
+                )
+                .await
+                .map_err(|e| e.to_string())?;
+            }
+        }
+    }
+
+    Ok(if sent + skipped + failed > 0 {
+        format!("报告已发送 {}，按偏好跳过 {}，失败 {}", sent, skipped, failed)
+    } else {
+        String::new()
+    })
+}
+
+// 设置了 default_reminder_minutes 的组织及其提醒时间点（分钟）
+async fn org_reminder_minutes(client: &Arc<Client>) -> Result<HashMap<String, Vec<i64>>, String> {
+    let mut cursor = organization_collection(client)
+        .find(doc! { "settings.default_reminder_minutes.0": { "$exists": true } }, None)
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut minutes = HashMap::new();
+    while let Some(org) = cursor.try_next().await.map_err(|e| e.to_string())? {
+        let Ok(oid) = org.get_object_id("_id") else { continue };
+        let points: Vec<i64> = org
+            .get_document("settings")
+            .ok()
+            .and_then(|s| s.get_array("default_reminder_minutes").ok())
+            .into_iter()
+            .flatten()
+            .filter_map(|m| m.as_i32().map(i64::from).or_else(|| m.as_i64()))
+            .filter(|m| *m > 0)
+            .collect();
+        if !points.is_empty() {
+            minutes.insert(oid.to_hex(), points);
+        }
+    }
+    Ok(minutes)
+}
+
+// 开播前提醒已报名观众与讲者：所属组织设置了 default_reminder_minutes 时在每个时间点各提醒一次，
+// 否则在 lead_minutes 分钟内提醒一次。已提醒的时间点记入 reminders_sent，同时到期的多个时间点只发一条
+pub async fn send_lecture_reminders(client: Arc<Client>) -> Result<String, String> {
+    let lead = config::get().job_param("lecture_reminders", "lead_minutes", DEFAULT_REMINDER_LEAD_MINUTES);
+    let org_minutes = org_reminder_minutes(&client).await?;
+    let horizon = org_minutes.values().flatten().copied().fold(lead, i64::max);
+    let now = Utc::now().timestamp_millis();
+    let coll = lecture_collection(&client);
+    // 只有 reminder_sent_at 的旧数据已按 lead_minutes 提醒过
+    let not_legacy = doc! { "$or": [
+        { "reminders_sent": { "$exists": true } },
+        { "reminder_sent_at": { "$exists": false } },
+    ] };
+    let mut filter = doc! {
+        "status": LectureStatus::Scheduled.as_i32(),
+        "start_time": { "$gt": now, "$lte": now + horizon * 60_000 },
+        "archived": { "$ne": true },
+    };
+    filter.extend(not_legacy.clone());
+    let mut cursor = coll.find(filter, None).await.map_err(|e| e.to_string())?;
+
+    let (mut lectures, mut recipients) = (0, 0);
+    while let Some(lecture) = cursor.try_next().await.map_err(|e| e.to_string())? {
+        let Ok(oid) = lecture.get_object_id("_id") else { continue };
+        let start_time = lecture.get_i64("start_time").unwrap_or(0);
+        let points = lecture
+            .get_str("org_id")
+            .ok()
+            .and_then(|org_id| org_minutes.get(org_id))
+            .map(Vec::as_slice)
+            .unwrap_or(std::slice::from_ref(&lead));
+        let sent: Vec<i64> = lecture
+            .get_array("reminders_sent")
+            .into_iter()
+            .flatten()
+            .filter_map(|m| m.as_i64().or_else(|| m.as_i32().map(i64::from)))
+            .collect();
+        let due: Vec<i64> = points
+            .iter()
+            .copied()
+            .filter(|m| start_time - now <= m * 60_000 && !sent.contains(m))
+            .collect();
+        if due.is_empty() {
+            continue;
+        }
+
+        let mut claim = doc! { "_id": oid, "reminders_sent": { "$nin": &due } };
+        claim.extend(not_legacy.clone());
+        let claimed = coll
+            .update_one(
+                claim,
+                doc! {
+                    "$addToSet": { "reminders_sent": { "$each": &due } },
+                    "$set": { "reminder_sent_at": now },
+                },
+                None,
+            )
+            .await
+            .map_err(|e| e.to_string())?;
+        if claimed.modified_count == 0 {
+            continue;
+        }
+
+        let mut users: Vec<ObjectId> = la_collection(&client)
+            .find(doc! { "lecture_id": oid }, None)
+            .await
+            .map_err(|e| e.to_string())?
+            .try_collect::<Vec<Document>>()
+            .await
+            .map_err(|e| e.to_string())?
+            .iter()
+            .filter_map(|la| la.get_object_id("audience_id").ok())
+            .collect();
+        users.extend(lecture.get_str("speaker_id").ok().and_then(|s| ObjectId::parse_str(s).ok()));
+        users.sort();
+        users.dedup();
+
+        for user_id in users {
+            let payload = doc! {
+                "lecture_id": oid.to_hex(),
+                "topic": lecture.get_str("topic").unwrap_or(""),
+                "start_time": start_time,
+                "minutes_left": (start_time - now) / 60_000,
+            };
+            if let Err(e) = notify::push(&client, user_id, "lecture_reminder", payload).await {
+                println!("[job:lecture_reminders] 提醒 {} 失败: {}", user_id.to_hex(), e);
+                continue;
+            }
+            recipients += 1;
+        }
+        lectures += 1;
+    }
+
+    Ok(if lectures > 0 { format!("已提醒 {} 场演讲共 {} 人", lectures, recipients) } else { String::new() })
+}
+
+// 秒级时间戳对应的最小 ObjectId，用于按创建时间筛选没有 created_at 的旧数据
+fn oid_at(ms: i64) -> ObjectId {
+    let mut bytes = [0u8; 12];
+    bytes[..4].copy_from_slice(&((ms / 1000) as u32).to_be_bytes());
+    ObjectId::from_bytes(bytes)
+}
+
+// 新邀请的默认有效天数，也用于没有 expires_at 的早期邀请
+pub fn invitation_valid_days() -> i64 {
+    config::get().job_param("expire_invitations", "after_days", DEFAULT_INVITATION_EXPIRE_DAYS)
+}
+
+// 待回应的邀请过了 expires_at（早期邀请按创建后 after_days 天），或演讲已开始/结束/取消时标记为已过期，
+// 并通知演讲的组织者。逐条按 status 条件更新，与讲者同时接受时只有一方生效
+pub async fn expire_invitations(client: Arc<Client>) -> Result<String, String> {
+    let now = Utc::now().timestamp_millis();
+    let cutoff = now - invitation_valid_days() * 86_400_000;
+    let coll = invitation_collection(&client);
+
+    // 演讲已开始、结束或取消的，其上待回应的邀请不论是否到期一并过期
+    let pending_lectures = coll
+        .distinct("lecture_id", doc! { "status": 0 }, None)
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut closed = Vec::new();
+    if !pending_lectures.is_empty() {
+        let mut cursor = lecture_collection(&client)
+            .find(
+                doc! {
+                    "_id": { "$in": pending_lectures },
+                    "$or": [
+                        { "status": { "$in": [
+                            LectureStatus::Live.as_i32(),
+                            LectureStatus::Ended.as_i32(),
+                            LectureStatus::Cancelled.as_i32(),
+                        ] } },
+                        { "start_time": { "$lt": now } },
+                    ],
+                },
+                None,
+            )
+            .await
+            .map_err(|e| e.to_string())?;
+        while let Some(lecture) = cursor.try_next().await.map_err(|e| e.to_string())? {
+            closed.extend(lecture.get_object_id("_id").ok());
+        }
+    }
+
+    let filter = doc! {
+        "status": 0,
+        "$or": [
+            { "expires_at": { "$lte": now } },
+            { "expires_at": { "$exists": false }, "created_at": { "$lt": cutoff } },
+            { "expires_at": { "$exists": false }, "created_at": { "$exists": false }, "_id": { "$lt": oid_at(cutoff) } },
+            { "lecture_id": { "$in": &closed } },
+        ],
+    };
+    let expiring: Vec<Document> = coll
+        .find(filter, None)
+        .await
+        .map_err(|e| e.to_string())?
+        .try_collect()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut lectures: HashMap<ObjectId, Option<Document>> = HashMap::new();
+    let (mut stale, mut past, mut notified) = (0, 0, 0);
+    for invite in expiring {
+        let (Ok(id), Ok(lecture_oid)) = (invite.get_object_id("_id"), invite.get_object_id("lecture_id")) else { continue };
+        let result = coll
+            .update_one(
+                doc! { "_id": id, "status": 0 },
+                doc! { "$set": { "status": INVITATION_EXPIRED, "expired_at": now } },
+                None,
+            )
+            .await
+            .map_err(|e| e.to_string())?;
+        if result.modified_count == 0 {
+            continue;
+        }
+        let lecture_closed = closed.contains(&lecture_oid);
+        if lecture_closed { past += 1 } else { stale += 1 }
+
+        if let Entry::Vacant(slot) = lectures.entry(lecture_oid) {
+            slot.insert(
+                lecture_collection(&client)
+                    .find_one(doc! { "_id": lecture_oid }, None)
+                    .await
+                    .map_err(|e| e.to_string())?,
+            );
+        }
+        let Some(lecture) = lectures[&lecture_oid].as_ref() else { continue };
+        let Some(organizer) = lecture.get_str("organizer_id").ok().and_then(|s| ObjectId::parse_str(s).ok()) else { continue };
+        let payload = doc! {
+            "invitation_id": id.to_hex(),
+            "lecture_id": lecture_oid.to_hex(),
+            "topic": lecture.get_str("topic").unwrap_or(""),
+            "speaker_id": invitee_of(&invite).map(|o| o.to_hex()).unwrap_or_default(),
+            "invitee_role": InviteeRole::of(&invite).as_str(),
+            "reason": if lecture_closed { "lecture_started" } else { "timeout" },
+        };
+        match notify::push(&client, organizer, "invitation_expired", payload).await {
+            Ok(_) => notified += 1,
+            Err(e) => println!("[job:expire_invitations] 通知组织者 {} 失败: {}", organizer.to_hex(), e),
+        }
+    }
+
+    let total = stale + past;
+    Ok(if total > 0 {
+        format!("已过期 {} 份邀请（超时 {}，演讲已开始 {}），通知组织者 {} 次", total, stale, past, notified)
+    } else {
+        String::new()
+    })
+}
+
+// 超过结束时间 grace_minutes 分钟仍未结束的演讲自动置为已结束。
+// 未开始直接到已结束不在状态机允许的手动切换之内，只由本任务执行，并以 auto_ended 标记
+pub async fn auto_end_lectures(client: Arc<Client>) -> Result<String, String> {
+    let grace = config::get().job_param("auto_end_lectures", "grace_minutes", DEFAULT_AUTO_END_GRACE_MINUTES);
+    let now = Utc::now().timestamp_millis();
+    let coll = lecture_collection(&client);
+    let mut filter = doc! {
+        "$or": [
+            { "status": { "$in": [LectureStatus::Scheduled.as_i32(), LectureStatus::Live.as_i32()] } },
+            { "status": { "$exists": false } },
+        ],
+    };
+    filter.extend(ended_before(now - grace * 60_000));
+    let mut cursor = coll.find(filter, None).await.map_err(|e| e.to_string())?;
+
+    let mut ended = 0;
+    while let Some(lecture) = cursor.try_next().await.map_err(|e| e.to_string())? {
+        let Ok(oid) = lecture.get_object_id("_id") else { continue };
+        let from = LectureStatus::of(&lecture);
+        let status_filter = match lecture.get_i32("status") {
+            Ok(v) => bson::Bson::from(v),
+            Err(_) => bson::Bson::Document(doc! { "$exists": false }),
+        };
+        if lecture.get_bool("rehearsal").unwrap_or(false) {
+            wipe_rehearsal(&client, oid).await.map_err(|e| e.to_string())?;
+        }
+        let result = coll
+            .update_one(
+                doc! { "_id": oid, "status": status_filter },
+                doc! { "$set": {
+                    "status": LectureStatus::Ended.as_i32(),
+                    "ended_at": now,
+                    "auto_ended": true,
+                    "rehearsal": false,
+                } },
+                None,
+            )
+            .await
+            .map_err(|e| e.to_string())?;
+        if result.modified_count == 0 {
+            continue;
+        }
+        audit::record(
+            &client,
+            None,
+            "lecture.status",
+            &format!("lecture:{}", oid.to_hex()),
+            doc! { "from": from.name(), "to": LectureStatus::Ended.name(), "auto": true },
+        )
+        .await;
+        ended += 1;
+    }
+
+    Ok(if ended > 0 { format!("自动结束 {} 场演讲", ended) } else { String::new() })
+}
+
+// 听众登记离场或演讲结束后，提醒到场者填写反馈。以报名记录上的 feedback_prompted_at 抢占，
+// 每人每场只提醒一次；已提交过反馈的只打标记不提醒。只处理 lookback_hours 内的离场与结束，
+// 避免首次部署时给历史演讲补发
+pub async fn send_feedback_prompts(client: Arc<Client>) -> Result<String, String> {
+    let lookback = config::get().job_param("feedback_prompts", "lookback_hours", DEFAULT_FEEDBACK_PROMPT_LOOKBACK_HOURS);
+    let now = Utc::now().timestamp_millis();
+    let since = now - lookback * 3_600_000;
+    let ended = lecture_collection(&client)
+        .distinct(
+            "_id",
+            doc! { "status": LectureStatus::Ended.as_i32(), "ended_at": { "$gte": since }, "archived": { "$ne": true } },
+            None,
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+    let la = la_collection(&client);
+    let filter = doc! {
+        "is_present": true,
+        "feedback_prompted_at": { "$exists": false },
+        "$or": [
+            { "left_at": { "$gte": since } },
+            { "lecture_id": { "$in": ended } },
+        ],
+    };
+    let records: Vec<Document> = la
+        .find(filter, None)
+        .await
+        .map_err(|e| e.to_string())?
+        .try_collect()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut lectures: HashMap<ObjectId, Option<Document>> = HashMap::new();
+    let (mut sent, mut skipped, mut failed) = (0, 0, 0);
+    for record in records {
+        let (Ok(id), Ok(lecture_oid), Ok(user_id)) =
+            (record.get_object_id("_id"), record.get_object_id("lecture_id"), record.get_object_id("audience_id"))
+        else {
+            continue;
+        };
+        if let Entry::Vacant(slot) = lectures.entry(lecture_oid) {
+            slot.insert(
+                lecture_collection(&client)
+                    .find_one(doc! { "_id": lecture_oid }, None)
+                    .await
+                    .map_err(|e| e.to_string())?,
+            );
+        }
+        // 彩排中的离场不提醒；演讲已删除、归档或取消的不再处理
+        let Some(lecture) = lectures[&lecture_oid].as_ref() else { continue };
+        let status = LectureStatus::of(lecture);
+        if lecture.get_bool("rehearsal").unwrap_or(false) {
+            continue;
+        }
+        let closed = lecture.get_bool("archived").unwrap_or(false) || matches!(status, LectureStatus::Cancelled | LectureStatus::Draft);
+
+        let claimed = la
+            .update_one(
+                doc! { "_id": id, "feedback_prompted_at": { "$exists": false } },
+                doc! { "$set": { "feedback_prompted_at": now } },
+                None,
+            )
+            .await
+            .map_err(|e| e.to_string())?;
+        if claimed.modified_count == 0 {
+            continue;
+        }
+        let submitted = feedback_collection(&client)
+            .find_one(doc! { "lecture_id": lecture_oid, "user_id": user_id }, None)
+            .await
+            .map_err(|e| e.to_string())?
+            .is_some();
+        if closed || submitted {
+            skipped += 1;
+            continue;
+        }
+
+        let payload = doc! {
+            "lecture_id": lecture_oid.to_hex(),
+            "topic": lecture.get_str("topic").unwrap_or(""),
+            "reason": if record.contains_key("left_at") { "checkout" } else { "ended" },
+            "link": format!(
+                "{}/static/lecture-room-audience.html?lecture_id={}#feedback",
+                config::get().public_base_url.trim_end_matches('/'),
+                lecture_oid.to_hex(),
+            ),
+        };
+        if let Err(e) = notify::push(&client, user_id, "feedback_prompt", payload).await {
+            println!("[job:feedback_prompts] 提醒 {} 失败: {}", user_id.to_hex(), e);
+            // 撤销标记，下一轮重试
+            let _ = la
+                .update_one(doc! { "_id": id }, doc! { "$unset": { "feedback_prompted_at": "" } }, None)
+                .await;
+            failed += 1;
+            continue;
+        }
+        sent += 1;
+    }
+
+    Ok(if sent + skipped + failed > 0 {
+        format!("反馈提醒已发送 {}，跳过 {}，失败 {}", sent, skipped, failed)
+    } else {
+        String::new()
+    })
+}
+
+// 上传文件名取自 /static/uploads/<name> 形式的地址
+fn upload_name(url: &str) -> Option<String> {
+    url.strip_prefix("/static/uploads/").filter(|n| !n.contains('/')).map(|n| n.to_string())
+}
+
+// 删除 dir 下未被引用、且修改时间早于 min_age 的文件（不进入子目录），返回删除数量
+async fn remove_unreferenced(dir: &str, referenced: &HashSet<String>, min_age: Duration) -> Result<u64, String> {
+    let mut entries = match tokio::fs::read_dir(dir).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(format!("读取目录 {} 失败: {}", dir, e)),
+    };
+    let mut removed = 0;
+    while let Some(entry) = entries.next_entry().await.map_err(|e| e.to_string())? {
+        let Ok(meta) = entry.metadata().await else { continue };
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !meta.is_file() || name.starts_with('.') || referenced.contains(&name) {
+            continue;
+        }
+        // 刚写入、尚未落库的文件不能算作孤儿
+        let old_enough = meta.modified().ok().and_then(|m| m.elapsed().ok()).is_some_and(|age| age >= min_age);
+        if !old_enough {
+            continue;
+        }
+        match tokio::fs::remove_file(entry.path()).await {
+            Ok(()) => removed += 1,
+            Err(e) => println!("[job:clean_orphan_uploads] 删除 {} 失败: {}", entry.path().display(), e),
+        }
+    }
+    Ok(removed)
+}
+
+// 清理不再被用户头像/背景、组织 logo 或课件记录引用的上传文件
+pub async fn clean_orphan_uploads(client: Arc<Client>) -> Result<String, String> {
+    let hours = config::get().job_param("clean_orphan_uploads", "min_age_hours", DEFAULT_ORPHAN_MIN_AGE_HOURS);
+    let min_age = Duration::from_secs(hours.max(0) as u64 * 3600);
+
+    let mut uploads = HashSet::new();
+    let mut cursor = user_collection(&client)
+        .find(doc! {}, FindOptions::builder().projection(doc! { "avatar": 1, "background": 1 }).build())
+        .await
+        .map_err(|e| e.to_string())?;
+    while let Some(user) = cursor.try_next().await.map_err(|e| e.to_string())? {
+        for key in ["avatar", "background"] {
+            uploads.extend(user.get_str(key).ok().and_then(upload_name));
+        }
+    }
+    let mut cursor = organization_collection(&client)
+        .find(doc! {}, FindOptions::builder().projection(doc! { "settings.logo": 1 }).build())
+        .await
+        .map_err(|e| e.to_string())?;
+    while let Some(org) = cursor.try_next().await.map_err(|e| e.to_string())? {
+        let logo = org.get_document("settings").ok().and_then(|s| s.get_str("logo").ok());
+        uploads.extend(logo.and_then(upload_name));
+    }
+
+    let materials: HashSet<String> = material_collection(&client)
+        .distinct("stored_name", doc! {}, None)
+        .await
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .filter_map(|v| v.as_str().map(|s| s.to_string()))
+        .collect();
+
+    let cfg = config::get();
+    let removed_uploads = remove_unreferenced(&cfg.upload_dir, &uploads, min_age).await?;
+    let removed_materials = remove_unreferenced(&cfg.material_dir, &materials, min_age).await?;
+    let total = removed_uploads + removed_materials;
+    Ok(if total > 0 {
+        format!("已清理孤立文件 {} 个（上传 {}，课件 {}）", total, removed_uploads, removed_materials)
+    } else {
+        String::new()
+    })
+}
@@ -0,0 +1,98 @@
+use bson::{doc, Bson, Document};
+use mongodb::Collection;
+use once_cell::sync::Lazy;
+use rand::Rng;
+use regex::Regex;
+
+// 演讲码格式，部署时通过环境变量配置：
+// LECTURECODE_FORMAT=digits|alnum（默认 digits），LECTURECODE_LENGTH=6~8（默认 6）
+#[derive(Clone, Copy, PartialEq)]
+enum Charset {
+    Digits,
+    Alnum,
+}
+
+struct CodeFormat {
+    charset: Charset,
+    length: usize,
+}
+
+// 去掉易混淆的 0/O、1/I
+const ALNUM_CHARS: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+
+static FORMAT: Lazy<CodeFormat> = Lazy::new(|| {
+    let charset = match std::env::var("LECTURECODE_FORMAT").as_deref() {
+        Ok("alnum") => Charset::Alnum,
+        _ => Charset::Digits,
+    };
+    let length = std::env::var("LECTURECODE_LENGTH")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(6)
+        .clamp(6, 8);
+    CodeFormat { charset, length }
+});
+
+// 可选组织前缀（1~4 位字母数字）加 6~8 位主体；兼容旧的 6 位纯数字码
+static CODE_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(?:[A-Z0-9]{1,4}-)?[A-Z0-9]{6,8}$").unwrap());
+static PREFIX_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[A-Z0-9]{1,4}$").unwrap());
+
+pub fn is_valid_prefix(prefix: &str) -> bool {
+    PREFIX_RE.is_match(prefix)
+}
+
+fn random_code(prefix: Option<&str>) -> String {
+    let mut rng = rand::thread_rng();
+    let body: String = (0..FORMAT.length)
+        .map(|i| match FORMAT.charset {
+            // 首位不为 0，避免旧客户端按整数解析时丢位
+            Charset::Digits if i == 0 => char::from(b'1' + rng.gen_range(0..9)),
+            Charset::Digits => char::from(b'0' + rng.gen_range(0..10)),
+            Charset::Alnum => char::from(ALNUM_CHARS[rng.gen_range(0..ALNUM_CHARS.len())]),
+        })
+        .collect();
+    match prefix {
+        Some(p) => format!("{}-{}", p, body),
+        None => body,
+    }
+}
+
+// 规范化用户输入（去空白、转大写）并校验格式
+pub fn normalize(input: &str) -> Option<String> {
+    let code: String = input.trim().to_uppercase();
+    CODE_RE.is_match(&code).then_some(code)
+}
+
+// 按演讲码查询：迁移完成前旧数据仍以整数存储，需同时匹配
+pub fn lookup_filter(code: &str) -> Document {
+    let mut candidates = vec![Bson::String(code.to_string())];
+    if let Ok(n) = code.parse::<i32>() {
+        candidates.push(Bson::Int32(n));
+    }
+    doc! { "lecturecode": { "$in": candidates } }
+}
+
+pub async fn generate_unique(
+    coll: &Collection<Document>,
+    prefix: Option<&str>,
+) -> mongodb::error::Result<String> {
+    loop {
+        let code = random_code(prefix);
+        if coll.find_one(lookup_filter(&code), None).await?.is_none() {
+            return Ok(code);
+        }
+    }
+}
+
+// 将旧的整数演讲码统一转为字符串，返回修改条数
+pub async fn migrate_integer_codes(coll: &Collection<Document>) -> mongodb::error::Result<u64> {
+    let result = coll
+        .update_many(
+            doc! { "lecturecode": { "$type": ["int", "long"] } },
+            vec![doc! { "$set": { "lecturecode": { "$toString": "$lecturecode" } } }],
+            None,
+        )
+        .await?;
+    Ok(result.modified_count)
+}
@@ -0,0 +1,141 @@
+use crate::error::AppError;
+
+// 演讲状态机。库中仍以 i32 存储，沿用前端已有的取值（0 未开始 / 1 进行中 / -1 已结束），
+// 新增草稿与已取消两个状态：
+//   Draft ─→ Scheduled ─→ Live ─→ Ended
+//     │  ←─     │
+//     └────────→┴─→ Cancelled
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LectureStatus {
+    Draft,
+    Scheduled,
+    Live,
+    Ended,
+    Cancelled,
+}
+
+impl LectureStatus {
+    pub fn from_i32(v: i32) -> Option<LectureStatus> {
+        match v {
+            2 => Some(LectureStatus::Draft),
+            0 => Some(LectureStatus::Scheduled),
+            1 => Some(LectureStatus::Live),
+            -1 => Some(LectureStatus::Ended),
+            -2 => Some(LectureStatus::Cancelled),
+            _ => None,
+        }
+    }
+
+    pub fn as_i32(self) -> i32 {
+        match self {
+            LectureStatus::Draft => 2,
+            LectureStatus::Scheduled => 0,
+            LectureStatus::Live => 1,
+            LectureStatus::Ended => -1,
+            LectureStatus::Cancelled => -2,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LectureStatus::Draft => "draft",
+            LectureStatus::Scheduled => "scheduled",
+            LectureStatus::Live => "live",
+            LectureStatus::Ended => "ended",
+            LectureStatus::Cancelled => "cancelled",
+        }
+    }
+
+    // 库中缺失或无法识别的状态按未开始处理，与旧数据保持一致
+    pub fn of(lecture: &bson::Document) -> LectureStatus {
+        lecture
+            .get_i32("status")
+            .ok()
+            .and_then(LectureStatus::from_i32)
+            .unwrap_or(LectureStatus::Scheduled)
+    }
+
+    // 客户端传入的状态值
+    pub fn parse(v: i32) -> Result<LectureStatus, AppError> {
+        LectureStatus::from_i32(v).ok_or_else(|| {
+            AppError::BadRequest(format!("status 无效: {}（2 草稿 / 0 未开始 / 1 进行中 / -1 已结束 / -2 已取消）", v))
+        })
+    }
+
+    // 开播前（草稿或未开始）才允许彩排、取消等准备类操作
+    pub fn is_upcoming(self) -> bool {
+        matches!(self, LectureStatus::Draft | LectureStatus::Scheduled)
+    }
+
+    pub fn can_transition(self, to: LectureStatus) -> bool {
+        use LectureStatus::*;
+        matches!(
+            (self, to),
+            (Draft, Scheduled)
+                | (Scheduled, Draft)
+                | (Scheduled, Live)
+                | (Live, Ended)
+                | (Draft, Cancelled)
+                | (Scheduled, Cancelled)
+        )
+    }
+
+    pub fn check_transition(self, to: LectureStatus) -> Result<(), AppError> {
+        if self.can_transition(to) {
+            Ok(())
+        } else {
+            Err(AppError::Conflict(format!("演讲状态不能从 {} 变为 {}", self.name(), to.name()))
+                .with_details(serde_json::json!({ "from": self.name(), "to": to.name() })))
+        }
+    }
+
+    // 进入该状态时记录的时间戳字段
+    pub fn timestamp_field(self) -> Option<&'static str> {
+        match self {
+            LectureStatus::Live => Some("started_at"),
+            LectureStatus::Ended => Some("ended_at"),
+            LectureStatus::Cancelled => Some("cancelled_at"),
+            LectureStatus::Draft | LectureStatus::Scheduled => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LectureStatus::{self, *};
+
+    const ALL: [LectureStatus; 5] = [Draft, Scheduled, Live, Ended, Cancelled];
+
+    #[test]
+    fn allows_only_the_documented_transitions() {
+        let allowed = [
+            (Draft, Scheduled),
+            (Scheduled, Draft),
+            (Scheduled, Live),
+            (Live, Ended),
+            (Draft, Cancelled),
+            (Scheduled, Cancelled),
+        ];
+        for from in ALL {
+            for to in ALL {
+                assert_eq!(from.can_transition(to), allowed.contains(&(from, to)), "{:?} -> {:?}", from, to);
+            }
+        }
+    }
+
+    #[test]
+    fn rejected_transition_is_a_conflict() {
+        let err = Live.check_transition(Cancelled).unwrap_err();
+        assert_eq!(err.status(), axum::http::StatusCode::CONFLICT);
+        assert!(Scheduled.check_transition(Live).is_ok());
+    }
+
+    #[test]
+    fn stored_values_round_trip() {
+        for status in ALL {
+            assert_eq!(LectureStatus::from_i32(status.as_i32()), Some(status));
+        }
+        assert!(LectureStatus::parse(7).is_err());
+        assert_eq!(LectureStatus::of(&bson::doc! {}), Scheduled);
+    }
+}
@@ -0,0 +1,40 @@
+use once_cell::sync::Lazy;
+use std::future::Future;
+use std::pin::Pin;
+
+// 邮件发送器：默认只写日志，部署时可替换为 SMTP 或第三方邮件服务实现
+pub trait Mailer: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn send<'a>(
+        &'a self,
+        to: &'a str,
+        subject: &'a str,
+        body: &'a str,
+    ) -> Pin<Box<dyn Future<Output = Result<(), String>> + Send + 'a>>;
+}
+
+pub struct LogMailer;
+
+impl Mailer for LogMailer {
+    fn name(&self) -> &'static str {
+        "log"
+    }
+
+    fn send<'a>(
+        &'a self,
+        to: &'a str,
+        subject: &'a str,
+        body: &'a str,
+    ) -> Pin<Box<dyn Future<Output = Result<(), String>> + Send + 'a>> {
+        Box::pin(async move {
+            if !to.contains('@') {
+                return Err(format!("无效的邮箱地址: {}", to));
+            }
+            println!("[mail] to={} subject={} ({} 字)", to, subject, body.chars().count());
+            Ok(())
+        })
+    }
+}
+
+pub static MAILER: Lazy<Box<dyn Mailer>> = Lazy::new(|| Box::new(LogMailer));
@@ -1,33 +1,97 @@
 
 use axum::{
+    middleware,
     routing::{get, get_service},
     Router,
-    response::{IntoResponse, Redirect},
-    http::StatusCode,
+    response::Redirect,
+    http::{HeaderName, StatusCode},
 };
 use std::net::SocketAddr;
-use std::sync::Arc;
 use tower_http::{
     services::ServeDir,
-    cors::{CorsLayer, Any},
+    cors::{AllowOrigin, Any, CorsLayer},
     normalize_path::NormalizePathLayer,
 };
 
+mod anomaly;
+mod audit;
+mod auth;
+mod breaker;
+mod client_info;
+mod config;
+mod csvexport;
 mod db;
+mod envelope;
+mod error;
+mod ics;
+mod ids;
+mod jobs;
+mod lecturecode;
+mod lifecycle;
+mod mailer;
+mod maintenance;
+mod notify;
+mod orgexport;
+mod pagination;
+mod pdf;
+mod qr;
+mod quota;
+mod ratelimit;
+mod realtime;
+mod report;
+mod request_id;
+mod retry;
+mod scheduler;
+mod selfcheck;
+mod signing;
+mod summary;
+mod tags;
+mod timefmt;
+mod translate;
+mod zipfile;
 mod routes;
 
 use crate::db::get_db;
 use routes::{
-    user, lecture, invitation, feedback, la, discussion,
+    user, lecture, invitation, feedback, la, discussion, admin, organization, apikey, material,
+    banner, embed, notification, time, ws,
 };
 
 #[tokio::main]
 async fn main() {
+    // 加载并校验配置，配置有误时直接退出
+    let cfg = config::init().unwrap_or_else(|e| {
+        eprintln!("配置错误: {}", e);
+        std::process::exit(1);
+    });
+
+    if let Err(e) = ratelimit::init().await {
+        eprintln!("配置错误: {}", e);
+        std::process::exit(1);
+    }
+
     // 获取 MongoDB 客户端（Arc<Client>）
     let client = get_db().await;
+    if let Err(e) = db::verify_access(&client).await {
+        eprintln!("数据库配置错误: {}", e);
+        std::process::exit(1);
+    }
+
+    // 后台创建唯一索引并执行部署自检；失败不阻止启动，但需尽快处理（通常是已有重复数据）
+    let index_client = client.clone();
+    tokio::spawn(async move {
+        match db::init_indexes(&index_client).await {
+            Ok(()) => println!("数据库 {} 索引已就绪", cfg.database_name()),
+            Err(e) => eprintln!("创建数据库索引失败: {}", e),
+        }
+        selfcheck::log(&selfcheck::run(&index_client).await);
+    });
+
+    // 注册后台定时任务
+    jobs::register(client.clone());
 
     // 静态文件服务：/static/* → ./static/*
-    let static_files_service = get_service(ServeDir::new("static"))
+    let static_files_service = get_service(ServeDir::new(&cfg.static_dir))
         .handle_error(|error| async move {
             (
                 StatusCode::INTERNAL_SERVER_ERROR,
@@ -35,41 +99,92 @@ async fn main() {
             )
         });
 
+    // 需要登录的业务路由统一校验 JWT
+    let require_auth = middleware::from_fn_with_state(client.clone(), auth::require_auth);
+
     // 构建路由
-    let app = Router::new()
+    let mut app = Router::new()
         // === API 路由 ===
         .nest("/user", user::router())
-        .nest("/lecture", lecture::router())
-        .nest("/invitation", invitation::router())
-        .nest("/feedback", feedback::router())
-        .nest("/LA", la::router())
-        .nest("/discussion", discussion::router())
+        .nest("/lecture", lecture::router().route_layer(require_auth.clone()))
+        .nest("/invitation", invitation::router().route_layer(require_auth.clone()))
+        .nest("/feedback", feedback::router().route_layer(require_auth.clone()))
+        .nest("/LA", la::router().route_layer(require_auth.clone()))
+        .nest("/discussion", discussion::router().route_layer(require_auth.clone()))
+        .nest("/notification", notification::router().route_layer(require_auth.clone()))
+        // WebSocket 自行鉴权（支持 ?token=），不经过 require_auth
+        .nest("/ws", ws::router())
+        .nest("/org", organization::router().route_layer(require_auth.clone()))
+        .nest("/apikey", apikey::router().route_layer(require_auth.clone()))
+        .nest("/material", material::router().route_layer(require_auth.clone()).merge(material::download_router()))
+        .nest("/embed", embed::router())
+        .nest("/admin", admin::router())
+        .nest("/time", time::router())
+        .nest("/announcements", banner::router())
 
         // === 首页重定向 ===
         .route("/", get(|| async { Redirect::to("/static/login.html") }))
 
         // === 静态资源 ===
-        .nest_service("/static", static_files_service)
+        .nest_service("/static", static_files_service);
+
+    // 上传目录不在 static_dir 下时单独挂载，保持 /static/uploads/ 的访问路径不变
+    let default_upload_dir = std::path::Path::new(&cfg.static_dir).join("uploads");
+    if std::path::Path::new(&cfg.upload_dir) != default_upload_dir {
+        app = app.nest_service("/static/uploads", get_service(ServeDir::new(&cfg.upload_dir)));
+    }
+
+    // 未配置 cors_origins 时允许所有来源（开发环境）
+    let allow_origin = if cfg.cors_origins.is_empty() {
+        AllowOrigin::from(Any)
+    } else {
+        AllowOrigin::list(cfg.cors_origin_values())
+    };
+
+    let app = app
 
         // === 中间件 ===
+        .layer(middleware::from_fn(maintenance::guard))
+        // 数据库熔断期间快速失败，避免请求挂到超时
+        .layer(middleware::from_fn(breaker::guard))
+        .layer(middleware::from_fn_with_state(client.clone(), quota::enforce))
+        .layer(middleware::from_fn(ratelimit::enforce))
         .layer(NormalizePathLayer::trim_trailing_slash())
+        // 可选的统一响应信封，需在 request_id 之内以便写入 meta.request_id
+        .layer(middleware::from_fn(envelope::wrap))
         .layer(
             CorsLayer::new()
-                .allow_origin(Any)     // 开发环境允许所有来源
+                .allow_origin(allow_origin)
                 .allow_methods(Any)
-                .allow_headers(Any),
+                .allow_headers(Any)
+                .expose_headers([
+                    HeaderName::from_static(request_id::REQUEST_ID_HEADER),
+                    HeaderName::from_static("x-ratelimit-limit"),
+                    HeaderName::from_static("x-ratelimit-remaining"),
+                    HeaderName::from_static("x-ratelimit-reset"),
+                    HeaderName::from_static("ratelimit-limit"),
+                    HeaderName::from_static("ratelimit-remaining"),
+                    HeaderName::from_static("ratelimit-reset"),
+                    HeaderName::from_static("ratelimit-policy"),
+                    HeaderName::from_static("retry-after"),
+                    HeaderName::from_static(pagination::TOTAL_COUNT_HEADER),
+                    HeaderName::from_static(pagination::NEXT_PAGE_HEADER),
+                    HeaderName::from_static("link"),
+                ]),
         )
+        // 最外层：为所有响应（包括维护模式拦截）附带关联 ID
+        .layer(middleware::from_fn(request_id::propagate))
 
         // === 注入共享状态（MongoDB Client）===
         .with_state(client);
 
     // 启动服务器
-    let addr = SocketAddr::from(([127, 0, 0, 1], 8000));
+    let addr = cfg.bind_addr();
     println!("服务器已启动: http://{}", addr);
 
     axum::serve(
         tokio::net::TcpListener::bind(addr).await.unwrap(),
-        app,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
     )
     .await
     .unwrap();
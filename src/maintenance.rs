@@ -0,0 +1,62 @@
+use axum::{
+    extract::Request,
+    http::Method,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use once_cell::sync::Lazy;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::error::AppError;
+
+// 只读维护模式：开启后所有写操作返回 503，读操作照常
+// 启动时可通过环境变量 MAINTENANCE_MODE=1 直接开启，运行期由 /admin/maintenance 切换
+static READ_ONLY: Lazy<AtomicBool> = Lazy::new(|| {
+    let on = std::env::var("MAINTENANCE_MODE")
+        .map(|v| matches!(v.trim(), "1" | "true" | "on"))
+        .unwrap_or(false);
+    AtomicBool::new(on)
+});
+
+// 维护开关本身必须始终可写，否则开启后无法关闭；登录相关接口放行，
+// 否则会话在维护期间过期后用户连只读访问都无法恢复
+const EXEMPT_PATHS: &[&str] = &[
+    "/admin/maintenance",
+    "/user/login",
+    "/user/refresh",
+    "/user/logout",
+    "/user/magic_link",
+    "/user/magic_link/callback",
+];
+
+pub fn is_read_only() -> bool {
+    READ_ONLY.load(Ordering::Relaxed)
+}
+
+pub fn set_read_only(on: bool) {
+    READ_ONLY.store(on, Ordering::Relaxed);
+}
+
+fn is_mutating(method: &Method) -> bool {
+    !matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+}
+
+// 去掉末尾的 /，与路由匹配保持一致
+fn is_exempt(path: &str) -> bool {
+    let trimmed = path.trim_end_matches('/');
+    EXEMPT_PATHS.contains(&if trimmed.is_empty() { "/" } else { trimmed })
+}
+
+pub async fn guard(req: Request, next: Next) -> Response {
+    if is_read_only()
+        && is_mutating(req.method())
+        && !is_exempt(req.uri().path())
+    {
+        return (
+            [("Retry-After", "300")],
+            AppError::Unavailable("系统维护中，当前为只读模式，请稍后再试".to_string()),
+        )
+            .into_response();
+    }
+    next.run(req).await
+}
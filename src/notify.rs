@@ -0,0 +1,68 @@
+use bson::{doc, oid::ObjectId, Document};
+use chrono::Utc;
+use mongodb::options::{FindOneAndUpdateOptions, ReturnDocument};
+use mongodb::Client;
+use std::sync::Arc;
+
+use crate::db::notification_collection;
+
+// 摘要中保留的最近事件条数
+const DIGEST_MAX_ITEMS: i32 = 20;
+
+// 站内通知：写入 notifications 集合，前端按 user_id 拉取未读。
+// 同一用户同类通知在合并窗口内（自第一条起算）且仍未读时合并为一条摘要：
+// count 为事件总数，payload 为最新一条，items 保留最近若干条
+pub async fn push(
+    client: &Arc<Client>,
+    user_id: ObjectId,
+    kind: &str,
+    payload: Document,
+) -> mongodb::error::Result<ObjectId> {
+    let coll = notification_collection(client);
+    let now = Utc::now().timestamp_millis();
+    let window_secs = crate::config::get().notify_digest_window(kind);
+
+    if window_secs == 0 {
+        let result = coll
+            .insert_one(
+                doc! {
+                    "user_id": user_id,
+                    "kind": kind,
+                    "payload": &payload,
+                    "items": [&payload],
+                    "count": 1,
+                    "read": false,
+                    "created_at": now,
+                    "updated_at": now,
+                },
+                None,
+            )
+            .await?;
+        return Ok(result.inserted_id.as_object_id().unwrap_or_default());
+    }
+
+    // 窗口内没有可合并的未读通知时新建一条
+    let options = FindOneAndUpdateOptions::builder()
+        .upsert(true)
+        .sort(doc! { "created_at": -1 })
+        .return_document(ReturnDocument::After)
+        .build();
+    let notification = coll
+        .find_one_and_update(
+            doc! {
+                "user_id": user_id,
+                "kind": kind,
+                "read": false,
+                "created_at": { "$gte": now - window_secs as i64 * 1000 },
+            },
+            doc! {
+                "$setOnInsert": { "created_at": now },
+                "$set": { "payload": &payload, "updated_at": now },
+                "$inc": { "count": 1 },
+                "$push": { "items": { "$each": [&payload], "$slice": -DIGEST_MAX_ITEMS } },
+            },
+            options,
+        )
+        .await?;
+    Ok(notification.and_then(|n| n.get_object_id("_id").ok()).unwrap_or_default())
+}
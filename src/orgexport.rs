@@ -0,0 +1,419 @@
+use bson::{doc, oid::ObjectId, Document};
+use chrono::Utc;
+use futures_util::TryStreamExt;
+use mongodb::options::{FindOneAndUpdateOptions, FindOptions, ReturnDocument};
+use mongodb::Client;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use crate::db::{
+    discussion_collection, feedback_collection, la_collection, lecture_collection, org_export_collection, user_collection,
+};
+use crate::lifecycle::LectureStatus;
+use crate::routes::admin::{count, counts_by_lecture};
+use crate::timefmt::UserTime;
+use crate::zipfile::ZipWriter;
+use crate::{config, csvexport, signing};
+
+// 组织学期数据导出：管理员提交的导出请求存入 org_exports 排队，后台任务逐个取出，
+// 把时间范围内的演讲、出勤明细、反馈与讨论统计打包成 ZIP 写入 export_dir，完成后凭签名链接下载。
+// 状态：pending 排队 / running 生成中 / done 可下载 / failed 失败 / expired 文件已清理
+pub const STATUS_PENDING: &str = "pending";
+pub const STATUS_RUNNING: &str = "running";
+pub const STATUS_DONE: &str = "done";
+pub const STATUS_FAILED: &str = "failed";
+pub const STATUS_EXPIRED: &str = "expired";
+
+// 生成中超过这么久视为进程中途退出，重新排队
+const STALE_RUNNING_MINUTES: i64 = 30;
+const DEFAULT_RETENTION_DAYS: i64 = 7;
+
+pub fn file_path(export_oid: ObjectId) -> String {
+    format!("{}/{}.zip", config::get().export_dir, export_oid.to_hex())
+}
+
+pub fn download_payload(export_hex: &str, expires: i64) -> String {
+    format!("org_export:{}:{}", export_hex, expires)
+}
+
+pub fn file_name(export: &Document) -> String {
+    let time = UserTime::default();
+    let range = |field: &str| time.iso(export.get_i64(field).unwrap_or(0)).chars().take(10).collect::<String>();
+    format!("org-{}-{}_{}.zip", export.get_str("org_id").unwrap_or(""), range("from"), range("to"))
+}
+
+fn ratio(part: i64, whole: i64) -> String {
+    if whole > 0 { format!("{:.3}", part as f64 / whole as f64) } else { String::new() }
+}
+
+fn flag(doc: &Document, field: &str) -> String {
+    doc.get_bool(field).unwrap_or(false).to_string()
+}
+
+fn millis(doc: &Document, field: &str) -> Option<i64> {
+    match doc.get(field)? {
+        bson::Bson::Int64(v) => Some(*v),
+        bson::Bson::DateTime(d) => Some(d.timestamp_millis()),
+        _ => None,
+    }
+}
+
+// 按 lecture_id 汇总讨论：消息数、提问数、回复数、参与人数与点赞总数，不含彩排消息
+async fn discussion_stats(client: &Arc<Client>, lecture_ids: &[ObjectId]) -> mongodb::error::Result<HashMap<ObjectId, Document>> {
+    let pipeline = vec![
+        doc! { "$match": { "lecture_id": { "$in": lecture_ids }, "rehearsal": { "$ne": true } } },
+        doc! { "$group": {
+            "_id": "$lecture_id",
+            "messages": { "$sum": 1 },
+            "questions": { "$sum": { "$cond": [{ "$eq": ["$is_question", true] }, 1, 0] } },
+            "replies": { "$sum": { "$cond": [{ "$gt": ["$parent_id", null] }, 1, 0] } },
+            "hidden": { "$sum": { "$cond": [{ "$eq": ["$hidden", true] }, 1, 0] } },
+            "authors": { "$addToSet": "$user_id" },
+            "upvotes": { "$sum": { "$ifNull": ["$reactions.upvote", 0] } },
+        } },
+        doc! { "$set": { "authors": { "$size": "$authors" } } },
+    ];
+    let rows: Vec<Document> = discussion_collection(client).aggregate(pipeline, None).await?.try_collect().await?;
+    Ok(rows.into_iter().filter_map(|row| Some((row.get_object_id("_id").ok()?, row))).collect())
+}
+
+fn stat(row: Option<&Document>, field: &str) -> i64 {
+    match row.and_then(|r| r.get(field)) {
+        Some(bson::Bson::Int32(v)) => *v as i64,
+        Some(bson::Bson::Int64(v)) => *v,
+        _ => 0,
+    }
+}
+
+async fn usernames(client: &Arc<Client>, ids: HashSet<ObjectId>) -> mongodb::error::Result<HashMap<ObjectId, String>> {
+    if ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let ids: Vec<ObjectId> = ids.into_iter().collect();
+    let options = FindOptions::builder().projection(doc! { "username": 1 }).build();
+    let users: Vec<Document> = user_collection(client).find(doc! { "_id": { "$in": ids } }, options).await?.try_collect().await?;
+    Ok(users
+        .iter()
+        .filter_map(|u| Some((u.get_object_id("_id").ok()?, u.get_str("username").unwrap_or("").to_string())))
+        .collect())
+}
+
+// 生成压缩包内容，返回 (ZIP 字节, 各项条数)
+async fn build(client: &Arc<Client>, export: &Document) -> Result<(Vec<u8>, Document), String> {
+    let org_id = export.get_str("org_id").map_err(|_| "导出记录缺少 org_id")?;
+    let (from, to) = (export.get_i64("from").unwrap_or(0), export.get_i64("to").unwrap_or(0));
+    let time = UserTime::default();
+    let err = |e: mongodb::error::Error| e.to_string();
+
+    let lectures: Vec<Document> = lecture_collection(client)
+        .find(
+            doc! {
+                "org_id": org_id,
+                "start_time": { "$gte": from, "$lt": to },
+                "status": { "$ne": LectureStatus::Draft.as_i32() },
+            },
+            FindOptions::builder().sort(doc! { "start_time": 1 }).build(),
+        )
+        .await
+        .map_err(err)?
+        .try_collect()
+        .await
+        .map_err(err)?;
+    let lecture_ids: Vec<ObjectId> = lectures.iter().filter_map(|l| l.get_object_id("_id").ok()).collect();
+    let topics: HashMap<ObjectId, &str> = lectures
+        .iter()
+        .filter_map(|l| Some((l.get_object_id("_id").ok()?, l.get_str("topic").unwrap_or(""))))
+        .collect();
+    let topic = |oid: Option<ObjectId>| oid.and_then(|o| topics.get(&o).copied()).unwrap_or("").to_string();
+
+    let records: Vec<Document> = la_collection(client)
+        .find(
+            doc! { "lecture_id": { "$in": &lecture_ids } },
+            FindOptions::builder().sort(doc! { "lecture_id": 1, "joined_at": 1 }).build(),
+        )
+        .await
+        .map_err(err)?
+        .try_collect()
+        .await
+        .map_err(err)?;
+    let feedback: Vec<Document> = feedback_collection(client)
+        .find(
+            doc! { "lecture_id": { "$in": &lecture_ids }, "rehearsal": { "$ne": true } },
+            FindOptions::builder().sort(doc! { "lecture_id": 1, "created_at": 1 }).build(),
+        )
+        .await
+        .map_err(err)?
+        .try_collect()
+        .await
+        .map_err(err)?;
+    let la_counts = counts_by_lecture(la_collection(client), doc! {}, &lecture_ids, doc! {
+        "present": { "$eq": ["$is_present", true] },
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+    let feedback_counts = counts_by_lecture(feedback_collection(client), doc! { "rehearsal": { "$ne": true } }, &lecture_ids, doc! {})
+        .await
+        .map_err(|e| e.to_string())?;
+    let discussions = discussion_stats(client, &lecture_ids).await.map_err(err)?;
+
+    let mut people: HashSet<ObjectId> = records.iter().filter_map(|r| r.get_object_id("audience_id").ok()).collect();
+    for lecture in &lectures {
+        for field in ["organizer_id", "speaker_id"] {
+            people.extend(lecture.get_str(field).ok().and_then(|s| ObjectId::parse_str(s).ok()));
+        }
+    }
+    let names = usernames(client, people).await.map_err(err)?;
+    let name_of = |hex: &str| {
+        ObjectId::parse_str(hex).ok().and_then(|o| names.get(&o).cloned()).unwrap_or_default()
+    };
+
+    let mut totals: HashMap<&str, i64> = HashMap::new();
+    let mut lecture_rows = Vec::with_capacity(lectures.len());
+    let mut discussion_rows = Vec::with_capacity(lectures.len());
+    for lecture in &lectures {
+        let Ok(oid) = lecture.get_object_id("_id") else { continue };
+        let (la_row, fb_row, disc_row) = (la_counts.get(&oid), feedback_counts.get(&oid), discussions.get(&oid));
+        let (registered, present) = (count(la_row, "total"), count(la_row, "present"));
+        let status = LectureStatus::of(lecture);
+        for (field, n) in [
+            ("lectures", 1),
+            ("cancelled", (status == LectureStatus::Cancelled) as i64),
+            ("registered", registered),
+            ("present", present),
+            ("feedback", count(fb_row, "total")),
+            ("messages", stat(disc_row, "messages")),
+        ] {
+            *totals.entry(field).or_insert(0) += n;
+        }
+        let organizer = lecture.get_str("organizer_id").unwrap_or("");
+        let speaker = lecture.get_str("speaker_id").unwrap_or("");
+        lecture_rows.push(vec![
+            oid.to_hex(),
+            lecture.get_str("topic").unwrap_or("").to_string(),
+            time.iso(lecture.get_i64("start_time").unwrap_or(0)),
+            lecture.get_i32("duration").unwrap_or(0).to_string(),
+            status.name().to_string(),
+            name_of(organizer),
+            name_of(speaker),
+            lecture.get_i32("capacity").map(|c| c.to_string()).unwrap_or_default(),
+            registered.to_string(),
+            present.to_string(),
+            ratio(present, registered),
+            count(fb_row, "total").to_string(),
+            stat(disc_row, "messages").to_string(),
+        ]);
+        discussion_rows.push(vec![
+            oid.to_hex(),
+            lecture.get_str("topic").unwrap_or("").to_string(),
+            stat(disc_row, "messages").to_string(),
+            stat(disc_row, "questions").to_string(),
+            stat(disc_row, "replies").to_string(),
+            stat(disc_row, "authors").to_string(),
+            stat(disc_row, "upvotes").to_string(),
+            stat(disc_row, "hidden").to_string(),
+        ]);
+    }
+
+    let attendance_rows: Vec<Vec<String>> = records
+        .iter()
+        .map(|r| {
+            let lecture_oid = r.get_object_id("lecture_id").ok();
+            let audience = r.get_object_id("audience_id").ok();
+            let at = |field: &str| millis(r, field).map(|ms| time.iso(ms)).unwrap_or_default();
+            vec![
+                lecture_oid.map(|o| o.to_hex()).unwrap_or_default(),
+                topic(lecture_oid),
+                audience.map(|o| o.to_hex()).unwrap_or_default(),
+                audience.and_then(|o| names.get(&o).cloned()).unwrap_or_default(),
+                at("joined_at"),
+                flag(r, "is_present"),
+                at("checked_in_at"),
+                at("left_at"),
+                r.get_str("checkin_method").unwrap_or("").to_string(),
+                flag(r, "from_waitlist"),
+            ]
+        })
+        .collect();
+    // 反馈不带提交者，导出后可直接分享给讲者
+    let feedback_rows: Vec<Vec<String>> = feedback
+        .iter()
+        .map(|f| {
+            let lecture_oid = f.get_object_id("lecture_id").ok();
+            vec![
+                lecture_oid.map(|o| o.to_hex()).unwrap_or_default(),
+                topic(lecture_oid),
+                millis(f, "created_at").map(|ms| time.iso(ms)).unwrap_or_default(),
+                f.get_i32("rating").map(|r| r.to_string()).unwrap_or_default(),
+                flag(f, "too_fast"),
+                flag(f, "too_slow"),
+                flag(f, "boring"),
+                flag(f, "bad_question_quality"),
+                f.get_str("other").unwrap_or("").to_string(),
+            ]
+        })
+        .collect();
+
+    let get = |f: &str| totals.get(f).copied().unwrap_or(0);
+    let summary = serde_json::json!({
+        "org_id": org_id,
+        "org_name": export.get_str("org_name").unwrap_or(""),
+        "from": from,
+        "to": to,
+        "timezone": config::get().default_timezone,
+        "generated_at": Utc::now().timestamp_millis(),
+        "lectures": get("lectures"),
+        "cancelled": get("cancelled"),
+        "registered": get("registered"),
+        "present": get("present"),
+        "attendance_rate": (get("registered") > 0).then(|| get("present") as f64 / get("registered") as f64),
+        "feedback": get("feedback"),
+        "discussion_messages": get("messages"),
+    });
+
+    let mut zip = ZipWriter::new();
+    zip.add("summary.json", serde_json::to_string_pretty(&summary).unwrap_or_default().as_bytes())?;
+    zip.add(
+        "lectures.csv",
+        &csvexport::to_bytes(
+            &[
+                "lecture_id", "topic", "start_time", "duration", "status", "organizer", "speaker", "capacity",
+                "registered", "present", "attendance_rate", "feedback", "discussion_messages",
+            ],
+            &lecture_rows,
+        ),
+    )?;
+    zip.add(
+        "attendance.csv",
+        &csvexport::to_bytes(
+            &[
+                "lecture_id", "topic", "audience_id", "username", "joined_at", "is_present", "checked_in_at", "left_at",
+                "checkin_method", "from_waitlist",
+            ],
+            &attendance_rows,
+        ),
+    )?;
+    zip.add(
+        "feedback.csv",
+        &csvexport::to_bytes(
+            &["lecture_id", "topic", "created_at", "rating", "too_fast", "too_slow", "boring", "bad_question_quality", "other"],
+            &feedback_rows,
+        ),
+    )?;
+    zip.add(
+        "discussions.csv",
+        &csvexport::to_bytes(
+            &["lecture_id", "topic", "messages", "questions", "replies", "authors", "upvotes", "hidden"],
+            &discussion_rows,
+        ),
+    )?;
+    let counts = doc! {
+        "lectures": lecture_rows.len() as i64,
+        "attendance": attendance_rows.len() as i64,
+        "feedback": feedback_rows.len() as i64,
+    };
+    Ok((zip.finish(), counts))
+}
+
+// 排队中的导出逐个生成；同时清理过期的压缩包
+pub async fn run_pending(client: Arc<Client>) -> Result<String, String> {
+    let coll = org_export_collection(&client);
+    let now = Utc::now().timestamp_millis();
+    let dir = &config::get().export_dir;
+    tokio::fs::create_dir_all(dir).await.map_err(|e| format!("{} 无法创建: {}", dir, e))?;
+
+    coll.update_many(
+        doc! { "status": STATUS_RUNNING, "started_at": { "$lt": now - STALE_RUNNING_MINUTES * 60_000 } },
+        doc! { "$set": { "status": STATUS_PENDING } },
+        None,
+    )
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let mut finished = 0;
+    let mut failed = 0;
+    let claim = FindOneAndUpdateOptions::builder()
+        .sort(doc! { "created_at": 1 })
+        .return_document(ReturnDocument::After)
+        .build();
+    while let Some(export) = coll
+        .find_one_and_update(
+            doc! { "status": STATUS_PENDING },
+            doc! { "$set": { "status": STATUS_RUNNING, "started_at": Utc::now().timestamp_millis() } },
+            claim.clone(),
+        )
+        .await
+        .map_err(|e| e.to_string())?
+    {
+        let Ok(oid) = export.get_object_id("_id") else { continue };
+        let result = match build(&client, &export).await {
+            Ok((bytes, counts)) => {
+                let path = file_path(oid);
+                match tokio::fs::write(&path, &bytes).await {
+                    Ok(()) => Ok(doc! {
+                        "status": STATUS_DONE,
+                        "size": bytes.len() as i64,
+                        "counts": counts,
+                    }),
+                    Err(e) => Err(format!("写入 {} 失败: {}", path, e)),
+                }
+            }
+            Err(e) => Err(e),
+        };
+        let mut set_doc = result.unwrap_or_else(|e| {
+            println!("[job:org_exports] 导出 {} 失败: {}", oid.to_hex(), e);
+            doc! { "status": STATUS_FAILED, "error": e }
+        });
+        if set_doc.get_str("status") == Ok(STATUS_DONE) { finished += 1 } else { failed += 1 }
+        set_doc.insert("finished_at", Utc::now().timestamp_millis());
+        coll.update_one(doc! { "_id": oid }, doc! { "$set": set_doc }, None)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    let days = config::get().job_param("org_exports", "retention_days", DEFAULT_RETENTION_DAYS);
+    let expired: Vec<Document> = coll
+        .find(doc! { "status": STATUS_DONE, "finished_at": { "$lt": now - days * 86_400_000 } }, None)
+        .await
+        .map_err(|e| e.to_string())?
+        .try_collect()
+        .await
+        .map_err(|e| e.to_string())?;
+    for export in &expired {
+        let Ok(oid) = export.get_object_id("_id") else { continue };
+        if let Err(e) = tokio::fs::remove_file(file_path(oid)).await {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                println!("[job:org_exports] 删除 {} 失败: {}", file_path(oid), e);
+                continue;
+            }
+        }
+        coll.update_one(doc! { "_id": oid }, doc! { "$set": { "status": STATUS_EXPIRED } }, None)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    let mut parts = Vec::new();
+    if finished > 0 {
+        parts.push(format!("完成 {} 个导出", finished));
+    }
+    if failed > 0 {
+        parts.push(format!("{} 个导出失败", failed));
+    }
+    if !expired.is_empty() {
+        parts.push(format!("清理 {} 个过期导出", expired.len()));
+    }
+    Ok(parts.join("，"))
+}
+
+// 供状态查询使用的签名下载链接
+pub fn signed_url(export_hex: &str, ttl_secs: i64) -> (String, i64) {
+    let expires = Utc::now().timestamp() + ttl_secs;
+    let sig = signing::sign(&download_payload(export_hex, expires));
+    let url = format!(
+        "{}/admin/exports/{}/download?expires={}&sig={}",
+        config::get().public_base_url.trim_end_matches('/'),
+        export_hex,
+        expires,
+        sig
+    );
+    (url, expires)
+}
@@ -0,0 +1,131 @@
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, OriginalUri, Query},
+    http::{header, request::Parts, HeaderName, HeaderValue, Uri},
+    response::{IntoResponse, Json, Response},
+};
+use bson::Document;
+use mongodb::options::FindOptions;
+use serde::{Deserialize, Serialize};
+
+use crate::envelope::Pagination;
+use crate::error::AppError;
+
+// 列表接口的统一分页：?page=1&limit=50，page 从 1 开始
+pub const DEFAULT_LIMIT: u64 = 50;
+pub const MAX_LIMIT: u64 = 200;
+
+pub const TOTAL_COUNT_HEADER: &str = "x-total-count";
+pub const NEXT_PAGE_HEADER: &str = "x-next-page";
+
+#[derive(Deserialize)]
+struct RawPage {
+    page: Option<String>,
+    limit: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct PageParams {
+    pub page: u64,
+    pub limit: u64,
+    // 原始请求地址（嵌套路由前缀未剥离），用于生成 Link 响应头
+    uri: Uri,
+}
+
+fn parse_positive(raw: Option<&str>, field: &str, default: u64) -> Result<u64, AppError> {
+    match raw.map(str::trim).filter(|s| !s.is_empty()) {
+        None => Ok(default),
+        Some(s) => s
+            .parse::<u64>()
+            .ok()
+            .filter(|n| *n >= 1)
+            .ok_or_else(|| AppError::BadRequest(format!("{} 必须为正整数", field))),
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for PageParams {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let Query(raw) = Query::<RawPage>::try_from_uri(&parts.uri)
+            .map_err(|_| AppError::BadRequest("分页参数无效".to_string()))?;
+        let page = parse_positive(raw.page.as_deref(), "page", 1)?;
+        let limit = parse_positive(raw.limit.as_deref(), "limit", DEFAULT_LIMIT)?.min(MAX_LIMIT);
+        let uri = match parts.extensions.get::<OriginalUri>() {
+            Some(OriginalUri(uri)) => uri.clone(),
+            None => parts.uri.clone(),
+        };
+        Ok(PageParams { page, limit, uri })
+    }
+}
+
+// 同一地址换成指定页，其余查询参数原样保留
+fn page_url(uri: &Uri, page: u64, limit: u64) -> String {
+    let mut pairs: Vec<&str> = uri
+        .query()
+        .unwrap_or("")
+        .split('&')
+        .filter(|p| !p.is_empty())
+        .filter(|p| {
+            let key = p.split('=').next().unwrap_or("");
+            key != "page" && key != "limit"
+        })
+        .collect();
+    let paging = format!("page={}&limit={}", page, limit);
+    pairs.push(&paging);
+    format!("{}?{}", uri.path(), pairs.join("&"))
+}
+
+impl PageParams {
+    pub fn skip(&self) -> u64 {
+        (self.page - 1) * self.limit
+    }
+
+    // 分页必须有确定的排序，否则翻页时可能重复或遗漏
+    pub fn find_options(&self, sort: Document) -> FindOptions {
+        FindOptions::builder()
+            .sort(sort)
+            .skip(self.skip())
+            .limit(self.limit as i64)
+            .build()
+    }
+
+    // 响应体保持数组不变，分页信息放在响应头；信封模式下同时写入 meta.pagination
+    pub fn respond<T: Serialize>(&self, items: Vec<T>, total: u64) -> Response {
+        self.annotate(Json(items).into_response(), total)
+    }
+
+    // RFC 8288 Link：first / last 总是给出，prev / next 仅在存在时给出；空列表的 last 为第 1 页
+    fn link(&self, total: u64) -> String {
+        let last = total.div_ceil(self.limit).max(1);
+        let mut rels = vec![(1, "first")];
+        if self.page > 1 {
+            rels.push(((self.page - 1).min(last), "prev"));
+        }
+        if self.page < last {
+            rels.push((self.page + 1, "next"));
+        }
+        rels.push((last, "last"));
+        rels.iter()
+            .map(|(page, rel)| format!("<{}>; rel=\"{}\"", page_url(&self.uri, *page, self.limit), rel))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    // 响应体沿用既有结构（如 {"items": [...]}）时，只补上分页响应头与 meta
+    pub fn annotate(&self, mut resp: Response, total: u64) -> Response {
+        let headers = resp.headers_mut();
+        headers.insert(HeaderName::from_static(TOTAL_COUNT_HEADER), HeaderValue::from(total));
+        headers.insert(HeaderName::from_static("x-page"), HeaderValue::from(self.page));
+        headers.insert(HeaderName::from_static("x-limit"), HeaderValue::from(self.limit));
+        if self.page * self.limit < total {
+            headers.insert(HeaderName::from_static(NEXT_PAGE_HEADER), HeaderValue::from(self.page + 1));
+        }
+        if let Ok(link) = HeaderValue::from_str(&self.link(total)) {
+            headers.insert(header::LINK, link);
+        }
+        resp.extensions_mut().insert(Pagination { page: self.page, limit: self.limit, total });
+        resp
+    }
+}
@@ -0,0 +1,97 @@
+// 极简 PDF 生成：纯文本分页，使用阅读器内置的 STSong-Light CJK 字体（无需嵌入字体文件）
+
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 50.0;
+const LINE_HEIGHT: f32 = 18.0;
+
+pub struct Line {
+    pub text: String,
+    pub size: f32,
+}
+
+impl Line {
+    pub fn new(text: impl Into<String>, size: f32) -> Self {
+        Line { text: text.into(), size }
+    }
+}
+
+// UniGB-UCS2-H 编码：每个字符按 UCS-2 大端写成十六进制，超出 BMP 的字符以 ? 代替
+fn encode_text(text: &str) -> String {
+    text.chars()
+        .map(|c| {
+            let code = if (c as u32) <= 0xFFFF { c as u32 } else { '?' as u32 };
+            format!("{:04X}", code)
+        })
+        .collect()
+}
+
+fn page_content(lines: &[Line]) -> String {
+    let mut out = String::new();
+    let mut y = PAGE_HEIGHT - MARGIN;
+    for line in lines {
+        y -= LINE_HEIGHT.max(line.size * 1.4);
+        out.push_str(&format!(
+            "BT /F1 {} Tf {} {} Td <{}> Tj ET\n",
+            line.size,
+            MARGIN,
+            y,
+            encode_text(&line.text)
+        ));
+    }
+    out
+}
+
+pub fn render(lines: Vec<Line>) -> Vec<u8> {
+    let per_page = ((PAGE_HEIGHT - 2.0 * MARGIN) / LINE_HEIGHT) as usize - 1;
+    let pages: Vec<&[Line]> = if lines.is_empty() {
+        vec![&[]]
+    } else {
+        lines.chunks(per_page).collect()
+    };
+
+    // 对象编号：1 目录，2 页树，3 字体，4 CID 字体，之后每页占 页对象 + 内容流 两个编号
+    let mut objects: Vec<String> = Vec::new();
+    let kids: Vec<String> = (0..pages.len()).map(|i| format!("{} 0 R", 5 + i * 2)).collect();
+    objects.push("<< /Type /Catalog /Pages 2 0 R >>".to_string());
+    objects.push(format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), pages.len()));
+    objects.push(
+        "<< /Type /Font /Subtype /Type0 /BaseFont /STSong-Light /Encoding /UniGB-UCS2-H /DescendantFonts [4 0 R] >>"
+            .to_string(),
+    );
+    objects.push(
+        "<< /Type /Font /Subtype /CIDFontType0 /BaseFont /STSong-Light /CIDSystemInfo << /Registry (Adobe) /Ordering (GB1) /Supplement 2 >> /FontDescriptor << /Type /FontDescriptor /FontName /STSong-Light /Flags 6 /FontBBox [-25 -254 1000 880] /ItalicAngle 0 /Ascent 880 /Descent -120 /CapHeight 880 /StemV 93 >> >>"
+            .to_string(),
+    );
+    for (i, page) in pages.iter().enumerate() {
+        let content = page_content(page);
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources << /Font << /F1 3 0 R >> >> /Contents {} 0 R >>",
+            PAGE_WIDTH,
+            PAGE_HEIGHT,
+            6 + i * 2
+        ));
+        objects.push(format!("<< /Length {} >>\nstream\n{}endstream", content.len(), content));
+    }
+
+    let mut out = b"%PDF-1.4\n".to_vec();
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (i, obj) in objects.iter().enumerate() {
+        offsets.push(out.len());
+        out.extend_from_slice(format!("{} 0 obj\n{}\nendobj\n", i + 1, obj).as_bytes());
+    }
+    let xref_at = out.len();
+    out.extend_from_slice(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes());
+    for offset in offsets {
+        out.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+    }
+    out.extend_from_slice(
+        format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            xref_at
+        )
+        .as_bytes(),
+    );
+    out
+}
@@ -0,0 +1,31 @@
+use qrcode::{Color, QrCode};
+
+// 二维码渲染为灰度 PNG：每个模块 scale 像素见方，四周留 4 个模块的空白（扫码器需要）
+const QUIET_ZONE: usize = 4;
+
+pub fn png(data: &str, scale: usize) -> Result<Vec<u8>, String> {
+    let code = QrCode::new(data.as_bytes()).map_err(|e| format!("生成二维码失败: {}", e))?;
+    let modules = code.width();
+    let colors = code.to_colors();
+    let side = (modules + QUIET_ZONE * 2) * scale;
+
+    let mut pixels = vec![255u8; side * side];
+    for (i, color) in colors.iter().enumerate() {
+        if *color != Color::Dark {
+            continue;
+        }
+        let (x, y) = ((i % modules + QUIET_ZONE) * scale, (i / modules + QUIET_ZONE) * scale);
+        for row in y..y + scale {
+            pixels[row * side + x..row * side + x + scale].fill(0);
+        }
+    }
+
+    let mut out = Vec::new();
+    let mut encoder = png::Encoder::new(&mut out, side as u32, side as u32);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(|e| format!("写入 PNG 失败: {}", e))?;
+    writer.write_image_data(&pixels).map_err(|e| format!("写入 PNG 失败: {}", e))?;
+    writer.finish().map_err(|e| format!("写入 PNG 失败: {}", e))?;
+    Ok(out)
+}
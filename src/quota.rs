@@ -0,0 +1,117 @@
+use axum::{
+    extract::{Request, State},
+    http::{HeaderMap, HeaderValue},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use bson::{doc, Document};
+use chrono::{Duration, Utc};
+use mongodb::{options::{FindOneAndUpdateOptions, ReturnDocument}, Client};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+
+use crate::db::{api_key_collection, api_usage_collection};
+use crate::error::AppError;
+
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+// 通过校验的 API key 所属用户（hex），供认证中间件识别调用方
+#[derive(Clone)]
+pub struct ApiKeyOwner(pub String);
+// 全局默认配额，可被单个 key 的 daily_quota 覆盖
+pub fn default_daily_quota() -> i64 {
+    crate::config::get().api_key_daily_quota
+}
+
+// 数据库中只保存 key 的 SHA-256 摘要
+pub fn hash_key(raw: &str) -> String {
+    hex::encode(Sha256::digest(raw.as_bytes()))
+}
+
+pub fn usage_day(now: chrono::DateTime<Utc>) -> String {
+    now.format("%Y-%m-%d").to_string()
+}
+
+fn quota_headers(headers: &mut HeaderMap, limit: i64, used: i64, reset: i64) {
+    let pairs = [
+        ("x-ratelimit-limit", limit),
+        ("x-ratelimit-remaining", (limit - used).max(0)),
+        ("x-ratelimit-reset", reset),
+    ];
+    for (name, value) in pairs {
+        if let Ok(v) = HeaderValue::from_str(&value.to_string()) {
+            headers.insert(name, v);
+        }
+    }
+}
+
+async fn record_usage(
+    client: &Arc<Client>,
+    key: &Document,
+    day: &str,
+) -> Result<i64, mongodb::error::Error> {
+    let key_id = key.get_object_id("_id").ok();
+    let options = FindOneAndUpdateOptions::builder()
+        .upsert(true)
+        .return_document(ReturnDocument::After)
+        .build();
+    let usage = api_usage_collection(client)
+        .find_one_and_update(
+            doc! { "key_id": key_id, "day": day },
+            doc! {
+                "$inc": { "count": 1_i64 },
+                "$setOnInsert": { "owner_id": key.get_str("owner_id").unwrap_or("") },
+            },
+            options,
+        )
+        .await?;
+    Ok(usage.and_then(|u| u.get_i64("count").ok()).unwrap_or(1))
+}
+
+// 携带 X-Api-Key 的请求按 key 统计当日调用次数，超出配额返回 429
+pub async fn enforce(State(client): State<Arc<Client>>, mut req: Request, next: Next) -> Response {
+    let raw_key = match req.headers().get(API_KEY_HEADER).and_then(|v| v.to_str().ok()) {
+        Some(k) => k.trim().to_string(),
+        None => return next.run(req).await,
+    };
+
+    let key = match api_key_collection(&client)
+        .find_one(doc! { "key_hash": hash_key(&raw_key), "revoked": { "$ne": true } }, None)
+        .await
+    {
+        Ok(Some(k)) => k,
+        Ok(None) => return AppError::Unauthorized("API key 无效或已吊销".to_string()).into_response(),
+        Err(_) => return AppError::Internal("校验 API key 失败".to_string()).into_response(),
+    };
+
+    let now = Utc::now();
+    let limit = key.get_i64("daily_quota").unwrap_or_else(|_| default_daily_quota());
+    let reset = (now.date_naive() + Duration::days(1))
+        .and_hms_opt(0, 0, 0)
+        .map(|t| t.and_utc().timestamp())
+        .unwrap_or_default();
+
+    let used = match record_usage(&client, &key, &usage_day(now)).await {
+        Ok(n) => n,
+        Err(_) => return AppError::Internal("记录调用次数失败".to_string()).into_response(),
+    };
+
+    if used > limit {
+        let mut resp = AppError::TooManyRequests(format!("今日调用次数已达上限 {}，请于 UTC 零点后重试", limit))
+            .into_response();
+        quota_headers(resp.headers_mut(), limit, used, reset);
+        if let Ok(v) = HeaderValue::from_str(&(reset - now.timestamp()).max(0).to_string()) {
+            resp.headers_mut().insert("retry-after", v);
+        }
+        return resp;
+    }
+
+    // 仅登录后创建的 key 可代表所有者身份；早期无需登录即可创建的 key 只计配额
+    if key.get_bool("owner_verified").unwrap_or(false) {
+        req.extensions_mut()
+            .insert(ApiKeyOwner(key.get_str("owner_id").unwrap_or("").to_string()));
+    }
+    let mut resp = next.run(req).await;
+    quota_headers(resp.headers_mut(), limit, used, reset);
+    resp
+}
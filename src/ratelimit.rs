@@ -0,0 +1,133 @@
+use axum::{
+    extract::{ConnectInfo, Request},
+    http::{HeaderMap, HeaderValue},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::Utc;
+use once_cell::sync::{Lazy, OnceCell};
+use redis::aio::ConnectionManager;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Mutex;
+
+use crate::client_info::client_ip;
+use crate::config::{self, RateLimitRule};
+use crate::error::AppError;
+use crate::quota::{hash_key, API_KEY_HEADER};
+
+// 按路由限流：固定窗口计数，客户端以 API key（如有）或 IP 区分。
+// 配置了 redis_url 时计数放在 Redis 中，多个副本共享同一份额度；
+// 否则（或 Redis 暂时不可用时）退化为进程内计数
+static REDIS: OnceCell<ConnectionManager> = OnceCell::new();
+
+// 进程内计数：key -> (窗口编号, 次数)
+static LOCAL: Lazy<Mutex<HashMap<String, (i64, u64)>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+// 启动时调用；配置了 Redis 却无法连接时拒绝启动，避免各副本悄悄各算各的
+pub async fn init() -> Result<(), String> {
+    let url = &config::get().redis_url;
+    if url.is_empty() {
+        return Ok(());
+    }
+    let client = redis::Client::open(url.as_str()).map_err(|e| format!("redis_url 无效: {}", e))?;
+    let manager = ConnectionManager::new(client)
+        .await
+        .map_err(|e| format!("连接 Redis 失败: {}", e))?;
+    let _ = REDIS.set(manager);
+    println!("限流计数使用 Redis");
+    Ok(())
+}
+
+// 自检用：未配置 Redis 时返回 None
+pub async fn ping() -> Option<Result<(), String>> {
+    let mut conn = REDIS.get()?.clone();
+    Some(redis::cmd("PING").query_async::<_, String>(&mut conn).await.map(|_| ()).map_err(|e| e.to_string()))
+}
+
+async fn redis_hit(manager: &ConnectionManager, key: &str, window_secs: u64) -> redis::RedisResult<u64> {
+    let mut conn = manager.clone();
+    let (count,): (u64,) = redis::pipe()
+        .atomic()
+        .incr(key, 1)
+        .expire(key, window_secs as i64)
+        .ignore()
+        .query_async(&mut conn)
+        .await?;
+    Ok(count)
+}
+
+fn local_hit(key: &str, window: i64) -> u64 {
+    let mut counters = LOCAL.lock().unwrap();
+    // 顺带清理已过期窗口，防止表无限增长
+    if counters.len() > 10_000 {
+        counters.retain(|_, (w, _)| *w >= window);
+    }
+    let entry = counters.entry(key.to_string()).or_insert((window, 0));
+    if entry.0 != window {
+        *entry = (window, 0);
+    }
+    entry.1 += 1;
+    entry.1
+}
+
+// 返回本窗口内（含本次）的请求次数
+async fn hit(prefix: &str, client: &str, rule: &RateLimitRule, window: i64) -> u64 {
+    let key = format!("ratelimit:{}:{}:{}", prefix, client, window);
+    if let Some(manager) = REDIS.get() {
+        match redis_hit(manager, &key, rule.window_secs).await {
+            Ok(count) => return count,
+            Err(e) => println!("[ratelimit] Redis 计数失败，暂用进程内计数: {}", e),
+        }
+    }
+    local_hit(&key, window)
+}
+
+fn client_key(req: &Request) -> String {
+    if let Some(raw) = req.headers().get(API_KEY_HEADER).and_then(|v| v.to_str().ok()) {
+        return format!("key:{}", hash_key(raw.trim()));
+    }
+    let peer = req.extensions().get::<ConnectInfo<SocketAddr>>().map(|c| c.0);
+    format!("ip:{}", client_ip(req.headers(), peer))
+}
+
+// IETF RateLimit 头：Reset 为距窗口结束的秒数
+fn limit_headers(headers: &mut HeaderMap, rule: &RateLimitRule, count: u64, reset: i64) {
+    let pairs = [
+        ("ratelimit-limit", rule.limit.to_string()),
+        ("ratelimit-remaining", rule.limit.saturating_sub(count).to_string()),
+        ("ratelimit-reset", reset.to_string()),
+        ("ratelimit-policy", format!("{};w={}", rule.limit, rule.window_secs)),
+    ];
+    for (name, value) in pairs {
+        if let Ok(v) = HeaderValue::from_str(&value) {
+            headers.insert(name, v);
+        }
+    }
+}
+
+pub async fn enforce(req: Request, next: Next) -> Response {
+    let path = req.uri().path().to_string();
+    let Some((prefix, rule)) = config::get().rate_limit_for(&path) else {
+        return next.run(req).await;
+    };
+
+    let now = Utc::now().timestamp();
+    let window_secs = rule.window_secs as i64;
+    let window = now / window_secs;
+    let reset = (window + 1) * window_secs - now;
+    let count = hit(prefix, &client_key(&req), rule, window).await;
+
+    if count > rule.limit {
+        let mut resp = AppError::TooManyRequests(format!("请求过于频繁，请 {} 秒后重试", reset)).into_response();
+        limit_headers(resp.headers_mut(), rule, count, reset);
+        if let Ok(v) = HeaderValue::from_str(&reset.to_string()) {
+            resp.headers_mut().insert("retry-after", v);
+        }
+        return resp;
+    }
+
+    let mut resp = next.run(req).await;
+    limit_headers(resp.headers_mut(), rule, count, reset);
+    resp
+}
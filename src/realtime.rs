@@ -0,0 +1,108 @@
+use bson::oid::ObjectId;
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+// 演讲房间的实时推送：每场演讲一个广播通道，业务代码在写入成功后调用 publish，
+// 已连接的客户端（WebSocket 等）各自订阅。房间只存在于本进程内，多副本部署时
+// 同一房间的客户端需由负载均衡按 lecture_id 粘滞到同一实例
+const ROOM_BUFFER: usize = 256;
+
+struct Room {
+    tx: broadcast::Sender<String>,
+    // 在线用户及其连接数（同一用户可能开了多个标签页）
+    members: HashMap<ObjectId, usize>,
+}
+
+static ROOMS: Lazy<Mutex<HashMap<ObjectId, Room>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn event(kind: &str, data: serde_json::Value) -> String {
+    serde_json::json!({ "type": kind, "data": data, "at": Utc::now().timestamp_millis() }).to_string()
+}
+
+// 推送事件；房间内没有连接时直接丢弃
+pub fn publish(lecture: ObjectId, kind: &str, data: serde_json::Value) {
+    let rooms = ROOMS.lock().unwrap();
+    if let Some(room) = rooms.get(&lecture) {
+        let _ = room.tx.send(event(kind, data));
+    }
+}
+
+fn presence_of(room: &Room) -> serde_json::Value {
+    let users: Vec<String> = room.members.keys().map(|u| u.to_hex()).collect();
+    serde_json::json!({ "online": users.len(), "users": users })
+}
+
+fn receiver(lecture: ObjectId) -> broadcast::Receiver<String> {
+    let mut rooms = ROOMS.lock().unwrap();
+    rooms
+        .entry(lecture)
+        .or_insert_with(|| Room { tx: broadcast::channel(ROOM_BUFFER).0, members: HashMap::new() })
+        .tx
+        .subscribe()
+}
+
+// 只订阅事件、不计入在线名单（SSE 等只读通道）；丢弃时自动退订
+pub struct Subscription {
+    lecture: ObjectId,
+    rx: Option<broadcast::Receiver<String>>,
+}
+
+impl Subscription {
+    pub async fn recv(&mut self) -> Result<String, RecvError> {
+        match self.rx.as_mut() {
+            Some(rx) => rx.recv().await,
+            None => Err(RecvError::Closed),
+        }
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        drop(self.rx.take());
+        release(&mut ROOMS.lock().unwrap(), self.lecture);
+    }
+}
+
+pub fn subscribe(lecture: ObjectId) -> Subscription {
+    Subscription { lecture, rx: Some(receiver(lecture)) }
+}
+
+// 当前在线人数（按用户去重），房间不存在时为 0
+pub fn online_count(lecture: ObjectId) -> usize {
+    ROOMS.lock().unwrap().get(&lecture).map_or(0, |room| room.members.len())
+}
+
+// 加入房间并广播最新在线名单；返回订阅端与加入后的在线名单
+pub fn join(lecture: ObjectId, user: ObjectId) -> (broadcast::Receiver<String>, serde_json::Value) {
+    let rx = receiver(lecture);
+    let mut rooms = ROOMS.lock().unwrap();
+    let room = rooms.get_mut(&lecture).expect("subscribe 已创建房间");
+    *room.members.entry(user).or_insert(0) += 1;
+    let presence = presence_of(room);
+    let _ = room.tx.send(event("presence", presence.clone()));
+    (rx, presence)
+}
+
+// 交回订阅端后离开房间
+pub fn leave(lecture: ObjectId, user: ObjectId, rx: broadcast::Receiver<String>) {
+    drop(rx);
+    let mut rooms = ROOMS.lock().unwrap();
+    let Some(room) = rooms.get_mut(&lecture) else { return };
+    if let Some(n) = room.members.get_mut(&user) {
+        *n -= 1;
+        if *n == 0 {
+            room.members.remove(&user);
+            let _ = room.tx.send(event("presence", presence_of(room)));
+        }
+    }
+    release(&mut rooms, lecture);
+}
+
+fn release(rooms: &mut HashMap<ObjectId, Room>, lecture: ObjectId) {
+    if rooms.get(&lecture).is_some_and(|r| r.members.is_empty() && r.tx.receiver_count() == 0) {
+        rooms.remove(&lecture);
+    }
+}
@@ -0,0 +1,150 @@
+use bson::{doc, oid::ObjectId, Document};
+use futures_util::TryStreamExt;
+use mongodb::Client;
+use std::sync::Arc;
+
+use crate::db::{discussion_collection, feedback_collection, la_collection, transcript_collection};
+use crate::routes::transcript;
+use crate::summary::{looks_like_question, top_keywords, SUMMARIZER};
+use crate::timefmt::UserTime;
+
+// 演讲结束后的汇总报告：出勤、反馈与讨论概况，供邮件等渠道复用
+pub struct LectureReport {
+    pub topic: String,
+    pub start_time: i64,
+    pub duration: i32,
+    pub registered: u64,
+    pub present: u64,
+    pub feedback_count: u64,
+    pub too_fast: i32,
+    pub too_slow: i32,
+    pub boring: i32,
+    pub bad_question_quality: i32,
+    pub message_count: usize,
+    pub question_count: usize,
+    pub keywords: Vec<(String, usize)>,
+    pub summary: Option<String>,
+    // 现场记录各分节及条数，按分节开始的先后排列
+    pub transcript_sections: Vec<(String, i32)>,
+}
+
+async fn feedback_counts(client: &Arc<Client>, lecture_oid: ObjectId) -> mongodb::error::Result<Document> {
+    let pipeline = vec![
+        doc! { "$match": { "lecture_id": lecture_oid, "rehearsal": { "$ne": true } } },
+        doc! {
+            "$group": {
+                "_id": null,
+                "total": { "$sum": 1 },
+                "too_fast": { "$sum": { "$cond": [{ "$eq": ["$too_fast", true] }, 1, 0] } },
+                "too_slow": { "$sum": { "$cond": [{ "$eq": ["$too_slow", true] }, 1, 0] } },
+                "boring": { "$sum": { "$cond": [{ "$eq": ["$boring", true] }, 1, 0] } },
+                "bad_question_quality": { "$sum": { "$cond": [{ "$eq": ["$bad_question_quality", true] }, 1, 0] } },
+            }
+        },
+    ];
+    let mut cursor = feedback_collection(client).aggregate(pipeline, None).await?;
+    Ok(cursor.try_next().await?.unwrap_or_default())
+}
+
+async fn transcript_sections(client: &Arc<Client>, lecture_oid: ObjectId) -> mongodb::error::Result<Vec<(String, i32)>> {
+    let pipeline = vec![
+        doc! { "$match": { "lecture_id": lecture_oid } },
+        doc! { "$group": { "_id": "$section", "count": { "$sum": 1 }, "first": { "$min": "$created_at" } } },
+        doc! { "$sort": { "first": 1 } },
+    ];
+    let rows: Vec<Document> = transcript_collection(client).aggregate(pipeline, None).await?.try_collect().await?;
+    Ok(rows
+        .iter()
+        .map(|r| (r.get_str("_id").unwrap_or(transcript::DEFAULT_SECTION).to_string(), r.get_i32("count").unwrap_or(0)))
+        .collect())
+}
+
+pub async fn build(client: &Arc<Client>, lecture: &Document) -> mongodb::error::Result<LectureReport> {
+    let lecture_oid = lecture.get_object_id("_id").unwrap_or_default();
+
+    let la = la_collection(client);
+    let registered = la.count_documents(doc! { "lecture_id": lecture_oid }, None).await?;
+    let present = la
+        .count_documents(doc! { "lecture_id": lecture_oid, "is_present": true }, None)
+        .await?;
+
+    let fb = feedback_counts(client, lecture_oid).await?;
+
+    let mut cursor = discussion_collection(client)
+        .find(doc! { "lecture_id": lecture_oid, "rehearsal": { "$ne": true } }, None)
+        .await?;
+    let mut messages = Vec::new();
+    let mut question_count = 0;
+    while let Some(d) = cursor.try_next().await? {
+        let content = d.get_str("content").unwrap_or("").to_string();
+        if d.get_bool("is_question").unwrap_or(false) || looks_like_question(&content) {
+            question_count += 1;
+        }
+        messages.push(content);
+    }
+    let keywords = top_keywords(&messages, 10);
+    let summary = SUMMARIZER.summarize(&messages, &keywords).await;
+    let transcript_sections = transcript_sections(client, lecture_oid).await?;
+
+    Ok(LectureReport {
+        topic: lecture.get_str("topic").unwrap_or("").to_string(),
+        start_time: lecture.get_i64("start_time").unwrap_or(0),
+        duration: lecture.get_i32("duration").unwrap_or(0),
+        registered,
+        present,
+        feedback_count: fb.get_i32("total").unwrap_or(0) as u64,
+        too_fast: fb.get_i32("too_fast").unwrap_or(0),
+        too_slow: fb.get_i32("too_slow").unwrap_or(0),
+        boring: fb.get_i32("boring").unwrap_or(0),
+        bad_question_quality: fb.get_i32("bad_question_quality").unwrap_or(0),
+        message_count: messages.len(),
+        question_count,
+        keywords,
+        summary,
+        transcript_sections,
+    })
+}
+
+impl LectureReport {
+    pub fn subject(&self) -> String {
+        format!("演讲报告：{}", self.topic)
+    }
+
+    // 纯文本邮件正文，时间按收件人的时区与语言展示
+    pub fn to_text(&self, time: &UserTime) -> String {
+        let start = time.datetime(self.start_time);
+        let rate = if self.registered > 0 {
+            format!("{:.0}%", self.present as f64 * 100.0 / self.registered as f64)
+        } else {
+            "-".to_string()
+        };
+        let mut lines = vec![
+            format!("演讲「{}」已结束，以下是本场汇总。", self.topic),
+            String::new(),
+            format!("时间：{}（{} 分钟）", start, self.duration),
+            format!("报名 {} 人，到场 {} 人，到场率 {}", self.registered, self.present, rate),
+            String::new(),
+            format!("反馈 {} 份：", self.feedback_count),
+            format!("  节奏太快 {} / 节奏太慢 {} / 内容枯燥 {} / 问题质量差 {}",
+                self.too_fast, self.too_slow, self.boring, self.bad_question_quality),
+            String::new(),
+            format!("讨论 {} 条，其中提问 {} 条", self.message_count, self.question_count),
+        ];
+        if !self.keywords.is_empty() {
+            let words: Vec<&str> = self.keywords.iter().map(|(w, _)| w.as_str()).collect();
+            lines.push(format!("讨论关键词：{}", words.join("、")));
+        }
+        if let Some(summary) = &self.summary {
+            lines.push(format!("讨论摘要：{}", summary));
+        }
+        if !self.transcript_sections.is_empty() {
+            let total: i32 = self.transcript_sections.iter().map(|(_, n)| n).sum();
+            let sections: Vec<String> = self.transcript_sections.iter().map(|(s, n)| format!("{}（{}）", s, n)).collect();
+            lines.push(String::new());
+            lines.push(format!("现场记录 {} 条：{}", total, sections.join("、")));
+        }
+        lines.push(String::new());
+        lines.push("如不希望再收到演讲报告邮件，可在个人设置中关闭。".to_string());
+        lines.join("\n")
+    }
+}
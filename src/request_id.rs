@@ -0,0 +1,72 @@
+use axum::{
+    extract::Request,
+    http::{HeaderValue, Response},
+    middleware::Next,
+};
+use std::time::Instant;
+use uuid::Uuid;
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+const TRACEPARENT_HEADER: &str = "traceparent";
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+// 当前请求的关联 ID；不在请求上下文中（如后台任务）时返回 None
+pub fn current() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+// 只接受可打印 ASCII 且长度受限的外部 ID，避免日志注入
+fn sanitize(raw: &str) -> Option<String> {
+    let raw = raw.trim();
+    if raw.is_empty() || raw.len() > 128 || !raw.chars().all(|c| c.is_ascii_graphic()) {
+        return None;
+    }
+    Some(raw.to_string())
+}
+
+// W3C traceparent: version-traceid-parentid-flags，取其中的 trace-id
+fn trace_id_from_traceparent(raw: &str) -> Option<String> {
+    let parts: Vec<&str> = raw.trim().split('-').collect();
+    if parts.len() != 4 || parts[1].len() != 32 || !parts[1].chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    Some(parts[1].to_lowercase())
+}
+
+fn header(req: &Request, name: &str) -> Option<String> {
+    req.headers().get(name).and_then(|v| v.to_str().ok()).map(|s| s.to_string())
+}
+
+pub async fn propagate(req: Request, next: Next) -> Response<axum::body::Body> {
+    let traceparent = header(&req, TRACEPARENT_HEADER);
+    let id = header(&req, REQUEST_ID_HEADER)
+        .and_then(|v| sanitize(&v))
+        .or_else(|| traceparent.as_deref().and_then(trace_id_from_traceparent))
+        .unwrap_or_else(|| Uuid::new_v4().simple().to_string());
+
+    let method = req.method().clone();
+    let path = req.uri().path().to_string();
+    let started = Instant::now();
+
+    let mut resp = REQUEST_ID.scope(id.clone(), next.run(req)).await;
+
+    println!(
+        "[{}] {} {} -> {} ({}ms)",
+        id,
+        method,
+        path,
+        resp.status().as_u16(),
+        started.elapsed().as_millis()
+    );
+
+    if let Ok(v) = HeaderValue::from_str(&id) {
+        resp.headers_mut().insert(REQUEST_ID_HEADER, v);
+    }
+    if let Some(tp) = traceparent.and_then(|tp| HeaderValue::from_str(&tp).ok()) {
+        resp.headers_mut().insert(TRACEPARENT_HEADER, tp);
+    }
+    resp
+}
@@ -0,0 +1,90 @@
+use bson::{oid::ObjectId, Document};
+use mongodb::error::{Error, ErrorKind, WriteFailure, RETRYABLE_WRITE_ERROR, TRANSIENT_TRANSACTION_ERROR};
+use mongodb::Collection;
+use rand::Rng;
+use std::future::Future;
+use std::time::Duration;
+
+use crate::db;
+use crate::error::AppError;
+
+// 写操作的瞬时错误重试：网络中断、主节点切换等短暂故障按指数退避加随机抖动重试，
+// 重复键、校验失败等永久错误立即返回。只用于重放结果不变的写入（预先生成 _id 的插入、
+// 按条件的 $set 等），$inc、$push 之类重放会改变结果的写入不要包装
+const MAX_ATTEMPTS: u32 = 3;
+const BASE_DELAY_MS: u64 = 50;
+
+// 主节点切换、节点关闭、网络超时等服务端错误码（与驱动的可重试写入错误码一致）
+const TRANSIENT_CODES: [i32; 12] = [11600, 11602, 10107, 13435, 13436, 189, 91, 7, 6, 89, 9001, 262];
+
+pub fn is_transient(error: &Error) -> bool {
+    if error.contains_label(RETRYABLE_WRITE_ERROR) || error.contains_label(TRANSIENT_TRANSACTION_ERROR) {
+        return true;
+    }
+    match &*error.kind {
+        ErrorKind::Io(_) | ErrorKind::ConnectionPoolCleared { .. } | ErrorKind::ServerSelection { .. } => true,
+        ErrorKind::Command(e) => TRANSIENT_CODES.contains(&e.code),
+        ErrorKind::Write(WriteFailure::WriteConcernError(e)) => TRANSIENT_CODES.contains(&e.code),
+        _ => false,
+    }
+}
+
+// 第 n 次重试前等待 [base/2, base] 毫秒，base 每次翻倍，避免多个请求同时重试
+fn backoff(attempt: u32) -> Duration {
+    let cap = BASE_DELAY_MS << (attempt - 1);
+    Duration::from_millis(rand::thread_rng().gen_range(cap / 2..=cap))
+}
+
+pub async fn with_retry<T, F, Fut>(op: &str, mut f: F) -> mongodb::error::Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = mongodb::error::Result<T>>,
+{
+    let mut attempt = 1;
+    loop {
+        match f().await {
+            Err(e) if attempt < MAX_ATTEMPTS && is_transient(&e) => {
+                let delay = backoff(attempt);
+                println!("[retry] {} 第 {} 次失败，{} 毫秒后重试: {}", op, attempt, delay.as_millis(), e);
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            other => return other,
+        }
+    }
+}
+
+// 插入前生成 _id，重试因此是幂等的：上一次其实已写入、只是响应丢失时，
+// 重试会因 _id 重复失败，此时按成功处理
+pub async fn insert_one(coll: &Collection<Document>, doc: &mut Document) -> mongodb::error::Result<ObjectId> {
+    let id = match doc.get_object_id("_id") {
+        Ok(id) => id,
+        Err(_) => {
+            let id = ObjectId::new();
+            doc.insert("_id", id);
+            id
+        }
+    };
+    let mut attempts = 0;
+    let result = with_retry(&format!("{}.insert", coll.name()), || {
+        attempts += 1;
+        coll.insert_one(&*doc, None)
+    })
+    .await;
+    match result {
+        Ok(_) => Ok(id),
+        Err(e) if attempts > 1 && db::duplicate_key_index(&e).as_deref() == Some("_id_") => Ok(id),
+        Err(e) => Err(e),
+    }
+}
+
+// 重试后仍是瞬时错误时返回 503 提示稍后再试，其余按 message 返回 500
+pub fn db_error(error: Error, message: &str) -> AppError {
+    if is_transient(&error) {
+        println!("[retry] 数据库暂时不可用: {}", error);
+        AppError::Unavailable("数据库暂时不可用，请稍后重试".into())
+    } else {
+        println!("[retry] {}: {}", message, error);
+        AppError::Internal(message.into())
+    }
+}
@@ -0,0 +1,681 @@
+// src/routes/admin.rs
+use axum::{
+    body::{Body, Bytes},
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Json, Response},
+    routing::{get, post},
+    Router,
+};
+use bson::{doc, oid::ObjectId, Document};
+use futures_util::{StreamExt, TryStreamExt};
+use mongodb::options::FindOptions;
+use mongodb::Client;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use tokio_util::io::ReaderStream;
+
+use crate::auth::AuthUser;
+use crate::db::{
+    self, audit_collection, discussion_collection, feedback_collection, la_collection, lecture_collection, org_export_collection,
+    organization_collection, user_collection, waitlist_collection,
+};
+use crate::lifecycle::LectureStatus;
+use crate::timefmt::{parse_time_param, UserTime};
+use crate::{audit, breaker, config, ids, lecturecode, maintenance, orgexport, signing};
+use crate::error::AppError;
+
+type AppState = Arc<Client>;
+
+// ==================== 模型 ====================
+
+#[derive(Deserialize)]
+struct MaintenanceUpdate {
+    read_only: bool,
+}
+
+#[derive(Deserialize)]
+struct AuditExportQuery {
+    from: Option<String>,
+    to: Option<String>,
+    action: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct AnalyticsQuery {
+    // start_time 范围，缺省为最近半年
+    from: Option<String>,
+    to: Option<String>,
+}
+
+// 默认统计区间：约一个学期
+const ANALYTICS_DEFAULT_DAYS: i64 = 183;
+
+#[derive(Deserialize)]
+struct SearchQuery {
+    q: String,
+    // 每类最多返回条数
+    limit: Option<i64>,
+    // 逗号分隔的类型子集：user,lecture,discussion，缺省全部
+    types: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct OrgExportRequest {
+    // start_time 范围，缺省为最近半年至今
+    from: Option<String>,
+    to: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ExportDownloadQuery {
+    expires: i64,
+    sig: String,
+}
+
+// 导出完成后签发的下载链接有效期
+const EXPORT_LINK_TTL_SECS: i64 = 3600;
+
+const SEARCH_TYPES: [&str; 3] = ["user", "lecture", "discussion"];
+// 同时进行的查询数，避免一次搜索占满连接池
+const SEARCH_CONCURRENCY: usize = 2;
+
+// ==================== 工具函数 ====================
+
+// 管理接口通过 X-Admin-Token 与配置中的 admin_token 比对鉴权；未配置时管理接口不可用
+pub(crate) fn check_admin(headers: &HeaderMap) -> Result<(), AppError> {
+    let expected = Some(config::get().admin_token.as_str())
+        .filter(|t| !t.is_empty())
+        .ok_or(AppError::Forbidden("管理接口未启用".to_string()))?;
+    let provided = headers
+        .get("x-admin-token")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+    if provided != expected {
+        return Err(AppError::Unauthorized("管理员令牌无效".to_string()));
+    }
+    Ok(())
+}
+
+// 按 lecture_id 分组计数，sums 为 {输出字段: 条件表达式}
+pub(crate) async fn counts_by_lecture(
+    coll: mongodb::Collection<Document>,
+    mut filter: Document,
+    lecture_ids: &[ObjectId],
+    sums: Document,
+) -> Result<HashMap<ObjectId, Document>, AppError> {
+    filter.insert("lecture_id", doc! { "$in": lecture_ids });
+    let mut group = doc! { "_id": "$lecture_id", "total": { "$sum": 1 } };
+    for (field, cond) in sums {
+        group.insert(field, doc! { "$sum": { "$cond": [cond, 1, 0] } });
+    }
+    let rows: Vec<Document> = coll
+        .aggregate(vec![doc! { "$match": filter }, doc! { "$group": group }], None)
+        .await?
+        .try_collect()
+        .await?;
+    Ok(rows
+        .into_iter()
+        .filter_map(|row| Some((row.get_object_id("_id").ok()?, row)))
+        .collect())
+}
+
+pub(crate) fn count(row: Option<&Document>, field: &str) -> i64 {
+    row.and_then(|r| r.get_i32(field).ok()).unwrap_or(0) as i64
+}
+
+fn ratio(part: i64, whole: i64) -> Option<f64> {
+    (whole > 0).then(|| (part as f64 / whole as f64 * 1000.0).round() / 1000.0)
+}
+
+fn export_json(export: &Document) -> serde_json::Value {
+    let id = ids::oid_hex(export, "_id");
+    let status = export.get_str("status").unwrap_or(orgexport::STATUS_PENDING);
+    let mut v = serde_json::json!({
+        "export_id": id,
+        "org_id": export.get_str("org_id").unwrap_or(""),
+        "org_name": export.get_str("org_name").unwrap_or(""),
+        "from": export.get_i64("from").ok(),
+        "to": export.get_i64("to").ok(),
+        "status": status,
+        "created_at": export.get_i64("created_at").ok(),
+        "started_at": export.get_i64("started_at").ok(),
+        "finished_at": export.get_i64("finished_at").ok(),
+        "size": export.get_i64("size").ok(),
+        "counts": export.get_document("counts").ok().map(|c| ids::doc_to_json(c.clone())),
+        "error": export.get_str("error").ok(),
+    });
+    if status == orgexport::STATUS_DONE {
+        let (url, expires) = orgexport::signed_url(&id, EXPORT_LINK_TTL_SECS);
+        v["download_url"] = serde_json::json!(url);
+        v["download_expires_at"] = serde_json::json!(expires);
+    }
+    v
+}
+
+// ==================== 路由 ====================
+
+// GET /admin/maintenance
+async fn get_maintenance(
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, AppError> {
+    check_admin(&headers)?;
+    Ok(Json(serde_json::json!({ "read_only": maintenance::is_read_only() })))
+}
+
+// PUT /admin/maintenance
+async fn set_maintenance(
+    State(client): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<MaintenanceUpdate>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    check_admin(&headers)?;
+    maintenance::set_read_only(payload.read_only);
+    audit::record(&client, None, "admin.maintenance", "system", doc! { "read_only": payload.read_only }).await;
+    println!(
+        "[{}] 维护模式已{}",
+        crate::request_id::current().unwrap_or_default(),
+        if payload.read_only { "开启" } else { "关闭" }
+    );
+    Ok(Json(serde_json::json!({
+        "message": if payload.read_only { "已进入只读维护模式" } else { "已退出只读维护模式" },
+        "read_only": payload.read_only,
+    })))
+}
+
+// GET /admin/db_health -> 数据库熔断器状态
+async fn db_health(
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, AppError> {
+    check_admin(&headers)?;
+    Ok(Json(breaker::status()))
+}
+
+// GET /admin/audit/export?from=&to=&action= -> 按时间顺序以 NDJSON 流式导出审计日志。
+// 逐条从游标读取并写出，客户端读得慢时游标随之暂停，内存占用与导出范围无关
+async fn export_audit(
+    State(client): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<AuditExportQuery>,
+) -> Result<Response, AppError> {
+    check_admin(&headers)?;
+    let mut range = doc! {};
+    if let Some(from) = query.from.as_deref().filter(|s| !s.trim().is_empty()) {
+        range.insert("$gte", parse_time_param(from, "from")?);
+    }
+    if let Some(to) = query.to.as_deref().filter(|s| !s.trim().is_empty()) {
+        range.insert("$lt", parse_time_param(to, "to")?);
+    }
+    let mut filter = doc! {};
+    if !range.is_empty() {
+        filter.insert("at", range);
+    }
+    if let Some(action) = query.action.as_deref().map(str::trim).filter(|s| !s.is_empty()) {
+        filter.insert("action", action);
+    }
+
+    let options = FindOptions::builder().sort(doc! { "at": 1 }).batch_size(500).build();
+    let cursor = audit_collection(&client).find(filter, options).await?;
+    let lines = cursor.map(|entry| {
+        entry.map(|doc| {
+            let mut line = ids::doc_to_json(doc).to_string();
+            line.push('\n');
+            Bytes::from(line)
+        })
+    });
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/x-ndjson; charset=utf-8"),
+            (header::CONTENT_DISPOSITION, "attachment; filename=\"audit.ndjson\""),
+        ],
+        Body::from_stream(lines),
+    )
+        .into_response())
+}
+
+// GET /admin/organizer/:id/analytics?from=&to= -> 组织者各月演讲的出勤率、反馈评价与报名漏斗，
+// 供学期回顾使用。管理员令牌或组织者本人可访问；月份按配置的默认时区划分，草稿不计入
+async fn organizer_analytics(
+    State(client): State<AppState>,
+    headers: HeaderMap,
+    auth: Option<AuthUser>,
+    Path(organizer_id): Path<String>,
+    Query(query): Query<AnalyticsQuery>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let organizer_oid = ids::parse_oid(&organizer_id, "organizer_id")?;
+    if let Err(e) = check_admin(&headers) {
+        if auth.map(|a| a.id) != Some(organizer_oid) {
+            return Err(e);
+        }
+    }
+
+    let from = match query.from.as_deref().filter(|s| !s.trim().is_empty()) {
+        Some(from) => parse_time_param(from, "from")?,
+        None => chrono::Utc::now().timestamp_millis() - ANALYTICS_DEFAULT_DAYS * 86_400_000,
+    };
+    let mut range = doc! { "$gte": from };
+    if let Some(to) = query.to.as_deref().filter(|s| !s.trim().is_empty()) {
+        range.insert("$lt", parse_time_param(to, "to")?);
+    }
+    let lectures: Vec<Document> = lecture_collection(&client)
+        .find(
+            doc! {
+                "organizer_id": organizer_oid.to_hex(),
+                "start_time": range,
+                "status": { "$ne": LectureStatus::Draft.as_i32() },
+            },
+            FindOptions::builder()
+                .sort(doc! { "start_time": 1 })
+                .projection(doc! { "topic": 1, "start_time": 1, "status": 1, "capacity": 1 })
+                .build(),
+        )
+        .await?
+        .try_collect()
+        .await?;
+    let lecture_ids: Vec<ObjectId> = lectures.iter().filter_map(|l| l.get_object_id("_id").ok()).collect();
+
+    let la = counts_by_lecture(
+        la_collection(&client),
+        doc! {},
+        &lecture_ids,
+        doc! {
+            "present": { "$eq": ["$is_present", true] },
+            "from_waitlist": { "$eq": ["$from_waitlist", true] },
+        },
+    )
+    .await?;
+    let waitlist = counts_by_lecture(waitlist_collection(&client), doc! {}, &lecture_ids, doc! {}).await?;
+    // 未勾选任何负面选项的反馈视为好评
+    let feedback = counts_by_lecture(
+        feedback_collection(&client),
+        doc! { "rehearsal": { "$ne": true } },
+        &lecture_ids,
+        doc! {
+            "too_fast": { "$eq": ["$too_fast", true] },
+            "too_slow": { "$eq": ["$too_slow", true] },
+            "boring": { "$eq": ["$boring", true] },
+            "bad_question_quality": { "$eq": ["$bad_question_quality", true] },
+            "positive": { "$not": [{ "$or": [
+                { "$eq": ["$too_fast", true] },
+                { "$eq": ["$too_slow", true] },
+                { "$eq": ["$boring", true] },
+                { "$eq": ["$bad_question_quality", true] },
+            ] }] },
+        },
+    )
+    .await?;
+
+    let time = UserTime::default();
+    let fields = [
+        "lectures", "cancelled", "waitlisted", "registered", "from_waitlist", "present", "feedback",
+        "positive", "too_fast", "too_slow", "boring", "bad_question_quality",
+    ];
+    let mut months: BTreeMap<String, HashMap<&str, i64>> = BTreeMap::new();
+    let mut per_lecture = Vec::new();
+    for lecture in &lectures {
+        let Ok(oid) = lecture.get_object_id("_id") else { continue };
+        let start_time = lecture.get_i64("start_time").unwrap_or(0);
+        let (la_row, fb_row) = (la.get(&oid), feedback.get(&oid));
+        let status = LectureStatus::of(lecture);
+        let row = [
+            ("lectures", 1),
+            ("cancelled", (status == LectureStatus::Cancelled) as i64),
+            ("waitlisted", count(waitlist.get(&oid), "total")),
+            ("registered", count(la_row, "total")),
+            ("from_waitlist", count(la_row, "from_waitlist")),
+            ("present", count(la_row, "present")),
+            ("feedback", count(fb_row, "total")),
+            ("positive", count(fb_row, "positive")),
+            ("too_fast", count(fb_row, "too_fast")),
+            ("too_slow", count(fb_row, "too_slow")),
+            ("boring", count(fb_row, "boring")),
+            ("bad_question_quality", count(fb_row, "bad_question_quality")),
+        ];
+        let month = months.entry(time.month(start_time)).or_default();
+        for (field, n) in row {
+            *month.entry(field).or_insert(0) += n;
+        }
+        let get = |f: &str| row.iter().find(|(k, _)| *k == f).map(|(_, v)| *v).unwrap_or(0);
+        per_lecture.push(serde_json::json!({
+            "id": oid.to_hex(),
+            "topic": lecture.get_str("topic").unwrap_or(""),
+            "start_time": start_time,
+            "status": status.name(),
+            "capacity": lecture.get_i32("capacity").ok(),
+            "registered": get("registered"),
+            "present": get("present"),
+            "feedback": get("feedback"),
+            "attendance_rate": ratio(get("present"), get("registered")),
+            "positive_rate": ratio(get("positive"), get("feedback")),
+        }));
+    }
+
+    let mut totals: HashMap<&str, i64> = HashMap::new();
+    let monthly: Vec<serde_json::Value> = months
+        .into_iter()
+        .map(|(month, m)| {
+            let get = |f: &str| m.get(f).copied().unwrap_or(0);
+            for f in fields {
+                *totals.entry(f).or_insert(0) += get(f);
+            }
+            serde_json::json!({
+                "month": month,
+                "lectures": get("lectures"),
+                "cancelled": get("cancelled"),
+                // 报名漏斗：候补 → 报名（其中候补转正）→ 到场 → 提交反馈
+                "funnel": {
+                    "waitlisted": get("waitlisted"),
+                    "registered": get("registered"),
+                    "from_waitlist": get("from_waitlist"),
+                    "present": get("present"),
+                    "feedback": get("feedback"),
+                },
+                "attendance_rate": ratio(get("present"), get("registered")),
+                "feedback_rate": ratio(get("feedback"), get("present")),
+                "feedback_scores": {
+                    "positive_rate": ratio(get("positive"), get("feedback")),
+                    "too_fast": get("too_fast"),
+                    "too_slow": get("too_slow"),
+                    "boring": get("boring"),
+                    "bad_question_quality": get("bad_question_quality"),
+                },
+            })
+        })
+        .collect();
+    let total = |f: &str| totals.get(f).copied().unwrap_or(0);
+
+    Ok(Json(serde_json::json!({
+        "organizer_id": organizer_id,
+        "from": from,
+        "timezone": time.tz.name(),
+        "months": monthly,
+        "summary": {
+            "lectures": total("lectures"),
+            "cancelled": total("cancelled"),
+            "registered": total("registered"),
+            "present": total("present"),
+            "feedback": total("feedback"),
+            "attendance_rate": ratio(total("present"), total("registered")),
+            "positive_rate": ratio(total("positive"), total("feedback")),
+        },
+        "lectures": per_lecture,
+    })))
+}
+
+// 关键字按正则（转义后、忽略大小写）匹配；关键字本身是 ObjectId 时同时按 ID 精确匹配
+fn search_filter(kind: &str, q: &str) -> Document {
+    let pattern = doc! { "$regex": regex::escape(q), "$options": "i" };
+    let oid = ObjectId::parse_str(q).ok();
+    let mut any: Vec<Document> = Vec::new();
+    match kind {
+        "user" => {
+            any.push(doc! { "username": pattern.clone() });
+            any.push(doc! { "email": pattern });
+            if let Some(oid) = oid {
+                any.push(doc! { "_id": oid });
+            }
+        }
+        "lecture" => {
+            any.push(doc! { "topic": pattern.clone() });
+            any.push(doc! { "description": pattern });
+            if let Some(code) = lecturecode::normalize(q) {
+                any.push(lecturecode::lookup_filter(&code));
+            }
+            if let Some(oid) = oid {
+                any.push(doc! { "_id": oid });
+                any.push(doc! { "organizer_id": oid.to_hex() });
+                any.push(doc! { "speaker_id": oid.to_hex() });
+            }
+        }
+        _ => {
+            any.push(doc! { "content": pattern });
+            if let Some(oid) = oid {
+                any.push(doc! { "_id": oid });
+                any.push(doc! { "lecture_id": oid });
+                any.push(doc! { "user_id": oid });
+            }
+        }
+    }
+    doc! { "$or": any }
+}
+
+// 多取一条以判断是否还有更多结果
+async fn search_kind(client: AppState, kind: &'static str, q: String, limit: i64) -> Result<(&'static str, serde_json::Value), AppError> {
+    let coll = match kind {
+        "user" => user_collection(&client),
+        "lecture" => lecture_collection(&client),
+        _ => discussion_collection(&client),
+    };
+    let options = FindOptions::builder().sort(doc! { "_id": -1 }).limit(limit + 1).build();
+    let mut docs: Vec<Document> = coll.find(search_filter(kind, &q), options).await?.try_collect().await?;
+    let has_more = docs.len() as i64 > limit;
+    docs.truncate(limit as usize);
+    let items: Vec<serde_json::Value> = docs
+        .into_iter()
+        .map(|d| {
+            let mut v = if kind == "user" { ids::user_to_json(d) } else { ids::doc_to_json(d) };
+            if let Some(obj) = v.as_object_mut() {
+                obj.insert("type".to_string(), serde_json::json!(kind));
+            }
+            v
+        })
+        .collect();
+    Ok((kind, serde_json::json!({ "items": items, "has_more": has_more })))
+}
+
+// GET /admin/search?q=&limit=&types= -> 处理工单时按关键字或 ID 查找用户、演讲与讨论，
+// 各类并行查询（限制并发），结果按类型分组，每类按创建时间倒序
+async fn admin_search(
+    State(client): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<SearchQuery>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    check_admin(&headers)?;
+    let q = query.q.trim();
+    if q.is_empty() {
+        return Err(AppError::BadRequest("q 不能为空".into()));
+    }
+    if q.chars().count() > 100 {
+        return Err(AppError::BadRequest("q 不能超过 100 个字符".into()));
+    }
+    let limit = query.limit.unwrap_or(10).clamp(1, 50);
+    let kinds: Vec<&'static str> = match query.types.as_deref().filter(|s| !s.trim().is_empty()) {
+        Some(raw) => {
+            let mut kinds = Vec::new();
+            for t in raw.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+                let kind = SEARCH_TYPES
+                    .iter()
+                    .find(|k| **k == t)
+                    .ok_or(AppError::BadRequest(format!("未知类型: {}，可选 user、lecture、discussion", t)))?;
+                if !kinds.contains(kind) {
+                    kinds.push(*kind);
+                }
+            }
+            kinds
+        }
+        None => SEARCH_TYPES.to_vec(),
+    };
+
+    let searches: Vec<_> = kinds
+        .into_iter()
+        .map(|kind| search_kind(client.clone(), kind, q.to_string(), limit))
+        .collect();
+    let results: Vec<(&'static str, serde_json::Value)> = futures_util::stream::iter(searches)
+        .buffer_unordered(SEARCH_CONCURRENCY)
+        .try_collect()
+        .await?;
+    audit::record(&client, None, "admin.search", "system", doc! { "q": q }).await;
+
+    let mut grouped = serde_json::Map::new();
+    for kind in SEARCH_TYPES {
+        if let Some((_, group)) = results.iter().find(|(k, _)| *k == kind) {
+            grouped.insert(format!("{}s", kind), group.clone());
+        }
+    }
+    Ok(Json(serde_json::json!({ "q": q, "results": grouped })))
+}
+
+// GET /admin/selfcheck -> 重新执行部署自检；有失败项时返回 503，便于探活与排障
+async fn selfcheck(
+    State(client): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    check_admin(&headers)?;
+    let report = crate::selfcheck::run(&client).await;
+    let status = if report["status"] == "fail" { StatusCode::SERVICE_UNAVAILABLE } else { StatusCode::OK };
+    Ok((status, Json(report)).into_response())
+}
+
+// POST /admin/org/:id/export {from?, to?} -> 提交组织数据导出（演讲、出勤、反馈与讨论统计），
+// 由后台任务生成压缩包，返回 202 与状态查询地址；同一组织同一范围已在排队或生成中时直接返回该任务
+async fn create_org_export(
+    State(client): State<AppState>,
+    headers: HeaderMap,
+    Path(org_id): Path<String>,
+    Json(payload): Json<OrgExportRequest>,
+) -> Result<Response, AppError> {
+    check_admin(&headers)?;
+    let org_oid = ids::parse_oid(&org_id, "org_id")?;
+    let org = organization_collection(&client)
+        .find_one(doc! { "_id": org_oid }, None)
+        .await?
+        .ok_or(AppError::NotFound("组织不存在".into()))?;
+    let now = chrono::Utc::now().timestamp_millis();
+    let from = match payload.from.as_deref().filter(|s| !s.trim().is_empty()) {
+        Some(from) => parse_time_param(from, "from")?,
+        None => now - ANALYTICS_DEFAULT_DAYS * 86_400_000,
+    };
+    let to = match payload.to.as_deref().filter(|s| !s.trim().is_empty()) {
+        Some(to) => parse_time_param(to, "to")?,
+        None => now,
+    };
+    if from >= to {
+        return Err(AppError::BadRequest("from 必须早于 to".into()));
+    }
+
+    let coll = org_export_collection(&client);
+    let key = doc! { "org_id": org_oid.to_hex(), "from": from, "to": to };
+    let mut active = key.clone();
+    active.insert("status", doc! { "$in": [orgexport::STATUS_PENDING, orgexport::STATUS_RUNNING] });
+    let export = match coll.find_one(active, None).await? {
+        Some(existing) => existing,
+        None => {
+            let mut export = key;
+            export.insert("org_name", org.get_str("name").unwrap_or(""));
+            export.insert("status", orgexport::STATUS_PENDING);
+            export.insert("created_at", now);
+            let result = coll.insert_one(&export, None).await?;
+            export.insert("_id", result.inserted_id);
+            audit::record(
+                &client,
+                None,
+                "admin.org_export",
+                &format!("org:{}", org_oid.to_hex()),
+                doc! { "from": from, "to": to },
+            )
+            .await;
+            export
+        }
+    };
+    let mut body = export_json(&export);
+    body["status_url"] = serde_json::json!(format!("/admin/org/{}/export/{}", org_id, ids::oid_hex(&export, "_id")));
+    Ok((StatusCode::ACCEPTED, Json(body)).into_response())
+}
+
+// GET /admin/org/:id/export/:export_id -> 导出进度；完成时附带限时签名下载链接
+async fn get_org_export(
+    State(client): State<AppState>,
+    headers: HeaderMap,
+    Path((org_id, export_id)): Path<(String, String)>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    check_admin(&headers)?;
+    let org_oid = ids::parse_oid(&org_id, "org_id")?;
+    let export_oid = ids::parse_oid(&export_id, "export_id")?;
+    let export = org_export_collection(&client)
+        .find_one(doc! { "_id": export_oid, "org_id": org_oid.to_hex() }, None)
+        .await?
+        .ok_or(AppError::NotFound("导出任务不存在".into()))?;
+    Ok(Json(export_json(&export)))
+}
+
+// GET /admin/exports/:export_id/download?expires=&sig= -> 凭签名链接下载导出压缩包，无需管理员令牌
+async fn download_org_export(
+    State(client): State<AppState>,
+    Path(export_id): Path<String>,
+    Query(query): Query<ExportDownloadQuery>,
+) -> Result<Response, AppError> {
+    if !signing::verify(&orgexport::download_payload(&export_id, query.expires), &query.sig) {
+        return Err(AppError::Forbidden("签名无效".into()));
+    }
+    if query.expires < chrono::Utc::now().timestamp() {
+        return Err(AppError::Gone("下载链接已过期".into()));
+    }
+    let export_oid = ids::parse_oid(&export_id, "export_id")?;
+    let export = org_export_collection(&client)
+        .find_one(doc! { "_id": export_oid }, None)
+        .await?
+        .ok_or(AppError::NotFound("导出任务不存在".into()))?;
+    match export.get_str("status").unwrap_or("") {
+        orgexport::STATUS_DONE => {}
+        orgexport::STATUS_EXPIRED => return Err(AppError::Gone("导出文件已清理，请重新导出".into())),
+        _ => return Err(AppError::Conflict("导出尚未完成".into())),
+    }
+    let file = tokio::fs::File::open(orgexport::file_path(export_oid))
+        .await
+        .map_err(|_| AppError::NotFound("文件不存在".into()))?;
+    let size = file.metadata().await.map(|m| m.len()).unwrap_or(0);
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/zip".to_string()),
+            (header::CONTENT_LENGTH, size.to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", orgexport::file_name(&export))),
+        ],
+        Body::from_stream(ReaderStream::new(file)),
+    )
+        .into_response())
+}
+
+// POST /admin/migrate/lecturecodes -> 将旧的整数演讲码迁移为字符串
+async fn migrate_lecturecodes(
+    State(client): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, AppError> {
+    check_admin(&headers)?;
+    let migrated = lecturecode::migrate_integer_codes(&lecture_collection(&client))
+        .await
+        .map_err(|e| AppError::Internal(format!("迁移失败: {}", e)))?;
+    Ok(Json(serde_json::json!({ "migrated": migrated })))
+}
+
+// POST /admin/migrate/la_duplicates -> 清理重复报名，之后重建唯一索引
+async fn migrate_la_duplicates(
+    State(client): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, AppError> {
+    check_admin(&headers)?;
+    let removed = crate::routes::la::dedupe_records(&client).await?;
+    let indexes = match db::init_indexes(&client).await {
+        Ok(()) => "ok".to_string(),
+        Err(e) => format!("创建索引失败: {}", e),
+    };
+    Ok(Json(serde_json::json!({ "removed": removed, "indexes": indexes })))
+}
+
+// ==================== Router ====================
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/maintenance", get(get_maintenance).put(set_maintenance))
+        .route("/db_health", get(db_health))
+        .route("/selfcheck", get(selfcheck))
+        .route("/audit/export", get(export_audit))
+        .route("/migrate/lecturecodes", post(migrate_lecturecodes))
+        .route("/migrate/la_duplicates", post(migrate_la_duplicates))
+        .route("/organizer/:organizer_id/analytics", get(organizer_analytics))
+        .route("/search", get(admin_search))
+        .route("/org/:org_id/export", post(create_org_export))
+        .route("/org/:org_id/export/:export_id", get(get_org_export))
+        .route("/exports/:export_id/download", get(download_org_export))
+        .merge(crate::routes::banner::admin_router())
+}
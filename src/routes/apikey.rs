@@ -0,0 +1,238 @@
+// src/routes/apikey.rs
+use axum::{
+    extract::{Path, Query, State},
+    http::HeaderMap,
+    response::Json,
+    routing::{delete, get, post},
+    Router,
+};
+use bson::{doc, oid::ObjectId};
+use chrono::{Duration, Utc};
+use futures_util::TryStreamExt;
+use mongodb::Client;
+use rand::Rng;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::{ids, retry};
+use crate::auth::{AnyRole, AuthUser, RequireRole};
+use crate::db::{api_key_collection, api_usage_collection};
+use crate::quota::{default_daily_quota, hash_key, usage_day};
+use crate::error::AppError;
+use crate::routes::admin::check_admin;
+
+type AppState = Arc<Client>;
+
+// ==================== 模型 ====================
+
+#[derive(Deserialize)]
+struct ApiKeyCreate {
+    name: String,
+    daily_quota: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct UsageQuery {
+    days: Option<i64>,
+}
+
+// ==================== 工具函数 ====================
+
+fn generate_raw_key() -> String {
+    let bytes: [u8; 24] = rand::thread_rng().gen();
+    format!("rmk_{}", hex::encode(bytes))
+}
+
+// 管理 key 须通过登录会话，不能用 API key 本身再签发或吊销 key
+fn ensure_session(auth: &AuthUser) -> Result<(), AppError> {
+    if auth.session_id.is_none() {
+        return Err(AppError::Forbidden("请登录后管理 API key".into()));
+    }
+    Ok(())
+}
+
+// ==================== 路由 ====================
+
+// POST /apikey/create -> 明文 key 只在创建时返回一次，所有者为当前登录用户
+async fn create_key(
+    State(client): State<AppState>,
+    auth: RequireRole<AnyRole>,
+    headers: HeaderMap,
+    Json(payload): Json<ApiKeyCreate>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    ensure_session(&auth)?;
+    let owner_id = auth.id_hex();
+    let name = payload.name.trim().to_string();
+    if name.is_empty() {
+        return Err(AppError::BadRequest("name 不能为空".into()));
+    }
+    let daily_quota = payload.daily_quota.unwrap_or_else(default_daily_quota);
+    if daily_quota <= 0 {
+        return Err(AppError::BadRequest("daily_quota 必须为正数".into()));
+    }
+    // 超出全局默认配额须由管理员设置
+    if daily_quota > default_daily_quota() && check_admin(&headers).is_err() {
+        return Err(AppError::BadRequest(format!("daily_quota 不能超过 {}", default_daily_quota())));
+    }
+
+    let raw_key = generate_raw_key();
+    let mut key_doc = doc! {
+        "owner_id": &owner_id,
+        "name": &name,
+        "key_hash": hash_key(&raw_key),
+        "prefix": &raw_key[..12],
+        "daily_quota": daily_quota,
+        // 所有者取自登录会话，认证中间件据此允许以该 key 代表所有者
+        "owner_verified": true,
+        "revoked": false,
+        "created_at": Utc::now().timestamp_millis(),
+    };
+    let id = retry::insert_one(&api_key_collection(&client), &mut key_doc)
+        .await
+        .map_err(|e| retry::db_error(e, "创建 API key 失败"))?
+        .to_hex();
+
+    Ok(Json(serde_json::json!({
+        "id": id,
+        "name": name,
+        "key": raw_key,
+        "daily_quota": daily_quota,
+        "message": "请妥善保存该 key，之后将无法再次查看",
+    })))
+}
+
+// GET /apikey/owner/:owner_id -> 列出名下 key（不含明文）
+async fn list_keys(
+    State(client): State<AppState>,
+    auth: RequireRole<AnyRole>,
+    Path(owner_id): Path<String>,
+) -> Result<Json<Vec<serde_json::Value>>, AppError> {
+    ensure_session(&auth)?;
+    auth.ensure_self(&owner_id)?;
+    let mut cursor = api_key_collection(&client)
+        .find(doc! { "owner_id": &owner_id }, None)
+        .await
+        .map_err(|_| AppError::Internal("查询失败".into()))?;
+
+    let mut keys = Vec::new();
+    while let Some(doc) = cursor
+        .try_next()
+        .await
+        .map_err(|_| AppError::Internal("读取失败".into()))?
+    {
+        keys.push(serde_json::json!({
+            "id": ids::oid_hex(&doc, "_id"),
+            "name": doc.get_str("name").unwrap_or(""),
+            "prefix": doc.get_str("prefix").unwrap_or(""),
+            "daily_quota": doc.get_i64("daily_quota").unwrap_or_else(|_| default_daily_quota()),
+            "revoked": doc.get_bool("revoked").unwrap_or(false),
+            "created_at": doc.get_i64("created_at").unwrap_or(0),
+        }));
+    }
+    Ok(Json(keys))
+}
+
+// GET /apikey/usage/:owner_id?days=7 -> 名下各 key 的逐日调用量
+async fn get_usage(
+    State(client): State<AppState>,
+    auth: RequireRole<AnyRole>,
+    Path(owner_id): Path<String>,
+    Query(query): Query<UsageQuery>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    ensure_session(&auth)?;
+    auth.ensure_self(&owner_id)?;
+    let days = query.days.unwrap_or(7).clamp(1, 90);
+    let now = Utc::now();
+    let since = usage_day(now - Duration::days(days - 1));
+    let today = usage_day(now);
+
+    let mut cursor = api_usage_collection(&client)
+        .find(doc! { "owner_id": &owner_id, "day": { "$gte": &since } }, None)
+        .await
+        .map_err(|_| AppError::Internal("查询失败".into()))?;
+
+    let mut per_key: HashMap<String, Vec<serde_json::Value>> = HashMap::new();
+    let mut today_counts: HashMap<String, i64> = HashMap::new();
+    while let Some(doc) = cursor
+        .try_next()
+        .await
+        .map_err(|_| AppError::Internal("读取失败".into()))?
+    {
+        let key_id = ids::oid_hex(&doc, "key_id");
+        let day = doc.get_str("day").unwrap_or("").to_string();
+        let count = doc.get_i64("count").unwrap_or(0);
+        if day == today {
+            today_counts.insert(key_id.clone(), count);
+        }
+        per_key
+            .entry(key_id)
+            .or_default()
+            .push(serde_json::json!({ "day": day, "count": count }));
+    }
+
+    let mut cursor = api_key_collection(&client)
+        .find(doc! { "owner_id": &owner_id }, None)
+        .await
+        .map_err(|_| AppError::Internal("查询失败".into()))?;
+    let mut keys = Vec::new();
+    while let Some(doc) = cursor
+        .try_next()
+        .await
+        .map_err(|_| AppError::Internal("读取失败".into()))?
+    {
+        let id = ids::oid_hex(&doc, "_id");
+        let quota = doc.get_i64("daily_quota").unwrap_or_else(|_| default_daily_quota());
+        let used_today = today_counts.get(&id).copied().unwrap_or(0);
+        let mut daily = per_key.remove(&id).unwrap_or_default();
+        daily.sort_by(|a, b| a["day"].as_str().cmp(&b["day"].as_str()));
+        keys.push(serde_json::json!({
+            "id": id,
+            "name": doc.get_str("name").unwrap_or(""),
+            "revoked": doc.get_bool("revoked").unwrap_or(false),
+            "daily_quota": quota,
+            "used_today": used_today,
+            "remaining_today": (quota - used_today).max(0),
+            "daily": daily,
+        }));
+    }
+
+    Ok(Json(serde_json::json!({
+        "owner_id": owner_id,
+        "since": since,
+        "keys": keys,
+    })))
+}
+
+// DELETE /apikey/:key_id -> 吊销，只能吊销本人名下的 key
+async fn revoke_key(
+    State(client): State<AppState>,
+    auth: RequireRole<AnyRole>,
+    Path(key_id): Path<String>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let oid = ObjectId::parse_str(&key_id)
+        .map_err(|_| AppError::BadRequest("无效的 key_id".into()))?;
+    ensure_session(&auth)?;
+    let result = api_key_collection(&client)
+        .update_one(
+            doc! { "_id": oid, "owner_id": auth.id_hex() },
+            doc! { "$set": { "revoked": true, "revoked_at": Utc::now().timestamp_millis() } },
+            None,
+        )
+        .await
+        .map_err(|_| AppError::Internal("更新失败".into()))?;
+    if result.matched_count == 0 {
+        return Err(AppError::NotFound("API key not found".into()));
+    }
+    Ok(Json(serde_json::json!({ "message": "API key 已吊销" })))
+}
+
+// ==================== Router ====================
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/create", post(create_key))
+        .route("/owner/:owner_id", get(list_keys))
+        .route("/usage/:owner_id", get(get_usage))
+        .route("/:key_id", delete(revoke_key))
+}
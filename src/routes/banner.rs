@@ -0,0 +1,212 @@
+// src/routes/banner.rs
+// 全站公告横幅：管理员发布限时公告（维护通知、校园提醒等），前端轮询 GET /announcements 展示。
+// 可按角色、组织定向；roles、org_ids 为空表示不限，未登录用户只能看到不限对象的公告
+use axum::{
+    extract::{Path, Query, State},
+    http::HeaderMap,
+    response::Json,
+    routing::{delete, get},
+    Router,
+};
+use bson::{doc, oid::ObjectId, Bson, Document};
+use chrono::Utc;
+use futures_util::TryStreamExt;
+use mongodb::{options::FindOptions, Client};
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::auth::{AuthUser, ROLE_AUDIENCE, ROLE_ORGANIZER, ROLE_SPEAKER};
+use crate::db::{banner_collection, organization_collection};
+use crate::error::AppError;
+use crate::routes::admin::check_admin;
+use crate::timefmt::parse_time_param;
+use crate::{audit, ids, retry};
+
+type AppState = Arc<Client>;
+
+const LEVELS: [&str; 3] = ["info", "warning", "critical"];
+const MAX_TITLE_CHARS: usize = 100;
+const MAX_MESSAGE_CHARS: usize = 2000;
+// 同时生效的公告通常只有几条，防止误配置时一次返回过多
+const MAX_ACTIVE: i64 = 20;
+
+#[derive(Deserialize)]
+struct BannerCreate {
+    title: String,
+    message: Option<String>,
+    // info / warning / critical，缺省 info
+    level: Option<String>,
+    // 毫秒时间戳或 RFC3339，starts_at 缺省为立即生效
+    starts_at: Option<String>,
+    ends_at: String,
+    #[serde(default)]
+    roles: Vec<i32>,
+    #[serde(default)]
+    org_ids: Vec<String>,
+}
+
+#[derive(Deserialize, Default)]
+struct AdminListQuery {
+    // 为 true 时只列出当前生效的
+    #[serde(default)]
+    active: bool,
+}
+
+fn active_filter(now: i64) -> Document {
+    doc! { "starts_at": { "$lte": now }, "ends_at": { "$gt": now } }
+}
+
+// 用户所属组织（成员以 hex 字符串记录）
+async fn orgs_of(client: &AppState, user: &AuthUser) -> Result<Vec<String>, AppError> {
+    Ok(organization_collection(client)
+        .distinct("_id", doc! { "members": user.id_hex() }, None)
+        .await?
+        .iter()
+        .filter_map(Bson::as_object_id)
+        .map(|oid| oid.to_hex())
+        .collect())
+}
+
+// GET /announcements -> 当前对调用者生效的公告，严重程度高的在前
+async fn list_active(
+    State(client): State<AppState>,
+    auth: Option<AuthUser>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let now = Utc::now().timestamp_millis();
+    let mut filter = active_filter(now);
+    match &auth {
+        Some(user) => {
+            let orgs = orgs_of(&client, user).await?;
+            filter.insert(
+                "$and",
+                vec![
+                    doc! { "$or": [{ "roles": { "$size": 0 } }, { "roles": user.role }] },
+                    doc! { "$or": [{ "org_ids": { "$size": 0 } }, { "org_ids": { "$in": orgs } }] },
+                ],
+            );
+        }
+        None => {
+            filter.insert("roles", doc! { "$size": 0 });
+            filter.insert("org_ids", doc! { "$size": 0 });
+        }
+    }
+    let options = FindOptions::builder()
+        .sort(doc! { "severity": -1, "starts_at": -1 })
+        .limit(MAX_ACTIVE)
+        .projection(doc! { "title": 1, "message": 1, "level": 1, "starts_at": 1, "ends_at": 1 })
+        .build();
+    let items: Vec<serde_json::Value> = banner_collection(&client)
+        .find(filter, options)
+        .await?
+        .map_ok(ids::doc_to_json)
+        .try_collect()
+        .await?;
+    Ok(Json(serde_json::json!({ "items": items, "server_time": now })))
+}
+
+// POST /admin/announcements -> 发布公告
+async fn create_banner(
+    State(client): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<BannerCreate>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    check_admin(&headers)?;
+    let title = payload.title.trim().to_string();
+    if title.is_empty() || title.chars().count() > MAX_TITLE_CHARS {
+        return Err(AppError::BadRequest(format!("title 不能为空且不超过 {} 字", MAX_TITLE_CHARS)));
+    }
+    let message = payload.message.unwrap_or_default().trim().to_string();
+    if message.chars().count() > MAX_MESSAGE_CHARS {
+        return Err(AppError::BadRequest(format!("message 不超过 {} 字", MAX_MESSAGE_CHARS)));
+    }
+    let level = payload.level.as_deref().map(str::trim).unwrap_or("info");
+    let Some(severity) = LEVELS.iter().position(|l| *l == level) else {
+        return Err(AppError::BadRequest(format!("level 仅支持 {}", LEVELS.join(" / "))));
+    };
+    let now = Utc::now().timestamp_millis();
+    let starts_at = match payload.starts_at.as_deref().filter(|s| !s.trim().is_empty()) {
+        Some(raw) => parse_time_param(raw, "starts_at")?,
+        None => now,
+    };
+    let ends_at = parse_time_param(&payload.ends_at, "ends_at")?;
+    if ends_at <= starts_at || ends_at <= now {
+        return Err(AppError::BadRequest("ends_at 须晚于 starts_at 及当前时间".into()));
+    }
+    let valid_roles = [ROLE_ORGANIZER, ROLE_SPEAKER, ROLE_AUDIENCE];
+    if let Some(role) = payload.roles.iter().find(|r| !valid_roles.contains(r)) {
+        return Err(AppError::BadRequest(format!("未知角色: {}", role)));
+    }
+    let mut org_ids = Vec::with_capacity(payload.org_ids.len());
+    for raw in &payload.org_ids {
+        org_ids.push(ids::parse_oid(raw, "org_ids")?.to_hex());
+    }
+
+    let mut banner = doc! {
+        "title": &title,
+        "message": &message,
+        "level": level,
+        "severity": severity as i32,
+        "starts_at": starts_at,
+        "ends_at": ends_at,
+        "roles": &payload.roles,
+        "org_ids": &org_ids,
+        "created_at": now,
+    };
+    let id = retry::insert_one(&banner_collection(&client), &mut banner)
+        .await
+        .map_err(|e| retry::db_error(e, "发布公告失败"))?;
+    audit::record(
+        &client,
+        None,
+        "admin.announcement.create",
+        &format!("announcement:{}", id.to_hex()),
+        doc! { "title": &title, "level": level, "starts_at": starts_at, "ends_at": ends_at },
+    )
+    .await;
+    Ok(Json(ids::doc_to_json(banner)))
+}
+
+// GET /admin/announcements?active= -> 全部公告（含已过期、未开始），新发布的在前
+async fn list_all(
+    State(client): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<AdminListQuery>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    check_admin(&headers)?;
+    let filter = if query.active { active_filter(Utc::now().timestamp_millis()) } else { doc! {} };
+    let options = FindOptions::builder().sort(doc! { "created_at": -1 }).limit(200).build();
+    let items: Vec<serde_json::Value> = banner_collection(&client)
+        .find(filter, options)
+        .await?
+        .map_ok(ids::doc_to_json)
+        .try_collect()
+        .await?;
+    Ok(Json(serde_json::json!({ "items": items })))
+}
+
+// DELETE /admin/announcements/:id -> 撤下公告
+async fn delete_banner(
+    State(client): State<AppState>,
+    headers: HeaderMap,
+    Path(banner_id): Path<String>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    check_admin(&headers)?;
+    let oid: ObjectId = ids::parse_oid(&banner_id, "announcement_id")?;
+    let result = banner_collection(&client).delete_one(doc! { "_id": oid }, None).await?;
+    if result.deleted_count == 0 {
+        return Err(AppError::NotFound("公告不存在".into()));
+    }
+    audit::record(&client, None, "admin.announcement.delete", &format!("announcement:{}", banner_id), doc! {}).await;
+    Ok(Json(serde_json::json!({ "id": banner_id, "deleted": true })))
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/", get(list_active))
+}
+
+// 挂在 /admin 下
+pub fn admin_router() -> Router<AppState> {
+    Router::new()
+        .route("/announcements", get(list_all).post(create_banner))
+        .route("/announcements/:announcement_id", delete(delete_banner))
+}
@@ -1,18 +1,31 @@
 use axum::{
-    extract::{Path, State, Json},
-    http::StatusCode,
-    routing::{get, post},
+    extract::{Path, Query, State, Json},
+    http::HeaderMap,
+    routing::{delete, get, post},
     Router,
 };
-use axum::response::Json as RespJson;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{Json as RespJson, Response};
 use bson::{doc, oid::ObjectId, DateTime as BsonDateTime};
-use chrono::{DateTime, Utc, TimeZone};
-use futures_util::TryStreamExt;
-use mongodb::Client;
+use chrono::{DateTime, Utc};
+use futures_util::{stream, Stream, TryStreamExt};
+use mongodb::{options::FindOptions, Client};
+use once_cell::sync::Lazy;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::convert::Infallible;
 use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
 
-use crate::db::{discussion_collection, user_collection};
+use crate::{audit, ids, notify, realtime, retry, translate};
+use crate::auth::{require_lecture_role, AuthUser, LectureRole};
+use crate::db::{discussion_collection, reaction_collection, user_collection};
+use crate::routes::lecture::{ensure_not_archived, is_host, load_lecture, rehearsal_lecture};
+use crate::routes::{moderation, reaction, waiting_room};
+use crate::summary::{looks_like_question, top_keywords, SUMMARIZER};
+use crate::error::AppError;
+use crate::pagination::PageParams;
 
 type AppState = Arc<Client>;
 
@@ -21,6 +34,11 @@ struct DiscussionCreate {
     lecture_id: String,
     user_id: String,
     content: String,
+    // 回复某条讨论时填写
+    parent_id: Option<String>,
+    // 标记为提问，进入讲者的问题队列
+    #[serde(default)]
+    is_question: bool,
 }
 
 #[derive(Serialize)]
@@ -30,6 +48,9 @@ struct DiscussionOut {
     user_id: String,
     content: String,
     created_at: DateTime<Utc>,
+    parent_id: Option<String>,
+    is_question: bool,
+    mentions: Vec<MentionOut>,
 }
 
 #[derive(Serialize)]
@@ -41,78 +62,262 @@ struct DiscussionOutWithUser {
     created_at: DateTime<Utc>,
     username: String,
     avatar: String,
+    parent_id: Option<String>,
+    is_question: bool,
+    answered: bool,
+    mentions: Vec<MentionOut>,
+    pinned: bool,
+    hidden: bool,
+    // 各类回应计数及当前用户做过的回应
+    reactions: HashMap<String, i64>,
+    my_reactions: Vec<String>,
+    // 仅讨论串视图返回
+    #[serde(skip_serializing_if = "Option::is_none")]
+    replies: Option<Vec<DiscussionOutWithUser>>,
+}
+
+#[derive(Deserialize, Default)]
+struct ListQuery {
+    // 为 true 时按讨论串返回：分页针对顶层消息，回复嵌套在 replies 中
+    #[serde(default)]
+    threaded: bool,
+    // 管理者可查看已隐藏的消息
+    #[serde(default)]
+    include_hidden: bool,
+    // top：按点赞数从高到低，便于讲者挑选热门问题；缺省按发送顺序
+    sort: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+struct StreamQuery {
+    // 无法设置请求头的客户端（如手动重连）可用查询参数代替 Last-Event-ID
+    last_event_id: Option<String>,
+}
+
+// 回复的最大嵌套层数
+const MAX_THREAD_DEPTH: i32 = 8;
+
+#[derive(Deserialize)]
+struct QuestionFlag {
+    is_question: bool,
+}
+
+#[derive(Deserialize)]
+struct AnsweredFlag {
+    answered: bool,
+}
+
+#[derive(Deserialize)]
+struct TranslateQuery {
+    to: String,
+}
+
+#[derive(Deserialize, Default)]
+struct QuestionQuery {
+    #[serde(default)]
+    unanswered: bool,
+}
+
+// 单条消息最多通知的被提及人数
+const MAX_MENTIONS: usize = 10;
+
+static MENTION_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"@([\p{L}\p{N}_.\-]+)").unwrap());
+
+#[derive(Serialize)]
+struct MentionOut {
+    user_id: String,
+    username: String,
+}
+
+// 解析内容中的 @用户名 并对照用户表，不存在的用户名忽略；按用户名排序去重
+async fn resolve_mentions(client: &AppState, content: &str) -> Result<Vec<(ObjectId, String)>, AppError> {
+    let mut names: Vec<&str> = MENTION_RE.captures_iter(content).filter_map(|c| c.get(1)).map(|m| m.as_str()).collect();
+    names.sort();
+    names.dedup();
+    names.truncate(MAX_MENTIONS);
+    if names.is_empty() {
+        return Ok(Vec::new());
+    }
+    let options = FindOptions::builder().sort(doc! { "username": 1 }).projection(doc! { "username": 1 }).build();
+    let users: Vec<bson::Document> = user_collection(client)
+        .find(doc! { "username": { "$in": &names } }, options)
+        .await?
+        .try_collect()
+        .await?;
+    Ok(users
+        .iter()
+        .filter_map(|u| Some((u.get_object_id("_id").ok()?, u.get_str("username").ok()?.to_string())))
+        .collect())
+}
+
+fn mentions_of(doc: &bson::Document) -> Vec<MentionOut> {
+    doc.get_array("mentions")
+        .map(|a| {
+            a.iter()
+                .filter_map(|m| m.as_document())
+                .filter_map(|m| {
+                    Some(MentionOut {
+                        user_id: m.get_object_id("user_id").ok()?.to_hex(),
+                        username: m.get_str("username").unwrap_or("").to_string(),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// 通知消息中 @ 到的用户（发送者本人除外）
+async fn notify_mentions(client: &AppState, mentions: &[(ObjectId, String)], content: &str, author: ObjectId, lecture_oid: ObjectId, discussion_oid: ObjectId) {
+    let excerpt: String = content.chars().take(100).collect();
+    for &(user_id, _) in mentions.iter().filter(|(id, _)| *id != author) {
+        let payload = doc! {
+            "lecture_id": lecture_oid.to_hex(),
+            "discussion_id": discussion_oid.to_hex(),
+            "from_user_id": author.to_hex(),
+            "excerpt": &excerpt,
+        };
+        if let Err(e) = notify::push(client, user_id, "discussion_mention", payload).await {
+            println!("发送提及通知失败 {}: {}", user_id.to_hex(), e);
+        }
+    }
+}
+
+// 通知被回复的讨论作者（回复自己的除外）
+async fn notify_reply(client: &AppState, parent: &bson::Document, content: &str, author: ObjectId, discussion_oid: ObjectId) {
+    let Ok(parent_author) = parent.get_object_id("user_id") else { return };
+    if parent_author == author {
+        return;
+    }
+    let payload = doc! {
+        "lecture_id": parent.get_object_id("lecture_id").map(|oid| oid.to_hex()).unwrap_or_default(),
+        "discussion_id": discussion_oid.to_hex(),
+        "parent_id": parent.get_object_id("_id").map(|oid| oid.to_hex()).unwrap_or_default(),
+        "from_user_id": author.to_hex(),
+        "excerpt": content.chars().take(100).collect::<String>(),
+    };
+    if let Err(e) = notify::push(client, parent_author, "discussion_reply", payload).await {
+        println!("发送回复通知失败 {}: {}", parent_author.to_hex(), e);
+    }
 }
 
 // POST /discussion/add
 async fn add_discussion(
     State(client): State<AppState>,
+    auth: AuthUser,
     Json(payload): Json<DiscussionCreate>,
-) -> Result<RespJson<DiscussionOut>, (StatusCode, String)> {
+) -> Result<RespJson<DiscussionOut>, AppError> {
+    auth.ensure_self(&payload.user_id)?;
     let coll = discussion_collection(&client);
     let lecture_oid = ObjectId::parse_str(&payload.lecture_id)
-        .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid lecture_id".into()))?;
+        .map_err(|_| AppError::BadRequest("Invalid lecture_id".into()))?;
     let user_oid = ObjectId::parse_str(&payload.user_id)
-        .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid user_id".into()))?;
+        .map_err(|_| AppError::BadRequest("Invalid user_id".into()))?;
+    ensure_not_archived(&client, lecture_oid).await?;
+    let lecture = load_lecture(&client, &payload.lecture_id).await?;
+    if moderation::is_muted(&lecture, user_oid) {
+        return Err(AppError::Forbidden("你已被管理员禁言，演讲结束前无法发言".into()));
+    }
+    if waiting_room::is_holding(&lecture) && !is_host(&lecture, &payload.user_id) {
+        return Err(AppError::Conflict("候场中，演讲开始后开放讨论".into()));
+    }
+    let rehearsal = rehearsal_lecture(&client, lecture_oid).await?.is_some();
+    // 静默禁言：照常写入并返回给本人，但不通知、不推送
+    let shadowed = moderation::is_shadow_muted(&lecture, user_oid);
+    let mentions = resolve_mentions(&client, &payload.content).await?;
+
+    let parent = match payload.parent_id.as_deref().map(str::trim).filter(|s| !s.is_empty()) {
+        Some(parent_id) => {
+            let parent_oid = ids::parse_oid(parent_id, "parent_id")?;
+            let parent = coll
+                .find_one(doc! { "_id": parent_oid }, None)
+                .await?
+                .ok_or(AppError::NotFound("回复的讨论不存在".into()))?;
+            if parent.get_object_id("lecture_id").ok() != Some(lecture_oid) {
+                return Err(AppError::BadRequest("只能回复同一演讲下的讨论".into()));
+            }
+            if parent.get_i32("depth").unwrap_or(0) >= MAX_THREAD_DEPTH {
+                return Err(AppError::BadRequest(format!("回复最多嵌套 {} 层", MAX_THREAD_DEPTH)));
+            }
+            Some(parent)
+        }
+        None => None,
+    };
 
     let now = Utc::now();
-    let doc = doc! {
+    let mut doc = doc! {
         "lecture_id": lecture_oid,
         "user_id": user_oid,
         "content": &payload.content,
         "created_at": BsonDateTime::from_millis(now.timestamp_millis()),
+        "rehearsal": rehearsal,
+        "is_question": payload.is_question,
+        "shadowed": shadowed,
+        "mentions": mentions.iter().map(|(id, name)| doc! { "user_id": id, "username": name }).collect::<Vec<_>>(),
     };
+    // thread_id 指向所在讨论串的顶层消息，便于整串读取
+    if let Some(parent) = &parent {
+        let parent_oid = parent.get_object_id("_id").map_err(|_| AppError::Internal("讨论数据异常".into()))?;
+        doc.insert("parent_id", parent_oid);
+        doc.insert("thread_id", parent.get_object_id("thread_id").unwrap_or(parent_oid));
+        doc.insert("depth", parent.get_i32("depth").unwrap_or(0) + 1);
+    }
 
-    let result = coll
-        .insert_one(doc, None)
+    let inserted_oid = retry::insert_one(&coll, &mut doc)
         .await
-        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "插入失败".into()))?;
-
-    let id = result
-        .inserted_id
-        .as_object_id()
-        .unwrap()
-        .to_hex();
+        .map_err(|e| retry::db_error(e, "插入失败"))?;
+    // 彩排中的消息不打扰他人
+    if !rehearsal && !shadowed {
+        notify_mentions(&client, &mentions, &payload.content, user_oid, lecture_oid, inserted_oid).await;
+        if let Some(parent) = &parent {
+            notify_reply(&client, parent, &payload.content, user_oid, inserted_oid).await;
+        }
+    }
+    let id = inserted_oid.to_hex();
 
-    Ok(RespJson(DiscussionOut {
+    let out = DiscussionOut {
         id,
         lecture_id: payload.lecture_id,
         user_id: payload.user_id,
         content: payload.content,
         created_at: now,
-    }))
+        parent_id: parent.and_then(|p| p.get_object_id("_id").ok()).map(|oid| oid.to_hex()),
+        is_question: payload.is_question,
+        mentions: mentions.into_iter().map(|(id, username)| MentionOut { user_id: id.to_hex(), username }).collect(),
+    };
+    let mut event = serde_json::to_value(&out).unwrap_or_default();
+    if let Some(obj) = event.as_object_mut() {
+        obj.insert("rehearsal".to_string(), serde_json::json!(rehearsal));
+    }
+    if !shadowed {
+        realtime::publish(lecture_oid, "discussion.created", event);
+    }
+    Ok(RespJson(out))
 }
 
-// GET /discussion/lecture/{lecture_id}
-async fn get_discussions_by_lecture(
-    State(client): State<AppState>,
-    Path(lecture_id): Path<String>,
-) -> Result<RespJson<Vec<DiscussionOutWithUser>>, (StatusCode, String)> {
-    let disc_coll = discussion_collection(&client);
-    let user_coll = user_collection(&client);
-    let lecture_oid = ObjectId::parse_str(&lecture_id)
-        .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid lecture_id".into()))?;
-
-    let mut cursor = disc_coll
-        .find(doc! { "lecture_id": lecture_oid }, None)
-        .await
-        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "查询失败".into()))?;
-
-    let mut list = Vec::new();
-    while let Some(doc) = cursor.try_next().await.map_err(|_| {
-        (StatusCode::INTERNAL_SERVER_ERROR, "读取失败".into())
-    })? {
+// 附上发送者信息及 viewer 自己的回应；同一用户只查询一次
+async fn with_users(client: &AppState, viewer: ObjectId, docs: Vec<bson::Document>) -> Result<Vec<DiscussionOutWithUser>, AppError> {
+    let user_coll = user_collection(client);
+    let ids: Vec<ObjectId> = docs.iter().filter_map(|d| d.get_object_id("_id").ok()).collect();
+    let mut mine = reaction::mine(client, viewer, &ids).await?;
+    let mut users: HashMap<ObjectId, bson::Document> = HashMap::new();
+    let mut list = Vec::with_capacity(docs.len());
+    for doc in docs {
         let user_oid = doc.get_object_id("user_id").map_err(|_| {
-            (StatusCode::INTERNAL_SERVER_ERROR, "user_id 缺失".into())
+            AppError::Internal("user_id 缺失".into())
         })?;
-        let user_doc = user_coll
-            .find_one(doc! { "_id": user_oid }, None)
-            .await
-            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "查询用户失败".into()))?
-            .unwrap_or(doc! { "username": "未知用户", "avatar": "" });
+        if let std::collections::hash_map::Entry::Vacant(entry) = users.entry(user_oid) {
+            let user_doc = user_coll
+                .find_one(doc! { "_id": user_oid }, None)
+                .await
+                .map_err(|_| AppError::Internal("查询用户失败".into()))?
+                .unwrap_or(doc! { "username": "未知用户", "avatar": "" });
+            entry.insert(user_doc);
+        }
+        let user_doc = &users[&user_oid];
 
         list.push(DiscussionOutWithUser {
             id: doc.get_object_id("_id").unwrap().to_hex(),
-            lecture_id: lecture_oid.to_hex(),
+            lecture_id: doc.get_object_id("lecture_id").map(|oid| oid.to_hex()).unwrap_or_default(),
             user_id: user_oid.to_hex(),
             content: doc.get_str("content").unwrap_or("").to_string(),
             created_at: doc
@@ -121,14 +326,496 @@ async fn get_discussions_by_lecture(
                 .unwrap_or(Utc::now()),
             username: user_doc.get_str("username").unwrap_or("未知用户").to_string(),
             avatar: user_doc.get_str("avatar").unwrap_or("").to_string(),
+            parent_id: doc.get_object_id("parent_id").ok().map(|oid| oid.to_hex()),
+            is_question: doc.get_bool("is_question").unwrap_or(false),
+            answered: doc.get_bool("answered").unwrap_or(false),
+            mentions: mentions_of(&doc),
+            pinned: doc.get_bool("pinned").unwrap_or(false),
+            hidden: doc.get_bool("hidden").unwrap_or(false),
+            reactions: reaction::counts(&doc),
+            my_reactions: mine.remove(&doc.get_object_id("_id").unwrap().to_hex()).unwrap_or_default(),
+            replies: None,
         });
     }
+    Ok(list)
+}
+
+// 把回复挂到各自的上级消息下，同级按发送顺序
+fn attach_replies(node: &mut DiscussionOutWithUser, children: &mut HashMap<String, Vec<DiscussionOutWithUser>>) {
+    let mut replies = children.remove(&node.id).unwrap_or_default();
+    for reply in replies.iter_mut() {
+        attach_replies(reply, children);
+    }
+    node.replies = Some(replies);
+}
+
+// GET /discussion/lecture/{lecture_id}?threaded= -> 默认按发送顺序平铺（带 parent_id）；
+// threaded=true 时分页顶层消息，回复嵌套返回；已隐藏的消息及他人的静默消息仅管理者带 include_hidden=true 可见
+async fn get_discussions_by_lecture(
+    State(client): State<AppState>,
+    auth: AuthUser,
+    Path(lecture_id): Path<String>,
+    Query(query): Query<ListQuery>,
+    paging: PageParams,
+) -> Result<Response, AppError> {
+    let disc_coll = discussion_collection(&client);
+    let lecture_oid = ObjectId::parse_str(&lecture_id)
+        .map_err(|_| AppError::BadRequest("Invalid lecture_id".into()))?;
 
-    Ok(RespJson(list))
+    let mut visible = doc! { "lecture_id": lecture_oid };
+    if query.include_hidden {
+        require_lecture_role(&client, lecture_oid, &auth, LectureRole::Moderator).await?;
+    } else {
+        visible.extend(moderation::visible_filter(Some(auth.id)));
+    }
+    let order = match query.sort.as_deref().map(str::trim).filter(|s| !s.is_empty()) {
+        None => doc! { "_id": 1 },
+        Some("top") => doc! { format!("reactions.{}", reaction::UPVOTE): -1, "_id": 1 },
+        Some(other) => return Err(AppError::BadRequest(format!("sort 仅支持 top: {}", other))),
+    };
+    let mut filter = visible.clone();
+    if query.threaded {
+        filter.insert("parent_id", bson::Bson::Null);
+    }
+    let total = disc_coll
+        .count_documents(filter.clone(), None)
+        .await
+        .map_err(|_| AppError::Internal("查询失败".into()))?;
+    // 按发送顺序分页
+    let docs: Vec<bson::Document> = disc_coll
+        .find(filter, paging.find_options(order))
+        .await
+        .map_err(|_| AppError::Internal("查询失败".into()))?
+        .try_collect()
+        .await
+        .map_err(|_| AppError::Internal("读取失败".into()))?;
+    if !query.threaded {
+        return Ok(paging.respond(with_users(&client, auth.id, docs).await?, total));
+    }
+
+    let roots: Vec<ObjectId> = docs.iter().filter_map(|d| d.get_object_id("_id").ok()).collect();
+    let mut reply_filter = visible;
+    reply_filter.insert("thread_id", doc! { "$in": &roots });
+    let reply_docs: Vec<bson::Document> = disc_coll
+        .find(
+            reply_filter,
+            FindOptions::builder().sort(doc! { "_id": 1 }).build(),
+        )
+        .await
+        .map_err(|_| AppError::Internal("查询失败".into()))?
+        .try_collect()
+        .await
+        .map_err(|_| AppError::Internal("读取失败".into()))?;
+    let mut children: HashMap<String, Vec<DiscussionOutWithUser>> = HashMap::new();
+    for reply in with_users(&client, auth.id, reply_docs).await? {
+        children.entry(reply.parent_id.clone().unwrap_or_default()).or_default().push(reply);
+    }
+    let mut list = with_users(&client, auth.id, docs).await?;
+    for node in list.iter_mut() {
+        attach_replies(node, &mut children);
+    }
+    Ok(paging.respond(list, total))
+}
+
+// GET /discussion/lecture/{lecture_id}/summary
+async fn discussion_summary(
+    State(client): State<AppState>,
+    Path(lecture_id): Path<String>,
+) -> Result<RespJson<serde_json::Value>, AppError> {
+    let disc_coll = discussion_collection(&client);
+    let lecture_oid = ObjectId::parse_str(&lecture_id)
+        .map_err(|_| AppError::BadRequest("Invalid lecture_id".into()))?;
+
+    let mut cursor = disc_coll
+        .find(doc! { "lecture_id": lecture_oid, "hidden": { "$ne": true }, "shadowed": { "$ne": true } }, None)
+        .await
+        .map_err(|_| AppError::Internal("查询失败".into()))?;
+
+    let mut messages = Vec::new();
+    let mut questions = Vec::new();
+    while let Some(doc) = cursor.try_next().await.map_err(|_| {
+        AppError::Internal("读取失败".into())
+    })? {
+        let content = doc.get_str("content").unwrap_or("").to_string();
+        let upvotes = doc.get_i32("upvotes").unwrap_or(0);
+        if doc.get_bool("is_question").unwrap_or(false) || looks_like_question(&content) {
+            questions.push(serde_json::json!({
+                "id": ids::oid_hex(&doc, "_id"),
+                "content": &content,
+                "upvotes": upvotes,
+            }));
+        }
+        messages.push(content);
+    }
+
+    questions.sort_by(|a, b| b["upvotes"].as_i64().cmp(&a["upvotes"].as_i64()));
+    questions.truncate(10);
+
+    let keywords = top_keywords(&messages, 20);
+    let digest = SUMMARIZER.summarize(&messages, &keywords).await;
+
+    Ok(RespJson(serde_json::json!({
+        "lecture_id": lecture_id,
+        "message_count": messages.len(),
+        "keywords": keywords
+            .iter()
+            .map(|(word, count)| serde_json::json!({ "word": word, "count": count }))
+            .collect::<Vec<_>>(),
+        "top_questions": questions,
+        "summary": digest,
+        "summarizer": SUMMARIZER.name(),
+    })))
+}
+
+// =============== 问答 ===============
+
+async fn load_discussion(client: &AppState, discussion_id: &str, viewer: ObjectId) -> Result<(ObjectId, bson::Document), AppError> {
+    let oid = ids::parse_oid(discussion_id, "discussion_id")?;
+    let mut filter = doc! { "_id": oid };
+    filter.extend(moderation::visible_filter(Some(viewer)));
+    let discussion = discussion_collection(client)
+        .find_one(filter, None)
+        .await?
+        .ok_or(AppError::NotFound("Discussion not found".into()))?;
+    Ok((oid, discussion))
+}
+
+// POST /discussion/:id/translate?to=en -> 消息译文；译文按语言缓存在消息的 translations 字段上
+async fn translate_discussion(
+    State(client): State<AppState>,
+    auth: AuthUser,
+    Path(discussion_id): Path<String>,
+    Query(query): Query<TranslateQuery>,
+) -> Result<RespJson<serde_json::Value>, AppError> {
+    let lang = translate::normalize_lang(&query.to)
+        .ok_or(AppError::BadRequest("to 需为语言代码，如 en、zh-CN".into()))?;
+    let (oid, discussion) = load_discussion(&client, &discussion_id, auth.id).await?;
+    let original = discussion.get_str("content").unwrap_or("");
+    let cached = discussion
+        .get_document("translations")
+        .and_then(|t| t.get_document(&lang))
+        .ok();
+    if let Some(entry) = cached {
+        return Ok(RespJson(serde_json::json!({
+            "id": discussion_id,
+            "to": lang,
+            "content": entry.get_str("text").unwrap_or(""),
+            "original": original,
+            "provider": entry.get_str("provider").unwrap_or(""),
+            "cached": true,
+        })));
+    }
+
+    let translator = translate::TRANSLATOR
+        .as_ref()
+        .ok_or(AppError::Unavailable("未启用翻译服务".into()))?;
+    let text = translator.translate(original, &lang).await.map_err(|e| {
+        println!("[translate] {} 翻译失败 {}: {}", translator.name(), discussion_id, e);
+        AppError::Unavailable("翻译服务暂时不可用，请稍后重试".into())
+    })?;
+    discussion_collection(&client)
+        .update_one(
+            doc! { "_id": oid },
+            doc! { "$set": { format!("translations.{}", lang): {
+                "text": &text,
+                "provider": translator.name(),
+                "translated_at": Utc::now().timestamp_millis(),
+            } } },
+            None,
+        )
+        .await?;
+    Ok(RespJson(serde_json::json!({
+        "id": discussion_id,
+        "to": lang,
+        "content": text,
+        "original": original,
+        "provider": translator.name(),
+        "cached": false,
+    })))
+}
+
+// POST /discussion/:id/question {is_question} -> 作者本人或讨论管理员标记/取消提问
+async fn flag_question(
+    State(client): State<AppState>,
+    auth: AuthUser,
+    Path(discussion_id): Path<String>,
+    Json(payload): Json<QuestionFlag>,
+) -> Result<RespJson<serde_json::Value>, AppError> {
+    let (oid, discussion) = load_discussion(&client, &discussion_id, auth.id).await?;
+    let lecture_oid = discussion
+        .get_object_id("lecture_id")
+        .map_err(|_| AppError::Internal("讨论数据异常".into()))?;
+    if discussion.get_object_id("user_id").ok() != Some(auth.id) {
+        require_lecture_role(&client, lecture_oid, &auth, LectureRole::Moderator).await?;
+    }
+    discussion_collection(&client)
+        .update_one(doc! { "_id": oid }, doc! { "$set": { "is_question": payload.is_question } }, None)
+        .await?;
+    if !discussion.get_bool("shadowed").unwrap_or(false) {
+        realtime::publish(lecture_oid, "discussion.question", serde_json::json!({ "id": discussion_id, "is_question": payload.is_question }));
+    }
+    Ok(RespJson(serde_json::json!({ "id": discussion_id, "is_question": payload.is_question })))
+}
+
+// POST /discussion/:id/answered {answered} -> 组织者或讲者标记提问已回答/未回答，标记已回答时通知提问者
+async fn mark_answered(
+    State(client): State<AppState>,
+    auth: AuthUser,
+    Path(discussion_id): Path<String>,
+    Json(payload): Json<AnsweredFlag>,
+) -> Result<RespJson<serde_json::Value>, AppError> {
+    let (oid, discussion) = load_discussion(&client, &discussion_id, auth.id).await?;
+    let lecture_oid = discussion
+        .get_object_id("lecture_id")
+        .map_err(|_| AppError::Internal("讨论数据异常".into()))?;
+    let lecture = load_lecture(&client, &lecture_oid.to_hex()).await?;
+    if !is_host(&lecture, &auth.id_hex()) {
+        return Err(AppError::Forbidden("只有组织者或讲者可以标记提问已回答".into()));
+    }
+    if !discussion.get_bool("is_question").unwrap_or(false) {
+        return Err(AppError::Conflict("该消息不是提问".into()));
+    }
+    let update = if payload.answered {
+        doc! { "$set": { "answered": true, "answered_by": auth.id, "answered_at": Utc::now().timestamp_millis() } }
+    } else {
+        doc! { "$set": { "answered": false }, "$unset": { "answered_by": "", "answered_at": "" } }
+    };
+    let result = discussion_collection(&client)
+        .update_one(doc! { "_id": oid, "answered": { "$ne": payload.answered } }, update, None)
+        .await?;
+    if result.modified_count > 0 {
+        realtime::publish(lecture_oid, "discussion.answered", serde_json::json!({ "id": discussion_id, "answered": payload.answered }));
+        let author = discussion.get_object_id("user_id").ok().filter(|u| *u != auth.id);
+        if let (true, Some(author), false) = (payload.answered, author, discussion.get_bool("rehearsal").unwrap_or(false)) {
+            let notice = doc! {
+                "lecture_id": lecture_oid.to_hex(),
+                "discussion_id": &discussion_id,
+                "excerpt": discussion.get_str("content").unwrap_or("").chars().take(100).collect::<String>(),
+            };
+            if let Err(e) = notify::push(&client, author, "question_answered", notice).await {
+                println!("发送提问已回答通知失败 {}: {}", author.to_hex(), e);
+            }
+        }
+    }
+    Ok(RespJson(serde_json::json!({ "id": discussion_id, "answered": payload.answered })))
+}
+
+// GET /discussion/lecture/:lecture_id/questions?unanswered= -> 讲者的问题队列：
+// 未回答的在前，同状态按点赞数从高到低、再按提问先后
+async fn list_questions(
+    State(client): State<AppState>,
+    auth: AuthUser,
+    Path(lecture_id): Path<String>,
+    Query(query): Query<QuestionQuery>,
+    paging: PageParams,
+) -> Result<Response, AppError> {
+    let lecture_oid = ids::parse_oid(&lecture_id, "lecture_id")?;
+    let mut filter = doc! { "lecture_id": lecture_oid, "is_question": true };
+    filter.extend(moderation::visible_filter(Some(auth.id)));
+    if query.unanswered {
+        filter.insert("answered", doc! { "$ne": true });
+    }
+    let coll = discussion_collection(&client);
+    let total = coll.count_documents(filter.clone(), None).await?;
+    let order = doc! { "answered": 1, format!("reactions.{}", reaction::UPVOTE): -1, "_id": 1 };
+    let docs: Vec<bson::Document> = coll.find(filter, paging.find_options(order)).await?.try_collect().await?;
+    Ok(paging.respond(with_users(&client, auth.id, docs).await?, total))
+}
+
+// 控制面板用：未回答提问总数及最新的 limit 条（新到旧）
+pub async fn newest_unanswered(client: &AppState, lecture_oid: ObjectId, viewer: ObjectId, limit: i64) -> Result<(u64, serde_json::Value), AppError> {
+    let mut filter = doc! { "lecture_id": lecture_oid, "is_question": true, "answered": { "$ne": true } };
+    filter.extend(moderation::visible_filter(Some(viewer)));
+    let coll = discussion_collection(client);
+    let total = coll.count_documents(filter.clone(), None).await?;
+    let options = FindOptions::builder().sort(doc! { "_id": -1 }).limit(limit).build();
+    let docs: Vec<bson::Document> = coll.find(filter, options).await?.try_collect().await?;
+    let items = serde_json::to_value(with_users(client, viewer, docs).await?).map_err(|_| AppError::Internal("序列化失败".into()))?;
+    Ok((total, items))
+}
+
+// =============== SSE 推送 ===============
+
+// 断线重连时一次补发的最大条数，更早的消息请走分页接口
+const STREAM_BACKLOG_LIMIT: i64 = 500;
+
+// 与实时推送的 discussion.created 事件数据一致
+fn stream_item(doc: &bson::Document) -> Option<(ObjectId, serde_json::Value)> {
+    let id = doc.get_object_id("_id").ok()?;
+    let out = DiscussionOut {
+        id: id.to_hex(),
+        lecture_id: doc.get_object_id("lecture_id").ok()?.to_hex(),
+        user_id: doc.get_object_id("user_id").ok()?.to_hex(),
+        content: doc.get_str("content").unwrap_or("").to_string(),
+        created_at: doc.get_datetime("created_at").map(|dt| dt.to_chrono()).unwrap_or(Utc::now()),
+        parent_id: doc.get_object_id("parent_id").ok().map(|oid| oid.to_hex()),
+        is_question: doc.get_bool("is_question").unwrap_or(false),
+        mentions: mentions_of(doc),
+    };
+    let mut v = serde_json::to_value(&out).ok()?;
+    if let Some(obj) = v.as_object_mut() {
+        obj.insert("rehearsal".to_string(), serde_json::json!(doc.get_bool("rehearsal").unwrap_or(false)));
+    }
+    Some((id, v))
+}
+
+// 指定消息之后的讨论（按发送顺序）
+async fn discussions_after(client: &AppState, lecture_oid: ObjectId, after: ObjectId) -> VecDeque<(ObjectId, serde_json::Value)> {
+    let options = FindOptions::builder().sort(doc! { "_id": 1 }).limit(STREAM_BACKLOG_LIMIT).build();
+    let mut filter = doc! { "lecture_id": lecture_oid, "_id": { "$gt": after } };
+    filter.extend(moderation::visible_filter(None));
+    match discussion_collection(client).find(filter, options).await {
+        Ok(cursor) => cursor
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap_or_default()
+            .iter()
+            .filter_map(stream_item)
+            .collect(),
+        Err(e) => {
+            println!("[sse] 补发讨论失败 {}: {}", lecture_oid.to_hex(), e);
+            VecDeque::new()
+        }
+    }
+}
+
+struct StreamState {
+    client: AppState,
+    lecture: ObjectId,
+    sub: realtime::Subscription,
+    // 已发送的最后一条消息，用于去重及积压时从数据库补齐
+    last: Option<ObjectId>,
+    pending: VecDeque<(ObjectId, serde_json::Value)>,
+}
+
+impl StreamState {
+    async fn next_event(&mut self) -> Option<Event> {
+        loop {
+            if let Some((id, data)) = self.pending.pop_front() {
+                if self.last.is_some_and(|last| id <= last) {
+                    continue;
+                }
+                self.last = Some(id);
+                return Some(Event::default().id(id.to_hex()).event("discussion").data(data.to_string()));
+            }
+            match self.sub.recv().await {
+                Ok(text) => {
+                    let Ok(event) = serde_json::from_str::<serde_json::Value>(&text) else { continue };
+                    if event["type"] != "discussion.created" {
+                        continue;
+                    }
+                    let Some(id) = event["data"]["id"].as_str().and_then(|s| ObjectId::parse_str(s).ok()) else { continue };
+                    self.pending.push_back((id, event["data"].clone()));
+                }
+                // 消费太慢被跳过的消息从数据库补齐
+                Err(RecvError::Lagged(_)) => {
+                    if let Some(last) = self.last {
+                        self.pending = discussions_after(&self.client, self.lecture, last).await;
+                    }
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    }
+}
+
+// GET /discussion/lecture/:lecture_id/stream -> 无法使用 WebSocket 的客户端改用 SSE 接收新消息。
+// 事件名 discussion，id 为消息 ID；携带 Last-Event-ID 重连时先补发其后的消息
+async fn stream_discussions(
+    State(client): State<AppState>,
+    Path(lecture_id): Path<String>,
+    Query(query): Query<StreamQuery>,
+    headers: HeaderMap,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, AppError> {
+    let lecture = load_lecture(&client, &lecture_id).await?;
+    let lecture_oid = lecture.get_object_id("_id").map_err(|_| AppError::Internal("演讲ID无效".into()))?;
+    let last = match headers.get("last-event-id").and_then(|v| v.to_str().ok()).or(query.last_event_id.as_deref()) {
+        Some(raw) if !raw.trim().is_empty() => Some(ids::parse_oid(raw.trim(), "Last-Event-ID")?),
+        _ => None,
+    };
+
+    // 先订阅再补发，两者之间写入的消息靠 last 去重
+    let sub = realtime::subscribe(lecture_oid);
+    let pending = match last {
+        Some(last) => discussions_after(&client, lecture_oid, last).await,
+        None => VecDeque::new(),
+    };
+    let state = StreamState { client, lecture: lecture_oid, sub, last, pending };
+    let events = stream::unfold(state, |mut state| async move {
+        let event = state.next_event().await?;
+        Some((Ok(event), state))
+    });
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+// DELETE /discussion/:discussion_id -> 删除一条讨论：本人、演讲的组织者/讲者或本场讨论管理员
+async fn delete_discussion(
+    State(client): State<AppState>,
+    auth: AuthUser,
+    Path(discussion_id): Path<String>,
+) -> Result<RespJson<serde_json::Value>, AppError> {
+    let oid = ids::parse_oid(&discussion_id, "discussion_id")?;
+    let coll = discussion_collection(&client);
+    let discussion = coll
+        .find_one(doc! { "_id": oid }, None)
+        .await?
+        .ok_or(AppError::NotFound("Discussion not found".into()))?;
+    let lecture_oid = discussion
+        .get_object_id("lecture_id")
+        .map_err(|_| AppError::Internal("讨论数据异常".into()))?;
+    let moderated = discussion.get_object_id("user_id").ok() != Some(auth.id);
+    if moderated {
+        require_lecture_role(&client, lecture_oid, &auth, LectureRole::Moderator).await?;
+    }
+    // 连同其下的全部回复一起删除，避免讨论串中留下无主的回复
+    let mut removed = vec![oid];
+    let mut frontier = vec![oid];
+    while !frontier.is_empty() {
+        frontier = coll
+            .distinct("_id", doc! { "parent_id": { "$in": &frontier } }, None)
+            .await?
+            .iter()
+            .filter_map(|v| v.as_object_id())
+            .collect();
+        removed.extend(&frontier);
+    }
+    let result = coll.delete_many(doc! { "_id": { "$in": &removed } }, None).await?;
+    reaction_collection(&client).delete_many(doc! { "discussion_id": { "$in": &removed } }, None).await?;
+    // 管理者删除他人消息时留痕，原文写入审计以备申诉
+    if moderated {
+        audit::record(
+            &client,
+            Some(auth.id),
+            "discussion.delete",
+            &format!("discussion:{}", discussion_id),
+            doc! {
+                "lecture_id": lecture_oid.to_hex(),
+                "user_id": discussion.get_object_id("user_id").map(|oid| oid.to_hex()).unwrap_or_default(),
+                "content": discussion.get_str("content").unwrap_or(""),
+                "replies": (removed.len() - 1) as i64,
+            },
+        )
+        .await;
+    }
+    realtime::publish(
+        lecture_oid,
+        "discussion.deleted",
+        serde_json::json!({ "ids": removed.iter().map(|oid| oid.to_hex()).collect::<Vec<_>>() }),
+    );
+    Ok(RespJson(serde_json::json!({ "message": "讨论已删除", "id": discussion_id, "deleted": result.deleted_count })))
 }
 
 pub fn router() -> Router<AppState> {
     Router::new()
         .route("/add", post(add_discussion))
+        .route("/:discussion_id", delete(delete_discussion))
         .route("/lecture/:lecture_id", get(get_discussions_by_lecture))
+        .route("/lecture/:lecture_id/summary", get(discussion_summary))
+        .route("/lecture/:lecture_id/stream", get(stream_discussions))
+        .route("/:discussion_id/question", post(flag_question))
+        .route("/:discussion_id/translate", post(translate_discussion))
+        .route("/:discussion_id/answered", post(mark_answered))
+        .route("/lecture/:lecture_id/questions", get(list_questions))
+        .merge(moderation::router())
+        .merge(reaction::router())
 }
\ No newline at end of file
@@ -0,0 +1,303 @@
+// src/routes/embed.rs
+// 供院系网站嵌入的公开接口：只返回公开字段，可选渲染 HTML 片段。
+// 演讲可设置 embed_origins（未设置时沿用所属组织设置中的 embed_origins），设置后嵌入与按演讲码查询
+// 只接受来自这些来源（及本站前端）的请求，来源取 Origin，没有时取 Referer；CORS 只约束浏览器，这里在服务端再校验一次
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, HeaderMap},
+    response::{Html, IntoResponse, Json, Response},
+    routing::get,
+    Router,
+};
+use bson::{doc, oid::ObjectId, Document};
+use chrono::Utc;
+use futures_util::TryStreamExt;
+use mongodb::{options::FindOptions, Client};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::{config, ids};
+use crate::db::{lecture_collection, organization_collection, user_collection};
+use crate::routes::organization::branding_for;
+use crate::error::AppError;
+use crate::lifecycle::LectureStatus;
+
+type AppState = Arc<Client>;
+
+// ==================== 模型 ====================
+
+#[derive(Deserialize, Default)]
+struct EmbedQuery {
+    // json（默认）或 html
+    format: Option<String>,
+    limit: Option<i64>,
+}
+
+const EMBED_CACHE_CONTROL: &str = "public, max-age=60";
+const UPCOMING_DEFAULT_LIMIT: i64 = 10;
+const UPCOMING_MAX_LIMIT: i64 = 50;
+const MAX_EMBED_ORIGINS: usize = 20;
+
+// ==================== 工具函数 ====================
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+// scheme://host[:port]，去掉路径，统一小写
+fn origin_of(url: &str) -> Option<String> {
+    let (scheme, rest) = url.trim().split_once("://")?;
+    let host = rest.split(['/', '?', '#']).next().unwrap_or("");
+    if host.is_empty() || !(scheme.eq_ignore_ascii_case("http") || scheme.eq_ignore_ascii_case("https")) {
+        return None;
+    }
+    Some(format!("{}://{}", scheme, host).to_lowercase())
+}
+
+// 校验并规范化来源列表，演讲与组织设置共用
+pub fn normalize_origins(raw: Vec<String>, field: &str) -> Result<Vec<String>, AppError> {
+    let mut origins = Vec::new();
+    for item in raw.iter().map(|o| o.trim()).filter(|o| !o.is_empty()) {
+        let origin = origin_of(item)
+            .ok_or_else(|| AppError::BadRequest(format!("{} 需以 http:// 或 https:// 开头", field)))?;
+        if !origins.contains(&origin) {
+            origins.push(origin);
+        }
+    }
+    if origins.len() > MAX_EMBED_ORIGINS {
+        return Err(AppError::BadRequest(format!("{} 最多 {} 个", field, MAX_EMBED_ORIGINS)));
+    }
+    Ok(origins)
+}
+
+fn request_origin(headers: &HeaderMap) -> Option<String> {
+    [header::ORIGIN, header::REFERER]
+        .iter()
+        .filter_map(|h| headers.get(h).and_then(|v| v.to_str().ok()))
+        .find_map(origin_of)
+}
+
+fn origin_list(doc: Option<&Document>) -> Vec<String> {
+    doc.and_then(|d| d.get_array("embed_origins").ok())
+        .into_iter()
+        .flatten()
+        .filter_map(|o| o.as_str().map(str::to_string))
+        .collect()
+}
+
+// 演讲允许的嵌入来源：演讲自身的 embed_origins 优先，未设置时取所属组织设置中的 embed_origins
+pub async fn embed_origins_for(client: &AppState, lecture: &Document) -> Result<Vec<String>, AppError> {
+    let own = origin_list(Some(lecture));
+    if !own.is_empty() {
+        return Ok(own);
+    }
+    let Some(org_oid) = lecture.get_str("org_id").ok().and_then(|id| ObjectId::parse_str(id).ok()) else {
+        return Ok(own);
+    };
+    let org = organization_collection(client)
+        .find_one(doc! { "_id": org_oid }, None)
+        .await
+        .map_err(|_| AppError::Internal("查询失败".into()))?;
+    Ok(origin_list(org.as_ref().and_then(|o| o.get_document("settings").ok())))
+}
+
+// 来源名单非空时，请求来源须在名单内或为本站前端
+pub fn ensure_origin_allowed(origins: &[String], headers: &HeaderMap) -> Result<(), AppError> {
+    if origins.is_empty() {
+        return Ok(());
+    }
+    let origin = request_origin(headers).ok_or(AppError::Forbidden("该演讲仅允许在指定网站访问".into()))?;
+    let cfg = config::get();
+    let allowed = origins.contains(&origin)
+        || cfg.cors_origins.iter().filter_map(|o| origin_of(o)).any(|o| o == origin)
+        || origin_of(&config::get().public_base_url).as_deref() == Some(origin.as_str());
+    if allowed {
+        Ok(())
+    } else {
+        Err(AppError::Forbidden(format!("该演讲不允许在 {} 访问", origin)))
+    }
+}
+
+async fn speaker_names(client: &AppState, lectures: &[Document]) -> HashMap<String, String> {
+    let ids: Vec<ObjectId> = lectures
+        .iter()
+        .filter_map(|l| l.get_str("speaker_id").ok())
+        .filter_map(|s| ObjectId::parse_str(s).ok())
+        .collect();
+    let mut names = HashMap::new();
+    if ids.is_empty() {
+        return names;
+    }
+    if let Ok(mut cursor) = user_collection(client).find(doc! { "_id": { "$in": ids } }, None).await {
+        while let Ok(Some(u)) = cursor.try_next().await {
+            if let Ok(oid) = u.get_object_id("_id") {
+                names.insert(oid.to_hex(), u.get_str("username").unwrap_or("").to_string());
+            }
+        }
+    }
+    names
+}
+
+// 只暴露公开字段：不含演讲码、组织者等内部信息
+fn public_fields(lecture: &Document, names: &HashMap<String, String>) -> serde_json::Value {
+    let speaker = lecture
+        .get_str("speaker_id")
+        .ok()
+        .and_then(|id| names.get(id))
+        .cloned()
+        .unwrap_or_default();
+    serde_json::json!({
+        "id": ids::oid_hex(lecture, "_id"),
+        "topic": lecture.get_str("topic").unwrap_or(""),
+        "description": lecture.get_str("description").unwrap_or(""),
+        "start_time": lecture.get_i64("start_time").unwrap_or(0),
+        "duration": lecture.get_i32("duration").unwrap_or(0),
+        "status": lecture.get_i32("status").unwrap_or(0),
+        "speaker": speaker,
+    })
+}
+
+fn render_item(item: &serde_json::Value) -> String {
+    let start = chrono::DateTime::from_timestamp_millis(item["start_time"].as_i64().unwrap_or(0))
+        .map(|t| t.format("%Y-%m-%d %H:%M UTC").to_string())
+        .unwrap_or_default();
+    format!(
+        "<li class=\"rm-lecture\"><strong>{}</strong> <span class=\"rm-time\">{}</span> <span class=\"rm-speaker\">{}</span></li>",
+        escape_html(item["topic"].as_str().unwrap_or("")),
+        start,
+        escape_html(item["speaker"].as_str().unwrap_or("")),
+    )
+}
+
+fn render_list(items: &[serde_json::Value], branding: &serde_json::Value) -> String {
+    let color = branding["accent_color"].as_str().unwrap_or("#409eff");
+    let body: String = items.iter().map(render_item).collect();
+    format!(
+        "<ul class=\"rm-embed\" style=\"border-left:3px solid {}\">{}</ul>",
+        escape_html(color),
+        body
+    )
+}
+
+fn respond(query: &EmbedQuery, json: serde_json::Value, html: String) -> Response {
+    let mut resp = if query.format.as_deref() == Some("html") {
+        Html(html).into_response()
+    } else {
+        Json(json).into_response()
+    };
+    resp.headers_mut()
+        .insert(header::CACHE_CONTROL, header::HeaderValue::from_static(EMBED_CACHE_CONTROL));
+    resp
+}
+
+// ==================== 路由 ====================
+
+// GET /embed/lecture/:id?format=json|html
+async fn embed_lecture(
+    State(client): State<AppState>,
+    Path(lecture_id): Path<String>,
+    Query(query): Query<EmbedQuery>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let oid = ObjectId::parse_str(&lecture_id)
+        .map_err(|_| AppError::BadRequest("无效的 lecture_id".into()))?;
+    // 与即将开始列表一致：草稿与已取消的演讲不公开
+    let filter = doc! {
+        "_id": oid,
+        "status": { "$nin": [LectureStatus::Draft.as_i32(), LectureStatus::Cancelled.as_i32()] },
+        "archived": { "$ne": true },
+    };
+    let lecture = lecture_collection(&client)
+        .find_one(filter, None)
+        .await
+        .map_err(|_| AppError::Internal("查询失败".into()))?
+        .ok_or(AppError::NotFound("Lecture not found".into()))?;
+    let origins = embed_origins_for(&client, &lecture).await?;
+    ensure_origin_allowed(&origins, &headers)?;
+
+    let names = speaker_names(&client, std::slice::from_ref(&lecture)).await;
+    let item = public_fields(&lecture, &names);
+    let branding = match lecture.get_str("org_id") {
+        Ok(org_id) => branding_for(&client, org_id).await.unwrap_or_default(),
+        Err(_) => serde_json::Value::Null,
+    };
+    let html = render_list(std::slice::from_ref(&item), &branding);
+    let mut json = item;
+    json["branding"] = branding;
+    let mut resp = respond(&query, json, html);
+    // 结果因来源而异，共享缓存需按来源区分
+    if !origins.is_empty() {
+        resp.headers_mut()
+            .insert(header::VARY, header::HeaderValue::from_static("origin, referer"));
+    }
+    Ok(resp)
+}
+
+// GET /embed/organizer/:id/upcoming?limit=&format=json|html
+// 设置了来源名单且不允许当前来源的演讲不出现在列表中
+async fn embed_upcoming(
+    State(client): State<AppState>,
+    Path(organizer_id): Path<String>,
+    Query(query): Query<EmbedQuery>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    ObjectId::parse_str(&organizer_id)
+        .map_err(|_| AppError::BadRequest("无效的 organizer_id".into()))?;
+    let limit = query.limit.unwrap_or(UPCOMING_DEFAULT_LIMIT).clamp(1, UPCOMING_MAX_LIMIT);
+    let options = FindOptions::builder()
+        .sort(doc! { "start_time": 1 })
+        .limit(limit)
+        .build();
+    let filter = doc! {
+        "organizer_id": &organizer_id,
+        // 嵌入页只展示已公开且未结束、未取消的演讲
+        "status": { "$nin": [
+            LectureStatus::Draft.as_i32(),
+            LectureStatus::Ended.as_i32(),
+            LectureStatus::Cancelled.as_i32(),
+        ] },
+        "archived": { "$ne": true },
+        "start_time": { "$gte": Utc::now().timestamp_millis() },
+    };
+    let lectures: Vec<Document> = lecture_collection(&client)
+        .find(filter, options)
+        .await
+        .map_err(|_| AppError::Internal("查询失败".into()))?
+        .try_collect()
+        .await
+        .map_err(|_| AppError::Internal("读取失败".into()))?;
+
+    let mut visible = Vec::with_capacity(lectures.len());
+    let mut restricted = false;
+    for lecture in lectures {
+        let origins = embed_origins_for(&client, &lecture).await?;
+        restricted |= !origins.is_empty();
+        if ensure_origin_allowed(&origins, &headers).is_ok() {
+            visible.push(lecture);
+        }
+    }
+
+    let names = speaker_names(&client, &visible).await;
+    let items: Vec<serde_json::Value> = visible.iter().map(|l| public_fields(l, &names)).collect();
+    let html = render_list(&items, &serde_json::Value::Null);
+    let json = serde_json::json!({ "organizer_id": organizer_id, "items": items });
+    let mut resp = respond(&query, json, html);
+    if restricted {
+        resp.headers_mut()
+            .insert(header::VARY, header::HeaderValue::from_static("origin, referer"));
+    }
+    Ok(resp)
+}
+
+// ==================== Router ====================
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/lecture/:id", get(embed_lecture))
+        .route("/organizer/:id/upcoming", get(embed_upcoming))
+}
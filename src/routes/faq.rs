@@ -0,0 +1,233 @@
+// src/routes/faq.rs
+// 演讲 FAQ：组织者维护的问答对，已发布条目随演讲详情一起返回
+use axum::{
+    extract::{Path, State},
+    response::Json,
+    routing::{get, put},
+    Router,
+};
+use bson::{doc, oid::ObjectId, Document};
+use chrono::Utc;
+use futures_util::TryStreamExt;
+use mongodb::{options::FindOptions, Client};
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::ids::{self, parse_oid};
+use crate::retry;
+use crate::auth::{AuthUser, Organizer, RequireRole};
+use crate::db::{faq_collection, lecture_collection};
+use crate::routes::lecture::ensure_lecture_organizer;
+use crate::error::AppError;
+
+type AppState = Arc<Client>;
+
+// ==================== 模型 ====================
+
+#[derive(Deserialize)]
+struct FaqCreate {
+    question: String,
+    answer: String,
+    #[serde(default)]
+    published: bool,
+}
+
+#[derive(Deserialize, Default)]
+struct FaqUpdate {
+    question: Option<String>,
+    answer: Option<String>,
+    published: Option<bool>,
+}
+
+#[derive(Deserialize)]
+struct FaqOrder {
+    // 按新顺序排列的 FAQ id 列表
+    ids: Vec<String>,
+}
+
+const QUESTION_MAX_LEN: usize = 300;
+const ANSWER_MAX_LEN: usize = 3000;
+
+// ==================== 工具函数 ====================
+
+fn check_text(value: &str, field: &str, max: usize) -> Result<String, AppError> {
+    let value = value.trim();
+    if value.is_empty() || value.chars().count() > max {
+        return Err(AppError::BadRequest(format!("{} 需为 1~{} 字", field, max)));
+    }
+    Ok(value.to_string())
+}
+
+fn faq_to_json(doc: &Document) -> serde_json::Value {
+    serde_json::json!({
+        "id": ids::oid_hex(doc, "_id"),
+        "question": doc.get_str("question").unwrap_or(""),
+        "answer": doc.get_str("answer").unwrap_or(""),
+        "position": doc.get_i32("position").unwrap_or(0),
+        "published": doc.get_bool("published").unwrap_or(false),
+        "updated_at": doc.get_i64("updated_at").unwrap_or(0),
+    })
+}
+
+async fn load_faqs(client: &AppState, lecture_oid: ObjectId, published_only: bool) -> Result<Vec<serde_json::Value>, AppError> {
+    let mut filter = doc! { "lecture_id": lecture_oid };
+    if published_only {
+        filter.insert("published", true);
+    }
+    let options = FindOptions::builder().sort(doc! { "position": 1, "_id": 1 }).build();
+    let docs: Vec<Document> = faq_collection(client)
+        .find(filter, options)
+        .await
+        .map_err(|_| AppError::Internal("查询失败".into()))?
+        .try_collect()
+        .await
+        .map_err(|_| AppError::Internal("读取失败".into()))?;
+    Ok(docs.iter().map(faq_to_json).collect())
+}
+
+// 供演讲详情使用：只返回已发布条目
+pub async fn published_faqs(client: &AppState, lecture_oid: ObjectId) -> Vec<serde_json::Value> {
+    load_faqs(client, lecture_oid, true).await.unwrap_or_default()
+}
+
+// ==================== 路由 ====================
+
+// GET /lecture/:lecture_id/faq -> 组织者可见全部，其他用户只见已发布
+async fn list_faq(
+    State(client): State<AppState>,
+    auth: AuthUser,
+    Path(lecture_id): Path<String>,
+) -> Result<Json<Vec<serde_json::Value>>, AppError> {
+    let lecture_oid = parse_oid(&lecture_id, "lecture_id")?;
+    let lecture = lecture_collection(&client)
+        .find_one(doc! { "_id": lecture_oid }, None)
+        .await
+        .map_err(|_| AppError::Internal("查询失败".into()))?
+        .ok_or(AppError::NotFound("Lecture not found".into()))?;
+    let is_organizer = lecture.get_str("organizer_id").ok() == Some(auth.id_hex().as_str());
+    Ok(Json(load_faqs(&client, lecture_oid, !is_organizer).await?))
+}
+
+// POST /lecture/:lecture_id/faq
+async fn create_faq(
+    State(client): State<AppState>,
+    auth: RequireRole<Organizer>,
+    Path(lecture_id): Path<String>,
+    Json(payload): Json<FaqCreate>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let lecture_oid = parse_oid(&lecture_id, "lecture_id")?;
+    ensure_lecture_organizer(&client, lecture_oid, &auth).await?;
+    let question = check_text(&payload.question, "question", QUESTION_MAX_LEN)?;
+    let answer = check_text(&payload.answer, "answer", ANSWER_MAX_LEN)?;
+
+    let coll = faq_collection(&client);
+    // 新条目排在末尾
+    let last = coll
+        .find_one(
+            doc! { "lecture_id": lecture_oid },
+            mongodb::options::FindOneOptions::builder().sort(doc! { "position": -1 }).build(),
+        )
+        .await
+        .map_err(|_| AppError::Internal("查询失败".into()))?;
+    let position = last.and_then(|d| d.get_i32("position").ok()).map(|p| p + 1).unwrap_or(0);
+
+    let mut faq = doc! {
+        "lecture_id": lecture_oid,
+        "question": question,
+        "answer": answer,
+        "position": position,
+        "published": payload.published,
+        "updated_at": Utc::now().timestamp_millis(),
+    };
+    retry::insert_one(&coll, &mut faq)
+        .await
+        .map_err(|e| retry::db_error(e, "创建失败"))?;
+    Ok(Json(faq_to_json(&faq)))
+}
+
+// PUT /lecture/:lecture_id/faq/:faq_id
+async fn update_faq(
+    State(client): State<AppState>,
+    auth: RequireRole<Organizer>,
+    Path((lecture_id, faq_id)): Path<(String, String)>,
+    Json(payload): Json<FaqUpdate>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let lecture_oid = parse_oid(&lecture_id, "lecture_id")?;
+    let faq_oid = parse_oid(&faq_id, "faq_id")?;
+    ensure_lecture_organizer(&client, lecture_oid, &auth).await?;
+
+    let mut set = doc! { "updated_at": Utc::now().timestamp_millis() };
+    if let Some(q) = payload.question {
+        set.insert("question", check_text(&q, "question", QUESTION_MAX_LEN)?);
+    }
+    if let Some(a) = payload.answer {
+        set.insert("answer", check_text(&a, "answer", ANSWER_MAX_LEN)?);
+    }
+    if let Some(p) = payload.published {
+        set.insert("published", p);
+    }
+    let updated = faq_collection(&client)
+        .find_one_and_update(
+            doc! { "_id": faq_oid, "lecture_id": lecture_oid },
+            doc! { "$set": set },
+            mongodb::options::FindOneAndUpdateOptions::builder()
+                .return_document(mongodb::options::ReturnDocument::After)
+                .build(),
+        )
+        .await
+        .map_err(|_| AppError::Internal("更新失败".into()))?
+        .ok_or(AppError::NotFound("FAQ not found".into()))?;
+    Ok(Json(faq_to_json(&updated)))
+}
+
+// DELETE /lecture/:lecture_id/faq/:faq_id
+async fn delete_faq(
+    State(client): State<AppState>,
+    auth: RequireRole<Organizer>,
+    Path((lecture_id, faq_id)): Path<(String, String)>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let lecture_oid = parse_oid(&lecture_id, "lecture_id")?;
+    let faq_oid = parse_oid(&faq_id, "faq_id")?;
+    ensure_lecture_organizer(&client, lecture_oid, &auth).await?;
+    let result = faq_collection(&client)
+        .delete_one(doc! { "_id": faq_oid, "lecture_id": lecture_oid }, None)
+        .await
+        .map_err(|_| AppError::Internal("删除失败".into()))?;
+    if result.deleted_count == 0 {
+        return Err(AppError::NotFound("FAQ not found".into()));
+    }
+    Ok(Json(serde_json::json!({ "message": "FAQ 已删除", "id": faq_id })))
+}
+
+// PUT /lecture/:lecture_id/faq/order -> 按给定 id 顺序重排
+async fn reorder_faq(
+    State(client): State<AppState>,
+    auth: RequireRole<Organizer>,
+    Path(lecture_id): Path<String>,
+    Json(payload): Json<FaqOrder>,
+) -> Result<Json<Vec<serde_json::Value>>, AppError> {
+    let lecture_oid = parse_oid(&lecture_id, "lecture_id")?;
+    ensure_lecture_organizer(&client, lecture_oid, &auth).await?;
+    let coll = faq_collection(&client);
+    for (position, id) in payload.ids.iter().enumerate() {
+        let faq_oid = parse_oid(id, "faq id")?;
+        coll.update_one(
+            doc! { "_id": faq_oid, "lecture_id": lecture_oid },
+            doc! { "$set": { "position": position as i32 } },
+            None,
+        )
+        .await
+        .map_err(|_| AppError::Internal("更新失败".into()))?;
+    }
+    Ok(Json(load_faqs(&client, lecture_oid, false).await?))
+}
+
+// ==================== Router ====================
+
+// 挂载在 /lecture 下
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/:lecture_id/faq", get(list_faq).post(create_faq))
+        .route("/:lecture_id/faq/order", put(reorder_faq))
+        .route("/:lecture_id/faq/:faq_id", put(update_faq).delete(delete_faq))
+}
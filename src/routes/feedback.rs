@@ -1,18 +1,27 @@
 use axum::{
-    extract::{Path, State, Json},
-    http::StatusCode,
-    routing::{get, post},
+    extract::{Path, Query, State, Json},
+    routing::{delete, get, post},
     Router,
 };
-use axum::response::Json as RespJson;
-use bson::{doc, oid::ObjectId, Document, DateTime as BsonDateTime};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Json as RespJson, Response};
+use bson::{doc, oid::ObjectId, DateTime as BsonDateTime, Document};
 use chrono::Utc;
-use futures_util::TryStreamExt;
+use futures_util::{stream, Stream, TryStreamExt};
 use mongodb::Client;
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::convert::Infallible;
 use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
 
-use crate::db::{feedback_collection, user_collection};
+use crate::auth::{AuthUser, ROLE_AUDIENCE};
+use crate::db::{bulk_has_duplicate, feedback_collection, user_collection};
+use crate::routes::lecture::{ensure_not_archived, is_host, load_lecture, rehearsal_lecture};
+use crate::error::AppError;
+use crate::pagination::PageParams;
+use crate::timefmt::UserTime;
+use crate::{audit, csvexport, realtime};
 
 type AppState = Arc<Client>;
 
@@ -25,8 +34,42 @@ struct FeedbackRequest {
     boring: Option<bool>,
     bad_question_quality: Option<bool>,
     other: Option<String>,
+    // 1–5 星评分，可不填
+    rating: Option<u8>,
 }
 
+const RATING_MAX: u8 = 5;
+
+// 导入时各字段对应的 CSV 表头，缺省为同名列；表头比较不区分大小写
+#[derive(Deserialize)]
+struct ImportQuery {
+    lecture_id: String,
+    email: Option<String>,
+    too_fast: Option<String>,
+    too_slow: Option<String>,
+    boring: Option<String>,
+    bad_question_quality: Option<String>,
+    rating: Option<String>,
+    comment: Option<String>,
+    // 只校验不写入
+    #[serde(default)]
+    dry_run: bool,
+}
+
+#[derive(Deserialize, Default)]
+struct TimelineQuery {
+    // 分桶宽度，如 30s、5m、1h，缺省 5m
+    bucket: Option<String>,
+}
+
+const TIMELINE_DEFAULT_BUCKET_SECS: i64 = 300;
+const TIMELINE_MIN_BUCKET_SECS: i64 = 30;
+const TIMELINE_MAX_BUCKET_SECS: i64 = 6 * 3600;
+
+const IMPORT_MAX_ROWS: usize = 5000;
+// 出错时最多返回的行数，其余只计数
+const IMPORT_MAX_ERRORS: usize = 100;
+
 #[derive(Serialize)]
 struct FeedbackSubmitResp {
     message: String,
@@ -36,21 +79,34 @@ struct FeedbackSubmitResp {
 // POST /feedback/submit
 async fn submit_feedback(
     State(client): State<AppState>,
+    auth: AuthUser,
     Json(payload): Json<FeedbackRequest>,
-) -> Result<RespJson<FeedbackSubmitResp>, (StatusCode, String)> {
+) -> Result<RespJson<FeedbackSubmitResp>, AppError> {
+    auth.ensure_self(&payload.user_id)?;
+    if payload.rating.is_some_and(|r| !(1..=RATING_MAX).contains(&r)) {
+        return Err(AppError::BadRequest(format!("rating 须在 1 到 {} 之间", RATING_MAX)));
+    }
     let coll = feedback_collection(&client);
 
     let lecture_oid = ObjectId::parse_str(&payload.lecture_id)
-        .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid lecture_id".into()))?;
+        .map_err(|_| AppError::BadRequest("Invalid lecture_id".into()))?;
     let user_oid = ObjectId::parse_str(&payload.user_id)
-        .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid user_id".into()))?;
+        .map_err(|_| AppError::BadRequest("Invalid user_id".into()))?;
+    ensure_not_archived(&client, lecture_oid).await?;
+
+    // 平时仅听众可提交；彩排期间组织者与讲者也可试用
+    let rehearsal = rehearsal_lecture(&client, lecture_oid).await?;
+    let host_trial = rehearsal.as_ref().is_some_and(|l| is_host(l, &auth.id_hex()));
+    if auth.role != ROLE_AUDIENCE && !host_trial {
+        return Err(AppError::Forbidden("仅听众可执行该操作".into()));
+    }
 
     let filter = doc! {
         "lecture_id": lecture_oid,
         "user_id": user_oid,
     };
 
-    let update = doc! {
+    let mut update = doc! {
         "$set": {
             "too_fast": payload.too_fast.unwrap_or(false),
             "too_slow": payload.too_slow.unwrap_or(false),
@@ -58,8 +114,20 @@ async fn submit_feedback(
             "bad_question_quality": payload.bad_question_quality.unwrap_or(false),
             "other": payload.other.unwrap_or_default(),
             "created_at": BsonDateTime::from_millis(Utc::now().timestamp_millis()),
+            "rehearsal": rehearsal.is_some(),
         }
     };
+    // 整份覆盖：这次没有评分时清掉上次的评分
+    match payload.rating {
+        Some(rating) => {
+            if let Ok(set) = update.get_document_mut("$set") {
+                set.insert("rating", rating as i32);
+            }
+        }
+        None => {
+            update.insert("$unset", doc! { "rating": "" });
+        }
+    }
 
     let result = coll
         .update_one(
@@ -68,7 +136,10 @@ async fn submit_feedback(
             Some(mongodb::options::UpdateOptions::builder().upsert(true).build()),
         )
         .await
-        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "提交反馈失败".into()))?;
+        .map_err(|_| AppError::Internal("提交反馈失败".into()))?;
+
+    // 推送最新计数，讲者端无需轮询
+    publish_summary(&client, lecture_oid).await;
 
     let upserted = if let Some(id) = result.upserted_id {
         id.as_object_id().unwrap().to_hex()
@@ -82,62 +153,532 @@ async fn submit_feedback(
     }))
 }
 
-// GET /feedback/lecture/{lecture_id}/feedback_summary
-async fn feedback_summary(
+// since（毫秒）之后提交或修改的反馈中各选项的人数，控制面板据此显示最近变化
+pub async fn counts_since(client: &AppState, lecture_oid: ObjectId, since: i64) -> Result<Document, AppError> {
+    let flag = |field: &str| doc! { "$sum": { "$cond": [{ "$eq": [format!("${}", field), true] }, 1, 0] } };
+    let pipeline = vec![
+        doc! { "$match": { "lecture_id": lecture_oid, "created_at": { "$gte": BsonDateTime::from_millis(since) } } },
+        doc! { "$group": {
+            "_id": null,
+            "respondents": { "$sum": 1 },
+            "too_fast": flag("too_fast"),
+            "too_slow": flag("too_slow"),
+            "boring": flag("boring"),
+            "bad_question_quality": flag("bad_question_quality"),
+        } },
+        doc! { "$project": { "_id": 0 } },
+    ];
+    let mut cursor = feedback_collection(client).aggregate(pipeline, None).await?;
+    Ok(cursor.try_next().await?.unwrap_or_else(|| doc! {
+        "respondents": 0_i32,
+        "too_fast": 0_i32,
+        "too_slow": 0_i32,
+        "boring": 0_i32,
+        "bad_question_quality": 0_i32,
+    }))
+}
+
+async fn publish_summary(client: &AppState, lecture_oid: ObjectId) {
+    match summary_stats(client, lecture_oid).await {
+        Ok(stats) => realtime::publish(lecture_oid, "feedback.updated", serde_json::json!({ "feedback_summary": stats })),
+        Err(e) => println!("推送反馈计数失败 {}: {}", lecture_oid.to_hex(), e),
+    }
+}
+
+// DELETE /feedback/lecture/{lecture_id}/user/{user_id} -> 撤回自己的反馈，汇总随即更新
+async fn delete_feedback(
     State(client): State<AppState>,
-    Path(lecture_id): Path<String>,
-) -> Result<RespJson<serde_json::Value>, (StatusCode, String)> {
-    let coll = feedback_collection(&client);
+    auth: AuthUser,
+    Path((lecture_id, user_id)): Path<(String, String)>,
+) -> Result<RespJson<serde_json::Value>, AppError> {
+    auth.ensure_self(&user_id)?;
     let lecture_oid = ObjectId::parse_str(&lecture_id)
-        .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid lecture_id".into()))?;
+        .map_err(|_| AppError::BadRequest("Invalid lecture_id".into()))?;
+    ensure_not_archived(&client, lecture_oid).await?;
 
-    let pipeline = vec![
-        doc! { "$match": { "lecture_id": lecture_oid } },
-        doc! {
-            "$group": {
-                "_id": null,
-                "too_fast": { "$sum": { "$cond": [{ "$eq": ["$too_fast", true] }, 1, 0] } },
-                "too_slow": { "$sum": { "$cond": [{ "$eq": ["$too_slow", true] }, 1, 0] } },
-                "boring": { "$sum": { "$cond": [{ "$eq": ["$boring", true] }, 1, 0] } },
-                "bad_question_quality": { "$sum": { "$cond": [{ "$eq": ["$bad_question_quality", true] }, 1, 0] } },
-            }
-        },
-    ];
+    let result = feedback_collection(&client)
+        .delete_one(doc! { "lecture_id": lecture_oid, "user_id": auth.id }, None)
+        .await
+        .map_err(|_| AppError::Internal("删除反馈失败".into()))?;
+    if result.deleted_count == 0 {
+        return Err(AppError::NotFound("未找到该用户的反馈信息".into()));
+    }
+    publish_summary(&client, lecture_oid).await;
+    Ok(RespJson(serde_json::json!({ "lecture_id": lecture_id, "user_id": user_id, "deleted": true })))
+}
+
+// 各选项的累计人数及评分分布，供汇总接口与实时推送共用
+pub async fn summary_stats(client: &AppState, lecture_oid: ObjectId) -> Result<Document, AppError> {
+    let coll = feedback_collection(client);
+    let mut group = doc! {
+        "_id": null,
+        "respondents": { "$sum": 1 },
+        "too_fast": { "$sum": { "$cond": [{ "$eq": ["$too_fast", true] }, 1, 0] } },
+        "too_slow": { "$sum": { "$cond": [{ "$eq": ["$too_slow", true] }, 1, 0] } },
+        "boring": { "$sum": { "$cond": [{ "$eq": ["$boring", true] }, 1, 0] } },
+        "bad_question_quality": { "$sum": { "$cond": [{ "$eq": ["$bad_question_quality", true] }, 1, 0] } },
+        // 未评分的反馈没有 rating 字段，$avg 会自动跳过
+        "rating_average": { "$avg": "$rating" },
+    };
+    for star in 1..=RATING_MAX as i32 {
+        group.insert(format!("rating_{}", star), doc! { "$sum": { "$cond": [{ "$eq": ["$rating", star] }, 1, 0] } });
+    }
+    let pipeline = vec![doc! { "$match": { "lecture_id": lecture_oid } }, doc! { "$group": group }];
 
     let mut cursor = coll
         .aggregate(pipeline, None)
         .await
-        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "聚合失败".into()))?;
+        .map_err(|_| AppError::Internal("聚合失败".into()))?;
 
     let mut stats = doc! {
+        "respondents": 0_i32,
         "too_fast": 0_i32,
         "too_slow": 0_i32,
         "boring": 0_i32,
         "bad_question_quality": 0_i32,
     };
+    let mut distribution = Document::new();
+    let mut rated = 0;
+    let mut average = None;
 
     if let Some(doc) = cursor.try_next().await.map_err(|_| {
-        (StatusCode::INTERNAL_SERVER_ERROR, "读取聚合结果错误".into())
+        AppError::Internal("读取聚合结果错误".into())
     })? {
+        if let Ok(v) = doc.get_i32("respondents") { stats.insert("respondents", v); }
         if let Ok(v) = doc.get_i32("too_fast") { stats.insert("too_fast", v); }
         if let Ok(v) = doc.get_i32("too_slow") { stats.insert("too_slow", v); }
         if let Ok(v) = doc.get_i32("boring") { stats.insert("boring", v); }
         if let Ok(v) = doc.get_i32("bad_question_quality") { stats.insert("bad_question_quality", v); }
+        // 保留两位小数
+        average = doc.get_f64("rating_average").ok().map(|v| (v * 100.0).round() / 100.0);
+        for star in 1..=RATING_MAX {
+            let count = doc.get_i32(format!("rating_{}", star)).unwrap_or(0);
+            rated += count;
+            distribution.insert(star.to_string(), count);
+        }
     }
+    if distribution.is_empty() {
+        for star in 1..=RATING_MAX {
+            distribution.insert(star.to_string(), 0_i32);
+        }
+    }
+    stats.insert("rating_average", average);
+    stats.insert("rating_count", rated);
+    stats.insert("rating_distribution", distribution);
+    Ok(stats)
+}
 
+// GET /feedback/lecture/{lecture_id}/feedback_summary
+async fn feedback_summary(
+    State(client): State<AppState>,
+    Path(lecture_id): Path<String>,
+) -> Result<RespJson<serde_json::Value>, AppError> {
+    let lecture_oid = ObjectId::parse_str(&lecture_id)
+        .map_err(|_| AppError::BadRequest("Invalid lecture_id".into()))?;
+    let stats = summary_stats(&client, lecture_oid).await?;
     Ok(RespJson(serde_json::json!({ "feedback_summary": stats })))
 }
 
+struct SummaryStream {
+    client: AppState,
+    lecture: ObjectId,
+    sub: realtime::Subscription,
+    // 待发送的计数；连接时为当前值
+    pending: Option<serde_json::Value>,
+}
+
+impl SummaryStream {
+    async fn next_event(&mut self) -> Option<Event> {
+        loop {
+            if let Some(stats) = self.pending.take() {
+                return Some(Event::default().event("feedback_summary").data(stats.to_string()));
+            }
+            match self.sub.recv().await {
+                Ok(text) => {
+                    let Ok(event) = serde_json::from_str::<serde_json::Value>(&text) else { continue };
+                    if event["type"] == "feedback.updated" {
+                        self.pending = Some(event["data"]["feedback_summary"].clone());
+                    }
+                }
+                // 跳过的中间计数无需补发，直接取最新值
+                Err(RecvError::Lagged(_)) => match summary_stats(&self.client, self.lecture).await {
+                    Ok(stats) => self.pending = Some(serde_json::json!(stats)),
+                    Err(e) => println!("[sse] 读取反馈计数失败 {}: {}", self.lecture.to_hex(), e),
+                },
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    }
+}
+
+// GET /feedback/lecture/:lecture_id/stream -> 讲者看板：SSE 推送反馈计数（事件名 feedback_summary），
+// 连接时先发送当前值，之后每次有反馈提交推送一次最新计数。仅组织者、讲者可订阅
+async fn stream_feedback_summary(
+    State(client): State<AppState>,
+    auth: AuthUser,
+    Path(lecture_id): Path<String>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, AppError> {
+    let lecture = load_lecture(&client, &lecture_id).await?;
+    if !is_host(&lecture, &auth.id_hex()) {
+        return Err(AppError::Forbidden("只有组织者或讲者可以订阅反馈计数".into()));
+    }
+    let lecture_oid = lecture.get_object_id("_id").map_err(|_| AppError::Internal("演讲ID无效".into()))?;
+    // 先订阅再读当前值，两者之间的提交会再推送一次，不会漏掉
+    let sub = realtime::subscribe(lecture_oid);
+    let initial = summary_stats(&client, lecture_oid).await?;
+    let state = SummaryStream { client, lecture: lecture_oid, sub, pending: Some(serde_json::json!(initial)) };
+    let events = stream::unfold(state, |mut state| async move {
+        let event = state.next_event().await?;
+        Some((Ok(event), state))
+    });
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+// 解析 30s / 5m / 1h 形式的时长，返回秒数
+fn parse_bucket(raw: &str) -> Result<i64, AppError> {
+    let raw = raw.trim();
+    let invalid = || AppError::BadRequest(format!("bucket 格式应为数字加 s/m/h，如 5m: {}", raw));
+    let Some((unit_at, _)) = raw.char_indices().last() else { return Err(invalid()) };
+    let (num, unit) = raw.split_at(unit_at);
+    let n: i64 = num.parse().map_err(|_| invalid())?;
+    let secs = match unit {
+        "s" => n,
+        "m" => n * 60,
+        "h" => n * 3600,
+        _ => return Err(invalid()),
+    };
+    if !(TIMELINE_MIN_BUCKET_SECS..=TIMELINE_MAX_BUCKET_SECS).contains(&secs) {
+        return Err(AppError::BadRequest(format!(
+            "bucket 须在 {} 秒到 {} 小时之间",
+            TIMELINE_MIN_BUCKET_SECS,
+            TIMELINE_MAX_BUCKET_SECS / 3600
+        )));
+    }
+    Ok(secs)
+}
+
+// GET /feedback/lecture/:lecture_id/timeline?bucket=5m -> 按提交时间分桶统计各选项人数，
+// 分桶从演讲开始时间起算（开始前提交的落在负偏移的桶里），只返回有提交的桶。
+// 反馈可覆盖提交，按最后一次提交时间计
+async fn feedback_timeline(
+    State(client): State<AppState>,
+    Path(lecture_id): Path<String>,
+    Query(query): Query<TimelineQuery>,
+) -> Result<RespJson<serde_json::Value>, AppError> {
+    let bucket_secs = match query.bucket.as_deref().filter(|b| !b.trim().is_empty()) {
+        Some(raw) => parse_bucket(raw)?,
+        None => TIMELINE_DEFAULT_BUCKET_SECS,
+    };
+    let lecture = load_lecture(&client, &lecture_id).await?;
+    let lecture_oid = lecture.get_object_id("_id").map_err(|_| AppError::Internal("演讲ID无效".into()))?;
+    let start = lecture.get_i64("start_time").unwrap_or(0);
+    let bucket_ms = bucket_secs * 1000;
+
+    let flag = |field: &str| doc! { "$sum": { "$cond": [{ "$eq": [format!("${}", field), true] }, 1, 0] } };
+    let pipeline = vec![
+        doc! { "$match": { "lecture_id": lecture_oid, "created_at": { "$type": "date" } } },
+        doc! {
+            "$group": {
+                "_id": { "$floor": { "$divide": [{ "$subtract": [{ "$toLong": "$created_at" }, start] }, bucket_ms] } },
+                "count": { "$sum": 1 },
+                "too_fast": flag("too_fast"),
+                "too_slow": flag("too_slow"),
+                "boring": flag("boring"),
+                "bad_question_quality": flag("bad_question_quality"),
+                "rating_average": { "$avg": "$rating" },
+            }
+        },
+        doc! { "$sort": { "_id": 1 } },
+    ];
+    let rows: Vec<Document> = feedback_collection(&client).aggregate(pipeline, None).await?.try_collect().await?;
+    let buckets: Vec<serde_json::Value> = rows
+        .iter()
+        .map(|row| {
+            let index = match row.get("_id") {
+                Some(bson::Bson::Int64(n)) => *n,
+                Some(bson::Bson::Int32(n)) => *n as i64,
+                Some(bson::Bson::Double(n)) => *n as i64,
+                _ => 0,
+            };
+            let count = |field: &str| row.get_i32(field).unwrap_or(0);
+            serde_json::json!({
+                "start": start + index * bucket_ms,
+                "offset_seconds": index * bucket_secs,
+                "count": count("count"),
+                "too_fast": count("too_fast"),
+                "too_slow": count("too_slow"),
+                "boring": count("boring"),
+                "bad_question_quality": count("bad_question_quality"),
+                "rating_average": row.get_f64("rating_average").ok().map(|v| (v * 100.0).round() / 100.0),
+            })
+        })
+        .collect();
+    Ok(RespJson(serde_json::json!({
+        "lecture_id": lecture_id,
+        "start_time": start,
+        "bucket_seconds": bucket_secs,
+        "buckets": buckets,
+    })))
+}
+
+// 导出时关联的用户名与邮箱
+fn user_lookup() -> Document {
+    doc! { "$lookup": { "from": "users", "localField": "user_id", "foreignField": "_id", "as": "user" } }
+}
+
+fn joined_user(doc: &Document, field: &str) -> String {
+    doc.get_array("user")
+        .ok()
+        .and_then(|a| a.first())
+        .and_then(|u| u.as_document())
+        .and_then(|u| u.get_str(field).ok())
+        .unwrap_or("")
+        .to_string()
+}
+
+// GET /feedback/lecture/:lecture_id/export.csv -> 逐条导出反馈（用户、各选项、评分、意见、提交时间），
+// 时间按导出者的时区显示。仅组织者、讲者可导出
+async fn export_feedback_csv(
+    State(client): State<AppState>,
+    auth: AuthUser,
+    Path(lecture_id): Path<String>,
+) -> Result<Response, AppError> {
+    let lecture = load_lecture(&client, &lecture_id).await?;
+    if !is_host(&lecture, &auth.id_hex()) {
+        return Err(AppError::Forbidden("只有组织者或讲者可以导出反馈".into()));
+    }
+    let lecture_oid = lecture.get_object_id("_id").map_err(|_| AppError::Internal("演讲ID无效".into()))?;
+    let viewer = user_collection(&client).find_one(doc! { "_id": auth.id }, None).await?.unwrap_or_default();
+    let time = UserTime::for_user(&viewer);
+
+    let pipeline = vec![
+        doc! { "$match": { "lecture_id": lecture_oid } },
+        doc! { "$sort": { "created_at": 1, "_id": 1 } },
+        user_lookup(),
+    ];
+    let cursor = feedback_collection(&client).aggregate(pipeline, None).await?;
+    let flag = |doc: &Document, field: &str| if doc.get_bool(field).unwrap_or(false) { "1" } else { "0" }.to_string();
+    let rows = cursor.map_ok(move |fb| {
+        vec![
+            fb.get_object_id("user_id").map(|oid| oid.to_hex()).unwrap_or_default(),
+            joined_user(&fb, "username"),
+            joined_user(&fb, "email"),
+            flag(&fb, "too_fast"),
+            flag(&fb, "too_slow"),
+            flag(&fb, "boring"),
+            flag(&fb, "bad_question_quality"),
+            fb.get_i32("rating").map(|r| r.to_string()).unwrap_or_default(),
+            fb.get_str("other").unwrap_or("").to_string(),
+            fb.get_datetime("created_at").map(|t| time.iso(t.timestamp_millis())).unwrap_or_default(),
+        ]
+    });
+    Ok(csvexport::response(
+        &format!("feedback-{}.csv", lecture_id),
+        &["user_id", "username", "email", "too_fast", "too_slow", "boring", "bad_question_quality", "rating", "comment", "submitted_at"],
+        rows,
+    ))
+}
+
+// 问卷工具导出的勾选值五花八门，常见写法都认
+fn parse_flag(raw: &str) -> Option<bool> {
+    match raw.trim().to_lowercase().as_str() {
+        "" | "0" | "false" | "no" | "n" | "否" | "不是" => Some(false),
+        "1" | "true" | "yes" | "y" | "x" | "✓" | "√" | "是" => Some(true),
+        _ => None,
+    }
+}
+
+fn parse_rating(raw: &str) -> Result<Option<i32>, String> {
+    let raw = raw.trim();
+    if raw.is_empty() {
+        return Ok(None);
+    }
+    match raw.parse::<u8>() {
+        Ok(r) if (1..=RATING_MAX).contains(&r) => Ok(Some(r as i32)),
+        _ => Err(format!("rating 须在 1 到 {} 之间: {}", RATING_MAX, raw)),
+    }
+}
+
+// POST /feedback/import?lecture_id=&email=&too_fast=...&dry_run= -> 导入纸质或第三方问卷的 CSV 导出（请求体为 CSV 文本）。
+// 查询参数指定各字段所在列的表头；邮箱须对应已注册用户。任一行有误时整批不写入并返回出错行，
+// 已提交过反馈的用户跳过。仅组织者、讲者可导入
+async fn import_feedback(
+    State(client): State<AppState>,
+    auth: AuthUser,
+    Query(query): Query<ImportQuery>,
+    body: String,
+) -> Result<RespJson<serde_json::Value>, AppError> {
+    let lecture = load_lecture(&client, &query.lecture_id).await?;
+    if !is_host(&lecture, &auth.id_hex()) {
+        return Err(AppError::Forbidden("只有组织者或讲者可以导入反馈".into()));
+    }
+    let lecture_oid = lecture.get_object_id("_id").map_err(|_| AppError::Internal("演讲ID无效".into()))?;
+    ensure_not_archived(&client, lecture_oid).await?;
+
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .flexible(true)
+        .from_reader(body.trim_start_matches('\u{feff}').as_bytes());
+    let headers: Vec<String> = reader
+        .headers()
+        .map_err(|e| AppError::BadRequest(format!("CSV 表头无法解析: {}", e)))?
+        .iter()
+        .map(|h| h.to_lowercase())
+        .collect();
+    let column = |mapped: &Option<String>, default: &str| -> Option<usize> {
+        let name = mapped.as_deref().unwrap_or(default).trim().to_lowercase();
+        headers.iter().position(|h| *h == name)
+    };
+    let Some(email_col) = column(&query.email, "email") else {
+        return Err(AppError::BadRequest(format!("CSV 缺少邮箱列: {}", query.email.as_deref().unwrap_or("email"))));
+    };
+    let flag_cols: Vec<(&str, Option<usize>)> = vec![
+        ("too_fast", column(&query.too_fast, "too_fast")),
+        ("too_slow", column(&query.too_slow, "too_slow")),
+        ("boring", column(&query.boring, "boring")),
+        ("bad_question_quality", column(&query.bad_question_quality, "bad_question_quality")),
+    ];
+    let rating_col = column(&query.rating, "rating");
+    let comment_col = column(&query.comment, "comment");
+
+    // 先逐行解析，行号按文件计（表头为第 1 行）
+    let mut rows = Vec::new();
+    let mut errors = Vec::new();
+    for (idx, record) in reader.records().enumerate() {
+        let line = idx + 2;
+        if rows.len() >= IMPORT_MAX_ROWS {
+            return Err(AppError::BadRequest(format!("单次最多导入 {} 行", IMPORT_MAX_ROWS)));
+        }
+        let record = match record {
+            Ok(r) => r,
+            Err(e) => {
+                errors.push(serde_json::json!({ "row": line, "message": format!("无法解析: {}", e) }));
+                continue;
+            }
+        };
+        let cell = |col: Option<usize>| col.and_then(|c| record.get(c)).unwrap_or("");
+        if record.iter().all(|c| c.is_empty()) {
+            continue;
+        }
+        let email = cell(Some(email_col)).to_string();
+        let mut problems = Vec::new();
+        if email.is_empty() {
+            problems.push("邮箱为空".to_string());
+        }
+        let mut fields = doc! {};
+        for (name, col) in &flag_cols {
+            match parse_flag(cell(*col)) {
+                Some(v) => {
+                    fields.insert(*name, v);
+                }
+                None => problems.push(format!("{} 无法识别: {}", name, cell(*col))),
+            }
+        }
+        match parse_rating(cell(rating_col)) {
+            Ok(Some(r)) => {
+                fields.insert("rating", r);
+            }
+            Ok(None) => {}
+            Err(e) => problems.push(e),
+        }
+        fields.insert("other", cell(comment_col));
+        if problems.is_empty() {
+            rows.push((line, email, fields));
+        } else {
+            errors.push(serde_json::json!({ "row": line, "email": email, "message": problems.join("; ") }));
+        }
+    }
+
+    // 邮箱对应到用户，同一用户在文件中只能出现一次
+    let emails: Vec<&str> = rows.iter().map(|(_, email, _)| email.as_str()).collect();
+    let mut users: HashMap<String, ObjectId> = HashMap::new();
+    let mut cursor = user_collection(&client).find(doc! { "email": { "$in": &emails } }, None).await?;
+    while let Some(user) = cursor.try_next().await? {
+        if let (Ok(email), Ok(oid)) = (user.get_str("email"), user.get_object_id("_id")) {
+            users.insert(email.to_string(), oid);
+        }
+    }
+    let mut seen = HashSet::new();
+    let mut resolved = Vec::with_capacity(rows.len());
+    for (line, email, fields) in rows {
+        match users.get(&email) {
+            None => errors.push(serde_json::json!({ "row": line, "email": email, "message": "邮箱未注册" })),
+            Some(oid) if !seen.insert(*oid) => {
+                errors.push(serde_json::json!({ "row": line, "email": email, "message": "该用户在文件中重复出现" }))
+            }
+            Some(oid) => resolved.push((line, *oid, fields)),
+        }
+    }
+    if !errors.is_empty() {
+        let count = errors.len();
+        errors.sort_by_key(|e| e["row"].as_u64());
+        errors.truncate(IMPORT_MAX_ERRORS);
+        return Err(AppError::BadRequest(format!("{} 行数据有误，未导入任何反馈", count))
+            .with_details(serde_json::json!({ "errors": errors })));
+    }
+
+    let user_ids: Vec<ObjectId> = resolved.iter().map(|(_, oid, _)| *oid).collect();
+    let existing: HashSet<ObjectId> = feedback_collection(&client)
+        .distinct("user_id", doc! { "lecture_id": lecture_oid, "user_id": { "$in": &user_ids } }, None)
+        .await?
+        .iter()
+        .filter_map(|v| v.as_object_id())
+        .collect();
+    let now = BsonDateTime::from_millis(Utc::now().timestamp_millis());
+    let mut skipped = Vec::new();
+    let mut docs = Vec::new();
+    for (line, user_oid, mut fields) in resolved {
+        if existing.contains(&user_oid) {
+            skipped.push(serde_json::json!({ "row": line, "user_id": user_oid.to_hex(), "message": "已提交过反馈" }));
+            continue;
+        }
+        fields.insert("lecture_id", lecture_oid);
+        fields.insert("user_id", user_oid);
+        fields.insert("created_at", now);
+        fields.insert("rehearsal", false);
+        fields.insert("source", "import");
+        fields.insert("imported_by", auth.id);
+        docs.push(fields);
+    }
+
+    let imported = docs.len();
+    if !query.dry_run && !docs.is_empty() {
+        match feedback_collection(&client).insert_many(docs, None).await {
+            Ok(_) => {}
+            // 导入期间有人提交了反馈
+            Err(e) if bulk_has_duplicate(&e) => {
+                return Err(AppError::Conflict("导入期间有新的反馈写入，请重新导入".into()));
+            }
+            Err(e) => return Err(e.into()),
+        }
+        audit::record(
+            &client,
+            Some(auth.id),
+            "feedback.import",
+            &format!("lecture:{}", query.lecture_id),
+            doc! { "imported": imported as i64, "skipped": skipped.len() as i64 },
+        )
+        .await;
+        publish_summary(&client, lecture_oid).await;
+    }
+    Ok(RespJson(serde_json::json!({
+        "lecture_id": query.lecture_id,
+        "dry_run": query.dry_run,
+        "imported": imported,
+        "skipped": skipped,
+    })))
+}
+
 // GET /feedback/lecture/{lecture_id}/user/{user_id}/feedback
 async fn get_user_feedback(
     State(client): State<AppState>,
     Path((lecture_id, user_id)): Path<(String, String)>,
-) -> Result<RespJson<serde_json::Value>, (StatusCode, String)> {
+) -> Result<RespJson<serde_json::Value>, AppError> {
     let coll = feedback_collection(&client);
     let lecture_oid = ObjectId::parse_str(&lecture_id)
-        .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid lecture_id".into()))?;
+        .map_err(|_| AppError::BadRequest("Invalid lecture_id".into()))?;
     let user_oid = ObjectId::parse_str(&user_id)
-        .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid user_id".into()))?;
+        .map_err(|_| AppError::BadRequest("Invalid user_id".into()))?;
 
     let doc = coll
         .find_one(
@@ -148,79 +689,75 @@ async fn get_user_feedback(
             None,
         )
         .await
-        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "查询失败".into()))?
-        .ok_or((StatusCode::NOT_FOUND, "未找到该用户的反馈信息".into()))?;
+        .map_err(|_| AppError::Internal("查询失败".into()))?
+        .ok_or(AppError::NotFound("未找到该用户的反馈信息".into()))?;
 
     let resp = serde_json::json!({
         "too_fast": doc.get_bool("too_fast").unwrap_or(false),
         "too_slow": doc.get_bool("too_slow").unwrap_or(false),
         "boring": doc.get_bool("boring").unwrap_or(false),
         "bad_question_quality": doc.get_bool("bad_question_quality").unwrap_or(false),
-        "other": doc.get_str("other").unwrap_or("")
+        "other": doc.get_str("other").unwrap_or(""),
+        "rating": doc.get_i32("rating").ok(),
     });
 
     Ok(RespJson(resp))
 }
 
-// GET /feedback/lecture/{lecture_id}/feedback_details
+// GET /feedback/lecture/{lecture_id}/feedback_details?page=&limit= -> 填写了意见的反馈，新到旧；
+// 一次聚合关联用户信息，分页信息见响应头
 async fn feedback_detail_comments(
     State(client): State<AppState>,
     Path(lecture_id): Path<String>,
-) -> Result<RespJson<serde_json::Value>, (StatusCode, String)> {
+    paging: PageParams,
+) -> Result<Response, AppError> {
     let fb_coll = feedback_collection(&client);
-    let user_coll = user_collection(&client);
     let lecture_oid = ObjectId::parse_str(&lecture_id)
-        .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid lecture_id".into()))?;
+        .map_err(|_| AppError::BadRequest("Invalid lecture_id".into()))?;
 
-    let mut cursor = fb_coll
-        .find(
-            doc! {
-                "lecture_id": lecture_oid,
-                "other": { "$ne": "" }
-            },
-            None,
-        )
-        .await
-        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "查询失败".into()))?;
+    // $gt "" 只匹配非空字符串，缺少 other 字段的记录不计入
+    let filter = doc! { "lecture_id": lecture_oid, "other": { "$gt": "" } };
+    let total = fb_coll.count_documents(filter.clone(), None).await?;
+    let pipeline = vec![
+        doc! { "$match": filter },
+        doc! { "$sort": { "created_at": -1, "_id": -1 } },
+        doc! { "$skip": paging.skip() as i64 },
+        doc! { "$limit": paging.limit as i64 },
+        user_lookup(),
+        doc! { "$project": {
+            "_id": 0,
+            "user_id": 1,
+            "comment": "$other",
+            "username": { "$ifNull": [{ "$arrayElemAt": ["$user.username", 0] }, "未知用户"] },
+            "avatar": { "$ifNull": [{ "$arrayElemAt": ["$user.avatar", 0] }, ""] },
+        } },
+    ];
+    let comments: Vec<serde_json::Value> = fb_coll
+        .aggregate(pipeline, None)
+        .await?
+        .map_ok(|fb| {
+            serde_json::json!({
+                "user_id": fb.get_object_id("user_id").map(|oid| oid.to_hex()).unwrap_or_default(),
+                "username": fb.get_str("username").unwrap_or("未知用户"),
+                "avatar": fb.get_str("avatar").unwrap_or(""),
+                "comment": fb.get_str("comment").unwrap_or(""),
+            })
+        })
+        .try_collect()
+        .await?;
 
-    let mut comments = Vec::new();
-    while let Some(fb) = cursor.try_next().await.map_err(|_| {
-        (StatusCode::INTERNAL_SERVER_ERROR, "读取失败".into())
-    })? {
-        let user_oid = fb.get_object_id("user_id").map_err(|_| {
-            (StatusCode::INTERNAL_SERVER_ERROR, "字段缺失".into())
-        })?;
-        let user = user_coll
-            .find_one(doc! { "_id": user_oid }, None)
-            .await
-            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "查询用户失败".into()))?;
-
-        let username = user
-            .as_ref()
-            .and_then(|u| u.get_str("username").ok())
-            .unwrap_or("未知用户")
-            .to_string();
-        let avatar = user
-            .as_ref()
-            .and_then(|u| u.get_str("avatar").ok())
-            .unwrap_or("")
-            .to_string();
-
-        comments.push(serde_json::json!({
-            "user_id": user_oid.to_hex(),
-            "username": username,
-            "avatar": avatar,
-            "comment": fb.get_str("other").unwrap_or("")
-        }));
-    }
-
-    Ok(RespJson(serde_json::json!({ "feedback_comments": comments })))
+    Ok(paging.annotate(RespJson(serde_json::json!({ "feedback_comments": comments })).into_response(), total))
 }
 
 pub fn router() -> Router<AppState> {
     Router::new()
         .route("/submit", post(submit_feedback))
+        .route("/import", post(import_feedback))
         .route("/lecture/:lecture_id/feedback_summary", get(feedback_summary))
+        .route("/lecture/:lecture_id/stream", get(stream_feedback_summary))
+        .route("/lecture/:lecture_id/timeline", get(feedback_timeline))
+        .route("/lecture/:lecture_id/export.csv", get(export_feedback_csv))
+        .route("/lecture/:lecture_id/user/:user_id", delete(delete_feedback))
         .route("/lecture/:lecture_id/user/:user_id/feedback", get(get_user_feedback))
         .route("/lecture/:lecture_id/feedback_details", get(feedback_detail_comments))
 }
\ No newline at end of file
@@ -1,15 +1,26 @@
 use axum::{
-    extract::{Path, State, Json},
+    extract::{Path, Query, State, Json},
     routing::{get, post, put, delete},
     Router,
 };
-use axum::response::Json as RespJson;
-use bson::{doc, oid::ObjectId, Document};
+use axum::response::{Json as RespJson, Response};
+use bson::{doc, oid::ObjectId};
+use chrono::Utc;
 use mongodb::Client;
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
-use crate::db::{invitation_collection, lecture_collection};
+use crate::ids;
+use crate::jobs::{invitation_valid_days, INVITATION_EXPIRED, INVITATION_SUPERSEDED};
+use crate::auth::{AuthUser, Organizer, RequireRole, RoleSet, Speaker, ROLE_SPEAKER};
+use crate::routes::{invitation_template, la};
+use crate::routes::lecture::{ensure_lecture_organizer, find_schedule_conflicts, schedule_conflict_error};
+use crate::db::{invitation_collection, la_collection, lecture_collection, user_collection};
+use crate::{notify, retry};
+use crate::error::AppError;
+use crate::lifecycle::LectureStatus;
+use crate::pagination::PageParams;
 use futures_util::TryStreamExt;
 
 type AppState = Arc<Client>;
@@ -17,8 +28,131 @@ type AppState = Arc<Client>;
 #[derive(Deserialize)]
 struct InvitationCreate {
     lecture_id: String,
+    // 讲者邀请填 speaker_id；听众邀请填 invitee_id
+    #[serde(default)]
     speaker_id: String,
+    invitee_id: Option<String>,
+    // speaker（缺省）或 audience
+    invitee_role: Option<String>,
     status: i32,
+    // 附言：套用自己的邀请模板，或直接填写（同样支持占位符）
+    template_id: Option<String>,
+    message: Option<String>,
+    // 过期时间（毫秒时间戳），缺省见 expiry_for
+    expires_at: Option<i64>,
+}
+
+#[derive(Deserialize, Default)]
+struct AcceptQuery {
+    // 为 true 时允许与讲者已有演讲时间重叠
+    #[serde(default)]
+    allow_conflict: bool,
+}
+
+#[derive(Deserialize)]
+struct BroadcastRequest {
+    lecture_id: String,
+    // 期望的讲者专长标签，命中任意一个即视为匹配
+    tags: Vec<String>,
+    // 本次最多发出的邀请数
+    cap: Option<usize>,
+    template_id: Option<String>,
+    message: Option<String>,
+    expires_at: Option<i64>,
+}
+
+#[derive(Serialize)]
+struct BroadcastInvited {
+    invitation_id: String,
+    speaker_id: String,
+    username: String,
+    matched_tags: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct BulkInviteRequest {
+    lecture_id: String,
+    speaker_ids: Vec<String>,
+    template_id: Option<String>,
+    message: Option<String>,
+    expires_at: Option<i64>,
+}
+
+const BULK_MAX_SPEAKERS: usize = 100;
+
+const BROADCAST_DEFAULT_CAP: usize = 20;
+const BROADCAST_MAX_CAP: usize = 100;
+
+// 提醒限流：两次提醒至少间隔 1 小时，单个邀请最多提醒 5 次
+const REMIND_MIN_INTERVAL_MS: i64 = 60 * 60 * 1000;
+const REMIND_MAX_COUNT: i32 = 5;
+
+// 被邀请者的身份：讲者邀请接受后成为演讲的讲者，听众邀请接受后自动报名。
+// 早期邀请没有 invitee_role 字段，均为讲者邀请；听众邀请的用户存于 invitee_id，讲者邀请仍存于 speaker_id
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum InviteeRole {
+    Speaker,
+    Audience,
+}
+
+impl InviteeRole {
+    fn parse(raw: Option<&str>) -> Result<InviteeRole, AppError> {
+        match raw.map(str::trim).filter(|s| !s.is_empty()) {
+            None | Some("speaker") => Ok(InviteeRole::Speaker),
+            Some("audience") => Ok(InviteeRole::Audience),
+            Some(other) => Err(AppError::BadRequest(format!("未知的 invitee_role: {}，可选 speaker、audience", other))),
+        }
+    }
+
+    pub fn of(invite: &bson::Document) -> InviteeRole {
+        match invite.get_str("invitee_role") {
+            Ok("audience") => InviteeRole::Audience,
+            _ => InviteeRole::Speaker,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            InviteeRole::Speaker => "speaker",
+            InviteeRole::Audience => "audience",
+        }
+    }
+
+    // 存放被邀请者的字段
+    fn id_field(self) -> &'static str {
+        match self {
+            InviteeRole::Speaker => "speaker_id",
+            InviteeRole::Audience => "invitee_id",
+        }
+    }
+}
+
+pub fn invitee_of(invite: &bson::Document) -> Option<ObjectId> {
+    invite.get_object_id(InviteeRole::of(invite).id_field()).ok()
+}
+
+// 向被邀请者发送邀请通知（新建与提醒共用），通知失败不影响邀请本身
+async fn notify_invitation(
+    client: &AppState,
+    invitation_id: ObjectId,
+    lecture_id: ObjectId,
+    speaker_id: ObjectId,
+    role: InviteeRole,
+    message: Option<&str>,
+    reminder: bool,
+) {
+    let kind = if reminder { "invitation_reminder" } else { "invitation" };
+    let mut payload = doc! {
+        "invitation_id": invitation_id.to_hex(),
+        "lecture_id": lecture_id.to_hex(),
+        "invitee_role": role.as_str(),
+    };
+    if let Some(message) = message {
+        payload.insert("message", message);
+    }
+    if let Err(e) = notify::push(client, speaker_id, kind, payload).await {
+        println!("发送邀请通知失败 {}: {}", invitation_id.to_hex(), e);
+    }
 }
 
 #[derive(Serialize)]
@@ -26,201 +160,991 @@ struct InvitationResponse {
     id: String,
     lecture_id: String,
     speaker_id: String,
+    invitee_id: String,
+    invitee_role: &'static str,
     status: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expires_at: Option<i64>,
+}
+
+impl InvitationResponse {
+    fn from_doc(doc: &bson::Document) -> InvitationResponse {
+        InvitationResponse {
+            id: ids::oid_hex(doc, "_id"),
+            lecture_id: ids::oid_hex(doc, "lecture_id"),
+            speaker_id: ids::oid_hex(doc, "speaker_id"),
+            invitee_id: invitee_of(doc).map(|o| o.to_hex()).unwrap_or_default(),
+            invitee_role: InviteeRole::of(doc).as_str(),
+            status: doc.get_i32("status").unwrap_or(0),
+            message: doc.get_str("message").ok().map(str::to_string),
+            expires_at: doc.get_i64("expires_at").ok(),
+        }
+    }
 }
 
+// 邀请过期时间：指定时需晚于现在且不晚于演讲开始；缺省为 expire_invitations.after_days 天后，
+// 演讲更早开始时以开始时间为准
+fn expiry_for(lecture: &bson::Document, now: i64, requested: Option<i64>) -> Result<i64, AppError> {
+    let start = lecture.get_i64("start_time").unwrap_or(0);
+    match requested {
+        Some(at) if at <= now => Err(AppError::BadRequest("expires_at 须晚于当前时间".into())),
+        Some(at) if start > now && at > start => Err(AppError::BadRequest("expires_at 不能晚于演讲开始时间".into())),
+        Some(at) => Ok(at),
+        None => {
+            let default = now + invitation_valid_days() * 86_400_000;
+            Ok(if start > now { default.min(start) } else { default })
+        }
+    }
+}
+
+// 待回应但已过有效期的邀请（定时任务可能尚未将其标记为已过期）同样视为过期
+fn is_expired(invite: &bson::Document, now: i64) -> bool {
+    let status = invite.get_i32("status").unwrap_or(0);
+    status == INVITATION_EXPIRED || (status == 0 && invite.get_i64("expires_at").is_ok_and(|at| at <= now))
+}
+
+// 请求中的被邀请者：讲者邀请可填 invitee_id 或 speaker_id，听众邀请填 invitee_id
+fn requested_invitee(role: InviteeRole, payload: &InvitationCreate) -> &str {
+    match role {
+        InviteeRole::Speaker => payload.invitee_id.as_deref().filter(|s| !s.trim().is_empty()).unwrap_or(&payload.speaker_id),
+        InviteeRole::Audience => payload.invitee_id.as_deref().unwrap_or(""),
+    }
+}
+
+// 听众邀请只能以待回应状态发出；演讲已结束或取消、被邀请者已报名或已有待回应的听众邀请时拒绝
+async fn check_audience_invite(client: &AppState, lecture: &bson::Document, invitee: ObjectId, status: i32) -> Result<(), AppError> {
+    if status != 0 {
+        return Err(AppError::BadRequest("听众邀请只能以待回应状态创建".into()));
+    }
+    if matches!(LectureStatus::of(lecture), LectureStatus::Ended | LectureStatus::Cancelled) {
+        return Err(AppError::Conflict("演讲已结束或已取消，无法邀请听众".into()));
+    }
+    let lecture_oid = lecture.get_object_id("_id").map_err(|_| AppError::Internal("演讲ID无效".into()))?;
+    if user_collection(client).find_one(doc! { "_id": invitee }, None).await?.is_none() {
+        return Err(AppError::NotFound("被邀请的用户不存在".into()));
+    }
+    if la_collection(client).find_one(doc! { "lecture_id": lecture_oid, "audience_id": invitee }, None).await?.is_some() {
+        return Err(AppError::Conflict("该用户已报名该演讲".into()));
+    }
+    let pending = invitation_collection(client)
+        .find_one(doc! { "lecture_id": lecture_oid, "invitee_id": invitee, "status": 0 }, None)
+        .await?;
+    if let Some(pending) = pending {
+        return Err(AppError::Conflict("已向该用户发出听众邀请".into())
+            .with_details(serde_json::json!({ "invitation_id": ids::oid_hex(&pending, "_id") })));
+    }
+    Ok(())
+}
+
+// POST /invitation/ {lecture_id, speaker_id | invitee_id, invitee_role?, status, ...}
+// invitee_role 为 audience 时邀请指定用户作为听众参加，接受后自动报名
 async fn create_invitation(
     State(client): State<AppState>,
+    auth: RequireRole<Organizer>,
     Json(payload): Json<InvitationCreate>,
-) -> Result<RespJson<InvitationResponse>, (axum::http::StatusCode, String)> {
+) -> Result<RespJson<InvitationResponse>, AppError> {
     let coll = invitation_collection(&client);
 
     // 验证并转换为 ObjectId 存库
     let lec_oid = ObjectId::parse_str(&payload.lecture_id)
-        .map_err(|_| (axum::http::StatusCode::BAD_REQUEST, "Invalid lecture_id format".into()))?;
-    let spk_oid = ObjectId::parse_str(&payload.speaker_id)
-        .map_err(|_| (axum::http::StatusCode::BAD_REQUEST, "Invalid speaker_id format".into()))?;
+        .map_err(|_| AppError::BadRequest("Invalid lecture_id format".into()))?;
+    let lecture = ensure_lecture_organizer(&client, lec_oid, &auth).await?;
+    let role = InviteeRole::parse(payload.invitee_role.as_deref())?;
+    let spk_oid = ObjectId::parse_str(requested_invitee(role, &payload).trim())
+        .map_err(|_| AppError::BadRequest(format!("Invalid {} format", role.id_field())))?;
+    if role == InviteeRole::Audience {
+        check_audience_invite(&client, &lecture, spk_oid, payload.status).await?;
+    }
+    let body = invitation_template::resolve(&client, auth.id, payload.template_id.as_deref(), payload.message.as_deref()).await?;
+    // 时间占位符按被邀请者的时区与语言显示
+    let message = match body {
+        Some(body) => {
+            let speaker = user_collection(&client).find_one(doc! { "_id": spk_oid }, None).await?.unwrap_or_default();
+            Some(invitation_template::render(&body, &lecture, &speaker))
+        }
+        None => None,
+    };
 
-    let doc = doc! {
+    let now = Utc::now().timestamp_millis();
+    let mut doc = doc! {
         "lecture_id": lec_oid,
-        "speaker_id": spk_oid,
+        role.id_field(): spk_oid,
         "status": payload.status,
+        "created_at": now,
     };
+    if role == InviteeRole::Audience {
+        doc.insert("invitee_role", role.as_str());
+    }
+    if payload.status == 0 {
+        doc.insert("expires_at", expiry_for(&lecture, now, payload.expires_at)?);
+    }
+    if let Some(message) = &message {
+        doc.insert("message", message);
+    }
 
-    let result = coll.insert_one(doc, None)
+    let inv_oid = retry::insert_one(&coll, &mut doc)
         .await
-        .map_err(|_| (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "创建邀请失败".into()))?;
-
-    let id = result.inserted_id.as_object_id().unwrap().to_hex();
-    Ok(RespJson(InvitationResponse {
-        id,
-        lecture_id: payload.lecture_id,
-        speaker_id: payload.speaker_id,
-        status: payload.status,
-    }))
+        .map_err(|e| retry::db_error(e, "创建邀请失败"))?;
+    if payload.status == 0 {
+        notify_invitation(&client, inv_oid, lec_oid, spk_oid, role, message.as_deref(), false).await;
+    }
+    Ok(RespJson(InvitationResponse::from_doc(&doc)))
 }
 
 // GET /invitation/ -> 全部邀请
 async fn get_all_invitations(
     State(client): State<AppState>,
-) -> Result<RespJson<Vec<InvitationResponse>>, (axum::http::StatusCode, String)> {
+    paging: PageParams,
+) -> Result<Response, AppError> {
     let coll = invitation_collection(&client);
+    let total = coll
+        .count_documents(doc! {}, None)
+        .await
+        .map_err(|_| AppError::Internal("查询失败".into()))?;
     let mut cursor = coll
-        .find(doc! {}, None)
+        .find(doc! {}, paging.find_options(doc! { "_id": 1 }))
         .await
-        .map_err(|_| (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "查询失败".into()))?;
+        .map_err(|_| AppError::Internal("查询失败".into()))?;
     let mut items = Vec::new();
-    while let Some(doc) = cursor.try_next().await.map_err(|_| (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "读取失败".into()))? {
-        let id = doc.get_object_id("_id").map(|o| o.to_hex()).unwrap_or_default();
-        let lecture_id = doc.get_object_id("lecture_id").map(|o| o.to_hex()).unwrap_or_default();
-        let speaker_id = doc.get_object_id("speaker_id").map(|o| o.to_hex()).unwrap_or_default();
-        let status = doc.get_i32("status").unwrap_or(0);
-        items.push(InvitationResponse { id, lecture_id, speaker_id, status });
+    while let Some(doc) = cursor.try_next().await.map_err(|_| AppError::Internal("读取失败".into()))? {
+        items.push(InvitationResponse::from_doc(&doc));
     }
-    Ok(RespJson(items))
+    Ok(paging.respond(items, total))
 }
 
 // GET /invitation/:invitation_id
 async fn get_invitation(
     State(client): State<AppState>,
     Path(invitation_id): Path<String>,
-) -> Result<RespJson<InvitationResponse>, (axum::http::StatusCode, String)> {
+) -> Result<RespJson<InvitationResponse>, AppError> {
     let coll = invitation_collection(&client);
     let oid = ObjectId::parse_str(&invitation_id)
-        .map_err(|_| (axum::http::StatusCode::BAD_REQUEST, "Invalid invitation_id format".into()))?;
+        .map_err(|_| AppError::BadRequest("Invalid invitation_id format".into()))?;
     let doc = coll
         .find_one(doc! { "_id": oid }, None)
         .await
-        .map_err(|_| (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "查询失败".into()))?
-        .ok_or((axum::http::StatusCode::NOT_FOUND, "Invitation not found".into()))?;
-    let lecture_id = doc.get_object_id("lecture_id").map(|o| o.to_hex()).unwrap_or_default();
-    let speaker_id = doc.get_object_id("speaker_id").map(|o| o.to_hex()).unwrap_or_default();
-    let status = doc.get_i32("status").unwrap_or(0);
-    Ok(RespJson(InvitationResponse { id: invitation_id, lecture_id, speaker_id, status }))
+        .map_err(|_| AppError::Internal("查询失败".into()))?
+        .ok_or(AppError::NotFound("Invitation not found".into()))?;
+    Ok(RespJson(InvitationResponse::from_doc(&doc)))
 }
 
-// PUT /invitation/:invitation_id
+// 当前用户是否为该演讲的组织者；演讲已删除时视为否
+async fn is_lecture_organizer(client: &AppState, lecture_oid: ObjectId, auth: &AuthUser) -> Result<bool, AppError> {
+    let lecture = lecture_collection(client).find_one(doc! { "_id": lecture_oid }, None).await?;
+    Ok(lecture.is_some_and(|l| l.get_str("organizer_id").ok() == Some(auth.id_hex().as_str())))
+}
+
+// PUT /invitation/:invitation_id -> 组织者可修改邀请的演讲与被邀请者（邀请身份 invitee_role 不可修改）；
+// 被邀请者本人只能拒绝待回应的邀请（status = -1），接受须走 /invitation/accept/:invitation_id，
+// 以便讲者邀请写入演讲的讲者、听众邀请完成报名
 async fn update_invitation(
     State(client): State<AppState>,
+    auth: AuthUser,
     Path(invitation_id): Path<String>,
     Json(payload): Json<InvitationCreate>,
-) -> Result<RespJson<InvitationResponse>, (axum::http::StatusCode, String)> {
+) -> Result<RespJson<InvitationResponse>, AppError> {
     let coll = invitation_collection(&client);
     let oid = ObjectId::parse_str(&invitation_id)
-        .map_err(|_| (axum::http::StatusCode::BAD_REQUEST, "Invalid ID format".into()))?;
+        .map_err(|_| AppError::BadRequest("Invalid ID format".into()))?;
     let lec_oid = ObjectId::parse_str(&payload.lecture_id)
-        .map_err(|_| (axum::http::StatusCode::BAD_REQUEST, "Invalid lecture_id format".into()))?;
-    let spk_oid = ObjectId::parse_str(&payload.speaker_id)
-        .map_err(|_| (axum::http::StatusCode::BAD_REQUEST, "Invalid speaker_id format".into()))?;
+        .map_err(|_| AppError::BadRequest("Invalid lecture_id format".into()))?;
+
+    let mut invite = coll
+        .find_one(doc! { "_id": oid }, None)
+        .await
+        .map_err(|_| AppError::Internal("查询失败".into()))?
+        .ok_or(AppError::NotFound("Invitation not found".into()))?;
+    let role = InviteeRole::of(&invite);
+    if payload.invitee_role.is_some() && InviteeRole::parse(payload.invitee_role.as_deref())? != role {
+        return Err(AppError::BadRequest("invitee_role 不能修改".into()));
+    }
+    let invitee_oid = ObjectId::parse_str(requested_invitee(role, &payload).trim())
+        .map_err(|_| AppError::BadRequest(format!("Invalid {} format", role.id_field())))?;
+    let current_lecture = invite.get_object_id("lecture_id").map_err(|_| AppError::Internal("字段缺失".into()))?;
+    let current_invitee = invitee_of(&invite);
+    let current_status = invite.get_i32("status").unwrap_or(0);
+    let is_organizer = is_lecture_organizer(&client, current_lecture, &auth).await?;
+    let is_invitee = current_invitee == Some(auth.id);
+    if !is_organizer && !is_invitee {
+        return Err(AppError::Forbidden("只有该演讲的组织者或被邀请者可以修改邀请".into()));
+    }
+
+    if lec_oid != current_lecture || Some(invitee_oid) != current_invitee {
+        if !is_organizer {
+            return Err(AppError::Forbidden("只有该演讲的组织者可以修改邀请的演讲或被邀请者".into()));
+        }
+        let lecture = ensure_lecture_organizer(&client, lec_oid, &auth).await?;
+        if role == InviteeRole::Audience {
+            check_audience_invite(&client, &lecture, invitee_oid, current_status).await?;
+        }
+    }
+    if current_status != payload.status {
+        if !is_invitee {
+            return Err(AppError::Forbidden("只有被邀请者本人可以回应邀请".into()));
+        }
+        if payload.status == 1 {
+            return Err(AppError::BadRequest("接受邀请请使用 PUT /invitation/accept/:invitation_id".into()));
+        }
+        if payload.status != -1 {
+            return Err(AppError::BadRequest("status 只能改为 -1（拒绝）".into()));
+        }
+        if current_status != 0 {
+            return Err(not_pending_error(current_status));
+        }
+    }
 
+    // 以读取时的状态为条件，避免覆盖同时发生的接受或过期
     let update = doc! {
-        "$set": { "lecture_id": lec_oid, "speaker_id": spk_oid, "status": payload.status }
+        "$set": { "lecture_id": lec_oid, role.id_field(): invitee_oid, "status": payload.status }
     };
     let result = coll
-        .update_one(doc! { "_id": oid }, update, None)
+        .update_one(doc! { "_id": oid, "status": current_status }, update, None)
         .await
-        .map_err(|_| (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "更新失败".into()))?;
-    if result.matched_count == 0 { return Err((axum::http::StatusCode::NOT_FOUND, "Invitation not found".into())); }
-    Ok(RespJson(InvitationResponse { id: invitation_id, lecture_id: payload.lecture_id, speaker_id: payload.speaker_id, status: payload.status }))
+        .map_err(|_| AppError::Internal("更新失败".into()))?;
+    if result.matched_count == 0 {
+        return Err(AppError::Conflict("邀请状态已变化，请刷新后重试".into()));
+    }
+    invite.insert("lecture_id", lec_oid);
+    invite.insert(role.id_field(), invitee_oid);
+    invite.insert("status", payload.status);
+    Ok(RespJson(InvitationResponse::from_doc(&invite)))
 }
 
-// DELETE /invitation/:invitation_id
+// DELETE /invitation/:invitation_id -> 仅该演讲的组织者
 async fn delete_invitation(
     State(client): State<AppState>,
+    auth: RequireRole<Organizer>,
     Path(invitation_id): Path<String>,
-) -> Result<RespJson<serde_json::Value>, (axum::http::StatusCode, String)> {
+) -> Result<RespJson<serde_json::Value>, AppError> {
     let coll = invitation_collection(&client);
     let oid = ObjectId::parse_str(&invitation_id)
-        .map_err(|_| (axum::http::StatusCode::BAD_REQUEST, "Invalid invitation_id format".into()))?;
+        .map_err(|_| AppError::BadRequest("Invalid invitation_id format".into()))?;
+    let invite = coll
+        .find_one(doc! { "_id": oid }, None)
+        .await
+        .map_err(|_| AppError::Internal("查询失败".into()))?
+        .ok_or(AppError::NotFound("Invitation not found".into()))?;
+    let lecture_oid = invite.get_object_id("lecture_id").map_err(|_| AppError::Internal("字段缺失".into()))?;
+    ensure_lecture_organizer(&client, lecture_oid, &auth).await?;
     let result = coll
         .delete_one(doc! { "_id": oid }, None)
         .await
-        .map_err(|_| (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "删除失败".into()))?;
-    if result.deleted_count == 0 { return Err((axum::http::StatusCode::NOT_FOUND, "Invitation not found".into())); }
+        .map_err(|_| AppError::Internal("删除失败".into()))?;
+    if result.deleted_count == 0 { return Err(AppError::NotFound("Invitation not found".into())); }
     Ok(RespJson(serde_json::json!({"message": format!("Invitation {} deleted successfully", invitation_id)})))
 }
 
-// GET /invitation/byspeaker/:speaker_id -> 该讲者的邀请列表
+// GET /invitation/byspeaker/:speaker_id -> 该讲者的邀请列表，附带演讲主题、时间与组织者名称，
+// 收件箱页面无需再逐条查询演讲和用户；也包含发给该用户的听众邀请。演讲已删除的邀请 lecture 与 organizer 为 null
 async fn get_invitations_by_speaker(
     State(client): State<AppState>,
     Path(speaker_id): Path<String>,
-) -> Result<RespJson<Vec<InvitationResponse>>, (axum::http::StatusCode, String)> {
+    paging: PageParams,
+) -> Result<Response, AppError> {
     let coll = invitation_collection(&client);
     let spk_oid = ObjectId::parse_str(&speaker_id)
-        .map_err(|_| (axum::http::StatusCode::BAD_REQUEST, "Invalid speaker_id format".into()))?;
-    let mut cursor = coll
-        .find(doc! { "speaker_id": spk_oid }, None)
+        .map_err(|_| AppError::BadRequest("Invalid speaker_id format".into()))?;
+    // 同时列出讲者邀请与听众邀请
+    let mine = doc! { "$or": [{ "speaker_id": spk_oid }, { "invitee_id": spk_oid }] };
+    let total = coll
+        .count_documents(mine.clone(), None)
         .await
-        .map_err(|_| (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "查询失败".into()))?;
-    let mut items = Vec::new();
-    while let Some(doc) = cursor.try_next().await.map_err(|_| (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "读取失败".into()))? {
-        let id = doc.get_object_id("_id").map(|o| o.to_hex()).unwrap_or_default();
-        let lecture_id = doc.get_object_id("lecture_id").map(|o| o.to_hex()).unwrap_or_default();
-        let speaker_id = doc.get_object_id("speaker_id").map(|o| o.to_hex()).unwrap_or_default();
-        let status = doc.get_i32("status").unwrap_or(0);
-        items.push(InvitationResponse { id, lecture_id, speaker_id, status });
+        .map_err(|_| AppError::Internal("查询失败".into()))?;
+
+    // lecture.organizer_id 以 hex 字符串存储，转成 ObjectId 后关联用户；格式异常时不关联
+    let pipeline = vec![
+        doc! { "$match": mine },
+        doc! { "$sort": { "_id": 1 } },
+        doc! { "$skip": paging.skip() as i64 },
+        doc! { "$limit": paging.limit as i64 },
+        doc! { "$lookup": { "from": "lecture", "localField": "lecture_id", "foreignField": "_id", "as": "lecture" } },
+        doc! { "$set": { "lecture": { "$arrayElemAt": ["$lecture", 0] } } },
+        doc! { "$lookup": {
+            "from": "users",
+            "let": { "oid": { "$convert": {
+                "input": "$lecture.organizer_id",
+                "to": "objectId",
+                "onError": null,
+                "onNull": null,
+            } } },
+            "pipeline": [
+                { "$match": { "$expr": { "$eq": ["$_id", "$$oid"] } } },
+                { "$project": { "username": 1 } },
+            ],
+            "as": "organizer",
+        } },
+        doc! { "$set": { "organizer": { "$arrayElemAt": ["$organizer", 0] } } },
+    ];
+    let rows: Vec<bson::Document> = coll
+        .aggregate(pipeline, None)
+        .await
+        .map_err(|_| AppError::Internal("查询失败".into()))?
+        .try_collect()
+        .await
+        .map_err(|_| AppError::Internal("读取失败".into()))?;
+    let items: Vec<serde_json::Value> = rows
+        .iter()
+        .map(|row| {
+            let mut item = serde_json::to_value(InvitationResponse::from_doc(row)).unwrap_or_default();
+            item["lecture"] = match row.get_document("lecture") {
+                Ok(lecture) => serde_json::json!({
+                    "id": ids::oid_hex(lecture, "_id"),
+                    "topic": lecture.get_str("topic").unwrap_or(""),
+                    "start_time": lecture.get_i64("start_time").unwrap_or(0),
+                    "duration": lecture.get_i32("duration").unwrap_or(0),
+                    "status": LectureStatus::of(lecture).as_i32(),
+                    "status_name": LectureStatus::of(lecture).name(),
+                }),
+                Err(_) => serde_json::Value::Null,
+            };
+            item["organizer"] = match row.get_document("organizer") {
+                Ok(organizer) => serde_json::json!({
+                    "id": ids::oid_hex(organizer, "_id"),
+                    "username": organizer.get_str("username").unwrap_or(""),
+                }),
+                Err(_) => serde_json::Value::Null,
+            };
+            item
+        })
+        .collect();
+    Ok(paging.respond(items, total))
+}
+
+// 在同一事务内接受邀请、写入演讲的 speaker_id，并把同一演讲其余待回应的讲者邀请标记为已被取代，
+// 任一步失败整体回滚，不会出现邀请已接受而演讲没有讲者的情况。邀请已不是待回应状态时返回 Ok(None)
+async fn accept_in_transaction(
+    client: &AppState,
+    invitation_oid: ObjectId,
+    lecture_oid: ObjectId,
+    speaker_oid: ObjectId,
+) -> mongodb::error::Result<Option<u64>> {
+    let mut session = client.start_session(None).await?;
+    session.start_transaction(None).await?;
+    let result = async {
+        // 只接受待回应的邀请；与定时任务或其他讲者同时处理时以先写入者为准
+        let accepted = invitation_collection(client)
+            .update_one_with_session(
+                doc! { "_id": invitation_oid, "status": 0 },
+                doc! { "$set": { "status": 1, "accepted_at": Utc::now().timestamp_millis() } },
+                None,
+                &mut session,
+            )
+            .await?;
+        if accepted.matched_count == 0 {
+            return Ok(None);
+        }
+        // lecture 的 speaker_id 存 hex 字符串，兼容现有 lecture 结构
+        lecture_collection(client)
+            .update_one_with_session(
+                doc! { "_id": lecture_oid },
+                doc! { "$set": { "speaker_id": speaker_oid.to_hex() } },
+                None,
+                &mut session,
+            )
+            .await?;
+        let superseded = invitation_collection(client)
+            .update_many_with_session(
+                doc! {
+                    "lecture_id": lecture_oid,
+                    "status": 0,
+                    "_id": { "$ne": invitation_oid },
+                    "invitee_role": { "$ne": InviteeRole::Audience.as_str() },
+                },
+                doc! { "$set": {
+                    "status": INVITATION_SUPERSEDED,
+                    "superseded_at": Utc::now().timestamp_millis(),
+                    "superseded_by": invitation_oid,
+                } },
+                None,
+                &mut session,
+            )
+            .await?;
+        Ok(Some(superseded.modified_count))
+    }
+    .await;
+    match result {
+        Ok(Some(n)) => {
+            session.commit_transaction().await?;
+            Ok(Some(n))
+        }
+        other => {
+            let _ = session.abort_transaction().await;
+            other
+        }
+    }
+}
+
+// 邀请已不是待回应状态：过期返回 410，已接受、已拒绝或已被取代返回 409
+fn not_pending_error(status: i32) -> AppError {
+    match status {
+        INVITATION_EXPIRED => AppError::Gone("邀请已过期".into()),
+        INVITATION_SUPERSEDED => AppError::Conflict("该演讲已由其他讲者接受".into()),
+        1 => AppError::Conflict("邀请已接受".into()),
+        -1 => AppError::Conflict("邀请已被拒绝".into()),
+        _ => AppError::Conflict("邀请不是待回应状态".into()),
+    }
+}
+
+// 被取代的讲者收到通知，不必再回复
+async fn notify_superseded(client: &AppState, invitation_oid: ObjectId, lecture_oid: ObjectId) {
+    let speakers: Vec<bson::Bson> = match invitation_collection(client)
+        .distinct("speaker_id", doc! { "superseded_by": invitation_oid }, None)
+        .await
+    {
+        Ok(speakers) => speakers,
+        Err(e) => {
+            println!("查询被取代的邀请失败 {}: {}", invitation_oid.to_hex(), e);
+            return;
+        }
+    };
+    for speaker_oid in speakers.iter().filter_map(|b| b.as_object_id()) {
+        let payload = doc! { "lecture_id": lecture_oid.to_hex() };
+        if let Err(e) = notify::push(client, speaker_oid, "invitation_superseded", payload).await {
+            println!("发送邀请取消通知失败 {}: {}", speaker_oid.to_hex(), e);
+        }
     }
-    Ok(RespJson(items))
 }
 
-// PUT /invitation/accept/:invitation_id -> 接受邀请，并把 speaker_id 写入 lecture（以字符串十六进制存储）
+// 听众邀请：被邀请者本人接受后自动报名，演讲随即出现在其即将参加的演讲中。
+// 报名失败时把邀请退回待回应，可再次接受
+async fn accept_audience_invitation(
+    client: &AppState,
+    auth: &AuthUser,
+    invite: bson::Document,
+) -> Result<RespJson<InvitationResponse>, AppError> {
+    let oid = invite.get_object_id("_id").map_err(|_| AppError::Internal("字段缺失".into()))?;
+    let lecture_oid = invite.get_object_id("lecture_id").map_err(|_| AppError::Internal("字段缺失".into()))?;
+    let invitee_oid = invitee_of(&invite).ok_or(AppError::Internal("字段缺失".into()))?;
+    auth.ensure_self(&invitee_oid.to_hex())?;
+    if is_expired(&invite, Utc::now().timestamp_millis()) {
+        return Err(AppError::Gone("邀请已过期".into())
+            .with_details(serde_json::json!({ "expires_at": invite.get_i64("expires_at").ok() })));
+    }
+    let lecture = lecture_collection(client)
+        .find_one(doc! { "_id": lecture_oid }, None)
+        .await?
+        .ok_or(AppError::NotFound("Lecture not found".into()))?;
+    if matches!(LectureStatus::of(&lecture), LectureStatus::Ended | LectureStatus::Cancelled) {
+        return Err(AppError::Conflict("演讲已结束或已取消".into()));
+    }
+
+    // 已接受的邀请可重复接受，用于补登记
+    let was_pending = invite.get_i32("status").ok() == Some(0);
+    let accepted = invitation_collection(client)
+        .update_one(
+            doc! { "_id": oid, "status": { "$in": [0, 1] } },
+            doc! { "$set": { "status": 1, "accepted_at": Utc::now().timestamp_millis() } },
+            None,
+        )
+        .await?;
+    if accepted.matched_count == 0 {
+        return Err(AppError::Conflict("邀请已被拒绝或已失效".into()));
+    }
+    let registered = match la::register_invited(client, lecture_oid, invitee_oid, oid).await {
+        Ok(registered) => registered,
+        Err(e) => {
+            if was_pending {
+                invitation_collection(client)
+                    .update_one(doc! { "_id": oid }, doc! { "$set": { "status": 0 }, "$unset": { "accepted_at": "" } }, None)
+                    .await?;
+            }
+            return Err(e);
+        }
+    };
+
+    if registered {
+        if let Some(organizer_oid) = lecture.get_str("organizer_id").ok().and_then(|s| ObjectId::parse_str(s).ok()) {
+            let payload = doc! {
+                "invitation_id": oid.to_hex(),
+                "lecture_id": lecture_oid.to_hex(),
+                "invitee_id": invitee_oid.to_hex(),
+                "invitee_role": InviteeRole::Audience.as_str(),
+            };
+            if let Err(e) = notify::push(client, organizer_oid, "invitation_accepted", payload).await {
+                println!("发送接受邀请通知失败 {}: {}", oid.to_hex(), e);
+            }
+        }
+    }
+
+    let mut invite = invite;
+    invite.insert("status", 1);
+    Ok(RespJson(InvitationResponse::from_doc(&invite)))
+}
+
+// PUT /invitation/accept/:invitation_id -> 接受邀请，并把 speaker_id 写入 lecture（以字符串十六进制存储）；
+// 与讲者已有演讲时间冲突时返回 409，确认重叠可带 ?allow_conflict=true。
+// 同一演讲的其他待回应邀请随之失效；只有待回应的邀请可以接受，已接受、已拒绝或已被取代时返回 409。听众邀请见 accept_audience_invitation
 async fn accept_invitation(
     State(client): State<AppState>,
+    auth: AuthUser,
     Path(invitation_id): Path<String>,
-) -> Result<RespJson<InvitationResponse>, (axum::http::StatusCode, String)> {
+    Query(query): Query<AcceptQuery>,
+) -> Result<RespJson<InvitationResponse>, AppError> {
     let inv_coll = invitation_collection(&client);
     let lec_coll = lecture_collection(&client);
     let oid = ObjectId::parse_str(&invitation_id)
-        .map_err(|_| (axum::http::StatusCode::BAD_REQUEST, "Invalid invitation ID".into()))?;
+        .map_err(|_| AppError::BadRequest("Invalid invitation ID".into()))?;
 
     // 找邀请
     let invite = inv_coll
         .find_one(doc! { "_id": oid }, None)
         .await
-        .map_err(|_| (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "查询失败".into()))?
-        .ok_or((axum::http::StatusCode::NOT_FOUND, "Invitation not found".into()))?;
+        .map_err(|_| AppError::Internal("查询失败".into()))?
+        .ok_or(AppError::NotFound("Invitation not found".into()))?;
+    if InviteeRole::of(&invite) == InviteeRole::Audience {
+        return accept_audience_invitation(&client, &auth, invite).await;
+    }
+    if !Speaker::ROLES.contains(&auth.role) {
+        return Err(AppError::Forbidden(format!("仅{}可执行该操作", Speaker::NAME)));
+    }
 
-    let lecture_oid = invite.get_object_id("lecture_id").map_err(|_| (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "字段缺失".into()))?;
-    let speaker_oid = invite.get_object_id("speaker_id").map_err(|_| (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "字段缺失".into()))?;
+    let lecture_oid = invite.get_object_id("lecture_id").map_err(|_| AppError::Internal("字段缺失".into()))?;
+    let speaker_oid = invite.get_object_id("speaker_id").map_err(|_| AppError::Internal("字段缺失".into()))?;
+    // 只有被邀请的讲者本人可以接受
+    auth.ensure_self(&speaker_oid.to_hex())?;
+    if is_expired(&invite, Utc::now().timestamp_millis()) {
+        return Err(AppError::Gone("邀请已过期".into())
+            .with_details(serde_json::json!({ "expires_at": invite.get_i64("expires_at").ok() })));
+    }
+    let status = invite.get_i32("status").unwrap_or(0);
+    if status != 0 {
+        return Err(not_pending_error(status));
+    }
 
-    // 更新邀请状态
-    inv_coll
-        .update_one(doc! { "_id": oid }, doc! { "$set": { "status": 1 } }, None)
+    let lecture = lec_coll
+        .find_one(doc! { "_id": lecture_oid }, None)
+        .await?
+        .ok_or(AppError::NotFound("Lecture not found".into()))?;
+    if !query.allow_conflict {
+        let conflicts = find_schedule_conflicts(
+            &client,
+            &[speaker_oid.to_hex().as_str()],
+            lecture.get_i64("start_time").unwrap_or(0),
+            lecture.get_i32("duration").unwrap_or(0),
+            Some(lecture_oid),
+        )
+        .await?;
+        if !conflicts.is_empty() {
+            return Err(schedule_conflict_error(conflicts));
+        }
+    }
+
+    let superseded = match accept_in_transaction(&client, oid, lecture_oid, speaker_oid).await? {
+        Some(n) => n,
+        // 检查之后状态被并发修改：按最新状态给出原因
+        None => {
+            let status = inv_coll.find_one(doc! { "_id": oid }, None).await?.and_then(|i| i.get_i32("status").ok());
+            return Err(not_pending_error(status.unwrap_or(INVITATION_EXPIRED)));
+        }
+    };
+    if superseded > 0 {
+        notify_superseded(&client, oid, lecture_oid).await;
+    }
+
+    // 通知组织者讲者已接受
+    if let Some(organizer_oid) = lecture.get_str("organizer_id").ok().and_then(|s| ObjectId::parse_str(s).ok()) {
+        let payload = doc! {
+            "invitation_id": oid.to_hex(),
+            "lecture_id": lecture_oid.to_hex(),
+            "speaker_id": speaker_oid.to_hex(),
+        };
+        if let Err(e) = notify::push(&client, organizer_oid, "invitation_accepted", payload).await {
+            println!("发送接受邀请通知失败 {}: {}", oid.to_hex(), e);
+        }
+    }
+
+    let mut invite = invite;
+    invite.insert("status", 1);
+    Ok(RespJson(InvitationResponse::from_doc(&invite)))
+}
+
+
+// POST /invitation/broadcast -> 按专长标签向匹配的讲者批量发出待处理邀请
+async fn broadcast_invitations(
+    State(client): State<AppState>,
+    auth: RequireRole<Organizer>,
+    Json(payload): Json<BroadcastRequest>,
+) -> Result<RespJson<serde_json::Value>, AppError> {
+    let inv_coll = invitation_collection(&client);
+    let user_coll = user_collection(&client);
+
+    let lecture_oid = ObjectId::parse_str(&payload.lecture_id)
+        .map_err(|_| AppError::BadRequest("Invalid lecture_id format".into()))?;
+    let lecture = ensure_lecture_organizer(&client, lecture_oid, &auth).await?;
+    let body = invitation_template::resolve(&client, auth.id, payload.template_id.as_deref(), payload.message.as_deref()).await?;
+
+    let mut tags: Vec<String> = payload
+        .tags
+        .iter()
+        .map(|t| t.trim().to_lowercase())
+        .filter(|t| !t.is_empty())
+        .collect();
+    tags.sort();
+    tags.dedup();
+    if tags.is_empty() {
+        return Err(AppError::BadRequest("tags 不能为空".into()));
+    }
+    let cap = payload.cap.unwrap_or(BROADCAST_DEFAULT_CAP).clamp(1, BROADCAST_MAX_CAP);
+    let now = Utc::now().timestamp_millis();
+    let expires_at = expiry_for(&lecture, now, payload.expires_at)?;
+
+    // 已收到过该演讲邀请的讲者不再重复邀请
+    let mut already = Vec::new();
+    let mut cursor = inv_coll
+        .find(doc! { "lecture_id": lecture_oid }, None)
+        .await
+        .map_err(|_| AppError::Internal("查询失败".into()))?;
+    while let Some(doc) = cursor.try_next().await.map_err(|_| AppError::Internal("读取失败".into()))? {
+        if let Ok(oid) = doc.get_object_id("speaker_id") {
+            already.push(oid);
+        }
+    }
+
+    // 命中标签越多越靠前
+    let pipeline = vec![
+        doc! { "$match": { "role": 2, "expertise": { "$in": &tags }, "_id": { "$nin": &already } } },
+        doc! { "$addFields": { "matched_tags": { "$setIntersection": ["$expertise", &tags] } } },
+        doc! { "$addFields": { "match_count": { "$size": "$matched_tags" } } },
+        doc! { "$sort": { "match_count": -1, "username": 1 } },
+        doc! { "$limit": cap as i64 },
+        doc! { "$project": { "username": 1, "matched_tags": 1, "preferences": 1 } },
+    ];
+    let mut cursor = user_coll
+        .aggregate(pipeline, None)
         .await
-        .map_err(|_| (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "更新失败".into()))?;
+        .map_err(|_| AppError::Internal("查询讲者失败".into()))?;
+
+    let mut speakers = Vec::new();
+    while let Some(doc) = cursor.try_next().await.map_err(|_| AppError::Internal("读取失败".into()))? {
+        let Ok(oid) = doc.get_object_id("_id") else { continue };
+        let matched_tags = doc
+            .get_array("matched_tags")
+            .map(|a| a.iter().filter_map(|t| t.as_str().map(|s| s.to_string())).collect())
+            .unwrap_or_default();
+        let message = body.as_deref().map(|b| invitation_template::render(b, &lecture, &doc));
+        speakers.push((oid, doc.get_str("username").unwrap_or("").to_string(), matched_tags, message));
+    }
 
-    // 同步更新 lecture 的 speaker_id（存 hex 字符串，兼容现有 lecture 结构）
-    lec_coll
+    let mut invited = Vec::new();
+    if !speakers.is_empty() {
+        let docs = speakers.iter().map(|(oid, _, _, message)| {
+            let mut d = doc! {
+                "lecture_id": lecture_oid,
+                "speaker_id": oid,
+                "status": 0,
+                "created_at": now,
+                "expires_at": expires_at,
+            };
+            if let Some(message) = message {
+                d.insert("message", message);
+            }
+            d
+        });
+        let result = inv_coll
+            .insert_many(docs, None)
+            .await
+            .map_err(|_| AppError::Internal("创建邀请失败".into()))?;
+        for (idx, (oid, username, matched_tags, message)) in speakers.into_iter().enumerate() {
+            let invitation_id = result
+                .inserted_ids
+                .get(&idx)
+                .and_then(|b| b.as_object_id());
+            if let Some(inv_oid) = invitation_id {
+                notify_invitation(&client, inv_oid, lecture_oid, oid, InviteeRole::Speaker, message.as_deref(), false).await;
+            }
+            let invitation_id = invitation_id.map(|o| o.to_hex()).unwrap_or_default();
+            invited.push(BroadcastInvited {
+                invitation_id,
+                speaker_id: oid.to_hex(),
+                username,
+                matched_tags,
+            });
+        }
+    }
+
+    Ok(RespJson(serde_json::json!({
+        "lecture_id": payload.lecture_id,
+        "tags": tags,
+        "cap": cap,
+        "expires_at": expires_at,
+        "already_invited": already.len(),
+        "invited_count": invited.len(),
+        "invited": invited,
+    })))
+}
+
+// POST /invitation/bulk {lecture_id, speaker_ids, template_id?, message?, expires_at?}
+// -> 一次邀请多位讲者，按 speaker_ids 顺序逐个返回结果：invited / already_invited（已收到过该演讲的邀请）/
+// duplicate（列表内重复）/ invalid_id / not_found / not_speaker。新邀请一次 insert_many 写入
+async fn bulk_invitations(
+    State(client): State<AppState>,
+    auth: RequireRole<Organizer>,
+    Json(payload): Json<BulkInviteRequest>,
+) -> Result<RespJson<serde_json::Value>, AppError> {
+    let inv_coll = invitation_collection(&client);
+    let lecture_oid = ObjectId::parse_str(&payload.lecture_id)
+        .map_err(|_| AppError::BadRequest("Invalid lecture_id format".into()))?;
+    let lecture = ensure_lecture_organizer(&client, lecture_oid, &auth).await?;
+    if payload.speaker_ids.is_empty() {
+        return Err(AppError::BadRequest("speaker_ids 不能为空".into()));
+    }
+    if payload.speaker_ids.len() > BULK_MAX_SPEAKERS {
+        return Err(AppError::BadRequest(format!("单次最多邀请 {} 位讲者", BULK_MAX_SPEAKERS)));
+    }
+    let body = invitation_template::resolve(&client, auth.id, payload.template_id.as_deref(), payload.message.as_deref()).await?;
+    let now = Utc::now().timestamp_millis();
+    let expires_at = expiry_for(&lecture, now, payload.expires_at)?;
+
+    let parsed: Vec<(String, Option<ObjectId>)> = payload
+        .speaker_ids
+        .iter()
+        .map(|raw| (raw.trim().to_string(), ObjectId::parse_str(raw.trim()).ok()))
+        .collect();
+    let candidates: Vec<ObjectId> = parsed.iter().filter_map(|(_, oid)| *oid).collect();
+    let already: HashSet<ObjectId> = inv_coll
+        .distinct("speaker_id", doc! { "lecture_id": lecture_oid, "speaker_id": { "$in": &candidates } }, None)
+        .await?
+        .into_iter()
+        .filter_map(|b| b.as_object_id())
+        .collect();
+    let users: HashMap<ObjectId, bson::Document> = user_collection(&client)
+        .find(
+            doc! { "_id": { "$in": &candidates } },
+            mongodb::options::FindOptions::builder().projection(doc! { "username": 1, "role": 1, "preferences": 1 }).build(),
+        )
+        .await?
+        .try_collect::<Vec<_>>()
+        .await?
+        .into_iter()
+        .filter_map(|u| Some((u.get_object_id("_id").ok()?, u)))
+        .collect();
+
+    // 先逐个判定，再把需要新建的一次写入；pending 记下每项在 results 中的位置，写入后回填 invitation_id
+    let mut seen = HashSet::new();
+    let mut results = Vec::with_capacity(parsed.len());
+    let mut pending: Vec<(usize, ObjectId, Option<String>)> = Vec::new();
+    for (raw, oid) in parsed {
+        let outcome = match oid {
+            None => "invalid_id",
+            Some(oid) if !seen.insert(oid) => "duplicate",
+            Some(oid) if already.contains(&oid) => "already_invited",
+            Some(oid) => match users.get(&oid) {
+                None => "not_found",
+                Some(user) if user.get_i32("role").ok() != Some(ROLE_SPEAKER) => "not_speaker",
+                Some(user) => {
+                    let message = body.as_deref().map(|b| invitation_template::render(b, &lecture, user));
+                    pending.push((results.len(), oid, message));
+                    "invited"
+                }
+            },
+        };
+        let username = oid.and_then(|o| users.get(&o)).and_then(|u| u.get_str("username").ok()).unwrap_or("");
+        results.push(serde_json::json!({
+            "speaker_id": raw,
+            "username": username,
+            "result": outcome,
+            "invitation_id": null,
+        }));
+    }
+
+    if !pending.is_empty() {
+        let docs = pending.iter().map(|(_, oid, message)| {
+            let mut d = doc! {
+                "lecture_id": lecture_oid,
+                "speaker_id": oid,
+                "status": 0,
+                "created_at": now,
+                "expires_at": expires_at,
+            };
+            if let Some(message) = message {
+                d.insert("message", message);
+            }
+            d
+        });
+        let result = inv_coll
+            .insert_many(docs, None)
+            .await
+            .map_err(|e| retry::db_error(e, "创建邀请失败"))?;
+        for (idx, (slot, oid, message)) in pending.iter().enumerate() {
+            let Some(inv_oid) = result.inserted_ids.get(&idx).and_then(|b| b.as_object_id()) else { continue };
+            results[*slot]["invitation_id"] = serde_json::json!(inv_oid.to_hex());
+            notify_invitation(&client, inv_oid, lecture_oid, *oid, InviteeRole::Speaker, message.as_deref(), false).await;
+        }
+    }
+
+    let tally = |name: &str| results.iter().filter(|r| r["result"] == name).count();
+    Ok(RespJson(serde_json::json!({
+        "lecture_id": payload.lecture_id,
+        "expires_at": expires_at,
+        "requested": results.len(),
+        "invited_count": tally("invited"),
+        "skipped_count": results.len() - tally("invited"),
+        "results": results,
+    })))
+}
+
+// POST /invitation/:invitation_id/remind -> 对未回复的邀请重新发送通知（限流）
+async fn remind_invitation(
+    State(client): State<AppState>,
+    auth: RequireRole<Organizer>,
+    Path(invitation_id): Path<String>,
+) -> Result<RespJson<serde_json::Value>, AppError> {
+    let coll = invitation_collection(&client);
+    let oid = ObjectId::parse_str(&invitation_id)
+        .map_err(|_| AppError::BadRequest("Invalid invitation_id format".into()))?;
+    let invite = coll
+        .find_one(doc! { "_id": oid }, None)
+        .await
+        .map_err(|_| AppError::Internal("查询失败".into()))?
+        .ok_or(AppError::NotFound("Invitation not found".into()))?;
+
+    if let Ok(lecture_oid) = invite.get_object_id("lecture_id") {
+        ensure_lecture_organizer(&client, lecture_oid, &auth).await?;
+    }
+    let now = Utc::now().timestamp_millis();
+    if is_expired(&invite, now) {
+        return Err(AppError::Gone("邀请已过期，无法提醒".into()));
+    }
+    if invite.get_i32("status").unwrap_or(0) != 0 {
+        return Err(AppError::Conflict("邀请已处理，无需提醒".into()));
+    }
+    let count = invite.get_i32("reminder_count").unwrap_or(0);
+    if count >= REMIND_MAX_COUNT {
+        return Err(AppError::TooManyRequests(format!("最多提醒 {} 次", REMIND_MAX_COUNT)));
+    }
+    if let Ok(last) = invite.get_i64("last_reminded_at") {
+        let wait = last + REMIND_MIN_INTERVAL_MS - now;
+        if wait > 0 {
+            return Err(AppError::TooManyRequests(format!("提醒过于频繁，请 {} 分钟后再试", (wait + 59_999) / 60_000)));
+        }
+    }
+
+    // 条件更新避免并发请求重复提醒
+    let mut filter = doc! { "_id": oid, "status": 0, "reminder_count": { "$lt": REMIND_MAX_COUNT } };
+    match invite.get_i64("last_reminded_at") {
+        Ok(last) => filter.insert("last_reminded_at", last),
+        Err(_) => filter.insert("last_reminded_at", doc! { "$exists": false }),
+    };
+    let result = coll
         .update_one(
-            doc! { "_id": lecture_oid },
-            doc! { "$set": { "speaker_id": speaker_oid.to_hex() } },
+            filter,
+            doc! { "$inc": { "reminder_count": 1 }, "$set": { "last_reminded_at": now } },
             None,
         )
         .await
-        .map_err(|_| (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "更新演讲失败".into()))?;
+        .map_err(|_| AppError::Internal("更新失败".into()))?;
+    if result.modified_count == 0 {
+        return Err(AppError::TooManyRequests("提醒过于频繁，请稍后再试".into()));
+    }
 
-    Ok(RespJson(InvitationResponse {
-        id: invitation_id,
-        lecture_id: lecture_oid.to_hex(),
-        speaker_id: speaker_oid.to_hex(),
-        status: 1,
-    }))
+    let lecture_oid = invite.get_object_id("lecture_id").map_err(|_| AppError::Internal("字段缺失".into()))?;
+    let invitee_oid = invitee_of(&invite).ok_or(AppError::Internal("字段缺失".into()))?;
+    notify_invitation(&client, oid, lecture_oid, invitee_oid, InviteeRole::of(&invite), invite.get_str("message").ok(), true).await;
+
+    Ok(RespJson(serde_json::json!({
+        "id": invitation_id,
+        "reminder_count": count + 1,
+        "last_reminded_at": now,
+        "next_allowed_at": now + REMIND_MIN_INTERVAL_MS,
+    })))
 }
 
+// GET /invitation/unanswered/:organizer_id -> 组织者视角：待回复邀请，等待最久的排前面
+async fn get_unanswered_by_organizer(
+    State(client): State<AppState>,
+    auth: RequireRole<Organizer>,
+    Path(organizer_id): Path<String>,
+) -> Result<RespJson<Vec<serde_json::Value>>, AppError> {
+    auth.ensure_self(&organizer_id)?;
+    let inv_coll = invitation_collection(&client);
+    let lec_coll = lecture_collection(&client);
+    let user_coll = user_collection(&client);
+
+    // lecture 中 organizer_id 以 hex 字符串存储
+    let mut lectures = std::collections::HashMap::new();
+    let mut cursor = lec_coll
+        .find(doc! { "organizer_id": &organizer_id }, None)
+        .await
+        .map_err(|_| AppError::Internal("查询失败".into()))?;
+    while let Some(doc) = cursor.try_next().await.map_err(|_| AppError::Internal("读取失败".into()))? {
+        if let Ok(oid) = doc.get_object_id("_id") {
+            lectures.insert(oid, doc.get_str("topic").unwrap_or("").to_string());
+        }
+    }
+    if lectures.is_empty() {
+        return Ok(RespJson(Vec::new()));
+    }
+    let lecture_ids: Vec<ObjectId> = lectures.keys().cloned().collect();
+
+    let mut pending = Vec::new();
+    let mut cursor = inv_coll
+        .find(doc! { "lecture_id": { "$in": &lecture_ids }, "status": 0 }, None)
+        .await
+        .map_err(|_| AppError::Internal("查询失败".into()))?;
+    while let Some(doc) = cursor.try_next().await.map_err(|_| AppError::Internal("读取失败".into()))? {
+        pending.push(doc);
+    }
+
+    let speaker_ids: Vec<ObjectId> = pending.iter().filter_map(invitee_of).collect();
+    let mut usernames = std::collections::HashMap::new();
+    let mut cursor = user_coll
+        .find(doc! { "_id": { "$in": &speaker_ids } }, None)
+        .await
+        .map_err(|_| AppError::Internal("查询失败".into()))?;
+    while let Some(doc) = cursor.try_next().await.map_err(|_| AppError::Internal("读取失败".into()))? {
+        if let Ok(oid) = doc.get_object_id("_id") {
+            usernames.insert(oid, doc.get_str("username").unwrap_or("").to_string());
+        }
+    }
+
+    let now = Utc::now().timestamp_millis();
+    let mut items: Vec<(i64, serde_json::Value)> = pending
+        .iter()
+        .filter_map(|doc| {
+            let id = doc.get_object_id("_id").ok()?;
+            let lecture_oid = doc.get_object_id("lecture_id").ok()?;
+            let speaker_oid = invitee_of(doc)?;
+            // 旧数据没有 created_at，用 ObjectId 内的时间戳代替
+            let created_at = doc
+                .get_i64("created_at")
+                .unwrap_or_else(|_| id.timestamp().timestamp_millis());
+            let waiting_ms = now - created_at;
+            Some((waiting_ms, serde_json::json!({
+                "id": id.to_hex(),
+                "lecture_id": lecture_oid.to_hex(),
+                "topic": lectures.get(&lecture_oid).cloned().unwrap_or_default(),
+                "speaker_id": speaker_oid.to_hex(),
+                "invitee_role": InviteeRole::of(doc).as_str(),
+                "username": usernames.get(&speaker_oid).cloned().unwrap_or_default(),
+                "created_at": created_at,
+                "expires_at": doc.get_i64("expires_at").ok(),
+                "waiting_hours": waiting_ms / 3_600_000,
+                "reminder_count": doc.get_i32("reminder_count").unwrap_or(0),
+                "last_reminded_at": doc.get_i64("last_reminded_at").ok(),
+            })))
+        })
+        .collect();
+    items.sort_by_key(|(waiting_ms, _)| std::cmp::Reverse(*waiting_ms));
+    Ok(RespJson(items.into_iter().map(|(_, v)| v).collect()))
+}
 
-// DELETE /invitation/lid/:lecture_id
+// DELETE /invitation/lid/:lecture_id -> 仅该演讲的组织者
 async fn delete_invitation_by_lid(
     State(client): State<AppState>,
+    auth: RequireRole<Organizer>,
     Path(lecture_id): Path<String>,
-) -> Result<RespJson<serde_json::Value>, (axum::http::StatusCode, String)> {
+) -> Result<RespJson<serde_json::Value>, AppError> {
     let coll = invitation_collection(&client);
     let oid = ObjectId::parse_str(&lecture_id)
-        .map_err(|_| (axum::http::StatusCode::BAD_REQUEST, "Invalid invitation_id format".into()))?;
+        .map_err(|_| AppError::BadRequest("Invalid invitation_id format".into()))?;
+    ensure_lecture_organizer(&client, oid, &auth).await?;
     let result = coll
         .delete_one(doc! { "lecture_id": oid }, None)
         .await
-        .map_err(|_| (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "删除失败".into()))?;
-    if result.deleted_count == 0 { return Err((axum::http::StatusCode::NOT_FOUND, "Invitation not found".into())); }
+        .map_err(|_| AppError::Internal("删除失败".into()))?;
+    if result.deleted_count == 0 { return Err(AppError::NotFound("Invitation not found".into())); }
     Ok(RespJson(serde_json::json!({"message": format!("Invitation which lecture_id is {} deleted successfully", lecture_id)})))
 }
 
@@ -228,6 +1152,10 @@ async fn delete_invitation_by_lid(
 pub fn router() -> Router<AppState> {
     Router::new()
         .route("/create", post(create_invitation))
+        .route("/broadcast", post(broadcast_invitations))
+        .route("/bulk", post(bulk_invitations))
+        .route("/unanswered/:organizer_id", get(get_unanswered_by_organizer))
+        .route("/:invitation_id/remind", post(remind_invitation))
         .route("/", get(get_all_invitations))
         .route("/:invitation_id", get(get_invitation))
         .route("/:invitation_id", put(update_invitation))
@@ -235,5 +1163,6 @@ pub fn router() -> Router<AppState> {
         .route("/byspeaker/:speaker_id", get(get_invitations_by_speaker))
         .route("/accept/:invitation_id", put(accept_invitation))
         .route("/lid/:lecture_id", delete(delete_invitation_by_lid))
+        .merge(invitation_template::router())
 }
 
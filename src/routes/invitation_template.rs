@@ -0,0 +1,201 @@
+// src/routes/invitation_template.rs
+// 邀请模板：组织者保存常用的邀请措辞，发邀请（单个或按标签批量）时指定 template_id 套用。
+// 正文中的 {{topic}}、{{time}}、{{venue}} 按演讲信息替换，时间按受邀讲者的时区与语言显示
+use axum::{
+    extract::{Path, State},
+    response::Json,
+    routing::get,
+    Router,
+};
+use bson::{doc, oid::ObjectId, Document};
+use chrono::Utc;
+use futures_util::TryStreamExt;
+use mongodb::{options::FindOptions, Client};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::auth::{Organizer, RequireRole};
+use crate::db::{duplicate_key_index, invitation_template_collection};
+use crate::error::AppError;
+use crate::timefmt::UserTime;
+use crate::{ids, retry};
+
+type AppState = Arc<Client>;
+
+const PLACEHOLDERS: [&str; 3] = ["topic", "time", "venue"];
+const MAX_NAME_CHARS: usize = 50;
+const MAX_BODY_CHARS: usize = 2000;
+const MAX_TEMPLATES: u64 = 50;
+
+static PLACEHOLDER_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\{\{\s*([A-Za-z_]+)\s*\}\}").unwrap());
+
+#[derive(Deserialize)]
+struct TemplateRequest {
+    name: String,
+    body: String,
+}
+
+// 校验名称、正文长度及占位符，返回修整后的 (name, body)
+fn validate(payload: TemplateRequest) -> Result<(String, String), AppError> {
+    let name = payload.name.trim().to_string();
+    if name.is_empty() || name.chars().count() > MAX_NAME_CHARS {
+        return Err(AppError::BadRequest(format!("name 不能为空且不超过 {} 字", MAX_NAME_CHARS)));
+    }
+    Ok((name, check_body(&payload.body, "body")?))
+}
+
+// 正文长度与占位符校验，模板与发邀请时直接填写的 message 共用
+fn check_body(raw: &str, field: &str) -> Result<String, AppError> {
+    let body = raw.trim().to_string();
+    if body.is_empty() || body.chars().count() > MAX_BODY_CHARS {
+        return Err(AppError::BadRequest(format!("{} 不能为空且不超过 {} 字", field, MAX_BODY_CHARS)));
+    }
+    if let Some(unknown) = PLACEHOLDER_RE
+        .captures_iter(&body)
+        .filter_map(|c| c.get(1))
+        .find(|m| !PLACEHOLDERS.contains(&m.as_str()))
+    {
+        return Err(AppError::BadRequest(format!(
+            "未知占位符 {{{{{}}}}}，可用: {}",
+            unknown.as_str(),
+            PLACEHOLDERS.map(|p| format!("{{{{{}}}}}", p)).join(" ")
+        )));
+    }
+    Ok(body)
+}
+
+// 按演讲与受邀讲者填充占位符
+pub fn render(body: &str, lecture: &Document, speaker: &Document) -> String {
+    let time = UserTime::for_user(speaker);
+    PLACEHOLDER_RE
+        .replace_all(body, |caps: &regex::Captures| match &caps[1] {
+            "topic" => lecture.get_str("topic").unwrap_or("").to_string(),
+            "time" => time.datetime(lecture.get_i64("start_time").unwrap_or(0)),
+            "venue" => lecture.get_str("venue").ok().filter(|v| !v.is_empty()).unwrap_or("待定").to_string(),
+            _ => caps[0].to_string(),
+        })
+        .into_owned()
+}
+
+// 发邀请时的附言来源：自己的模板或直接填写的 message，二选一，都不给则不带附言
+pub async fn resolve(client: &AppState, owner: ObjectId, template_id: Option<&str>, message: Option<&str>) -> Result<Option<String>, AppError> {
+    match (template_id, message) {
+        (Some(_), Some(_)) => Err(AppError::BadRequest("template_id 与 message 只能指定一个".into())),
+        (Some(template_id), None) => {
+            let oid = ids::parse_oid(template_id, "template_id")?;
+            let template = invitation_template_collection(client)
+                .find_one(doc! { "_id": oid, "organizer_id": owner }, None)
+                .await?
+                .ok_or(AppError::NotFound("邀请模板不存在".into()))?;
+            Ok(template.get_str("body").ok().map(|b| b.to_string()))
+        }
+        (None, Some(message)) => check_body(message, "message").map(Some),
+        (None, None) => Ok(None),
+    }
+}
+
+fn name_conflict(e: mongodb::error::Error) -> AppError {
+    match duplicate_key_index(&e) {
+        Some(_) => AppError::Conflict("已有同名模板".into()),
+        None => retry::db_error(e, "保存模板失败"),
+    }
+}
+
+// GET /invitation/templates -> 自己的模板，按名称排序
+async fn list_templates(
+    State(client): State<AppState>,
+    auth: RequireRole<Organizer>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let options = FindOptions::builder().sort(doc! { "name": 1 }).build();
+    let items: Vec<serde_json::Value> = invitation_template_collection(&client)
+        .find(doc! { "organizer_id": auth.id }, options)
+        .await?
+        .map_ok(ids::doc_to_json)
+        .try_collect()
+        .await?;
+    Ok(Json(serde_json::json!({ "items": items, "placeholders": PLACEHOLDERS })))
+}
+
+// POST /invitation/templates {name, body}
+async fn create_template(
+    State(client): State<AppState>,
+    auth: RequireRole<Organizer>,
+    Json(payload): Json<TemplateRequest>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let (name, body) = validate(payload)?;
+    let coll = invitation_template_collection(&client);
+    if coll.count_documents(doc! { "organizer_id": auth.id }, None).await? >= MAX_TEMPLATES {
+        return Err(AppError::Conflict(format!("最多保存 {} 个模板", MAX_TEMPLATES)));
+    }
+    let now = Utc::now().timestamp_millis();
+    let mut template = doc! {
+        "organizer_id": auth.id,
+        "name": &name,
+        "body": &body,
+        "created_at": now,
+        "updated_at": now,
+    };
+    retry::insert_one(&coll, &mut template).await.map_err(name_conflict)?;
+    Ok(Json(ids::doc_to_json(template)))
+}
+
+// GET /invitation/templates/:template_id
+async fn get_template(
+    State(client): State<AppState>,
+    auth: RequireRole<Organizer>,
+    Path(template_id): Path<String>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let oid = ids::parse_oid(&template_id, "template_id")?;
+    let template = invitation_template_collection(&client)
+        .find_one(doc! { "_id": oid, "organizer_id": auth.id }, None)
+        .await?
+        .ok_or(AppError::NotFound("邀请模板不存在".into()))?;
+    Ok(Json(ids::doc_to_json(template)))
+}
+
+// PUT /invitation/templates/:template_id {name, body} -> 整体替换；已发出的邀请不受影响
+async fn update_template(
+    State(client): State<AppState>,
+    auth: RequireRole<Organizer>,
+    Path(template_id): Path<String>,
+    Json(payload): Json<TemplateRequest>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let oid = ids::parse_oid(&template_id, "template_id")?;
+    let (name, body) = validate(payload)?;
+    let updated = invitation_template_collection(&client)
+        .find_one_and_update(
+            doc! { "_id": oid, "organizer_id": auth.id },
+            doc! { "$set": { "name": &name, "body": &body, "updated_at": Utc::now().timestamp_millis() } },
+            mongodb::options::FindOneAndUpdateOptions::builder()
+                .return_document(mongodb::options::ReturnDocument::After)
+                .build(),
+        )
+        .await
+        .map_err(name_conflict)?
+        .ok_or(AppError::NotFound("邀请模板不存在".into()))?;
+    Ok(Json(ids::doc_to_json(updated)))
+}
+
+// DELETE /invitation/templates/:template_id
+async fn delete_template(
+    State(client): State<AppState>,
+    auth: RequireRole<Organizer>,
+    Path(template_id): Path<String>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let oid = ids::parse_oid(&template_id, "template_id")?;
+    let result = invitation_template_collection(&client)
+        .delete_one(doc! { "_id": oid, "organizer_id": auth.id }, None)
+        .await?;
+    if result.deleted_count == 0 {
+        return Err(AppError::NotFound("邀请模板不存在".into()));
+    }
+    Ok(Json(serde_json::json!({ "id": template_id, "deleted": true })))
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/templates", get(list_templates).post(create_template))
+        .route("/templates/:template_id", get(get_template).put(update_template).delete(delete_template))
+}
@@ -354,20 +354,32 @@
 
 // src/routes/la.rs
 use axum::{
-    extract::{Path, Query, State},
-    http::StatusCode,
-    response::Json,
-    routing::{delete, get, patch, post},
+    extract::{ConnectInfo, Path, Query, State},
+    http::{header, HeaderMap},
+    response::{IntoResponse, Json, Response},
+    routing::{delete, get, post},
     Router,
 };
-use bson::{doc, oid::ObjectId, Document};
+use bson::{doc, oid::ObjectId};
 use futures_util::stream::StreamExt;
+use futures_util::TryStreamExt;
+use mongodb::options::{FindOneAndDeleteOptions, FindOptions};
 use mongodb::Client;
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::net::SocketAddr;
 use std::sync::Arc;
 use chrono::Utc;
 
-use crate::db::{la_collection, user_collection};
+use crate::timefmt::UserTime;
+use crate::{anomaly, audit, config, csvexport, ids, lecturecode, qr, signing};
+use crate::auth::{require_lecture_role, Audience, AuthUser, LectureRole, Organizer, RequireRole};
+use crate::lifecycle::LectureStatus;
+use crate::routes::lecture::{effective_capacity, ensure_lecture_organizer, ensure_registration_open, MAX_OVERBOOKING_PERCENT};
+use crate::client_info::{client_ip, device_id};
+use crate::db::{self, la_collection, lecture_collection, user_collection, waitlist_collection};
+use crate::{notify, realtime};
+use crate::error::AppError;
 
 type AppState = Arc<Client>;
 
@@ -377,14 +389,15 @@ type AppState = Arc<Client>;
 struct LARecord {
     lecture_id: String,
     audience_id: String,
-    is_present: Option<bool>,
-    joined_at: Option<i64>,
 }
 
 #[derive(Deserialize)]
 struct LACreateRequest {
     lecture_id: String,
     audience_id: String,
+    // 名额已满时是否进入候补；默认直接拒绝
+    #[serde(default)]
+    waitlist: bool,
 }
 
 #[derive(Serialize)]
@@ -396,6 +409,99 @@ struct LAResponse {
     joined_at: Option<i64>,
 }
 
+// 报名记录的对外形式
+#[derive(Serialize)]
+struct LADocument {
+    id: String,
+    lecture_id: String,
+    audience_id: String,
+    is_present: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    joined_at: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    checked_in_at: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    left_at: Option<i64>,
+    from_waitlist: bool,
+}
+
+impl LADocument {
+    fn from_doc(doc: &bson::Document) -> LADocument {
+        LADocument {
+            id: ids::oid_hex(doc, "_id"),
+            lecture_id: ids::oid_hex(doc, "lecture_id"),
+            audience_id: ids::oid_hex(doc, "audience_id"),
+            is_present: doc.get_bool("is_present").unwrap_or(false),
+            joined_at: millis(doc, "joined_at"),
+            checked_in_at: millis(doc, "checked_in_at"),
+            left_at: millis(doc, "left_at"),
+            from_waitlist: doc.get_bool("from_waitlist").unwrap_or(false),
+        }
+    }
+}
+
+// created：签到时顺带报名；updated：出勤状态有变化；unchanged：与原记录相同（重复提交）
+#[derive(Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum PresenceOutcome {
+    Created,
+    Updated,
+    Unchanged,
+}
+
+#[derive(Serialize)]
+struct PresenceResponse {
+    message: String,
+    outcome: PresenceOutcome,
+    // 报名时间，签到不会改动
+    joined_at: Option<i64>,
+    record: LADocument,
+}
+
+#[derive(Deserialize)]
+struct CheckinRequest {
+    lecturecode: String,
+    audience_id: String,
+}
+
+#[derive(Deserialize)]
+struct QrTokenQuery {
+    lecture_id: String,
+    // 有效期（秒），缺省 60
+    ttl: Option<i64>,
+    // json（默认）或 png
+    format: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct QrCheckinRequest {
+    token: String,
+}
+
+#[derive(Deserialize)]
+struct BulkPresenceEntry {
+    audience_id: String,
+    is_present: bool,
+}
+
+#[derive(Deserialize)]
+struct BulkPresenceRequest {
+    lecture_id: String,
+    entries: Vec<BulkPresenceEntry>,
+}
+
+#[derive(Deserialize)]
+struct MyLecturesQuery {
+    #[serde(default)]
+    upcoming: bool,
+}
+
+#[derive(Deserialize)]
+struct CheckoutRequest {
+    lecture_id: String,
+    audience_id: String,
+}
+
 #[derive(Deserialize)]
 struct UpdateIsPresent {
     lecture_id: String,
@@ -405,43 +511,277 @@ struct UpdateIsPresent {
 
 // ==================== 工具函数 ====================
 
-fn convert_doc_ids(doc: &mut Document) -> Result<(), (StatusCode, String)> {
-    let id = doc.get_object_id("_id")
-        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "Invalid _id".into()))?
-        .to_hex();
-    doc.insert("_id", id);
+// 签到二维码：现场大屏定时刷新，令牌很快过期，截图转发到场外基本来不及使用
+const QR_DEFAULT_TTL_SECS: i64 = 60;
+const QR_MIN_TTL_SECS: i64 = 15;
+const QR_MAX_TTL_SECS: i64 = 600;
+const QR_PNG_SCALE: usize = 8;
+
+fn qr_payload(lecture_hex: &str, expires_at: i64) -> String {
+    format!("checkin:{}:{}", lecture_hex, expires_at)
+}
+
+// 令牌格式：演讲ID.过期时间(毫秒).签名
+fn qr_token(lecture_oid: ObjectId, expires_at: i64) -> String {
+    let lecture_hex = lecture_oid.to_hex();
+    let sig = signing::sign(&qr_payload(&lecture_hex, expires_at));
+    format!("{}.{}.{}", lecture_hex, expires_at, sig)
+}
 
-    if let Ok(oid) = doc.get_object_id("lecture_id") {
-        doc.insert("lecture_id", oid.to_hex());
+fn parse_qr_token(token: &str) -> Result<ObjectId, AppError> {
+    let invalid = || AppError::BadRequest("签到二维码无效".into());
+    let mut parts = token.trim().split('.');
+    let (Some(lecture_hex), Some(expires), Some(sig), None) = (parts.next(), parts.next(), parts.next(), parts.next()) else {
+        return Err(invalid());
+    };
+    let expires_at: i64 = expires.parse().map_err(|_| invalid())?;
+    if !signing::verify(&qr_payload(lecture_hex, expires_at), sig) {
+        return Err(invalid());
     }
-    if let Ok(oid) = doc.get_object_id("audience_id") {
-        doc.insert("audience_id", oid.to_hex());
+    if expires_at <= Utc::now().timestamp_millis() {
+        return Err(AppError::Gone("签到二维码已过期，请扫描最新的二维码".into()));
+    }
+    ObjectId::parse_str(lecture_hex).map_err(|_| invalid())
+}
+
+// 名额以演讲上的 registered_count 计数，报名前原子占位、退出时释放，并发报名不会超出
+// capacity 加超额部分（见 lecture::effective_capacity）。旧数据没有计数字段时先按现有报名记录初始化
+async fn ensure_seat_counter(client: &AppState, lecture_oid: ObjectId) -> Result<(), AppError> {
+    let lectures = lecture_collection(client);
+    let lecture = lectures
+        .find_one(doc! { "_id": lecture_oid }, None)
+        .await?
+        .ok_or(AppError::NotFound("Lecture not found".into()))?;
+    if lecture.contains_key("registered_count") {
+        return Ok(());
     }
+    let count = la_collection(client).count_documents(doc! { "lecture_id": lecture_oid }, None).await?;
+    lectures
+        .update_one(
+            doc! { "_id": lecture_oid, "registered_count": { "$exists": false } },
+            doc! { "$set": { "registered_count": count as i64 } },
+            None,
+        )
+        .await?;
     Ok(())
 }
 
+// 占用一个名额；未设上限时总能成功，已满时返回 false
+async fn reserve_seat(client: &AppState, lecture_oid: ObjectId) -> Result<bool, AppError> {
+    ensure_seat_counter(client, lecture_oid).await?;
+    let result = lecture_collection(client)
+        .update_one(
+            doc! {
+                "_id": lecture_oid,
+                "$or": [
+                    { "capacity": null },
+                    // 与 lecture::effective_capacity 的计算保持一致
+                    { "$expr": { "$lt": [
+                        "$registered_count",
+                        { "$floor": { "$divide": [
+                            { "$multiply": ["$capacity", { "$add": [100, { "$ifNull": ["$overbooking_percent", 0] }] }] },
+                            100,
+                        ] } },
+                    ] } },
+                ],
+            },
+            doc! { "$inc": { "registered_count": 1 } },
+            None,
+        )
+        .await?;
+    Ok(result.modified_count > 0)
+}
+
+async fn release_seat(client: &AppState, lecture_oid: ObjectId) -> Result<(), AppError> {
+    lecture_collection(client)
+        .update_one(
+            doc! { "_id": lecture_oid, "registered_count": { "$gt": 0 } },
+            doc! { "$inc": { "registered_count": -1 } },
+            None,
+        )
+        .await?;
+    Ok(())
+}
+
+// 唯一索引 uniq_lecture_audience 兜底；先查一次，避免已报名者白占名额或被放进候补
+async fn ensure_not_registered(client: &AppState, lecture_oid: ObjectId, audience_oid: ObjectId) -> Result<(), AppError> {
+    let existing = la_collection(client)
+        .find_one(doc! { "lecture_id": lecture_oid, "audience_id": audience_oid }, None)
+        .await?;
+    match existing {
+        Some(la) => Err(AppError::Conflict("已报名该演讲".into())
+            .with_details(serde_json::json!({ "la_id": ids::oid_hex(&la, "_id") }))),
+        None => Ok(()),
+    }
+}
+
+// 报名与加入候补须在演讲的报名时间窗内
+async fn ensure_registration_window(client: &AppState, lecture_oid: ObjectId) -> Result<(), AppError> {
+    let lecture = lecture_collection(client)
+        .find_one(doc! { "_id": lecture_oid }, None)
+        .await?
+        .ok_or(AppError::NotFound("Lecture not found".into()))?;
+    ensure_registration_open(&lecture)
+}
+
+// 清理唯一索引建立前写入的重复报名：每对 (演讲, 听众) 保留最早的一条，任意一条已签到则保留签到状态，
+// 再按剩余记录重算受影响演讲的 registered_count。返回删除的记录数
+pub async fn dedupe_records(client: &AppState) -> Result<u64, AppError> {
+    let coll = la_collection(client);
+    let pipeline = vec![
+        doc! { "$sort": { "_id": 1 } },
+        doc! { "$group": {
+            "_id": { "lecture_id": "$lecture_id", "audience_id": "$audience_id" },
+            "ids": { "$push": "$_id" },
+            "present": { "$max": { "$eq": ["$is_present", true] } },
+            "count": { "$sum": 1 },
+        } },
+        doc! { "$match": { "count": { "$gt": 1 } } },
+    ];
+    let groups: Vec<bson::Document> = coll.aggregate(pipeline, None).await?.try_collect().await?;
+    let mut removed = 0;
+    let mut lectures = Vec::new();
+    for group in groups {
+        let ids: Vec<ObjectId> = group
+            .get_array("ids")
+            .map(|a| a.iter().filter_map(|b| b.as_object_id()).collect())
+            .unwrap_or_default();
+        let Some((keep, extra)) = ids.split_first() else { continue };
+        if group.get_bool("present").unwrap_or(false) {
+            coll.update_one(doc! { "_id": keep }, doc! { "$set": { "is_present": true } }, None).await?;
+        }
+        removed += coll.delete_many(doc! { "_id": { "$in": extra } }, None).await?.deleted_count;
+        if let Ok(lecture_oid) = group.get_document("_id").and_then(|k| k.get_object_id("lecture_id")) {
+            if !lectures.contains(&lecture_oid) {
+                lectures.push(lecture_oid);
+            }
+        }
+    }
+    for lecture_oid in lectures {
+        let count = coll.count_documents(doc! { "lecture_id": lecture_oid }, None).await?;
+        lecture_collection(client)
+            .update_one(
+                doc! { "_id": lecture_oid, "registered_count": { "$exists": true } },
+                doc! { "$set": { "registered_count": count as i64 } },
+                None,
+            )
+            .await?;
+    }
+    Ok(removed)
+}
+
+fn insert_error(e: mongodb::error::Error) -> AppError {
+    match db::duplicate_key_index(&e) {
+        Some(_) => AppError::Conflict("已报名该演讲".into()),
+        None => AppError::Internal("创建失败".into()),
+    }
+}
+
+// 有人退出后，把空出的名额直接转给最早候补的听众，计数保持不变；没有候补时才释放名额
+async fn promote_or_release(client: &AppState, lecture_oid: ObjectId) -> Result<(), AppError> {
+    let options = FindOneAndDeleteOptions::builder().sort(doc! { "created_at": 1 }).build();
+    while let Some(entry) = waitlist_collection(client)
+        .find_one_and_delete(doc! { "lecture_id": lecture_oid }, options.clone())
+        .await?
+    {
+        let Ok(audience_oid) = entry.get_object_id("audience_id") else { continue };
+        let now = Utc::now().timestamp_millis();
+        let la_doc = doc! {
+            "lecture_id": lecture_oid,
+            "audience_id": audience_oid,
+            "is_present": false,
+            "joined_at": now,
+            "from_waitlist": true,
+        };
+        match la_collection(client).insert_one(la_doc, None).await {
+            Ok(_) => {
+                let payload = doc! { "lecture_id": lecture_oid.to_hex() };
+                if let Err(e) = notify::push(client, audience_oid, "waitlist_promoted", payload).await {
+                    println!("发送候补转正通知失败 {}: {}", audience_oid.to_hex(), e);
+                }
+                return Ok(());
+            }
+            // 已通过其他途径报名，继续看下一位
+            Err(e) if db::duplicate_key_index(&e).is_some() => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    release_seat(client, lecture_oid).await
+}
+
+// 接受听众邀请后自动报名：组织者点名邀请，不受名额上限与报名时间窗限制；
+// 如在候补名单中一并移除。已报名时返回 Ok(false)
+pub async fn register_invited(
+    client: &AppState,
+    lecture_oid: ObjectId,
+    audience_oid: ObjectId,
+    invitation_oid: ObjectId,
+) -> Result<bool, AppError> {
+    ensure_seat_counter(client, lecture_oid).await?;
+    lecture_collection(client)
+        .update_one(doc! { "_id": lecture_oid }, doc! { "$inc": { "registered_count": 1 } }, None)
+        .await?;
+    let la_doc = doc! {
+        "lecture_id": lecture_oid,
+        "audience_id": audience_oid,
+        "is_present": false,
+        "joined_at": Utc::now().timestamp_millis(),
+        "from_invitation": invitation_oid,
+    };
+    match la_collection(client).insert_one(la_doc, None).await {
+        Ok(_) => {}
+        Err(e) if db::duplicate_key_index(&e).is_some() => {
+            release_seat(client, lecture_oid).await?;
+            return Ok(false);
+        }
+        Err(e) => {
+            release_seat(client, lecture_oid).await?;
+            return Err(e.into());
+        }
+    }
+    waitlist_collection(client)
+        .delete_one(doc! { "lecture_id": lecture_oid, "audience_id": audience_oid }, None)
+        .await?;
+    Ok(true)
+}
+
+
 // ==================== 路由 ====================
 
+// 旧版报名接口，与 /create 一样只能为自己报名；出勤与加入时间由服务端决定
 async fn add_la(
     State(client): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    auth: RequireRole<Audience>,
+    headers: HeaderMap,
     Json(payload): Json<LARecord>,
-) -> Result<Json<LAResponse>, (StatusCode, String)> {
+) -> Result<Json<LAResponse>, AppError> {
+    auth.ensure_self(&payload.audience_id)?;
     let coll = la_collection(&client);
 
     let lecture_oid = ObjectId::parse_str(&payload.lecture_id)
-        .map_err(|_| (StatusCode::BAD_REQUEST, "无效的 lecture_id".into()))?;
+        .map_err(|_| AppError::BadRequest("无效的 lecture_id".into()))?;
     let audience_oid = ObjectId::parse_str(&payload.audience_id)
-        .map_err(|_| (StatusCode::BAD_REQUEST, "无效的 audience_id".into()))?;
+        .map_err(|_| AppError::BadRequest("无效的 audience_id".into()))?;
 
     let doc = doc! {
         "lecture_id": lecture_oid,
         "audience_id": audience_oid,
-        "is_present": payload.is_present.unwrap_or(false),
-        "joined_at": payload.joined_at.unwrap_or_else(|| Utc::now().timestamp_millis()),
+        "is_present": false,
+        "joined_at": Utc::now().timestamp_millis(),
+        "client_ip": client_ip(&headers, Some(peer)),
+        "device_id": device_id(&headers),
     };
 
-    coll.insert_one(doc, None).await
-        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "插入失败".into()))?;
+    ensure_not_registered(&client, lecture_oid, audience_oid).await?;
+    ensure_registration_window(&client, lecture_oid).await?;
+    if !reserve_seat(&client, lecture_oid).await? {
+        return Err(AppError::Conflict("报名人数已满".into()));
+    }
+    if let Err(e) = coll.insert_one(doc, None).await {
+        release_seat(&client, lecture_oid).await?;
+        return Err(insert_error(e));
+    }
 
     Ok(Json(LAResponse {
         message: "加入成功".into(),
@@ -450,28 +790,45 @@ async fn add_la(
     }))
 }
 
+// 退出报名只能由听众本人或该演讲的组织者操作（会触发候补转正）
 async fn delete_la(
     State(client): State<AppState>,
+    auth: AuthUser,
     query: Query<std::collections::HashMap<String, String>>,
-) -> Result<Json<LAResponse>, (StatusCode, String)> {
+) -> Result<Json<LAResponse>, AppError> {
     let coll = la_collection(&client);
-    let lecture_id = query.get("lecture_id").ok_or((StatusCode::BAD_REQUEST, "缺少 lecture_id".into()))?;
-    let audience_id = query.get("audience_id").ok_or((StatusCode::BAD_REQUEST, "缺少 audience_id".into()))?;
+    let lecture_id = query.get("lecture_id").ok_or(AppError::BadRequest("缺少 lecture_id".into()))?;
+    let audience_id = query.get("audience_id").ok_or(AppError::BadRequest("缺少 audience_id".into()))?;
 
     let lecture_oid = ObjectId::parse_str(lecture_id)
-        .map_err(|_| (StatusCode::BAD_REQUEST, "无效的 lecture_id".into()))?;
+        .map_err(|_| AppError::BadRequest("无效的 lecture_id".into()))?;
     let audience_oid = ObjectId::parse_str(audience_id)
-        .map_err(|_| (StatusCode::BAD_REQUEST, "无效的 audience_id".into()))?;
+        .map_err(|_| AppError::BadRequest("无效的 audience_id".into()))?;
+    if audience_oid != auth.id {
+        ensure_lecture_organizer(&client, lecture_oid, &auth).await?;
+    }
 
     let result = coll.delete_one(doc! {
         "lecture_id": lecture_oid,
         "audience_id": audience_oid,
     }, None).await
-        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "删除失败".into()))?;
+        .map_err(|_| AppError::Internal("删除失败".into()))?;
 
     if result.deleted_count == 0 {
-        return Err((StatusCode::NOT_FOUND, "记录未找到".into()));
+        // 只在候补名单中时，退出即放弃候补
+        let waitlisted = waitlist_collection(&client)
+            .delete_one(doc! { "lecture_id": lecture_oid, "audience_id": audience_oid }, None)
+            .await?;
+        if waitlisted.deleted_count == 0 {
+            return Err(AppError::NotFound("记录未找到".into()));
+        }
+        return Ok(Json(LAResponse {
+            message: "已退出候补".into(),
+            la_id: None,
+            joined_at: None,
+        }));
     }
+    promote_or_release(&client, lecture_oid).await?;
 
     Ok(Json(LAResponse {
         message: "删除成功".into(),
@@ -480,45 +837,56 @@ async fn delete_la(
     }))
 }
 
+// 报名记录中的签到来源只用于异常检测，不对外返回
+fn record_projection() -> FindOptions {
+    FindOptions::builder()
+        .projection(doc! { "client_ip": 0, "device_id": 0 })
+        .build()
+}
+
+// 报名名单只对该演讲的组织者可见
 async fn get_by_lecture(
     State(client): State<AppState>,
+    auth: RequireRole<Organizer>,
     query: Query<std::collections::HashMap<String, String>>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, AppError> {
     let coll = la_collection(&client);
-    let lecture_id = query.get("lecture_id").ok_or((StatusCode::BAD_REQUEST, "缺少 lecture_id".into()))?;
+    let lecture_id = query.get("lecture_id").ok_or(AppError::BadRequest("缺少 lecture_id".into()))?;
     let oid = ObjectId::parse_str(lecture_id)
-        .map_err(|_| (StatusCode::BAD_REQUEST, "无效的 lecture_id".into()))?;
+        .map_err(|_| AppError::BadRequest("无效的 lecture_id".into()))?;
+    ensure_lecture_organizer(&client, oid, &auth).await?;
 
-    let mut cursor = coll.find(doc! { "lecture_id": oid }, None).await
-        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "查询失败".into()))?;
+    let mut cursor = coll.find(doc! { "lecture_id": oid }, record_projection()).await
+        .map_err(|_| AppError::Internal("查询失败".into()))?;
 
     let mut records = Vec::new();
-    while let Some(mut doc) = cursor.next().await {
-        let mut doc = doc.map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "读取错误".into()))?;
-        convert_doc_ids(&mut doc)?;
-        records.push(doc);
+    while let Some(doc) = cursor.next().await {
+        let doc = doc.map_err(|_| AppError::Internal("读取错误".into()))?;
+        records.push(ids::doc_to_json(doc));
     }
 
     Ok(Json(serde_json::json!({ "records": records })))
 }
 
+// 听众只能查看自己的报名记录
 async fn get_by_audience(
     State(client): State<AppState>,
+    auth: AuthUser,
     query: Query<std::collections::HashMap<String, String>>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, AppError> {
     let coll = la_collection(&client);
-    let audience_id = query.get("audience_id").ok_or((StatusCode::BAD_REQUEST, "缺少 audience_id".into()))?;
+    let audience_id = query.get("audience_id").ok_or(AppError::BadRequest("缺少 audience_id".into()))?;
+    auth.ensure_self(audience_id)?;
     let oid = ObjectId::parse_str(audience_id)
-        .map_err(|_| (StatusCode::BAD_REQUEST, "无效的 audience_id".into()))?;
+        .map_err(|_| AppError::BadRequest("无效的 audience_id".into()))?;
 
-    let mut cursor = coll.find(doc! { "audience_id": oid }, None).await
-        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "查询失败".into()))?;
+    let mut cursor = coll.find(doc! { "audience_id": oid }, record_projection()).await
+        .map_err(|_| AppError::Internal("查询失败".into()))?;
 
     let mut records = Vec::new();
-    while let Some(mut doc) = cursor.next().await {
-        let mut doc = doc.map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "读取错误".into()))?;
-        convert_doc_ids(&mut doc)?;
-        records.push(doc);
+    while let Some(doc) = cursor.next().await {
+        let doc = doc.map_err(|_| AppError::Internal("读取错误".into()))?;
+        records.push(ids::doc_to_json(doc));
     }
 
     Ok(Json(serde_json::json!({ "records": records })))
@@ -526,26 +894,27 @@ async fn get_by_audience(
 
 async fn get_present_users(
     State(client): State<AppState>,
+    auth: RequireRole<Organizer>,
     query: Query<std::collections::HashMap<String, String>>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, AppError> {
     let coll = la_collection(&client);
     let user_coll = user_collection(&client);
-    let lecture_id = query.get("lecture_id").ok_or((StatusCode::BAD_REQUEST, "缺少 lecture_id".into()))?;
+    let lecture_id = query.get("lecture_id").ok_or(AppError::BadRequest("缺少 lecture_id".into()))?;
 
-    let lecture_oid = match ObjectId::parse_str(lecture_id) {
-        Ok(oid) => oid,
-        Err(_) => return Ok(Json(serde_json::json!({ "error": "无效的 lecture_id" }))),
-    };
+    let lecture_oid = ObjectId::parse_str(lecture_id)
+        .map_err(|_| AppError::BadRequest("无效的 lecture_id".into()))?;
+    // 到场名单只对该演讲的组织者可见
+    ensure_lecture_organizer(&client, lecture_oid, &auth).await?;
 
     let mut cursor = coll.find(doc! {
         "lecture_id": lecture_oid,
         "is_present": true,
     }, None).await
-        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "查询失败".into()))?;
+        .map_err(|_| AppError::Internal("查询失败".into()))?;
 
     let mut user_ids = Vec::new();
     while let Some(doc) = cursor.next().await {
-        let doc = doc.map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "读取错误".into()))?;
+        let doc = doc.map_err(|_| AppError::Internal("读取错误".into()))?;
         if let Ok(oid) = doc.get_object_id("audience_id") {
             user_ids.push(oid);
         }
@@ -558,15 +927,12 @@ async fn get_present_users(
     let mut user_cursor = user_coll.find(doc! {
         "_id": { "$in": user_ids }
     }, None).await
-        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "查询用户失败".into()))?;
+        .map_err(|_| AppError::Internal("查询用户失败".into()))?;
 
     let mut users = Vec::new();
-    while let Some(mut doc) = user_cursor.next().await {
-        let mut doc = doc.map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "读取用户错误".into()))?;
-        if let Ok(oid) = doc.get_object_id("_id") {
-            doc.insert("_id", oid.to_hex());
-        }
-        users.push(doc);
+    while let Some(doc) = user_cursor.next().await {
+        let doc = doc.map_err(|_| AppError::Internal("读取用户错误".into()))?;
+        users.push(ids::user_to_json(doc));
     }
 
     Ok(Json(serde_json::json!({ "users": users })))
@@ -575,44 +941,415 @@ async fn get_present_users(
 
 async fn update_is_present(
     State(client): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    auth: AuthUser,
+    headers: HeaderMap,
     Json(payload): Json<UpdateIsPresent>,
-) -> Result<Json<LAResponse>, (StatusCode, String)> {
+) -> Result<Json<PresenceResponse>, AppError> {
     let coll = la_collection(&client);
 
     let lecture_oid = ObjectId::parse_str(&payload.lecture_id)
-        .map_err(|_| (StatusCode::BAD_REQUEST, "无效的 lecture_id".into()))?;
+        .map_err(|_| AppError::BadRequest("无效的 lecture_id".into()))?;
     let audience_oid = ObjectId::parse_str(&payload.audience_id)
-        .map_err(|_| (StatusCode::BAD_REQUEST, "无效的 audience_id".into()))?;
+        .map_err(|_| AppError::BadRequest("无效的 audience_id".into()))?;
+    // 听众只能登记自己；为他人登记需是组织者、讲者或本场考勤员
+    if audience_oid != auth.id {
+        require_lecture_role(&client, lecture_oid, &auth, LectureRole::AttendanceTaker).await?;
+    }
+
+    // 签到时记录来源，供异常检测使用
+    let mut set_doc = doc! { "is_present": payload.is_present };
+    if payload.is_present {
+        set_doc.insert("checked_in_at", Utc::now().timestamp_millis());
+        set_doc.insert("client_ip", client_ip(&headers, Some(peer)));
+        set_doc.insert("device_id", device_id(&headers));
+    }
 
-    let result = coll.update_one(
+    // 只改出勤相关字段，不新建记录：未报名的返回 404，需先走报名流程占名额。
+    // 重新登记出勤后之前的离场时间作废
+    let mut record = coll.find_one_and_update(
         doc! {
             "lecture_id": lecture_oid,
             "audience_id": audience_oid,
         },
-        doc! { "$set": { "is_present": payload.is_present } },
-        None,
+        doc! { "$set": set_doc.clone(), "$unset": { "left_at": "" } },
+        mongodb::options::FindOneAndUpdateOptions::builder()
+            .return_document(mongodb::options::ReturnDocument::Before)
+            .build(),
     ).await
-        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "更新失败".into()))?;
+        .map_err(|_| AppError::Internal("更新失败".into()))?
+        .ok_or(AppError::NotFound("记录未找到".into()))?;
 
-    if result.matched_count == 0 {
-        return Err((StatusCode::NOT_FOUND, "记录未找到".into()));
+    let outcome = if record.get_bool("is_present").unwrap_or(false) == payload.is_present && !record.contains_key("left_at") {
+        PresenceOutcome::Unchanged
+    } else {
+        PresenceOutcome::Updated
+    };
+    record.remove("left_at");
+    record.extend(set_doc);
+    if outcome == PresenceOutcome::Updated {
+        realtime::publish(
+            lecture_oid,
+            "attendance.updated",
+            serde_json::json!({ "audience_id": audience_oid.to_hex(), "is_present": payload.is_present }),
+        );
     }
 
-    Ok(Json(LAResponse {
+    let record = LADocument::from_doc(&record);
+    Ok(Json(PresenceResponse {
         message: format!("is_present 已更新为 {}", payload.is_present),
-        la_id: None,
-        joined_at: None,
+        outcome,
+        joined_at: record.joined_at,
+        record,
+    }))
+}
+
+// 批量登记出勤单次最多条数
+const BULK_PRESENCE_MAX: usize = 2000;
+
+// POST /LA/bulk_update_presence {lecture_id, entries: [{audience_id, is_present}]} -> 按纸质签到表批量核对出勤
+// （组织者、讲者或本场考勤员）。任一 audience_id 无效时整批拒绝；同一听众出现多次以最后一条为准；
+// 未报名的不新建记录，在 not_registered 中返回。按目标状态分两次 update_many 写入，只改动与目标不同的记录
+async fn bulk_update_presence(
+    State(client): State<AppState>,
+    auth: AuthUser,
+    Json(payload): Json<BulkPresenceRequest>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let lecture_oid = ids::parse_oid(&payload.lecture_id, "lecture_id")?;
+    require_lecture_role(&client, lecture_oid, &auth, LectureRole::AttendanceTaker).await?;
+    if payload.entries.is_empty() {
+        return Err(AppError::BadRequest("entries 不能为空".into()));
+    }
+    if payload.entries.len() > BULK_PRESENCE_MAX {
+        return Err(AppError::BadRequest(format!("单次最多登记 {} 条", BULK_PRESENCE_MAX)));
+    }
+
+    let mut desired: Vec<(ObjectId, bool)> = Vec::with_capacity(payload.entries.len());
+    let mut invalid = Vec::new();
+    for (index, entry) in payload.entries.iter().enumerate() {
+        match ObjectId::parse_str(entry.audience_id.trim()) {
+            Ok(oid) => match desired.iter_mut().find(|(a, _)| *a == oid) {
+                Some(existing) => existing.1 = entry.is_present,
+                None => desired.push((oid, entry.is_present)),
+            },
+            Err(_) => invalid.push(serde_json::json!({ "index": index, "audience_id": entry.audience_id })),
+        }
+    }
+    if !invalid.is_empty() {
+        return Err(AppError::BadRequest("存在无效的 audience_id，未做任何修改".into())
+            .with_details(serde_json::json!({ "invalid": invalid })));
+    }
+
+    let coll = la_collection(&client);
+    let audience_ids: Vec<ObjectId> = desired.iter().map(|(a, _)| *a).collect();
+    let registered: HashSet<ObjectId> = coll
+        .find(doc! { "lecture_id": lecture_oid, "audience_id": { "$in": &audience_ids } }, None)
+        .await?
+        .try_collect::<Vec<bson::Document>>()
+        .await?
+        .iter()
+        .filter_map(|r| r.get_object_id("audience_id").ok())
+        .collect();
+    let not_registered: Vec<String> = audience_ids.iter().filter(|a| !registered.contains(a)).map(|a| a.to_hex()).collect();
+    let pick = |present: bool| -> Vec<ObjectId> {
+        desired.iter().filter(|(a, p)| *p == present && registered.contains(a)).map(|(a, _)| *a).collect()
+    };
+    let (to_present, to_absent) = (pick(true), pick(false));
+
+    // 已到场的保留原签到时间；改为缺席或重新到场时之前的离场时间作废
+    let now = Utc::now().timestamp_millis();
+    let marked_present = if to_present.is_empty() {
+        0
+    } else {
+        coll.update_many(
+            doc! { "lecture_id": lecture_oid, "audience_id": { "$in": &to_present }, "is_present": { "$ne": true } },
+            doc! {
+                "$set": { "is_present": true, "checked_in_at": now, "checkin_method": "bulk" },
+                "$unset": { "left_at": "" },
+            },
+            None,
+        )
+        .await?
+        .modified_count
+    };
+    let marked_absent = if to_absent.is_empty() {
+        0
+    } else {
+        coll.update_many(
+            doc! { "lecture_id": lecture_oid, "audience_id": { "$in": &to_absent }, "is_present": true },
+            doc! { "$set": { "is_present": false }, "$unset": { "left_at": "" } },
+            None,
+        )
+        .await?
+        .modified_count
+    };
+
+    if marked_present + marked_absent > 0 {
+        realtime::publish(
+            lecture_oid,
+            "attendance.bulk_updated",
+            serde_json::json!({ "marked_present": marked_present, "marked_absent": marked_absent }),
+        );
+        audit::record(
+            &client,
+            Some(auth.id),
+            "attendance.bulk_update",
+            &format!("lecture:{}", payload.lecture_id),
+            doc! { "marked_present": marked_present as i64, "marked_absent": marked_absent as i64 },
+        )
+        .await;
+    }
+    let applied = (to_present.len() + to_absent.len()) as u64;
+    Ok(Json(serde_json::json!({
+        "lecture_id": payload.lecture_id,
+        "requested": desired.len(),
+        "marked_present": marked_present,
+        "marked_absent": marked_absent,
+        "unchanged": applied - marked_present - marked_absent,
+        "not_registered": not_registered,
+    })))
+}
+
+// POST /LA/checkin {lecturecode, audience_id} -> 按演讲码签到：在演讲时段内（前后各放宽
+// checkin_grace_minutes）标记出勤，尚未报名的顺带报名（占用名额）
+async fn checkin(
+    State(client): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    auth: AuthUser,
+    headers: HeaderMap,
+    Json(payload): Json<CheckinRequest>,
+) -> Result<Json<PresenceResponse>, AppError> {
+    let code = lecturecode::normalize(&payload.lecturecode)
+        .ok_or(AppError::BadRequest("演讲码格式无效".into()))?;
+    let audience_oid = ObjectId::parse_str(&payload.audience_id)
+        .map_err(|_| AppError::BadRequest("无效的 audience_id".into()))?;
+    let mut filter = lecturecode::lookup_filter(&code);
+    filter.insert("archived", doc! { "$ne": true });
+    let lecture = lecture_collection(&client)
+        .find_one(filter, None)
+        .await?
+        .ok_or(AppError::NotFound("Lecture not found".into()))?;
+    let lecture_oid = lecture.get_object_id("_id").map_err(|_| AppError::Internal("演讲ID无效".into()))?;
+    if audience_oid != auth.id {
+        require_lecture_role(&client, lecture_oid, &auth, LectureRole::AttendanceTaker).await?;
+    }
+    let extra = doc! {
+        "client_ip": client_ip(&headers, Some(peer)),
+        "device_id": device_id(&headers),
+        "checkin_method": "code",
+    };
+    check_in(&client, &lecture, audience_oid, extra).await.map(Json)
+}
+
+// 签到公共流程：校验演讲状态与签到时段，已报名的标记出勤，未报名的占名额后直接以出勤状态报名。
+// extra 为随签到一并写入的字段（来源 IP、签到方式等）
+async fn check_in(
+    client: &AppState,
+    lecture: &bson::Document,
+    audience_oid: ObjectId,
+    extra: bson::Document,
+) -> Result<PresenceResponse, AppError> {
+    let lecture_oid = lecture.get_object_id("_id").map_err(|_| AppError::Internal("演讲ID无效".into()))?;
+    let status = LectureStatus::of(lecture);
+    if matches!(status, LectureStatus::Draft | LectureStatus::Cancelled) {
+        return Err(AppError::Conflict(format!("演讲{}，无法签到", status.name())));
+    }
+    let grace_ms = config::get().checkin_grace_minutes as i64 * 60_000;
+    let start = lecture.get_i64("start_time").unwrap_or(0);
+    let end = start + lecture.get_i32("duration").unwrap_or(0).max(0) as i64 * 60_000;
+    let (opens_at, closes_at) = (start - grace_ms, end + grace_ms);
+    let now = Utc::now().timestamp_millis();
+    if now < opens_at || now > closes_at {
+        let message = if now < opens_at { "签到尚未开始" } else { "签到已结束" };
+        return Err(AppError::Conflict(message.into())
+            .with_details(serde_json::json!({ "opens_at": opens_at, "closes_at": closes_at, "now": now })));
+    }
+
+    let mut set_doc = doc! {
+        "is_present": true,
+        "checked_in_at": now,
+    };
+    set_doc.extend(extra);
+    let coll = la_collection(client);
+    let key = doc! { "lecture_id": lecture_oid, "audience_id": audience_oid };
+    let mark = || {
+        coll.find_one_and_update(
+            key.clone(),
+            doc! { "$set": set_doc.clone(), "$unset": { "left_at": "" } },
+            mongodb::options::FindOneAndUpdateOptions::builder()
+                .return_document(mongodb::options::ReturnDocument::Before)
+                .build(),
+        )
+    };
+    let (mut record, outcome) = match mark().await? {
+        // 离场后再次签到视为回到现场
+        Some(before) => {
+            let outcome = if before.get_bool("is_present").unwrap_or(false) && !before.contains_key("left_at") {
+                PresenceOutcome::Unchanged
+            } else {
+                PresenceOutcome::Updated
+            };
+            (before, outcome)
+        }
+        // 未报名者现场签到时补登记，不受报名时间窗限制
+        None => {
+            if !reserve_seat(client, lecture_oid).await? {
+                return Err(AppError::Conflict("报名人数已满".into()));
+            }
+            let mut la_doc = key.clone();
+            la_doc.insert("joined_at", now);
+            la_doc.extend(set_doc.clone());
+            match coll.insert_one(&la_doc, None).await {
+                Ok(result) => {
+                    la_doc.insert("_id", result.inserted_id);
+                    (la_doc, PresenceOutcome::Created)
+                }
+                // 并发请求已先一步报名，退回名额后按已有记录签到
+                Err(e) if db::duplicate_key_index(&e).is_some() => {
+                    release_seat(client, lecture_oid).await?;
+                    let before = mark().await?.ok_or(AppError::Internal("签到失败".into()))?;
+                    (before, PresenceOutcome::Updated)
+                }
+                Err(e) => {
+                    release_seat(client, lecture_oid).await?;
+                    return Err(e.into());
+                }
+            }
+        }
+    };
+    record.remove("left_at");
+    record.extend(set_doc);
+    if outcome != PresenceOutcome::Unchanged {
+        realtime::publish(
+            lecture_oid,
+            "attendance.updated",
+            serde_json::json!({ "audience_id": audience_oid.to_hex(), "is_present": true }),
+        );
+    }
+
+    let record = LADocument::from_doc(&record);
+    Ok(PresenceResponse {
+        message: "签到成功".into(),
+        outcome,
+        joined_at: record.joined_at,
+        record,
+    })
+}
+
+// POST /LA/checkout {lecture_id, audience_id} -> 登记离场，记下 left_at 供出勤报告计算在场时长。
+// 只有已签到的记录可以离场；重复提交保留第一次的离场时间，再次签到后可重新离场
+async fn checkout(
+    State(client): State<AppState>,
+    auth: AuthUser,
+    Json(payload): Json<CheckoutRequest>,
+) -> Result<Json<PresenceResponse>, AppError> {
+    let lecture_oid = ids::parse_oid(&payload.lecture_id, "lecture_id")?;
+    let audience_oid = ids::parse_oid(&payload.audience_id, "audience_id")?;
+    if audience_oid != auth.id {
+        require_lecture_role(&client, lecture_oid, &auth, LectureRole::AttendanceTaker).await?;
+    }
+
+    let coll = la_collection(&client);
+    let key = doc! { "lecture_id": lecture_oid, "audience_id": audience_oid };
+    let mut filter = key.clone();
+    filter.insert("is_present", true);
+    filter.insert("left_at", doc! { "$exists": false });
+    let set_doc = doc! { "left_at": Utc::now().timestamp_millis() };
+    let (record, outcome) = match coll.find_one_and_update(filter, doc! { "$set": set_doc.clone() }, None).await? {
+        Some(mut before) => {
+            before.extend(set_doc);
+            (before, PresenceOutcome::Updated)
+        }
+        None => {
+            let record = coll.find_one(key, None).await?.ok_or(AppError::NotFound("记录未找到".into()))?;
+            if !record.get_bool("is_present").unwrap_or(false) {
+                return Err(AppError::Conflict("尚未签到，无法登记离场".into()));
+            }
+            (record, PresenceOutcome::Unchanged)
+        }
+    };
+
+    let record = LADocument::from_doc(&record);
+    if outcome == PresenceOutcome::Updated {
+        realtime::publish(
+            lecture_oid,
+            "attendance.updated",
+            serde_json::json!({ "audience_id": audience_oid.to_hex(), "is_present": true, "left_at": record.left_at }),
+        );
+    }
+    Ok(Json(PresenceResponse {
+        message: "已登记离场".into(),
+        outcome,
+        joined_at: record.joined_at,
+        record,
     }))
 }
 
+// GET /LA/checkin/token?lecture_id=&ttl=&format=json|png -> 生成短期有效的签到令牌，
+// format=png 时直接返回二维码图片（组织者、讲者或本场考勤员）
+async fn checkin_token(
+    State(client): State<AppState>,
+    auth: AuthUser,
+    Query(query): Query<QrTokenQuery>,
+) -> Result<Response, AppError> {
+    let lecture_oid = ObjectId::parse_str(&query.lecture_id)
+        .map_err(|_| AppError::BadRequest("无效的 lecture_id".into()))?;
+    require_lecture_role(&client, lecture_oid, &auth, LectureRole::AttendanceTaker).await?;
+    let ttl = query.ttl.unwrap_or(QR_DEFAULT_TTL_SECS).clamp(QR_MIN_TTL_SECS, QR_MAX_TTL_SECS);
+    let expires_at = Utc::now().timestamp_millis() + ttl * 1000;
+    let token = qr_token(lecture_oid, expires_at);
+
+    let mut resp = if query.format.as_deref() == Some("png") {
+        let image = qr::png(&token, QR_PNG_SCALE).map_err(AppError::Internal)?;
+        ([(header::CONTENT_TYPE, "image/png")], image).into_response()
+    } else {
+        Json(serde_json::json!({
+            "lecture_id": query.lecture_id,
+            "token": token,
+            "ttl": ttl,
+            "expires_at": expires_at,
+        }))
+        .into_response()
+    };
+    let headers = resp.headers_mut();
+    headers.insert(header::CACHE_CONTROL, header::HeaderValue::from_static("no-store"));
+    headers.insert("x-token-expires-at", header::HeaderValue::from(expires_at));
+    Ok(resp)
+}
+
+// POST /LA/checkin/qr {token} -> 扫描现场二维码签到，只能为自己签到
+async fn checkin_qr(
+    State(client): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    auth: AuthUser,
+    headers: HeaderMap,
+    Json(payload): Json<QrCheckinRequest>,
+) -> Result<Json<PresenceResponse>, AppError> {
+    let lecture_oid = parse_qr_token(&payload.token)?;
+    let lecture = lecture_collection(&client)
+        .find_one(doc! { "_id": lecture_oid, "archived": { "$ne": true } }, None)
+        .await?
+        .ok_or(AppError::NotFound("Lecture not found".into()))?;
+    let extra = doc! {
+        "client_ip": client_ip(&headers, Some(peer)),
+        "device_id": device_id(&headers),
+        "checkin_method": "qr",
+    };
+    check_in(&client, &lecture, auth.id, extra).await.map(Json)
+}
+
 async fn create_la_entry(
     State(client): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    auth: RequireRole<Audience>,
+    headers: HeaderMap,
     Json(data): Json<LACreateRequest>,
-) -> Result<Json<LAResponse>, (StatusCode, String)> {
+) -> Result<Json<LAResponse>, AppError> {
+    auth.ensure_self(&data.audience_id)?;
     let coll = la_collection(&client);
 
-    if !ObjectId::parse_str(&data.lecture_id).is_ok() || !ObjectId::parse_str(&data.audience_id).is_ok() {
-        return Err((StatusCode::BAD_REQUEST, "无效的 lecture_id 或 audience_id".into()));
+    if ObjectId::parse_str(&data.lecture_id).is_err() || ObjectId::parse_str(&data.audience_id).is_err() {
+        return Err(AppError::BadRequest("无效的 lecture_id 或 audience_id".into()));
     }
 
     let lecture_oid = ObjectId::parse_str(&data.lecture_id).unwrap();
@@ -623,13 +1360,49 @@ async fn create_la_entry(
         "audience_id": audience_oid,
         "is_present": false,
         "joined_at": Utc::now().timestamp_millis(),
+        "client_ip": client_ip(&headers, Some(peer)),
+        "device_id": device_id(&headers),
     };
 
-    let result = coll.insert_one(la_doc, None).await
-        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "创建失败".into()))?;
+    ensure_not_registered(&client, lecture_oid, audience_oid).await?;
+    ensure_registration_window(&client, lecture_oid).await?;
+    if !reserve_seat(&client, lecture_oid).await? {
+        if !data.waitlist {
+            return Err(AppError::Conflict("报名人数已满".into())
+                .with_details(serde_json::json!({ "waitlist_available": true })));
+        }
+        let now = Utc::now().timestamp_millis();
+        let waitlist = waitlist_collection(&client);
+        match waitlist
+            .insert_one(doc! { "lecture_id": lecture_oid, "audience_id": audience_oid, "created_at": now }, None)
+            .await
+        {
+            Ok(_) => {}
+            Err(e) if db::duplicate_key_index(&e).is_some() => {
+                return Err(AppError::Conflict("已在候补名单中".into()));
+            }
+            Err(e) => return Err(e.into()),
+        }
+        let position = waitlist
+            .count_documents(doc! { "lecture_id": lecture_oid, "created_at": { "$lte": now } }, None)
+            .await?;
+        return Ok(Json(LAResponse {
+            message: format!("名额已满，已加入候补（第 {} 位）", position),
+            la_id: None,
+            joined_at: None,
+        }));
+    }
+
+    let result = match coll.insert_one(la_doc, None).await {
+        Ok(result) => result,
+        Err(e) => {
+            release_seat(&client, lecture_oid).await?;
+            return Err(insert_error(e));
+        }
+    };
 
     let la_id = result.inserted_id.as_object_id()
-        .ok_or((StatusCode::INTERNAL_SERVER_ERROR, "插入ID无效".into()))?
+        .ok_or(AppError::Internal("插入ID无效".into()))?
         .to_hex();
 
     Ok(Json(LAResponse {
@@ -639,25 +1412,416 @@ async fn create_la_entry(
     }))
 }
 
+// GET /LA/lectures_by_user/:user_id?upcoming=true -> 用户报名的演讲：报名与出勤字段之外附带演讲信息，
+// 一次聚合取回，不必再逐个查询演讲。upcoming=true 时只返回未结束、未取消的，按开始时间先后排列；
+// 否则按开始时间倒序。演讲已删除的报名记录不返回
 async fn get_lectures_by_user(
     State(client): State<AppState>,
     Path(user_id): Path<String>,
-) -> Result<Json<Vec<Document>>, (StatusCode, String)> {
-    let coll = la_collection(&client);
+    Query(query): Query<MyLecturesQuery>,
+) -> Result<Json<Vec<serde_json::Value>>, AppError> {
     let oid = ObjectId::parse_str(&user_id)
-        .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid user_id".into()))?;
+        .map_err(|_| AppError::BadRequest("Invalid user_id".into()))?;
+
+    let mut pipeline = vec![
+        doc! { "$match": { "audience_id": oid } },
+        doc! { "$lookup": { "from": "lecture", "localField": "lecture_id", "foreignField": "_id", "as": "lecture" } },
+        doc! { "$unwind": "$lecture" },
+    ];
+    if query.upcoming {
+        pipeline.push(doc! { "$match": { "lecture.status": { "$nin": [
+            LectureStatus::Ended.as_i32(),
+            LectureStatus::Cancelled.as_i32(),
+        ] } } });
+    }
+    let order = if query.upcoming { 1 } else { -1 };
+    pipeline.push(doc! { "$sort": { "lecture.start_time": order, "_id": 1 } });
+
+    let rows: Vec<bson::Document> = la_collection(&client)
+        .aggregate(pipeline, None)
+        .await?
+        .try_collect()
+        .await?;
+    let lectures = rows
+        .iter()
+        .map(|row| {
+            let lecture = row.get_document("lecture").cloned().unwrap_or_default();
+            let mut item = serde_json::to_value(LADocument::from_doc(row)).unwrap_or_default();
+            item["lecture"] = serde_json::json!({
+                "id": ids::oid_hex(&lecture, "_id"),
+                "topic": lecture.get_str("topic").unwrap_or(""),
+                "description": lecture.get_str("description").unwrap_or(""),
+                "lecturecode": lecture.get_str("lecturecode").unwrap_or(""),
+                "start_time": lecture.get_i64("start_time").unwrap_or(0),
+                "duration": lecture.get_i32("duration").unwrap_or(0),
+                "status": LectureStatus::of(&lecture).as_i32(),
+                "status_name": LectureStatus::of(&lecture).name(),
+                "speaker_id": lecture.get_str("speaker_id").unwrap_or(""),
+                "organizer_id": lecture.get_str("organizer_id").unwrap_or(""),
+                "archived": lecture.get_bool("archived").unwrap_or(false),
+            });
+            item
+        })
+        .collect();
 
-    let mut cursor = coll.find(doc! { "audience_id": oid }, None).await
-        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "查询失败".into()))?;
+    Ok(Json(lectures))
+}
 
-    let mut lectures = Vec::new();
-    while let Some(mut doc) = cursor.next().await {
-        let mut doc = doc.map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "读取错误".into()))?;
-        convert_doc_ids(&mut doc)?;
-        lectures.push(doc);
+// 估算缺席率时参考的最近已结束演讲数，以及报名人数不足多少时不做估算
+const NO_SHOW_LOOKBACK: i64 = 20;
+const NO_SHOW_MIN_SAMPLE: i32 = 20;
+
+// 组织者最近已结束演讲的缺席率（未到场 / 报名）及样本人数；样本不足时返回 None
+async fn organizer_no_show_rate(client: &AppState, organizer_id: &str, exclude: ObjectId) -> Result<Option<(f64, i32)>, AppError> {
+    let options = FindOptions::builder()
+        .sort(doc! { "start_time": -1 })
+        .limit(NO_SHOW_LOOKBACK)
+        .projection(doc! { "_id": 1 })
+        .build();
+    let filter = doc! { "organizer_id": organizer_id, "status": LectureStatus::Ended.as_i32(), "_id": { "$ne": exclude } };
+    let lectures: Vec<ObjectId> = lecture_collection(client)
+        .find(filter, options)
+        .await?
+        .try_collect::<Vec<_>>()
+        .await?
+        .iter()
+        .filter_map(|l| l.get_object_id("_id").ok())
+        .collect();
+    if lectures.is_empty() {
+        return Ok(None);
     }
+    let pipeline = vec![
+        doc! { "$match": { "lecture_id": { "$in": &lectures } } },
+        doc! { "$group": {
+            "_id": null,
+            "registered": { "$sum": 1 },
+            "present": { "$sum": { "$cond": [{ "$eq": ["$is_present", true] }, 1, 0] } },
+        } },
+    ];
+    let Some(row) = la_collection(client).aggregate(pipeline, None).await?.try_next().await? else { return Ok(None) };
+    let registered = row.get_i32("registered").unwrap_or(0);
+    if registered < NO_SHOW_MIN_SAMPLE {
+        return Ok(None);
+    }
+    let present = row.get_i32("present").unwrap_or(0);
+    Ok(Some((1.0 - present as f64 / registered as f64, registered)))
+}
 
-    Ok(Json(lectures))
+// 按缺席率建议的超额比例：使预计到场人数约等于 capacity
+fn suggested_overbooking(no_show_rate: f64) -> i32 {
+    if no_show_rate >= 1.0 {
+        return MAX_OVERBOOKING_PERCENT;
+    }
+    ((no_show_rate / (1.0 - no_show_rate) * 100.0).floor() as i32).clamp(0, MAX_OVERBOOKING_PERCENT)
+}
+
+// GET /LA/stats/:lecture_id -> 组织者统计：报名/到场人数，以及重新检测后的可疑签到记录
+async fn lecture_stats(
+    State(client): State<AppState>,
+    auth: RequireRole<Organizer>,
+    Path(lecture_id): Path<String>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let coll = la_collection(&client);
+    let lecture_oid = ObjectId::parse_str(&lecture_id)
+        .map_err(|_| AppError::BadRequest("无效的 lecture_id".into()))?;
+    let lecture = ensure_lecture_organizer(&client, lecture_oid, &auth).await?;
+
+    let mut cursor = coll.find(doc! { "lecture_id": lecture_oid }, None).await
+        .map_err(|_| AppError::Internal("查询失败".into()))?;
+    let mut records = Vec::new();
+    while let Some(doc) = cursor.next().await {
+        records.push(doc.map_err(|_| AppError::Internal("读取错误".into()))?);
+    }
+
+    // 每次统计都重新检测：先清除旧标记，再写入本次结果
+    let flags = anomaly::detect(&records);
+    coll.update_many(
+        doc! { "lecture_id": lecture_oid, "suspect": true },
+        doc! { "$set": { "suspect": false }, "$unset": { "suspect_reasons": "" } },
+        None,
+    ).await
+        .map_err(|_| AppError::Internal("更新失败".into()))?;
+    for flag in &flags {
+        let (filter, update) = anomaly::mark_update(flag);
+        coll.update_one(filter, update, None).await
+            .map_err(|_| AppError::Internal("更新失败".into()))?;
+    }
+
+    let suspects: Vec<serde_json::Value> = flags
+        .iter()
+        .filter_map(|flag| {
+            let record = records.iter().find(|r| r.get_object_id("_id").ok() == Some(flag.la_id))?;
+            Some(serde_json::json!({
+                "la_id": flag.la_id.to_hex(),
+                "audience_id": ids::oid_hex(record, "audience_id"),
+                "client_ip": record.get_str("client_ip").unwrap_or(""),
+                "checked_in_at": record.get_i64("checked_in_at").ok(),
+                "reasons": &flag.reasons,
+            }))
+        })
+        .collect();
+
+    let present = records.iter().filter(|r| r.get_bool("is_present").unwrap_or(false)).count();
+    // 超额报名：历史缺席率、建议比例与预计到场人数
+    let no_show = organizer_no_show_rate(&client, lecture.get_str("organizer_id").unwrap_or(""), lecture_oid).await?;
+    let overbooking = serde_json::json!({
+        "capacity": lecture.get_i32("capacity").ok(),
+        "overbooking_percent": lecture.get_i32("overbooking_percent").unwrap_or(0),
+        "effective_capacity": effective_capacity(&lecture),
+        "historical_no_show_rate": no_show.map(|(rate, _)| (rate * 1000.0).round() / 1000.0),
+        "historical_sample": no_show.map(|(_, sample)| sample),
+        "suggested_overbooking_percent": no_show.map(|(rate, _)| suggested_overbooking(rate)),
+        "predicted_attendance": no_show.map(|(rate, _)| (records.len() as f64 * (1.0 - rate)).round() as i64),
+    });
+    Ok(Json(serde_json::json!({
+        "lecture_id": lecture_id,
+        "registered": records.len(),
+        "present": present,
+        "overbooking": overbooking,
+        "suspect_count": suspects.len(),
+        "suspects": suspects,
+    })))
+}
+
+// 报名时间相对开始时间的分段（提前多久报名），按先后顺序输出
+const JOIN_BUCKETS: [(&str, i64); 4] = [
+    ("after_start", 0),
+    ("within_1h", 3_600_000),
+    ("within_1d", 86_400_000),
+    ("within_7d", 7 * 86_400_000),
+];
+const JOIN_BUCKET_EARLIER: &str = "earlier";
+// 签到时间相对开始时间的分段（迟到多久），含提前签到
+const CHECKIN_BUCKETS: [(&str, i64); 4] = [
+    ("before_start", 0),
+    ("late_5m", 5 * 60_000),
+    ("late_15m", 15 * 60_000),
+    ("late_30m", 30 * 60_000),
+];
+const CHECKIN_BUCKET_LATER: &str = "later";
+// 在场不足该分钟数的计为短暂停留
+const SHORT_STAY_MINUTES: i64 = 10;
+
+// 按阈值分段：第一个满足 value < 阈值 的分段，都不满足时为 fallback
+fn bucket_switch(value: bson::Document, buckets: &[(&str, i64)], fallback: &str) -> bson::Document {
+    let branches: Vec<bson::Document> = buckets
+        .iter()
+        .map(|(label, bound)| doc! { "case": { "$lt": [value.clone(), bound] }, "then": label })
+        .collect();
+    doc! { "$switch": { "branches": branches, "default": fallback } }
+}
+
+// 分段计数按固定顺序输出，没有记录的分段补 0
+fn ordered_counts(rows: &[bson::Document], labels: Vec<String>) -> Vec<serde_json::Value> {
+    labels
+        .into_iter()
+        .map(|label| {
+            let count = rows
+                .iter()
+                .find(|r| r.get_str("_id").ok() == Some(label.as_str()))
+                .and_then(|r| r.get_i32("count").ok())
+                .unwrap_or(0);
+            serde_json::json!({ "bucket": label, "count": count })
+        })
+        .collect()
+}
+
+fn ratio(part: i32, whole: i32) -> Option<f64> {
+    (whole > 0).then(|| (part as f64 / whole as f64 * 1000.0).round() / 1000.0)
+}
+
+// GET /LA/report/:lecture_id -> 出勤报告：报名与到场人数、缺席率、报名时间与签到时间分布、签到方式
+// 以及每位到场者的在场时长，全部由一次聚合算出（组织者、讲者或本场考勤员）
+async fn attendance_report(
+    State(client): State<AppState>,
+    auth: AuthUser,
+    Path(lecture_id): Path<String>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let lecture_oid = ids::parse_oid(&lecture_id, "lecture_id")?;
+    let lecture = require_lecture_role(&client, lecture_oid, &auth, LectureRole::AttendanceTaker).await?;
+    let start = lecture.get_i64("start_time").unwrap_or(0);
+    let status = LectureStatus::of(&lecture);
+    // 在场时长只算演讲进行的时段：实际开始（没有则按计划开始）到实际结束，进行中的算到现在。
+    // 签到早于开始、离场晚于结束或未登记离场的，按时段边界截断
+    let now = Utc::now().timestamp_millis();
+    let scheduled_end = start + lecture.get_i32("duration").unwrap_or(0).max(0) as i64 * 60_000;
+    let window_start = lecture.get_i64("started_at").unwrap_or(start);
+    let window_end = lecture
+        .get_i64("ended_at")
+        .unwrap_or(if status == LectureStatus::Live { now } else { scheduled_end })
+        .min(now)
+        .max(window_start);
+
+    // 报名时间早期以 BSON 日期存储，统一转成毫秒
+    let to_ms = |field: &str| doc! { "$convert": { "input": format!("${}", field), "to": "long", "onError": null, "onNull": null } };
+    let lead = doc! { "$subtract": [start, "$joined_ms"] };
+    let delay = doc! { "$subtract": ["$checkin_ms", start] };
+    let present = doc! { "$eq": ["$is_present", true] };
+    let pipeline = vec![
+        doc! { "$match": { "lecture_id": lecture_oid } },
+        doc! { "$addFields": {
+            "joined_ms": to_ms("joined_at"),
+            "checkin_ms": to_ms("checked_in_at"),
+            "left_ms": to_ms("left_at"),
+        } },
+        doc! { "$facet": {
+            "totals": [{ "$group": {
+                "_id": null,
+                "registered": { "$sum": 1 },
+                "present": { "$sum": { "$cond": [present.clone(), 1, 0] } },
+                "from_waitlist": { "$sum": { "$cond": [{ "$eq": ["$from_waitlist", true] }, 1, 0] } },
+            } }],
+            "joined": [
+                { "$match": { "joined_ms": { "$ne": null } } },
+                { "$group": { "_id": bucket_switch(lead, &JOIN_BUCKETS, JOIN_BUCKET_EARLIER), "count": { "$sum": 1 } } },
+            ],
+            "checkin": [
+                { "$match": { "is_present": true, "checkin_ms": { "$ne": null } } },
+                { "$group": { "_id": bucket_switch(delay, &CHECKIN_BUCKETS, CHECKIN_BUCKET_LATER), "count": { "$sum": 1 } } },
+            ],
+            "methods": [
+                { "$match": { "is_present": true } },
+                { "$group": { "_id": { "$ifNull": ["$checkin_method", "manual"] }, "count": { "$sum": 1 } } },
+                { "$sort": { "count": -1, "_id": 1 } },
+            ],
+            "attendees": [
+                { "$match": { "is_present": true } },
+                { "$addFields": { "attended_ms": { "$max": [0_i64, { "$subtract": [
+                    { "$min": [{ "$ifNull": ["$left_ms", window_end] }, window_end] },
+                    { "$max": [{ "$ifNull": ["$checkin_ms", window_start] }, window_start] },
+                ] }] } } },
+                { "$sort": { "attended_ms": 1, "_id": 1 } },
+                { "$lookup": { "from": "users", "localField": "audience_id", "foreignField": "_id", "as": "user" } },
+                { "$project": {
+                    "audience_id": 1,
+                    "username": { "$ifNull": [{ "$arrayElemAt": ["$user.username", 0] }, ""] },
+                    "checkin_ms": 1,
+                    "left_ms": 1,
+                    "attended_ms": 1,
+                } },
+            ],
+        } },
+    ];
+    let result = la_collection(&client)
+        .aggregate(pipeline, None)
+        .await?
+        .try_next()
+        .await?
+        .unwrap_or_default();
+    let facet = |name: &str| -> Vec<bson::Document> {
+        result
+            .get_array(name)
+            .map(|a| a.iter().filter_map(|b| b.as_document().cloned()).collect())
+            .unwrap_or_default()
+    };
+    let totals = facet("totals").into_iter().next().unwrap_or_default();
+    let registered = totals.get_i32("registered").unwrap_or(0);
+    let present = totals.get_i32("present").unwrap_or(0);
+    let labels = |buckets: &[(&str, i64)], last: &str| -> Vec<String> {
+        buckets.iter().map(|(l, _)| l.to_string()).chain(std::iter::once(last.to_string())).collect()
+    };
+    let methods: serde_json::Map<String, serde_json::Value> = facet("methods")
+        .iter()
+        .filter_map(|m| Some((m.get_str("_id").ok()?.to_string(), serde_json::json!(m.get_i32("count").unwrap_or(0)))))
+        .collect();
+    let attendees: Vec<(i64, serde_json::Value)> = facet("attendees")
+        .iter()
+        .map(|a| {
+            let attended = a.get_i64("attended_ms").unwrap_or(0);
+            let row = serde_json::json!({
+                "audience_id": ids::oid_hex(a, "audience_id"),
+                "username": a.get_str("username").unwrap_or(""),
+                "checked_in_at": a.get_i64("checkin_ms").ok(),
+                "left_at": a.get_i64("left_ms").ok(),
+                "attended_minutes": attended / 60_000,
+            });
+            (attended, row)
+        })
+        .collect();
+    let total_attended: i64 = attendees.iter().map(|(ms, _)| ms).sum();
+    let short_stays = attendees.iter().filter(|(ms, _)| *ms < SHORT_STAY_MINUTES * 60_000).count();
+    let average_minutes = (!attendees.is_empty())
+        .then(|| (total_attended as f64 / attendees.len() as f64 / 60_000.0 * 10.0).round() / 10.0);
+
+    Ok(Json(serde_json::json!({
+        "lecture_id": lecture_id,
+        "start_time": start,
+        "status": status.name(),
+        "registered": registered,
+        "present": present,
+        "absent": registered - present,
+        "attendance_rate": ratio(present, registered),
+        "absence_rate": ratio(registered - present, registered),
+        "from_waitlist": totals.get_i32("from_waitlist").unwrap_or(0),
+        "join_distribution": ordered_counts(&facet("joined"), labels(&JOIN_BUCKETS, JOIN_BUCKET_EARLIER)),
+        "checkin_distribution": ordered_counts(&facet("checkin"), labels(&CHECKIN_BUCKETS, CHECKIN_BUCKET_LATER)),
+        "checkin_methods": methods,
+        "duration": {
+            "window_start": window_start,
+            "window_end": window_end,
+            "average_minutes": average_minutes,
+            "short_stay_minutes": SHORT_STAY_MINUTES,
+            "short_stays": short_stays,
+        },
+        // 按在场时长从短到长
+        "attendees": attendees.into_iter().map(|(_, row)| row).collect::<Vec<_>>(),
+    })))
+}
+
+#[derive(Deserialize)]
+struct ExportQuery {
+    lecture_id: String,
+}
+
+// 报名时间早期以 BSON 日期存储，之后改为毫秒时间戳，两种都认
+fn millis(doc: &bson::Document, field: &str) -> Option<i64> {
+    match doc.get(field)? {
+        bson::Bson::Int64(ms) => Some(*ms),
+        bson::Bson::DateTime(t) => Some(t.timestamp_millis()),
+        _ => None,
+    }
+}
+
+// GET /LA/export.csv?lecture_id= -> 导出报名与出勤名单（用户、出勤状态、报名、签到及离场时间），
+// 时间按导出者的时区显示。仅该演讲的组织者可导出
+async fn export_attendance_csv(
+    State(client): State<AppState>,
+    auth: RequireRole<Organizer>,
+    Query(query): Query<ExportQuery>,
+) -> Result<axum::response::Response, AppError> {
+    let lecture_oid = ids::parse_oid(&query.lecture_id, "lecture_id")?;
+    ensure_lecture_organizer(&client, lecture_oid, &auth).await?;
+    let viewer = user_collection(&client).find_one(doc! { "_id": auth.id }, None).await?.unwrap_or_default();
+    let time = UserTime::for_user(&viewer);
+
+    let pipeline = vec![
+        doc! { "$match": { "lecture_id": lecture_oid } },
+        doc! { "$sort": { "joined_at": 1, "_id": 1 } },
+        doc! { "$lookup": { "from": "users", "localField": "audience_id", "foreignField": "_id", "as": "user" } },
+    ];
+    let cursor = la_collection(&client).aggregate(pipeline, None).await?;
+    let rows = cursor.map(move |record| {
+        record.map(|la| {
+            let user = la.get_array("user").ok().and_then(|a| a.first()).and_then(|u| u.as_document());
+            let user_field = |field: &str| user.and_then(|u| u.get_str(field).ok()).unwrap_or("").to_string();
+            let present = la.get_bool("is_present").unwrap_or(false);
+            vec![
+                ids::oid_hex(&la, "audience_id"),
+                user_field("username"),
+                user_field("email"),
+                if present { "present" } else { "absent" }.to_string(),
+                millis(&la, "joined_at").map(|ms| time.iso(ms)).unwrap_or_default(),
+                millis(&la, "checked_in_at").map(|ms| time.iso(ms)).unwrap_or_default(),
+                millis(&la, "left_at").map(|ms| time.iso(ms)).unwrap_or_default(),
+                if la.get_bool("suspect").unwrap_or(false) { "1" } else { "0" }.to_string(),
+            ]
+        })
+    });
+    Ok(csvexport::response(
+        &format!("attendance-{}.csv", query.lecture_id),
+        &["user_id", "username", "email", "status", "joined_at", "checked_in_at", "left_at", "suspect"],
+        rows,
+    ))
 }
 
 // ==================== Router ====================
@@ -670,6 +1834,45 @@ pub fn router() -> Router<AppState> {
         .route("/by-audience", get(get_by_audience))
         .route("/present", get(get_present_users))
         .route("/update_is_present", post(update_is_present))
+        .route("/bulk_update_presence", post(bulk_update_presence))
+        .route("/checkin", post(checkin))
+        .route("/checkin/token", get(checkin_token))
+        .route("/checkin/qr", post(checkin_qr))
+        .route("/checkout", post(checkout))
         .route("/create", post(create_la_entry))
         .route("/lectures_by_user/:user_id", get(get_lectures_by_user))
+        .route("/stats/:lecture_id", get(lecture_stats))
+        .route("/report/:lecture_id", get(attendance_report))
+        .route("/export.csv", get(export_attendance_csv))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::StatusCode;
+
+    #[test]
+    fn qr_token_round_trips_before_expiry() {
+        let oid = ObjectId::new();
+        let token = qr_token(oid, Utc::now().timestamp_millis() + 60_000);
+        assert_eq!(parse_qr_token(&token).unwrap(), oid);
+    }
+
+    #[test]
+    fn expired_qr_token_is_gone() {
+        let token = qr_token(ObjectId::new(), Utc::now().timestamp_millis() - 1);
+        assert_eq!(parse_qr_token(&token).unwrap_err().status(), StatusCode::GONE);
+    }
+
+    #[test]
+    fn tampered_or_malformed_qr_token_is_rejected() {
+        let expires = Utc::now().timestamp_millis() + 60_000;
+        let token = qr_token(ObjectId::new(), expires);
+        let other = ObjectId::new().to_hex();
+        let forged = format!("{}{}", other, &token[24..]);
+        let extended = token.replace(&expires.to_string(), &(expires + 1).to_string());
+        for bad in [forged.as_str(), extended.as_str(), "", "a.b", "a.b.c.d", &format!("{}.x", token)] {
+            assert_eq!(parse_qr_token(bad).unwrap_err().status(), StatusCode::BAD_REQUEST, "{}", bad);
+        }
+    }
 }
@@ -1,19 +1,34 @@
 // src/routes/lecture.rs
 use axum::{
-    extract::{Path, State, Json},
-    http::StatusCode,
+    extract::{Path, Query, State, Json},
+    http::HeaderMap,
     routing::{get, post},
     Router,
 };
-use axum::response::Json as RespJson;
+use axum::response::{Json as RespJson, Response};
 use bson::{doc, oid::ObjectId, Document};
 use futures_util::TryStreamExt;
+use mongodb::options::{FindOneAndUpdateOptions, ReturnDocument};
 use mongodb::Client;
-use rand::Rng;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
-use crate::db::lecture_collection;
+use crate::auth::{AuthUser, LectureRole, Organizer, RequireRole};
+use crate::db::{
+    announcement_collection, discussion_collection, faq_collection, feedback_collection, invitation_collection,
+    la_collection, lecture_collection, lecture_note_collection, lecture_role_collection, material_collection, organization_collection,
+    reaction_collection, transcript_collection, user_collection, waitlist_collection,
+};
+use crate::{audit, ics, ids, lecturecode, realtime, retry, tags};
+use crate::mailer::MAILER;
+use crate::notify;
+use crate::routes::faq::published_faqs;
+use crate::routes::{embed, material, time};
+use crate::routes::organization::branding_for;
+use crate::error::AppError;
+use crate::pagination::PageParams;
+use crate::lifecycle::LectureStatus;
+use crate::timefmt::{parse_time_param, UserTime};
 
 type AppState = Arc<Client>;
 
@@ -29,7 +44,27 @@ struct LectureCreate {
     // 前端可能传空字符串，按 None 处理
     speaker_id: Option<String>,
     organizer_id: String,
+    // 所属组织（可选），用于展示组织品牌
+    org_id: Option<String>,
+    // 仅可创建为草稿(2)或未开始(0)，缺省为未开始
+    #[serde(default)]
     status: i32,
+    // 报名人数上限，缺省不限
+    capacity: Option<i32>,
+    // 超额报名百分比，按历史缺席率多放出的名额，缺省 0
+    overbooking_percent: Option<i32>,
+    // 分类标签，公开时通知订阅了相同标签的用户
+    #[serde(default)]
+    tags: Vec<String>,
+    // 报名开放、截止时间（毫秒时间戳或 RFC3339），缺省不限
+    registration_opens_at: Option<serde_json::Value>,
+    registration_closes_at: Option<serde_json::Value>,
+    // 为 true 时跳过重复检测，强制创建
+    #[serde(default)]
+    force: bool,
+    // 为 true 时允许与组织者/讲者的其他演讲时间重叠
+    #[serde(default)]
+    allow_conflict: bool,
 }
 
 #[derive(Serialize)]
@@ -41,8 +76,14 @@ struct Lecture {
     description: String,
     speaker_id: Option<String>,
     organizer_id: Option<String>,
-    lecturecode: i32,
+    org_id: Option<String>,
+    lecturecode: String,
     status: i32,
+    capacity: Option<i32>,
+    tags: Vec<String>,
+    registration_opens_at: Option<i64>,
+    registration_closes_at: Option<i64>,
+    registration_status: &'static str,
 }
 
 #[derive(Deserialize, Default)]
@@ -53,44 +94,530 @@ struct LectureUpdate {
     description: Option<String>,
     speaker_id: Option<String>,
     organizer_id: Option<String>,
+    org_id: Option<String>,
+    status: Option<i32>,
+    // 报名人数上限，0 表示取消限制
+    capacity: Option<i32>,
+    // 超额报名百分比，0 表示不超额
+    overbooking_percent: Option<i32>,
+    // 整体替换标签，传空数组清空
+    tags: Option<Vec<String>>,
+    // 允许嵌入与按演讲码查询的来源，如 https://cs.example.edu；传空数组取消限制，仅组织者可改
+    embed_origins: Option<Vec<String>>,
+    // 报名开放、截止时间，传 0 或空字符串取消限制
+    registration_opens_at: Option<serde_json::Value>,
+    registration_closes_at: Option<serde_json::Value>,
+    // 为 true 时允许与组织者/讲者的其他演讲时间重叠
+    #[serde(default)]
+    allow_conflict: bool,
+}
+
+#[derive(Deserialize, Default)]
+struct ListQuery {
+    // 默认列表不含已归档演讲
+    include_archived: Option<bool>,
+    // 以下为 /lecture/ 的检索条件，均可选
     status: Option<i32>,
+    organizer_id: Option<String>,
+    speaker_id: Option<String>,
+    // 主题或简介关键词，不区分大小写
+    q: Option<String>,
+    // start_time 范围，毫秒时间戳或 RFC3339
+    from: Option<String>,
+    to: Option<String>,
+    // 按标签筛选
+    tag: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct CoListRequest {
+    org_id: String,
 }
 
+#[derive(Deserialize)]
+struct RehearsalToggle {
+    enabled: bool,
+}
+
+#[derive(Deserialize)]
+struct AnnounceRequest {
+    subject: Option<String>,
+    message: String,
+    // 是否同时发送邮件
+    #[serde(default)]
+    email: bool,
+}
+
+const ANNOUNCE_MAX_LEN: usize = 2000;
+
 // ==================== 工具函数 ====================
 
-async fn generate_unique_lecturecode(coll: &mongodb::Collection<Document>) -> i32 {
-    loop {
-        let code: i32 = {
-            let mut rng = rand::thread_rng();
-            rng.gen_range(100000..=999999)
-        };
-        if coll
-            .find_one(doc! { "lecturecode": code }, None)
-            .await
-            .unwrap()
-            .is_none()
-        {
-            return code;
+// 只有演讲的组织者可以执行的操作（其他模块也复用）
+pub async fn ensure_lecture_organizer(client: &AppState, lecture_oid: ObjectId, auth: &AuthUser) -> Result<Document, AppError> {
+    let lecture = lecture_collection(client)
+        .find_one(doc! { "_id": lecture_oid }, None)
+        .await
+        .map_err(|_| AppError::Internal("查询失败".into()))?
+        .ok_or(AppError::NotFound("Lecture not found".into()))?;
+    if lecture.get_str("organizer_id").ok() != Some(auth.id_hex().as_str()) {
+        return Err(AppError::Forbidden("只有该演讲的组织者可以执行该操作".into()));
+    }
+    Ok(lecture)
+}
+
+// 超额报名上限：按 capacity 多放出的比例
+pub const MAX_OVERBOOKING_PERCENT: i32 = 50;
+
+fn validate_overbooking(percent: i32) -> Result<i32, AppError> {
+    if !(0..=MAX_OVERBOOKING_PERCENT).contains(&percent) {
+        return Err(AppError::BadRequest(format!("overbooking_percent 须在 0 到 {} 之间", MAX_OVERBOOKING_PERCENT)));
+    }
+    Ok(percent)
+}
+
+// 实际接受报名的人数上限：capacity 加上超额部分（向下取整），未设 capacity 时不限
+pub fn effective_capacity(lecture: &Document) -> Option<i64> {
+    let capacity = lecture.get_i32("capacity").ok()? as i64;
+    let percent = lecture.get_i32("overbooking_percent").unwrap_or(0) as i64;
+    Some(capacity * (100 + percent) / 100)
+}
+
+// 报名时间窗字段：毫秒时间戳或 RFC3339，0 或空字符串表示不限
+fn parse_window_time(value: &serde_json::Value, field: &str) -> Result<Option<i64>, AppError> {
+    let invalid = || AppError::BadRequest(format!("{} 无效", field));
+    match value {
+        serde_json::Value::Null => Ok(None),
+        serde_json::Value::String(s) if s.trim().is_empty() => Ok(None),
+        serde_json::Value::String(s) => Ok(Some(
+            chrono::DateTime::parse_from_rfc3339(s.trim()).map_err(|_| invalid())?.timestamp_millis(),
+        )),
+        serde_json::Value::Number(n) => match n.as_i64().ok_or_else(invalid)? {
+            0 => Ok(None),
+            ms if ms > 0 => Ok(Some(ms)),
+            _ => Err(invalid()),
+        },
+        _ => Err(invalid()),
+    }
+}
+
+// 开放须早于截止，截止不晚于演讲结束
+fn validate_registration_window(opens: Option<i64>, closes: Option<i64>, start_time: i64, duration: i32) -> Result<(), AppError> {
+    if let (Some(opens), Some(closes)) = (opens, closes) {
+        if opens >= closes {
+            return Err(AppError::BadRequest("registration_opens_at 必须早于 registration_closes_at".into()));
+        }
+    }
+    let end = start_time + duration.max(0) as i64 * 60_000;
+    if closes.is_some_and(|c| c > end) {
+        return Err(AppError::BadRequest("registration_closes_at 不能晚于演讲结束时间".into())
+            .with_details(serde_json::json!({ "lecture_end": end })));
+    }
+    Ok(())
+}
+
+// 报名状态：not_open 未到开放时间，closed 已截止或演讲已结束、取消，其余为 open
+pub fn registration_status(lecture: &Document, now_ms: i64) -> &'static str {
+    if matches!(LectureStatus::of(lecture), LectureStatus::Ended | LectureStatus::Cancelled) {
+        return "closed";
+    }
+    if lecture.get_i64("registration_opens_at").is_ok_and(|t| now_ms < t) {
+        return "not_open";
+    }
+    if lecture.get_i64("registration_closes_at").is_ok_and(|t| now_ms >= t) {
+        return "closed";
+    }
+    "open"
+}
+
+// 报名与加入候补前调用；现场签到不受时间窗限制
+pub fn ensure_registration_open(lecture: &Document) -> Result<(), AppError> {
+    let now = chrono::Utc::now().timestamp_millis();
+    let message = match registration_status(lecture, now) {
+        "not_open" => "报名尚未开始",
+        "closed" => "报名已截止",
+        _ => return Ok(()),
+    };
+    Err(AppError::Conflict(message.into()).with_details(serde_json::json!({
+        "registration_opens_at": lecture.get_i64("registration_opens_at").ok(),
+        "registration_closes_at": lecture.get_i64("registration_closes_at").ok(),
+        "now": now,
+    })))
+}
+
+async fn load_own_lecture(client: &AppState, lecture_id: &str, auth: &AuthUser) -> Result<(ObjectId, Document), AppError> {
+    let oid = ObjectId::parse_str(lecture_id)
+        .map_err(|_| AppError::BadRequest("无效的 lecture_id".into()))?;
+    let lecture = ensure_lecture_organizer(client, oid, auth).await?;
+    Ok((oid, lecture))
+}
+
+// 主题相似度阈值（字符二元组 Dice 系数），达到即视为疑似重复
+const DUPLICATE_TOPIC_SIMILARITY: f64 = 0.6;
+
+fn topic_bigrams(topic: &str) -> std::collections::HashSet<(char, char)> {
+    let chars: Vec<char> = topic
+        .to_lowercase()
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .collect();
+    if chars.len() == 1 {
+        return [(chars[0], ' ')].into_iter().collect();
+    }
+    chars.windows(2).map(|w| (w[0], w[1])).collect()
+}
+
+fn topic_similarity(a: &str, b: &str) -> f64 {
+    let (a, b) = (topic_bigrams(a), topic_bigrams(b));
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    2.0 * a.intersection(&b).count() as f64 / (a.len() + b.len()) as f64
+}
+
+// 同一组织者、时间段重叠且主题相近的演讲
+async fn find_duplicates(
+    coll: &mongodb::Collection<Document>,
+    organizer_id: &str,
+    topic: &str,
+    start_time: i64,
+    duration: i32,
+) -> Result<Vec<serde_json::Value>, AppError> {
+    let end_time = start_time + duration.max(0) as i64 * 60_000;
+    let filter = doc! {
+        "organizer_id": organizer_id,
+        "start_time": { "$lt": end_time },
+        "$expr": { "$gt": [
+            { "$add": ["$start_time", { "$multiply": ["$duration", 60_000] }] },
+            start_time,
+        ] },
+    };
+    let mut cursor = coll
+        .find(filter, None)
+        .await
+        .map_err(|_| AppError::Internal("查询失败".into()))?;
+    let mut duplicates = Vec::new();
+    while let Some(doc) = cursor
+        .try_next()
+        .await
+        .map_err(|_| AppError::Internal("读取失败".into()))?
+    {
+        let other_topic = doc.get_str("topic").unwrap_or("");
+        let similarity = topic_similarity(topic, other_topic);
+        if similarity >= DUPLICATE_TOPIC_SIMILARITY {
+            duplicates.push(serde_json::json!({
+                "id": ids::oid_hex(&doc, "_id"),
+                "topic": other_topic,
+                "start_time": doc.get_i64("start_time").unwrap_or(0),
+                "duration": doc.get_i32("duration").unwrap_or(0),
+                "similarity": (similarity * 100.0).round() / 100.0,
+            }));
         }
     }
+    Ok(duplicates)
+}
+
+// 日程冲突：指定用户担任组织者或讲者、且时间段与 [start_time, start_time + duration) 重叠的演讲，
+// 已取消的不计；exclude 为正在修改的演讲本身
+pub async fn find_schedule_conflicts(
+    client: &AppState,
+    people: &[&str],
+    start_time: i64,
+    duration: i32,
+    exclude: Option<ObjectId>,
+) -> Result<Vec<serde_json::Value>, AppError> {
+    if people.is_empty() {
+        return Ok(Vec::new());
+    }
+    let end_time = start_time + duration.max(0) as i64 * 60_000;
+    let mut filter = doc! {
+        "$or": [
+            { "organizer_id": { "$in": people } },
+            { "speaker_id": { "$in": people } },
+        ],
+        "status": { "$ne": LectureStatus::Cancelled.as_i32() },
+        "start_time": { "$lt": end_time },
+        "$expr": { "$gt": [
+            { "$add": ["$start_time", { "$multiply": [{ "$ifNull": ["$duration", 0] }, 60_000] }] },
+            start_time,
+        ] },
+    };
+    if let Some(exclude) = exclude {
+        filter.insert("_id", doc! { "$ne": exclude });
+    }
+    let lectures: Vec<Document> = lecture_collection(client).find(filter, None).await?.try_collect().await?;
+    Ok(lectures
+        .iter()
+        .map(|doc| {
+            let organizer_id = doc.get_str("organizer_id").ok();
+            let speaker_id = doc.get_str("speaker_id").ok();
+            serde_json::json!({
+                "id": ids::oid_hex(doc, "_id"),
+                "topic": doc.get_str("topic").unwrap_or(""),
+                "start_time": doc.get_i64("start_time").unwrap_or(0),
+                "duration": doc.get_i32("duration").unwrap_or(0),
+                "organizer_id": organizer_id,
+                "speaker_id": speaker_id,
+                // 冲突涉及的用户
+                "users": people
+                    .iter()
+                    .filter(|p| organizer_id == Some(**p) || speaker_id == Some(**p))
+                    .collect::<Vec<_>>(),
+            })
+        })
+        .collect())
+}
+
+pub fn schedule_conflict_error(conflicts: Vec<serde_json::Value>) -> AppError {
+    AppError::Conflict("与已有演讲时间冲突，确认安排请携带 allow_conflict: true 重新提交".into())
+        .with_details(serde_json::json!({ "conflicts": conflicts }))
+}
+
+// 所属组织配置了 code_prefix 时，演讲码带上组织前缀
+async fn org_code_prefix(client: &AppState, org_id: Option<&String>) -> Option<String> {
+    let oid = ObjectId::parse_str(org_id?).ok()?;
+    let org = organization_collection(client)
+        .find_one(doc! { "_id": oid }, None)
+        .await
+        .ok()??;
+    org.get_document("settings")
+        .ok()?
+        .get_str("code_prefix")
+        .ok()
+        .filter(|p| !p.is_empty())
+        .map(|p| p.to_string())
+}
+
+fn archive_filter(filter: &mut Document, query: &ListQuery) {
+    if !query.include_archived.unwrap_or(false) {
+        filter.insert("archived", doc! { "$ne": true });
+    }
+}
+
+fn non_empty(v: &Option<String>) -> Option<&str> {
+    v.as_deref().map(str::trim).filter(|s| !s.is_empty())
+}
+
+// /lecture/ 的检索条件全部下推到 MongoDB 查询
+fn search_filter(query: &ListQuery) -> Result<Document, AppError> {
+    let mut filter = doc! {};
+    archive_filter(&mut filter, query);
+    if let Some(status) = query.status {
+        filter.insert("status", status);
+    }
+    if let Some(organizer_id) = non_empty(&query.organizer_id) {
+        filter.insert("organizer_id", organizer_id);
+    }
+    if let Some(speaker_id) = non_empty(&query.speaker_id) {
+        filter.insert("speaker_id", speaker_id);
+    }
+    if let Some(tag) = non_empty(&query.tag) {
+        filter.insert("tags", tag.to_lowercase());
+    }
+    if let Some(q) = non_empty(&query.q) {
+        // 中文没有分词，文本索引无法按子串匹配，这里用转义后的正则
+        let pattern = regex::escape(q);
+        filter.insert("$or", vec![
+            doc! { "topic": { "$regex": &pattern, "$options": "i" } },
+            doc! { "description": { "$regex": &pattern, "$options": "i" } },
+        ]);
+    }
+    let mut range = doc! {};
+    if let Some(from) = non_empty(&query.from) {
+        range.insert("$gte", parse_time_param(from, "from")?);
+    }
+    if let Some(to) = non_empty(&query.to) {
+        range.insert("$lte", parse_time_param(to, "to")?);
+    }
+    if !range.is_empty() {
+        filter.insert("start_time", range);
+    }
+    Ok(filter)
+}
+
+// 已归档演讲冻结讨论与反馈
+pub async fn ensure_not_archived(client: &AppState, lecture_oid: ObjectId) -> Result<(), AppError> {
+    let archived = lecture_collection(client)
+        .find_one(doc! { "_id": lecture_oid, "archived": true }, None)
+        .await
+        .map_err(|_| AppError::Internal("查询失败".into()))?;
+    if archived.is_some() {
+        return Err(AppError::Forbidden("演讲已归档，讨论与反馈已冻结".into()));
+    }
+    Ok(())
+}
+
+// 彩排模式：开播前讲者可试用讨论、反馈等实时工具，产生的数据带 rehearsal 标记，
+// 不计入统计，并在关闭彩排或演讲开始/结束时清除。返回处于彩排中的演讲文档
+pub async fn rehearsal_lecture(client: &AppState, lecture_oid: ObjectId) -> Result<Option<Document>, AppError> {
+    lecture_collection(client)
+        .find_one(doc! { "_id": lecture_oid, "rehearsal": true }, None)
+        .await
+        .map_err(|_| AppError::Internal("查询失败".into()))
+}
+
+// 演讲的组织者或已确定的讲者
+pub fn is_host(lecture: &Document, user_hex: &str) -> bool {
+    lecture.get_str("organizer_id").ok() == Some(user_hex) || lecture.get_str("speaker_id").ok() == Some(user_hex)
+}
+
+#[derive(Deserialize)]
+struct RoleGrant {
+    user_id: String,
+    role: String,
+}
+
+#[derive(Deserialize, Default)]
+struct CancelRequest {
+    reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RescheduleRequest {
+    // 毫秒时间戳或 RFC3339
+    start_time: serde_json::Value,
+    // 缺省保持原时长
+    duration: Option<i32>,
+    reason: Option<String>,
+    #[serde(default)]
+    allow_conflict: bool,
+}
+
+// 按状态机切换演讲状态：以当前状态为条件原子更新，并发切换时只有一个成功；
+// 离开开播前状态时彩排自动关闭，沙盒数据随之清除
+async fn transition(
+    client: &AppState,
+    actor: ObjectId,
+    lecture: &Document,
+    to: LectureStatus,
+    mut set_doc: Document,
+) -> Result<Document, AppError> {
+    let oid = lecture.get_object_id("_id").map_err(|_| AppError::Internal("演讲ID无效".into()))?;
+    let from = LectureStatus::of(lecture);
+    from.check_transition(to)?;
+    set_doc.insert("status", to.as_i32());
+    if let Some(field) = to.timestamp_field() {
+        set_doc.insert(field, chrono::Utc::now().timestamp_millis());
+    }
+    if !to.is_upcoming() && lecture.get_bool("rehearsal").unwrap_or(false) {
+        wipe_rehearsal(client, oid).await?;
+        set_doc.insert("rehearsal", false);
+    }
+
+    // 旧数据可能没有 status 字段，此时按未开始匹配
+    let status_filter = match lecture.get_i32("status") {
+        Ok(v) => bson::Bson::from(v),
+        Err(_) => bson::Bson::Document(doc! { "$exists": false }),
+    };
+    let options = FindOneAndUpdateOptions::builder().return_document(ReturnDocument::After).build();
+    let updated = lecture_collection(client)
+        .find_one_and_update(doc! { "_id": oid, "status": status_filter }, doc! { "$set": set_doc }, options)
+        .await
+        .map_err(|_| AppError::Internal("更新失败".into()))?
+        .ok_or(AppError::Conflict("演讲状态已被修改，请刷新后重试".into()))?;
+    audit::record(
+        client,
+        Some(actor),
+        "lecture.status",
+        &format!("lecture:{}", oid.to_hex()),
+        doc! { "from": from.name(), "to": to.name() },
+    )
+    .await;
+    // 开播时通知候场中的听众进入
+    if to == LectureStatus::Live {
+        crate::routes::waiting_room::open(client, &updated).await;
+    }
+    Ok(updated)
+}
+
+pub async fn load_lecture(client: &AppState, lecture_id: &str) -> Result<Document, AppError> {
+    let oid = ids::parse_oid(lecture_id, "lecture_id")?;
+    lecture_collection(client)
+        .find_one(doc! { "_id": oid }, None)
+        .await
+        .map_err(|_| AppError::Internal("查询失败".into()))?
+        .ok_or(AppError::NotFound("Lecture not found".into()))
+}
+
+pub async fn wipe_rehearsal(client: &AppState, lecture_oid: ObjectId) -> Result<u64, AppError> {
+    let filter = doc! { "lecture_id": lecture_oid, "rehearsal": true };
+    let discussions = discussion_collection(client)
+        .delete_many(filter.clone(), None)
+        .await
+        .map_err(|_| AppError::Internal("清除彩排数据失败".into()))?;
+    let feedback = feedback_collection(client)
+        .delete_many(filter.clone(), None)
+        .await
+        .map_err(|_| AppError::Internal("清除彩排数据失败".into()))?;
+    reaction_collection(client)
+        .delete_many(filter, None)
+        .await
+        .map_err(|_| AppError::Internal("清除彩排数据失败".into()))?;
+    Ok(discussions.deleted_count + feedback.deleted_count)
+}
+
+// 公开详情接口附带所属组织的品牌信息（logo、主题色）
+async fn attach_branding(client: &AppState, v: &mut serde_json::Value) {
+    let org_id = v.get("org_id").and_then(|o| o.as_str()).map(|s| s.to_string());
+    let branding = match org_id {
+        Some(org_id) => branding_for(client, &org_id).await,
+        None => None,
+    };
+    if let Some(obj) = v.as_object_mut() {
+        obj.insert("branding".to_string(), branding.unwrap_or(serde_json::Value::Null));
+    }
+}
+
+// 演讲详情附带已发布的 FAQ
+async fn attach_faq(client: &AppState, v: &mut serde_json::Value) {
+    let Some(oid) = v.get("id").and_then(|i| i.as_str()).and_then(|s| ObjectId::parse_str(s).ok()) else {
+        return;
+    };
+    let faq = published_faqs(client, oid).await;
+    if let Some(obj) = v.as_object_mut() {
+        obj.insert("faq".to_string(), serde_json::Value::from(faq));
+    }
 }
 
 // ==================== 路由 ====================
 
 async fn create_lecture(
     State(client): State<AppState>,
+    auth: RequireRole<Organizer>,
     Json(payload): Json<LectureCreate>,
-) -> Result<RespJson<Lecture>, (StatusCode, String)> {
+) -> Result<RespJson<Lecture>, AppError> {
+    auth.ensure_self(&payload.organizer_id)?;
     let coll = lecture_collection(&client);
 
     let topic = payload.topic;
     // 解析 ISO 字符串为 ms
     let start_time = chrono::DateTime::parse_from_rfc3339(&payload.start_time)
-        .map_err(|_| (StatusCode::BAD_REQUEST, "start_time 无效".into()))?
+        .map_err(|_| AppError::BadRequest("start_time 无效".into()))?
         .timestamp_millis();
     let duration = payload.duration;
     let description = payload.description.unwrap_or_default();
-    let status = payload.status;
+    let status = LectureStatus::parse(payload.status)?;
+    if !status.is_upcoming() {
+        return Err(AppError::BadRequest("新建演讲只能是草稿或未开始状态".into()));
+    }
+    let status = status.as_i32();
+    if payload.capacity.is_some_and(|c| c <= 0) {
+        return Err(AppError::BadRequest("capacity 必须为正整数".into()));
+    }
+    let overbooking_percent = payload.overbooking_percent.map(validate_overbooking).transpose()?.unwrap_or(0);
+    let tags = tags::validate(&payload.tags, tags::MAX_LECTURE_TAGS)?;
+    let registration_opens_at = payload
+        .registration_opens_at
+        .as_ref()
+        .map(|v| parse_window_time(v, "registration_opens_at"))
+        .transpose()?
+        .flatten();
+    let registration_closes_at = payload
+        .registration_closes_at
+        .as_ref()
+        .map(|v| parse_window_time(v, "registration_closes_at"))
+        .transpose()?
+        .flatten();
+    validate_registration_window(registration_opens_at, registration_closes_at, start_time, duration)?;
 
     let speaker_id = payload
         .speaker_id
@@ -102,31 +629,64 @@ async fn create_lecture(
     let organizer_id = ObjectId::parse_str(&payload.organizer_id)
         .ok()
         .map(|oid| oid.to_hex())
-        .ok_or((StatusCode::BAD_REQUEST, "organizer_id 无效".into()))?;
+        .ok_or(AppError::BadRequest("organizer_id 无效".into()))?;
+    let org_id = match payload.org_id.map(|s| s.trim().to_string()).filter(|s| !s.is_empty()) {
+        Some(s) => Some(
+            ObjectId::parse_str(&s)
+                .map_err(|_| AppError::BadRequest("org_id 无效".into()))?
+                .to_hex(),
+        ),
+        None => None,
+    };
+
+    // 防止误操作重复发布：未带 force 时返回疑似重复列表
+    if !payload.force {
+        let duplicates = find_duplicates(&coll, &organizer_id, &topic, start_time, duration).await?;
+        if !duplicates.is_empty() {
+            return Err(AppError::Conflict("存在时间重叠且主题相近的演讲，确认创建请携带 force: true 重新提交".into())
+                .with_details(serde_json::json!({ "duplicates": duplicates })));
+        }
+    }
+
+    if !payload.allow_conflict {
+        let mut people = vec![organizer_id.as_str()];
+        people.extend(speaker_id.as_deref());
+        let conflicts = find_schedule_conflicts(&client, &people, start_time, duration, None).await?;
+        if !conflicts.is_empty() {
+            return Err(schedule_conflict_error(conflicts));
+        }
+    }
 
-    let lecturecode = generate_unique_lecturecode(&coll).await;
+    let prefix = org_code_prefix(&client, org_id.as_ref()).await;
+    let lecturecode = lecturecode::generate_unique(&coll, prefix.as_deref())
+        .await
+        .map_err(|_| AppError::Internal("生成演讲码失败".into()))?;
 
-    let lecture_doc = doc! {
+    let mut lecture_doc = doc! {
         "topic": &topic,
         "start_time": start_time,
         "duration": duration,
         "description": &description,
         "speaker_id": speaker_id.as_ref(),
         "organizer_id": &organizer_id,
-        "lecturecode": lecturecode,
+        "org_id": org_id.as_ref(),
+        "lecturecode": &lecturecode,
         "status": status,
+        "capacity": payload.capacity,
+        "overbooking_percent": overbooking_percent,
+        "tags": &tags,
+        "registration_opens_at": registration_opens_at,
+        "registration_closes_at": registration_closes_at,
+        // 已占用名额，报名与退出时原子增减，用于容量校验
+        "registered_count": 0,
     };
 
-    let result = coll
-        .insert_one(lecture_doc, None)
+    let inserted_oid = retry::insert_one(&coll, &mut lecture_doc)
         .await
-        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "数据库插入失败".into()))?;
-
-    let inserted_id = result
-        .inserted_id
-        .as_object_id()
-        .ok_or((StatusCode::INTERNAL_SERVER_ERROR, "插入ID无效".into()))?
-        .to_hex();
+        .map_err(|e| retry::db_error(e, "数据库插入失败"))?;
+    let inserted_id = inserted_oid.to_hex();
+    let registration_status = registration_status(&lecture_doc, chrono::Utc::now().timestamp_millis());
+    tags::spawn_notify(&client, lecture_doc);
 
     Ok(RespJson(Lecture {
         id: inserted_id,
@@ -136,8 +696,14 @@ async fn create_lecture(
         description,
         speaker_id,
         organizer_id: Some(organizer_id),
+        org_id,
         lecturecode,
         status,
+        capacity: payload.capacity,
+        tags,
+        registration_opens_at,
+        registration_closes_at,
+        registration_status,
     }))
 }
 
@@ -146,239 +712,744 @@ async fn create_lecture(
 async fn list_by_organizer(
     State(client): State<AppState>,
     Path(organizer_id): Path<String>,
-) -> Result<RespJson<Vec<serde_json::Value>>, (StatusCode, String)> {
+    Query(query): Query<ListQuery>,
+    paging: PageParams,
+) -> Result<Response, AppError> {
     let coll = lecture_collection(&client);
     // organizer_id 存库为 hex 字符串
-    let filter = doc! { "organizer_id": &organizer_id };
+    let mut filter = doc! { "organizer_id": &organizer_id };
+    archive_filter(&mut filter, &query);
+    let total = coll
+        .count_documents(filter.clone(), None)
+        .await
+        .map_err(|_| AppError::Internal("查询失败".into()))?;
     let mut cursor = coll
-        .find(filter, None)
+        .find(filter, paging.find_options(doc! { "_id": 1 }))
         .await
-        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "查询失败".into()))?;
+        .map_err(|_| AppError::Internal("查询失败".into()))?;
 
     let mut items = Vec::new();
     while let Some(doc) = cursor
         .try_next()
         .await
-        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "读取失败".into()))?
+        .map_err(|_| AppError::Internal("读取失败".into()))?
     {
-        let id_hex = doc
-            .get_object_id("_id")
-            .map(|o| o.to_hex())
-            .unwrap_or_default();
-        let mut v: serde_json::Value = bson::from_document(doc)
-            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "序列化错误".into()))?;
-        if let Some(obj) = v.as_object_mut() {
-            obj.remove("_id");
-            obj.insert("id".to_string(), serde_json::Value::String(id_hex));
-        }
-        items.push(v);
+        items.push(ids::doc_to_json(doc));
     }
 
-    Ok(RespJson(items))
+    Ok(paging.respond(items, total))
 }
 
-// =============== 列表：全部 ===============
+// =============== 列表：全部（支持 status/organizer_id/speaker_id/q/from/to 检索）===============
 async fn list_all(
     State(client): State<AppState>,
-) -> Result<RespJson<Vec<serde_json::Value>>, (StatusCode, String)> {
+    Query(query): Query<ListQuery>,
+    paging: PageParams,
+) -> Result<Response, AppError> {
     let coll = lecture_collection(&client);
+    let filter = search_filter(&query)?;
+    let total = coll
+        .count_documents(filter.clone(), None)
+        .await
+        .map_err(|_| AppError::Internal("查询失败".into()))?;
     let mut cursor = coll
-        .find(doc! {}, None)
+        .find(filter, paging.find_options(doc! { "_id": 1 }))
         .await
-        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "查询失败".into()))?;
+        .map_err(|_| AppError::Internal("查询失败".into()))?;
 
     let mut items = Vec::new();
     while let Some(doc) = cursor
         .try_next()
         .await
-        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "读取失败".into()))?
+        .map_err(|_| AppError::Internal("读取失败".into()))?
     {
-        let id_hex = doc
-            .get_object_id("_id")
-            .map(|o| o.to_hex())
-            .unwrap_or_default();
-        let mut v: serde_json::Value = bson::from_document(doc)
-            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "序列化错误".into()))?;
-        if let Some(obj) = v.as_object_mut() {
-            obj.remove("_id");
-            obj.insert("id".to_string(), serde_json::Value::String(id_hex));
-        }
-        items.push(v);
+        items.push(ids::doc_to_json(doc));
     }
-    Ok(RespJson(items))
+    Ok(paging.respond(items, total))
+}
+
+// =============== 标签浏览 ===============
+// GET /lecture/by_tag/:tag -> 带该标签的演讲，支持 /lecture/ 的其余检索条件；未指定 status 时不含草稿
+async fn list_by_tag(
+    State(client): State<AppState>,
+    Path(tag): Path<String>,
+    Query(mut query): Query<ListQuery>,
+    paging: PageParams,
+) -> Result<Response, AppError> {
+    query.tag = Some(tags::validate_tag(&tag)?);
+    let mut filter = search_filter(&query)?;
+    if query.status.is_none() {
+        filter.insert("status", doc! { "$ne": LectureStatus::Draft.as_i32() });
+    }
+    let coll = lecture_collection(&client);
+    let total = coll
+        .count_documents(filter.clone(), None)
+        .await
+        .map_err(|_| AppError::Internal("查询失败".into()))?;
+    let lectures: Vec<Document> = coll
+        .find(filter, paging.find_options(doc! { "start_time": 1, "_id": 1 }))
+        .await
+        .map_err(|_| AppError::Internal("查询失败".into()))?
+        .try_collect()
+        .await
+        .map_err(|_| AppError::Internal("读取失败".into()))?;
+    let items: Vec<serde_json::Value> = lectures.into_iter().map(ids::doc_to_json).collect();
+    Ok(paging.respond(items, total))
+}
+
+// GET /lecture/tags -> 全部标签及使用次数（不含草稿与已归档演讲），按次数降序，供分类浏览
+async fn list_tags(State(client): State<AppState>) -> Result<RespJson<serde_json::Value>, AppError> {
+    let pipeline = vec![
+        doc! { "$match": {
+            "tags.0": { "$exists": true },
+            "status": { "$ne": LectureStatus::Draft.as_i32() },
+            "archived": { "$ne": true },
+        } },
+        doc! { "$unwind": "$tags" },
+        doc! { "$group": { "_id": "$tags", "count": { "$sum": 1 } } },
+        doc! { "$sort": { "count": -1, "_id": 1 } },
+    ];
+    let mut cursor = lecture_collection(&client)
+        .aggregate(pipeline, None)
+        .await
+        .map_err(|_| AppError::Internal("查询失败".into()))?;
+    let mut tags = Vec::new();
+    while let Some(d) = cursor
+        .try_next()
+        .await
+        .map_err(|_| AppError::Internal("读取失败".into()))?
+    {
+        tags.push(serde_json::json!({
+            "tag": d.get_str("_id").unwrap_or(""),
+            "count": d.get_i32("count").unwrap_or(0),
+        }));
+    }
+    Ok(RespJson(serde_json::json!({ "tags": tags })))
 }
 
 // =============== 详情：按 ID ===============
-// async fn get_lecture(
-//     State(client): State<AppState>,
-//     Path(lecture_id): Path<String>,
-// ) -> Result<RespJson<serde_json::Value>, (StatusCode, String)> {
-//     let coll = lecture_collection(&client);
-//     let oid = ObjectId::parse_str(&lecture_id)
-//         .map_err(|_| (StatusCode::BAD_REQUEST, "无效的 lecture_id".into()))?;
-//     let doc = coll
-//         .find_one(doc! { "_id": oid }, None)
-//         .await
-//         .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "查询失败".into()))?
-//         .ok_or((StatusCode::NOT_FOUND, "Lecture not found".into()))?;
-
-//     let mut v: serde_json::Value = bson::from_document(doc)
-//         .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "序列化错误".into()))?;
-//     if let Some(obj) = v.as_object_mut() {
-//         // 使用真实 hex id
-//         let id_hex = obj
-//             .remove("_id")
-//             .and_then(|oid| match oid { serde_json::Value::String(s) => Some(s), other => Some(other.to_string()) })
-//             .unwrap_or(lecture_id);
-//         obj.insert("id".to_string(), serde_json::Value::String(id_hex));
-//     }
-//     Ok(RespJson(v))
-// }
 async fn get_lecture(
     State(client): State<AppState>,
     Path(lecture_id): Path<String>,
-) -> Result<RespJson<serde_json::Value>, (StatusCode, String)> {
+) -> Result<RespJson<serde_json::Value>, AppError> {
     let coll = lecture_collection(&client);
     let oid = ObjectId::parse_str(&lecture_id)
-        .map_err(|_| (StatusCode::BAD_REQUEST, "无效的 lecture_id".into()))?;
+        .map_err(|_| AppError::BadRequest("无效的 lecture_id".into()))?;
     
     let doc = coll
         .find_one(doc! { "_id": oid }, None)
         .await
-        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "查询失败".into()))?
-        .ok_or((StatusCode::NOT_FOUND, "Lecture not found".into()))?;
-
-    // 正确提取 id 为字符串
-    let id_hex = oid.to_hex();
+        .map_err(|_| AppError::Internal("查询失败".into()))?
+        .ok_or(AppError::NotFound("Lecture not found".into()))?;
 
-    let mut v: serde_json::Value = bson::from_document(doc)
-        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "序列化错误".into()))?;
-    
+    // 倒计时以服务器时间为准
+    let now_ms = chrono::Utc::now().timestamp_millis();
+    let seconds_until_start = time::seconds_until_start(&doc, now_ms);
+    let registration_status = registration_status(&doc, now_ms);
+    let mut v = ids::doc_to_json(doc);
     if let Some(obj) = v.as_object_mut() {
-        obj.remove("_id");  // 移除原始 _id
-        obj.insert("id".to_string(), serde_json::Value::String(id_hex)); // 插入字符串 id
+        obj.insert("server_time".to_string(), serde_json::json!(now_ms));
+        obj.insert("seconds_until_start".to_string(), serde_json::json!(seconds_until_start));
+        obj.insert("registration_status".to_string(), serde_json::json!(registration_status));
     }
-    
+    attach_branding(&client, &mut v).await;
+    attach_faq(&client, &mut v).await;
+
     Ok(RespJson(v))
 }
 
 // =============== 更新：按 ID ===============
 async fn update_lecture(
     State(client): State<AppState>,
+    auth: AuthUser,
     Path(lecture_id): Path<String>,
     Json(mut payload): Json<LectureUpdate>,
-) -> Result<RespJson<serde_json::Value>, (StatusCode, String)> {
+) -> Result<RespJson<serde_json::Value>, AppError> {
     let coll = lecture_collection(&client);
     let oid = ObjectId::parse_str(&lecture_id)
-        .map_err(|_| (StatusCode::BAD_REQUEST, "无效的 lecture_id".into()))?;
+        .map_err(|_| AppError::BadRequest("无效的 lecture_id".into()))?;
+
+    // 组织者或已确定的讲者可以修改演讲
+    let current = coll
+        .find_one(doc! { "_id": oid }, None)
+        .await
+        .map_err(|_| AppError::Internal("查询失败".into()))?
+        .ok_or(AppError::NotFound("Lecture not found".into()))?;
+    let me = auth.id_hex();
+    let is_organizer = current.get_str("organizer_id").ok() == Some(me.as_str());
+    if !is_organizer && current.get_str("speaker_id").ok() != Some(me.as_str()) {
+        return Err(AppError::Forbidden("只有组织者或讲者可以修改演讲".into()));
+    }
 
     let mut set_doc = doc! {};
     if let Some(topic) = payload.topic.take() { set_doc.insert("topic", topic); }
     if let Some(description) = payload.description.take() { set_doc.insert("description", description); }
     if let Some(duration) = payload.duration.take() { set_doc.insert("duration", duration); }
-    if let Some(status) = payload.status.take() { set_doc.insert("status", status); }
+    if let Some(capacity) = payload.capacity.take() {
+        // 调低上限不影响已报名者，只是不再接受新报名
+        if capacity > 0 { set_doc.insert("capacity", capacity); } else { set_doc.insert("capacity", bson::Bson::Null); }
+    }
+    if let Some(percent) = payload.overbooking_percent.take() { set_doc.insert("overbooking_percent", validate_overbooking(percent)?); }
+    if let Some(tags) = payload.tags.take() { set_doc.insert("tags", tags::validate(&tags, tags::MAX_LECTURE_TAGS)?); }
+    if let Some(origins) = payload.embed_origins.take() {
+        if !is_organizer {
+            return Err(AppError::Forbidden("只有组织者可以设置嵌入来源".into()));
+        }
+        set_doc.insert("embed_origins", embed::normalize_origins(origins, "embed_origins")?);
+    }
+    // 状态变更走状态机校验，其余字段随同一次更新写入。该接口只用于发布/撤回草稿；
+    // 开始、结束、取消须走各自的接口，以便做角色校验并通知听众
+    let new_status = match payload.status.take().map(LectureStatus::parse).transpose()? {
+        Some(to) if to != LectureStatus::of(&current) => Some(to),
+        _ => None,
+    };
+    let dedicated = match new_status {
+        Some(LectureStatus::Live) => Some("start"),
+        Some(LectureStatus::Ended) => Some("end"),
+        Some(LectureStatus::Cancelled) => Some("cancel"),
+        _ => None,
+    };
+    if let Some(action) = dedicated {
+        return Err(AppError::BadRequest(format!(
+            "status 不能通过该接口修改，请使用 POST /lecture/{}/{}",
+            lecture_id, action
+        )));
+    }
     if let Some(sid) = payload.speaker_id.take() {
         let sid = sid.trim().to_string();
+        // 讲者只能由组织者更换，避免讲者把演讲转给他人
+        if current.get_str("speaker_id").ok().unwrap_or("") != sid && !is_organizer {
+            return Err(AppError::Forbidden("只有组织者可以更换讲者".into()));
+        }
         if !sid.is_empty() { set_doc.insert("speaker_id", sid); } else { set_doc.insert("speaker_id", bson::Bson::Null); }
     }
+    // 组织者不能通过该接口变更；原样回传的 organizer_id 忽略
     if let Some(oid_str) = payload.organizer_id.take() {
-        let oid_str = oid_str.trim().to_string();
-        if !oid_str.is_empty() { set_doc.insert("organizer_id", oid_str); }
+        let oid_str = oid_str.trim();
+        if !oid_str.is_empty() && current.get_str("organizer_id").ok() != Some(oid_str) {
+            return Err(AppError::BadRequest("organizer_id 不能修改".into()));
+        }
+    }
+    if let Some(org_str) = payload.org_id.take() {
+        let org_str = org_str.trim().to_string();
+        if org_str.is_empty() {
+            set_doc.insert("org_id", bson::Bson::Null);
+        } else {
+            let org_oid = ObjectId::parse_str(&org_str)
+                .map_err(|_| AppError::BadRequest("org_id 无效".into()))?;
+            set_doc.insert("org_id", org_oid.to_hex());
+        }
     }
     if let Some(st) = payload.start_time.take() {
         let ts_ms: i64 = match st {
             serde_json::Value::String(s) => chrono::DateTime::parse_from_rfc3339(&s)
-                .map_err(|_| (StatusCode::BAD_REQUEST, "start_time 无效".into()))?
+                .map_err(|_| AppError::BadRequest("start_time 无效".into()))?
                 .timestamp_millis(),
-            serde_json::Value::Number(n) => n.as_i64().ok_or((StatusCode::BAD_REQUEST, "start_time 无效".into()))?,
-            _ => return Err((StatusCode::BAD_REQUEST, "start_time 无效".into())),
+            serde_json::Value::Number(n) => n.as_i64().ok_or(AppError::BadRequest("start_time 无效".into()))?,
+            _ => return Err(AppError::BadRequest("start_time 无效".into())),
         };
         set_doc.insert("start_time", ts_ms);
     }
+    // 发布后改时间须走改期接口，以记录改期历史、通知听众并重置提醒；原样回传的取值忽略
+    if LectureStatus::of(&current) != LectureStatus::Draft
+        && ["start_time", "duration"]
+            .iter()
+            .any(|k| set_doc.get(*k).is_some_and(|v| current.get(*k) != Some(v)))
+    {
+        return Err(AppError::Conflict(format!(
+            "演讲已发布，start_time/duration 请通过 POST /lecture/{}/reschedule 修改",
+            lecture_id
+        )));
+    }
+    for (field, value) in [
+        ("registration_opens_at", payload.registration_opens_at.take()),
+        ("registration_closes_at", payload.registration_closes_at.take()),
+    ] {
+        if let Some(value) = value {
+            let ts = parse_window_time(&value, field)?;
+            set_doc.insert(field, ts.map_or(bson::Bson::Null, bson::Bson::Int64));
+        }
+    }
+    // 时间窗或演讲时间变化时按更新后的取值校验
+    if ["registration_opens_at", "registration_closes_at", "start_time", "duration"]
+        .iter()
+        .any(|k| set_doc.contains_key(*k))
+    {
+        let pick = |k: &str| set_doc.get(k).or_else(|| current.get(k));
+        validate_registration_window(
+            pick("registration_opens_at").and_then(|v| v.as_i64()),
+            pick("registration_closes_at").and_then(|v| v.as_i64()),
+            pick("start_time").and_then(|v| v.as_i64()).unwrap_or(0),
+            pick("duration").and_then(|v| v.as_i32()).unwrap_or(0),
+        )?;
+    }
 
-    if set_doc.is_empty() { return Err((StatusCode::BAD_REQUEST, "无可更新字段".into())); }
+    // 时间或人员有实际变化时检查日程冲突
+    let schedule_changed = ["start_time", "duration", "speaker_id"]
+        .iter()
+        .any(|k| set_doc.get(*k).is_some_and(|v| current.get(*k) != Some(v)));
+    if schedule_changed && !payload.allow_conflict {
+        let pick = |k: &str| set_doc.get(k).or_else(|| current.get(k));
+        let start_time = pick("start_time").and_then(|v| v.as_i64()).unwrap_or(0);
+        let duration = pick("duration").and_then(|v| v.as_i32()).unwrap_or(0);
+        let people: Vec<&str> = ["organizer_id", "speaker_id"]
+            .iter()
+            .filter_map(|k| pick(k).and_then(|v| v.as_str()))
+            .filter(|s| !s.is_empty())
+            .collect();
+        let conflicts = find_schedule_conflicts(&client, &people, start_time, duration, Some(oid)).await?;
+        if !conflicts.is_empty() {
+            return Err(schedule_conflict_error(conflicts));
+        }
+    }
 
-    let result = coll
-        .update_one(doc! { "_id": oid }, doc! { "$set": set_doc.clone() }, None)
+    // 发布（草稿转为未开始）或补充标签后通知订阅者，已通知过的演讲不会重复推送
+    if let Some(to) = new_status {
+        let doc = transition(&client, auth.id, &current, to, set_doc).await?;
+        tags::spawn_notify(&client, doc.clone());
+        return Ok(RespJson(ids::doc_to_json(doc)));
+    }
+    if set_doc.is_empty() { return Err(AppError::BadRequest("无可更新字段".into())); }
+
+    let update = doc! { "$set": set_doc };
+    let result = retry::with_retry("lecture.update", || coll.update_one(doc! { "_id": oid }, update.clone(), None))
         .await
-        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "更新失败".into()))?;
-    if result.matched_count == 0 { return Err((StatusCode::NOT_FOUND, "Lecture not found".into())); }
+        .map_err(|e| retry::db_error(e, "更新失败"))?;
+    if result.matched_count == 0 { return Err(AppError::NotFound("Lecture not found".into())); }
 
     // 返回最新
     let doc = coll
         .find_one(doc! { "_id": oid }, None)
         .await
-        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "查询失败".into()))?
-        .ok_or((StatusCode::NOT_FOUND, "Lecture not found".into()))?;
-    let mut v: serde_json::Value = bson::from_document(doc)
-        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "序列化错误".into()))?;
-    if let Some(obj) = v.as_object_mut() {
-        obj.insert("id".to_string(), serde_json::Value::String(lecture_id));
-        obj.remove("_id");
+        .map_err(|_| AppError::Internal("查询失败".into()))?
+        .ok_or(AppError::NotFound("Lecture not found".into()))?;
+    tags::spawn_notify(&client, doc.clone());
+    Ok(RespJson(ids::doc_to_json(doc)))
+}
+
+// =============== 彩排模式 ===============
+// POST /lecture/:id/rehearsal {enabled} -> 开播前开启/关闭彩排，关闭时清除彩排数据
+async fn set_rehearsal(
+    State(client): State<AppState>,
+    auth: AuthUser,
+    Path(lecture_id): Path<String>,
+    Json(payload): Json<RehearsalToggle>,
+) -> Result<RespJson<serde_json::Value>, AppError> {
+    let oid = ids::parse_oid(&lecture_id, "lecture_id")?;
+    let coll = lecture_collection(&client);
+    let lecture = coll
+        .find_one(doc! { "_id": oid }, None)
+        .await
+        .map_err(|_| AppError::Internal("查询失败".into()))?
+        .ok_or(AppError::NotFound("Lecture not found".into()))?;
+    if !is_host(&lecture, &auth.id_hex()) {
+        return Err(AppError::Forbidden("只有组织者或讲者可以切换彩排模式".into()));
     }
-    Ok(RespJson(v))
+    if payload.enabled && !LectureStatus::of(&lecture).is_upcoming() {
+        return Err(AppError::Conflict("演讲已开始或已结束，无法进入彩排".into()));
+    }
+
+    let wiped = if payload.enabled { 0 } else { wipe_rehearsal(&client, oid).await? };
+    coll.update_one(doc! { "_id": oid }, doc! { "$set": { "rehearsal": payload.enabled } }, None)
+        .await
+        .map_err(|_| AppError::Internal("更新失败".into()))?;
+
+    Ok(RespJson(serde_json::json!({
+        "lecture_id": lecture_id,
+        "rehearsal": payload.enabled,
+        "wiped": wiped,
+    })))
+}
+
+// =============== 状态切换 ===============
+// POST /lecture/:id/start -> 未开始 → 进行中，记录 started_at（组织者或讲者）
+async fn start_lecture(
+    State(client): State<AppState>,
+    auth: AuthUser,
+    Path(lecture_id): Path<String>,
+) -> Result<RespJson<serde_json::Value>, AppError> {
+    let lecture = load_lecture(&client, &lecture_id).await?;
+    if !is_host(&lecture, &auth.id_hex()) {
+        return Err(AppError::Forbidden("只有组织者或讲者可以开始演讲".into()));
+    }
+    let doc = transition(&client, auth.id, &lecture, LectureStatus::Live, doc! {}).await?;
+    Ok(RespJson(ids::doc_to_json(doc)))
+}
+
+// POST /lecture/:id/end -> 进行中 → 已结束，记录 ended_at（组织者或讲者）
+async fn end_lecture(
+    State(client): State<AppState>,
+    auth: AuthUser,
+    Path(lecture_id): Path<String>,
+) -> Result<RespJson<serde_json::Value>, AppError> {
+    let lecture = load_lecture(&client, &lecture_id).await?;
+    if !is_host(&lecture, &auth.id_hex()) {
+        return Err(AppError::Forbidden("只有组织者或讲者可以结束演讲".into()));
+    }
+    let doc = transition(&client, auth.id, &lecture, LectureStatus::Ended, doc! {}).await?;
+    Ok(RespJson(ids::doc_to_json(doc)))
+}
+
+// POST /lecture/:id/cancel {reason?} -> 草稿/未开始 → 已取消，记录 cancelled_at（仅组织者）
+async fn cancel_lecture(
+    State(client): State<AppState>,
+    auth: RequireRole<Organizer>,
+    Path(lecture_id): Path<String>,
+    payload: Option<Json<CancelRequest>>,
+) -> Result<RespJson<serde_json::Value>, AppError> {
+    let (_, lecture) = load_own_lecture(&client, &lecture_id, &auth).await?;
+    let reason = payload
+        .map(|Json(p)| p)
+        .unwrap_or_default()
+        .reason
+        .map(|r| r.trim().to_string())
+        .filter(|r| !r.is_empty());
+    let mut set_doc = doc! {};
+    if let Some(reason) = reason {
+        set_doc.insert("cancel_reason", reason);
+    }
+    let doc = transition(&client, auth.id, &lecture, LectureStatus::Cancelled, set_doc).await?;
+    let payload = doc! {
+        "topic": doc.get_str("topic").unwrap_or(""),
+        "start_time": doc.get_i64("start_time").unwrap_or(0),
+        "reason": doc.get_str("cancel_reason").ok(),
+    };
+    spawn_notify_attendees(&client, &doc, auth.id, "lecture_cancelled", payload);
+    Ok(RespJson(ids::doc_to_json(doc)))
+}
+
+// =============== 改期 ===============
+// POST /lecture/:id/reschedule {start_time, duration?, reason?, allow_conflict?}
+// 开播前由组织者或讲者调整时间：检查组织者/讲者的日程冲突，旧时间追加到 reschedule_history，
+// 并通知已报名、候补的观众及讲者。本系统没有场地档案，冲突只按人员检查
+async fn reschedule_lecture(
+    State(client): State<AppState>,
+    auth: AuthUser,
+    Path(lecture_id): Path<String>,
+    Json(payload): Json<RescheduleRequest>,
+) -> Result<RespJson<serde_json::Value>, AppError> {
+    let lecture = load_lecture(&client, &lecture_id).await?;
+    let oid = lecture.get_object_id("_id").map_err(|_| AppError::Internal("演讲ID无效".into()))?;
+    if !is_host(&lecture, &auth.id_hex()) {
+        return Err(AppError::Forbidden("只有组织者或讲者可以调整演讲时间".into()));
+    }
+    if !LectureStatus::of(&lecture).is_upcoming() {
+        return Err(AppError::Conflict("演讲已开始、结束或取消，无法改期".into()));
+    }
+
+    let start_time = match &payload.start_time {
+        serde_json::Value::String(s) => parse_time_param(s, "start_time")?,
+        serde_json::Value::Number(n) => n.as_i64().ok_or(AppError::BadRequest("start_time 无效".into()))?,
+        _ => return Err(AppError::BadRequest("start_time 无效".into())),
+    };
+    let old_start = lecture.get_i64("start_time").unwrap_or(0);
+    let old_duration = lecture.get_i32("duration").unwrap_or(0);
+    let duration = payload.duration.unwrap_or(old_duration);
+    if duration <= 0 {
+        return Err(AppError::BadRequest("duration 必须大于 0".into()));
+    }
+    let now = chrono::Utc::now().timestamp_millis();
+    if start_time <= now {
+        return Err(AppError::BadRequest("新的开始时间必须晚于当前时间".into()));
+    }
+    if start_time == old_start && duration == old_duration {
+        return Err(AppError::BadRequest("时间没有变化".into()));
+    }
+
+    if !payload.allow_conflict {
+        let people: Vec<&str> = ["organizer_id", "speaker_id"]
+            .iter()
+            .filter_map(|k| lecture.get_str(k).ok())
+            .filter(|s| !s.is_empty())
+            .collect();
+        let conflicts = find_schedule_conflicts(&client, &people, start_time, duration, Some(oid)).await?;
+        if !conflicts.is_empty() {
+            return Err(schedule_conflict_error(conflicts));
+        }
+    }
+
+    let reason = payload.reason.map(|r| r.trim().to_string()).filter(|r| !r.is_empty());
+    let entry = doc! {
+        "old_start_time": old_start,
+        "old_duration": old_duration,
+        "new_start_time": start_time,
+        "new_duration": duration,
+        "reason": reason.clone(),
+        "by": auth.id,
+        "at": now,
+    };
+    // 以原时间为条件更新，两人同时改期时只有一个成功；时间变了需要重新提醒
+    let options = FindOneAndUpdateOptions::builder().return_document(ReturnDocument::After).build();
+    let doc = lecture_collection(&client)
+        .find_one_and_update(
+            doc! { "_id": oid, "start_time": old_start, "status": lecture.get("status").cloned().unwrap_or(bson::Bson::Null) },
+            doc! {
+                "$set": { "start_time": start_time, "duration": duration },
+                "$unset": { "reminder_sent_at": "", "reminders_sent": "" },
+                "$push": { "reschedule_history": entry },
+            },
+            options,
+        )
+        .await
+        .map_err(|_| AppError::Internal("更新失败".into()))?
+        .ok_or(AppError::Conflict("演讲已被修改，请刷新后重试".into()))?;
+
+    audit::record(
+        &client,
+        Some(auth.id),
+        "lecture.reschedule",
+        &format!("lecture:{}", lecture_id),
+        doc! { "from": old_start, "to": start_time, "old_duration": old_duration, "duration": duration },
+    )
+    .await;
+    realtime::publish(oid, "lecture.rescheduled", serde_json::json!({ "start_time": start_time, "duration": duration }));
+    let notice = doc! {
+        "topic": doc.get_str("topic").unwrap_or(""),
+        "old_start_time": old_start,
+        "start_time": start_time,
+        "duration": duration,
+        "reason": reason,
+    };
+    spawn_notify_attendees(&client, &doc, auth.id, "lecture_rescheduled", notice);
+    Ok(RespJson(ids::doc_to_json(doc)))
+}
+
+// 在后台通知已报名、候补的观众及讲者（操作者本人除外），payload 自动带上 lecture_id
+fn spawn_notify_attendees(client: &AppState, lecture: &Document, actor: ObjectId, kind: &'static str, mut payload: Document) {
+    let Ok(lecture_oid) = lecture.get_object_id("_id") else { return };
+    let speaker = lecture.get_str("speaker_id").ok().and_then(|s| ObjectId::parse_str(s).ok());
+    payload.insert("lecture_id", lecture_oid.to_hex());
+    let client = client.clone();
+    tokio::spawn(async move {
+        let mut users: Vec<ObjectId> = Vec::new();
+        for coll in [la_collection(&client), waitlist_collection(&client)] {
+            match coll.distinct("audience_id", doc! { "lecture_id": lecture_oid }, None).await {
+                Ok(values) => users.extend(values.iter().filter_map(|v| v.as_object_id())),
+                Err(e) => println!("[{}] 查询报名者失败 {}: {}", kind, lecture_oid.to_hex(), e),
+            }
+        }
+        users.extend(speaker);
+        users.sort();
+        users.dedup();
+        users.retain(|u| *u != actor);
+
+        for user_id in users {
+            if let Err(e) = notify::push(&client, user_id, kind, payload.clone()).await {
+                println!("[{}] 通知 {} 失败: {}", kind, user_id.to_hex(), e);
+            }
+        }
+    });
+}
+
+// =============== 日历导出 ===============
+// GET /lecture/:id/ics -> 单场演讲的 iCalendar 文件
+async fn get_lecture_ics(
+    State(client): State<AppState>,
+    Path(lecture_id): Path<String>,
+) -> Result<Response, AppError> {
+    let lecture = load_lecture(&client, &lecture_id).await?;
+    let topic = lecture.get_str("topic").unwrap_or("").to_string();
+    Ok(ics::response(ics::calendar(&topic, &[lecture]), &format!("lecture-{}.ics", lecture_id)))
+}
+
+// =============== 单场角色授予 ===============
+// GET /lecture/:id/roles -> 本场已授予的角色（组织者或讲者可查看）
+async fn list_lecture_roles(
+    State(client): State<AppState>,
+    auth: AuthUser,
+    Path(lecture_id): Path<String>,
+) -> Result<RespJson<Vec<serde_json::Value>>, AppError> {
+    let lecture = load_lecture(&client, &lecture_id).await?;
+    if !is_host(&lecture, &auth.id_hex()) {
+        return Err(AppError::Forbidden("只有组织者或讲者可以查看角色".into()));
+    }
+    let oid = lecture.get_object_id("_id").map_err(|_| AppError::Internal("演讲ID无效".into()))?;
+    let grants: Vec<Document> = lecture_role_collection(&client)
+        .find(doc! { "lecture_id": oid }, None)
+        .await?
+        .try_collect()
+        .await?;
+    let user_ids: Vec<ObjectId> = grants.iter().filter_map(|g| g.get_object_id("user_id").ok()).collect();
+    let names: std::collections::HashMap<ObjectId, String> = user_collection(&client)
+        .find(doc! { "_id": { "$in": &user_ids } }, None)
+        .await?
+        .try_collect::<Vec<Document>>()
+        .await?
+        .into_iter()
+        .filter_map(|u| Some((u.get_object_id("_id").ok()?, u.get_str("username").unwrap_or("").to_string())))
+        .collect();
+    Ok(RespJson(
+        grants
+            .iter()
+            .map(|g| {
+                let user_id = g.get_object_id("user_id").ok();
+                serde_json::json!({
+                    "user_id": user_id.map(|u| u.to_hex()),
+                    "username": user_id.and_then(|u| names.get(&u).cloned()).unwrap_or_default(),
+                    "role": g.get_str("role").unwrap_or(""),
+                    "granted_at": g.get_i64("granted_at").ok(),
+                })
+            })
+            .collect(),
+    ))
+}
+
+// POST /lecture/:id/roles {user_id, role} -> 组织者为本场演讲授予角色，重复授予幂等
+async fn grant_lecture_role(
+    State(client): State<AppState>,
+    auth: RequireRole<Organizer>,
+    Path(lecture_id): Path<String>,
+    Json(payload): Json<RoleGrant>,
+) -> Result<RespJson<serde_json::Value>, AppError> {
+    let (oid, _) = load_own_lecture(&client, &lecture_id, &auth).await?;
+    let role = LectureRole::parse(&payload.role)?;
+    let user_oid = ids::parse_oid(&payload.user_id, "user_id")?;
+    user_collection(&client)
+        .find_one(doc! { "_id": user_oid }, None)
+        .await?
+        .ok_or(AppError::NotFound("User not found".into()))?;
+    lecture_role_collection(&client)
+        .update_one(
+            doc! { "lecture_id": oid, "user_id": user_oid, "role": role.as_str() },
+            doc! { "$setOnInsert": { "granted_by": auth.id, "granted_at": chrono::Utc::now().timestamp_millis() } },
+            mongodb::options::UpdateOptions::builder().upsert(true).build(),
+        )
+        .await?;
+    audit::record(
+        &client,
+        Some(auth.id),
+        "lecture_role.grant",
+        &format!("lecture:{}", lecture_id),
+        doc! { "user_id": user_oid, "role": role.as_str() },
+    )
+    .await;
+    Ok(RespJson(serde_json::json!({
+        "lecture_id": lecture_id,
+        "user_id": payload.user_id,
+        "role": role.as_str(),
+    })))
+}
+
+// DELETE /lecture/:id/roles/:user_id/:role -> 撤销角色
+async fn revoke_lecture_role(
+    State(client): State<AppState>,
+    auth: RequireRole<Organizer>,
+    Path((lecture_id, user_id, role)): Path<(String, String, String)>,
+) -> Result<RespJson<serde_json::Value>, AppError> {
+    let (oid, _) = load_own_lecture(&client, &lecture_id, &auth).await?;
+    let role = LectureRole::parse(&role)?;
+    let user_oid = ids::parse_oid(&user_id, "user_id")?;
+    let result = lecture_role_collection(&client)
+        .delete_one(doc! { "lecture_id": oid, "user_id": user_oid, "role": role.as_str() }, None)
+        .await?;
+    if result.deleted_count == 0 {
+        return Err(AppError::NotFound("该用户没有此角色".into()));
+    }
+    audit::record(
+        &client,
+        Some(auth.id),
+        "lecture_role.revoke",
+        &format!("lecture:{}", lecture_id),
+        doc! { "user_id": user_oid, "role": role.as_str() },
+    )
+    .await;
+    Ok(RespJson(serde_json::json!({ "message": "角色已撤销" })))
 }
 
 // =============== 删除：按 ID ===============
+// 在同一事务内删除演讲及其邀请、报名、反馈、讨论、课件、公告与 FAQ，任一步失败整体回滚。
+// 事务要求 MongoDB 以副本集或分片集群方式部署
+async fn delete_lecture_cascade(client: &AppState, oid: ObjectId) -> mongodb::error::Result<(u64, Document)> {
+    let mut session = client.start_session(None).await?;
+    session.start_transaction(None).await?;
+    let result = async {
+        let deleted = lecture_collection(client)
+            .delete_one_with_session(doc! { "_id": oid }, None, &mut session)
+            .await?
+            .deleted_count;
+        let mut removed = doc! {};
+        if deleted == 0 {
+            return Ok((0, removed));
+        }
+        let filter = doc! { "lecture_id": oid };
+        let dependents = [
+            ("invitations", invitation_collection(client)),
+            ("registrations", la_collection(client)),
+            ("feedback", feedback_collection(client)),
+            ("discussions", discussion_collection(client)),
+            ("materials", material_collection(client)),
+            ("announcements", announcement_collection(client)),
+            ("faq", faq_collection(client)),
+            ("roles", lecture_role_collection(client)),
+            ("waitlist", waitlist_collection(client)),
+            ("notes", lecture_note_collection(client)),
+            ("transcript", transcript_collection(client)),
+            ("reactions", reaction_collection(client)),
+        ];
+        for (name, coll) in dependents {
+            let n = coll.delete_many_with_session(filter.clone(), None, &mut session).await?.deleted_count;
+            removed.insert(name, n as i64);
+        }
+        Ok((deleted, removed))
+    }
+    .await;
+    match result {
+        Ok(r) => {
+            session.commit_transaction().await?;
+            Ok(r)
+        }
+        Err(e) => {
+            let _ = session.abort_transaction().await;
+            Err(e)
+        }
+    }
+}
+
 async fn delete_lecture(
     State(client): State<AppState>,
+    auth: RequireRole<Organizer>,
     Path(lecture_id): Path<String>,
-) -> Result<String, (StatusCode, String)> {
-    let coll = lecture_collection(&client);
-    let oid = ObjectId::parse_str(&lecture_id)
-        .map_err(|_| (StatusCode::BAD_REQUEST, "无效的 lecture_id".into()))?;
-    let result = coll
-        .delete_one(doc! { "_id": oid }, None)
-        .await
-        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "删除失败".into()))?;
-    if result.deleted_count == 0 { return Err((StatusCode::NOT_FOUND, "Lecture not found".into())); }
-    Ok(format!("Lecture with ID {} has been deleted", lecture_id))
+) -> Result<RespJson<serde_json::Value>, AppError> {
+    let (oid, _) = load_own_lecture(&client, &lecture_id, &auth).await?;
+    // 课件文件在事务提交后再删，事务回滚时文件仍在
+    let stored_names: Vec<String> = material_collection(&client)
+        .find(doc! { "lecture_id": oid }, None)
+        .await?
+        .try_collect::<Vec<Document>>()
+        .await?
+        .iter()
+        .filter_map(|m| m.get_str("stored_name").ok().map(str::to_string))
+        .collect();
+
+    let (deleted, removed) = delete_lecture_cascade(&client, oid).await?;
+    if deleted == 0 { return Err(AppError::NotFound("Lecture not found".into())); }
+    audit::record(&client, Some(auth.id), "lecture.delete", &format!("lecture:{}", lecture_id), removed.clone()).await;
+    material::remove_files(&stored_names).await;
+
+    Ok(RespJson(serde_json::json!({
+        "message": format!("Lecture with ID {} has been deleted", lecture_id),
+        "removed": removed,
+    })))
 }
 
 // =============== 详情：按 lecturecode ===============
 async fn get_by_code(
     State(client): State<AppState>,
-    Path(code): Path<i32>,
-) -> Result<RespJson<serde_json::Value>, (StatusCode, String)> {
+    Path(code): Path<String>,
+    headers: HeaderMap,
+) -> Result<RespJson<serde_json::Value>, AppError> {
     let coll = lecture_collection(&client);
+    let code = lecturecode::normalize(&code)
+        .ok_or(AppError::BadRequest("演讲码格式无效".into()))?;
     let doc = coll
-        .find_one(doc! { "lecturecode": code }, None)
+        .find_one(lecturecode::lookup_filter(&code), None)
         .await
-        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "查询失败".into()))?
-        .ok_or((StatusCode::NOT_FOUND, "Lecture not found".into()))?;
-    let mut v: serde_json::Value = bson::from_document(doc)
-        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "序列化错误".into()))?;
-    if let Some(obj) = v.as_object_mut() {
-        // let id_hex = obj
-        //     .remove("_id")
-        //     .and_then(|oid| match oid { serde_json::Value::String(s) => Some(s), other => Some(other.to_string()) })
-        //     .unwrap_or_default();
-        // obj.insert("id".to_string(), serde_json::Value::String(id_hex));
-
-        // let id = match obj.get("_id") {
-        //     Some(serde_json::Value::String(s)) => s.clone(),
-        //     Some(other_value) => other_value.to_string(),
-        //     None => "error".to_string().clone(), // 如果没有 _id，使用传入的 user_id
-        // };
-        // obj.insert("id".to_string(), serde_json::Value::String(id));
-        // obj.remove("_id");
-        let id = match obj.get("_id") {
-            Some(serde_json::Value::String(s)) => s.clone(),
-            Some(serde_json::Value::Object(map)) => {
-                // 处理 MongoDB 扩展 JSON 格式: {"$oid": "xxx"}
-                if let Some(serde_json::Value::String(oid_str)) = map.get("$oid") {
-                    oid_str.clone()
-                } else {
-                    "error".to_string()
-                }
-            }
-            Some(other) => other.to_string(),
-            None => "error".to_string(),
-        };
-        
-        obj.insert("id".to_string(), serde_json::Value::String(id));
-        obj.remove("_id");
-    }
+        .map_err(|_| AppError::Internal("查询失败".into()))?
+        .ok_or(AppError::NotFound("Lecture not found".into()))?;
+    embed::ensure_origin_allowed(&embed::embed_origins_for(&client, &doc).await?, &headers)?;
+    let mut v = ids::doc_to_json(doc);
+    attach_branding(&client, &mut v).await;
+    attach_faq(&client, &mut v).await;
     Ok(RespJson(v))
 }
 
@@ -386,46 +1457,320 @@ async fn get_by_code(
 async fn get_by_speaker(
     State(client): State<AppState>,
     Path(speaker_id): Path<String>,
-) -> Result<RespJson<Vec<serde_json::Value>>, (StatusCode, String)> {
+    Query(query): Query<ListQuery>,
+    paging: PageParams,
+) -> Result<Response, AppError> {
     let coll = lecture_collection(&client);
-    let filter = doc! { "speaker_id": &speaker_id };
+    let mut filter = doc! { "speaker_id": &speaker_id };
+    archive_filter(&mut filter, &query);
+    let total = coll
+        .count_documents(filter.clone(), None)
+        .await
+        .map_err(|_| AppError::Internal("查询失败".into()))?;
     let mut cursor = coll
-        .find(filter, None)
+        .find(filter, paging.find_options(doc! { "_id": 1 }))
         .await
-        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "查询失败".into()))?;
+        .map_err(|_| AppError::Internal("查询失败".into()))?;
 
     let mut items = Vec::new();
     while let Some(doc) = cursor.try_next().await
-        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "读取失败".into()))?
+        .map_err(|_| AppError::Internal("读取失败".into()))?
     {
-        let id_hex = doc.get_object_id("_id")
-            .map(|o| o.to_hex())
-            .unwrap_or_default();
-        let mut v: serde_json::Value = bson::from_document(doc)
-            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "序列化错误".into()))?;
-        if let Some(obj) = v.as_object_mut() {
-            obj.remove("_id");
-            obj.insert("id".to_string(), serde_json::Value::String(id_hex));
-        }
-        items.push(v);
+        items.push(ids::doc_to_json(doc));
     }
 
-    Ok(RespJson(items))
+    Ok(paging.respond(items, total))
 }
 
 
+// =============== 跨组织联合发布：发起申请 ===============
+async fn request_colisting(
+    State(client): State<AppState>,
+    auth: RequireRole<Organizer>,
+    Path(lecture_id): Path<String>,
+    Json(payload): Json<CoListRequest>,
+) -> Result<RespJson<serde_json::Value>, AppError> {
+    let coll = lecture_collection(&client);
+    let oid = ObjectId::parse_str(&lecture_id)
+        .map_err(|_| AppError::BadRequest("无效的 lecture_id".into()))?;
+    let org_oid = ObjectId::parse_str(&payload.org_id)
+        .map_err(|_| AppError::BadRequest("org_id 无效".into()))?;
+    let org_hex = org_oid.to_hex();
+
+    organization_collection(&client)
+        .find_one(doc! { "_id": org_oid }, None)
+        .await
+        .map_err(|_| AppError::Internal("查询失败".into()))?
+        .ok_or(AppError::NotFound("Organization not found".into()))?;
+
+    // 只有演讲的组织者可以替演讲申请联合展示
+    let lecture = ensure_lecture_organizer(&client, oid, &auth).await?;
+    if lecture.get_str("org_id").ok() == Some(org_hex.as_str()) {
+        return Err(AppError::BadRequest("演讲已属于该组织".into()));
+    }
+
+    // 同一组织只保留一条申请记录；已被拒绝的可重新申请
+    coll.update_one(
+        doc! { "_id": oid },
+        doc! { "$pull": { "co_listings": { "org_id": &org_hex, "status": "rejected" } } },
+        None,
+    )
+    .await
+    .map_err(|_| AppError::Internal("更新失败".into()))?;
+    let result = coll
+        .update_one(
+            doc! { "_id": oid, "co_listings.org_id": { "$ne": &org_hex } },
+            doc! { "$push": { "co_listings": {
+                "org_id": &org_hex,
+                "status": "pending",
+                "requested_at": chrono::Utc::now().timestamp_millis(),
+            } } },
+            None,
+        )
+        .await
+        .map_err(|_| AppError::Internal("更新失败".into()))?;
+    if result.modified_count == 0 {
+        return Err(AppError::Conflict("已向该组织提交过联合发布申请".into()));
+    }
+
+    Ok(RespJson(serde_json::json!({
+        "message": "联合发布申请已提交，等待对方组织审批",
+        "lecture_id": lecture_id,
+        "org_id": org_hex,
+        "status": "pending",
+    })))
+}
+
+// =============== 取消归档 ===============
+async fn unarchive_lecture(
+    State(client): State<AppState>,
+    auth: RequireRole<Organizer>,
+    Path(lecture_id): Path<String>,
+) -> Result<RespJson<serde_json::Value>, AppError> {
+    let coll = lecture_collection(&client);
+    let (oid, _) = load_own_lecture(&client, &lecture_id, &auth).await?;
+    // archive_exempt 防止定时任务再次将其归档
+    let result = coll
+        .update_one(
+            doc! { "_id": oid },
+            doc! {
+                "$set": { "archived": false, "archive_exempt": true, "unarchived_at": chrono::Utc::now().timestamp_millis() },
+                "$unset": { "archived_at": "" },
+            },
+            None,
+        )
+        .await
+        .map_err(|_| AppError::Internal("更新失败".into()))?;
+    if result.matched_count == 0 { return Err(AppError::NotFound("Lecture not found".into())); }
+    Ok(RespJson(serde_json::json!({ "message": "演讲已取消归档", "id": lecture_id })))
+}
 
 // ==================== Router ====================
 
 
+// POST /lecture/:lecture_id/announce -> 向所有报名听众群发通知（站内信，可选邮件），记录投递结果
+async fn announce(
+    State(client): State<AppState>,
+    auth: RequireRole<Organizer>,
+    Path(lecture_id): Path<String>,
+    Json(payload): Json<AnnounceRequest>,
+) -> Result<RespJson<serde_json::Value>, AppError> {
+    let (oid, lecture) = load_own_lecture(&client, &lecture_id, &auth).await?;
+    let message = payload.message.trim().to_string();
+    if message.is_empty() || message.chars().count() > ANNOUNCE_MAX_LEN {
+        return Err(AppError::BadRequest(format!("消息内容需为 1~{} 字", ANNOUNCE_MAX_LEN)));
+    }
+    let subject = payload
+        .subject
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| format!("演讲通知：{}", lecture.get_str("topic").unwrap_or("")));
+
+    let audience_ids: Vec<ObjectId> = la_collection(&client)
+        .find(doc! { "lecture_id": oid }, None)
+        .await
+        .map_err(|_| AppError::Internal("查询失败".into()))?
+        .try_collect::<Vec<Document>>()
+        .await
+        .map_err(|_| AppError::Internal("读取失败".into()))?
+        .iter()
+        .filter_map(|r| r.get_object_id("audience_id").ok())
+        .collect();
+    let users: Vec<Document> = user_collection(&client)
+        .find(doc! { "_id": { "$in": &audience_ids } }, None)
+        .await
+        .map_err(|_| AppError::Internal("查询失败".into()))?
+        .try_collect()
+        .await
+        .map_err(|_| AppError::Internal("读取失败".into()))?;
+
+    let ann_coll = announcement_collection(&client);
+    let announcement_id = ObjectId::new();
+    let mut deliveries = Vec::new();
+    let (mut in_app_sent, mut email_sent, mut email_failed) = (0, 0, 0);
+    for user in &users {
+        let Ok(user_oid) = user.get_object_id("_id") else { continue };
+        let payload_doc = doc! {
+            "announcement_id": announcement_id.to_hex(),
+            "lecture_id": &lecture_id,
+            "subject": &subject,
+            "message": &message,
+        };
+        let in_app = notify::push(&client, user_oid, "lecture_announcement", payload_doc).await.is_ok();
+        if in_app {
+            in_app_sent += 1;
+        }
+        let email_status = if !payload.email {
+            "skipped".to_string()
+        } else {
+            // 邮件正文附上按收件人时区与语言展示的演讲时间
+            let start = UserTime::for_user(user).datetime(lecture.get_i64("start_time").unwrap_or(0));
+            let body = format!("{}\n\n演讲时间：{}", message, start);
+            match MAILER.send(user.get_str("email").unwrap_or(""), &subject, &body).await {
+                Ok(()) => {
+                    email_sent += 1;
+                    "sent".to_string()
+                }
+                Err(e) => {
+                    email_failed += 1;
+                    format!("failed: {}", e)
+                }
+            }
+        };
+        deliveries.push(doc! { "user_id": user_oid, "in_app": in_app, "email": email_status });
+    }
+
+    let now = chrono::Utc::now().timestamp_millis();
+    ann_coll
+        .insert_one(
+            doc! {
+                "_id": announcement_id,
+                "lecture_id": oid,
+                "sender_id": auth.id,
+                "subject": &subject,
+                "message": &message,
+                "email": payload.email,
+                "mailer": MAILER.name(),
+                "recipients": users.len() as i32,
+                "in_app_sent": in_app_sent,
+                "email_sent": email_sent,
+                "email_failed": email_failed,
+                "deliveries": deliveries,
+                "created_at": now,
+            },
+            None,
+        )
+        .await
+        .map_err(|_| AppError::Internal("保存通知记录失败".into()))?;
+
+    Ok(RespJson(serde_json::json!({
+        "announcement_id": announcement_id.to_hex(),
+        "lecture_id": lecture_id,
+        "recipients": users.len(),
+        "in_app_sent": in_app_sent,
+        "email_sent": email_sent,
+        "email_failed": email_failed,
+        "created_at": now,
+    })))
+}
+
+// GET /lecture/:lecture_id/announcements -> 该演讲的群发历史（新到旧）
+async fn list_announcements(
+    State(client): State<AppState>,
+    auth: AuthUser,
+    Path(lecture_id): Path<String>,
+) -> Result<RespJson<Vec<serde_json::Value>>, AppError> {
+    let (oid, _) = load_own_lecture(&client, &lecture_id, &auth).await?;
+    let options = mongodb::options::FindOptions::builder().sort(doc! { "created_at": -1 }).build();
+    let docs: Vec<Document> = announcement_collection(&client)
+        .find(doc! { "lecture_id": oid }, options)
+        .await
+        .map_err(|_| AppError::Internal("查询失败".into()))?
+        .try_collect()
+        .await
+        .map_err(|_| AppError::Internal("读取失败".into()))?;
+    let items = docs
+        .into_iter()
+        .map(|d| {
+            let deliveries: Vec<serde_json::Value> = d
+                .get_array("deliveries")
+                .map(|arr| {
+                    arr.iter()
+                        .filter_map(|b| b.as_document())
+                        .map(|x| serde_json::json!({
+                            "user_id": ids::oid_hex(x, "user_id"),
+                            "in_app": x.get_bool("in_app").unwrap_or(false),
+                            "email": x.get_str("email").unwrap_or(""),
+                        }))
+                        .collect()
+                })
+                .unwrap_or_default();
+            serde_json::json!({
+                "id": ids::oid_hex(&d, "_id"),
+                "subject": d.get_str("subject").unwrap_or(""),
+                "message": d.get_str("message").unwrap_or(""),
+                "email": d.get_bool("email").unwrap_or(false),
+                "recipients": d.get_i32("recipients").unwrap_or(0),
+                "in_app_sent": d.get_i32("in_app_sent").unwrap_or(0),
+                "email_sent": d.get_i32("email_sent").unwrap_or(0),
+                "email_failed": d.get_i32("email_failed").unwrap_or(0),
+                "created_at": d.get_i64("created_at").unwrap_or(0),
+                "deliveries": deliveries,
+            })
+        })
+        .collect();
+    Ok(RespJson(items))
+}
+
 pub fn router() -> Router<AppState> {
     Router::new()
         .route("/create", post(create_lecture))
         .route("/by_organizer/:organizer_id", get(list_by_organizer))
         .route("/", get(list_all))
+        .route("/tags", get(list_tags))
+        .route("/by_tag/:tag", get(list_by_tag))
         .route("/:lecture_id", get(get_lecture))
         .route("/:lecture_id", axum::routing::put(update_lecture))
         .route("/:lecture_id", axum::routing::delete(delete_lecture))
         .route("/by_code/:code", get(get_by_code))
         .route("/by_speaker/:speaker_id", get(get_by_speaker))
-}
\ No newline at end of file
+        .route("/:lecture_id/colist", post(request_colisting))
+        .route("/:lecture_id/unarchive", post(unarchive_lecture))
+        .route("/:lecture_id/announce", post(announce))
+        .route("/:lecture_id/announcements", get(list_announcements))
+        .route("/:lecture_id/rehearsal", post(set_rehearsal))
+        .route("/:lecture_id/start", post(start_lecture))
+        .route("/:lecture_id/end", post(end_lecture))
+        .route("/:lecture_id/cancel", post(cancel_lecture))
+        .route("/:lecture_id/reschedule", post(reschedule_lecture))
+        .route("/:lecture_id/ics", get(get_lecture_ics))
+        .route("/:lecture_id/roles", get(list_lecture_roles).post(grant_lecture_role))
+        .route("/:lecture_id/roles/:user_id/:role", axum::routing::delete(revoke_lecture_role))
+        .merge(crate::routes::faq::router())
+        .merge(crate::routes::notes::router())
+        .merge(crate::routes::transcript::router())
+        .merge(crate::routes::waiting_room::router())
+        .merge(crate::routes::live::router())
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identical_topics_ignore_case_and_punctuation() {
+        assert_eq!(topic_similarity("Rust 入门", "rust入门！"), 1.0);
+    }
+
+    #[test]
+    fn near_duplicates_reach_the_threshold() {
+        assert!(topic_similarity("Intro to Rust async", "Intro to Rust async (part 1)") >= DUPLICATE_TOPIC_SIMILARITY);
+        assert!(topic_similarity("Rust 异步编程", "Go 并发模型") < DUPLICATE_TOPIC_SIMILARITY);
+    }
+
+    #[test]
+    fn empty_or_symbol_only_topics_never_match() {
+        assert_eq!(topic_similarity("", "Rust"), 0.0);
+        assert_eq!(topic_similarity("!!!", "!!!"), 0.0);
+        assert_eq!(topic_similarity("R", "R"), 1.0);
+    }
+}
@@ -0,0 +1,82 @@
+// src/routes/live.rs
+// 讲者/组织者演讲中控制面板：一次请求汇总在线人数、反馈近况与待回答提问，面板每隔几秒轮询。
+// 各部分并发查询；since 传上次响应的 generated_at 即可得到两次刷新之间新增的反馈
+use axum::{
+    extract::{Path, Query, State},
+    http::header,
+    response::{IntoResponse, Json, Response},
+    routing::get,
+    Router,
+};
+use chrono::Utc;
+use mongodb::Client;
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::auth::AuthUser;
+use crate::error::AppError;
+use crate::lifecycle::LectureStatus;
+use crate::realtime;
+use crate::routes::lecture::{is_host, load_lecture};
+use crate::routes::{discussion, feedback};
+
+type AppState = Arc<Client>;
+
+// 未指定 since 时统计最近 5 分钟，最多回看 1 小时
+const DEFAULT_WINDOW_MS: i64 = 5 * 60 * 1000;
+const MAX_WINDOW_MS: i64 = 60 * 60 * 1000;
+const QUESTION_LIMIT: i64 = 10;
+
+#[derive(Deserialize, Default)]
+struct OverviewQuery {
+    since: Option<i64>,
+}
+
+// GET /lecture/:lecture_id/live_overview?since= -> 控制面板汇总（组织者或讲者）
+async fn live_overview(
+    State(client): State<AppState>,
+    auth: AuthUser,
+    Path(lecture_id): Path<String>,
+    Query(query): Query<OverviewQuery>,
+) -> Result<Response, AppError> {
+    let lecture = load_lecture(&client, &lecture_id).await?;
+    if !is_host(&lecture, &auth.id_hex()) {
+        return Err(AppError::Forbidden("只有组织者或讲者可以查看控制面板".into()));
+    }
+    let lecture_oid = lecture.get_object_id("_id").map_err(|_| AppError::Internal("演讲ID无效".into()))?;
+    let now = Utc::now().timestamp_millis();
+    let since = query.since.unwrap_or(now - DEFAULT_WINDOW_MS).clamp(now - MAX_WINDOW_MS, now);
+
+    let (summary, recent, (unanswered, questions)) = tokio::try_join!(
+        feedback::summary_stats(&client, lecture_oid),
+        feedback::counts_since(&client, lecture_oid, since),
+        discussion::newest_unanswered(&client, lecture_oid, auth.id, QUESTION_LIMIT),
+    )?;
+
+    let status = LectureStatus::of(&lecture);
+    let started_at = lecture.get_i64("started_at").ok();
+    let mut resp = Json(serde_json::json!({
+        "lecture_id": lecture_id,
+        "status": status.name(),
+        "started_at": started_at,
+        "elapsed_seconds": started_at.filter(|_| status == LectureStatus::Live).map(|t| (now - t).max(0) / 1000),
+        "online": realtime::online_count(lecture_oid),
+        "feedback": {
+            "summary": summary,
+            "since": since,
+            "recent": recent,
+        },
+        "questions": {
+            "unanswered": unanswered,
+            "newest": questions,
+        },
+        "generated_at": now,
+    }))
+    .into_response();
+    resp.headers_mut().insert(header::CACHE_CONTROL, header::HeaderValue::from_static("no-store"));
+    Ok(resp)
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/:lecture_id/live_overview", get(live_overview))
+}
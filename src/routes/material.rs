@@ -0,0 +1,479 @@
+// src/routes/material.rs
+use axum::{
+    body::Body,
+    extract::{DefaultBodyLimit, Multipart, Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Json, Response},
+    routing::{get, post},
+    Router,
+};
+use bson::{doc, oid::ObjectId, Document};
+use chrono::Utc;
+use futures_util::TryStreamExt;
+use mongodb::Client;
+use serde::Deserialize;
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tokio_util::io::ReaderStream;
+use uuid::Uuid;
+
+use crate::ids;
+use crate::auth::{AnyRole, Host, RequireRole};
+use crate::db::{la_collection, lecture_collection, material_collection};
+use crate::{retry, signing};
+use crate::error::AppError;
+use crate::routes::lecture::is_host;
+
+type AppState = Arc<Client>;
+
+// 签名链接有效期（秒）
+const LINK_TTL_SECS: i64 = 300;
+// 录像文件较大，上传上限单独放宽
+const MAX_UPLOAD_BYTES: usize = 1024 * 1024 * 1024;
+
+// 课件存放在 static 之外（config.material_dir），只能经由本模块的下载接口访问
+fn material_dir() -> &'static str {
+    &crate::config::get().material_dir
+}
+
+// 演讲删除后清理课件文件；文件缺失不影响删除结果
+pub async fn remove_files(stored_names: &[String]) {
+    for name in stored_names {
+        if let Err(e) = tokio::fs::remove_file(format!("{}/{}", material_dir(), name)).await {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                eprintln!("删除课件文件 {} 失败: {}", name, e);
+            }
+        }
+    }
+}
+
+// ==================== 模型 ====================
+
+#[derive(Deserialize)]
+struct DownloadQuery {
+    uid: Option<String>,
+    expires: Option<i64>,
+    sig: Option<String>,
+    // inline=true 时浏览器内直接播放/预览，否则作为附件下载
+    inline: Option<bool>,
+}
+
+// ==================== 工具函数 ====================
+
+fn content_type_for(filename: &str) -> &'static str {
+    let ext = std::path::Path::new(filename)
+        .extension()
+        .and_then(|s| s.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default();
+    match ext.as_str() {
+        "pdf" => "application/pdf",
+        "ppt" => "application/vnd.ms-powerpoint",
+        "pptx" => "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+        "doc" => "application/msword",
+        "docx" => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+        "xls" => "application/vnd.ms-excel",
+        "xlsx" => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        "zip" => "application/zip",
+        "txt" | "md" => "text/plain; charset=utf-8",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "mp4" | "m4v" => "video/mp4",
+        "webm" => "video/webm",
+        "mov" => "video/quicktime",
+        "mp3" => "audio/mpeg",
+        "m4a" => "audio/mp4",
+        "wav" => "audio/wav",
+        _ => "application/octet-stream",
+    }
+}
+
+// RFC 6266：ASCII 回退名 + UTF-8 编码的 filename*，兼容中文文件名
+fn content_disposition(filename: &str, inline: bool) -> String {
+    let fallback: String = filename
+        .chars()
+        .map(|c| if c == ' ' || (c.is_ascii_graphic() && c != '"' && c != '\\') { c } else { '_' })
+        .collect();
+    format!(
+        "{}; filename=\"{}\"; filename*=UTF-8''{}",
+        if inline { "inline" } else { "attachment" },
+        fallback,
+        utf8_percent_encode(filename, NON_ALPHANUMERIC)
+    )
+}
+
+// 解析单段 Range 请求头，返回闭区间 [start, end]；格式错误或不可满足时返回 Err
+fn parse_range(value: &str, size: u64) -> Result<(u64, u64), ()> {
+    let spec = value.trim().strip_prefix("bytes=").ok_or(())?;
+    if spec.contains(',') || size == 0 {
+        return Err(());
+    }
+    let (start, end) = spec.split_once('-').ok_or(())?;
+    let (start, end) = match (start.trim(), end.trim()) {
+        ("", suffix) => {
+            let len: u64 = suffix.parse().map_err(|_| ())?;
+            if len == 0 {
+                return Err(());
+            }
+            (size.saturating_sub(len), size - 1)
+        }
+        (s, "") => (s.parse().map_err(|_| ())?, size - 1),
+        (s, e) => (s.parse().map_err(|_| ())?, e.parse::<u64>().map_err(|_| ())?.min(size - 1)),
+    };
+    if start > end || start >= size {
+        return Err(());
+    }
+    Ok((start, end))
+}
+
+fn signed_payload(material_id: &str, uid: &str, expires: i64) -> String {
+    format!("{}:{}:{}", material_id, uid, expires)
+}
+
+// 先校验签名再看有效期，篡改过 expires 的链接按签名无效处理
+fn verify_signed_link(material_id: &str, uid: &str, expires: i64, sig: &str, now: i64) -> Result<(), AppError> {
+    if !signing::verify(&signed_payload(material_id, uid, expires), sig) {
+        return Err(AppError::Forbidden("签名无效".into()));
+    }
+    if expires < now {
+        return Err(AppError::Gone("下载链接已过期".into()));
+    }
+    Ok(())
+}
+
+async fn find_material(client: &AppState, material_id: &str) -> Result<Document, AppError> {
+    let oid = ObjectId::parse_str(material_id)
+        .map_err(|_| AppError::BadRequest("无效的 material_id".into()))?;
+    material_collection(client)
+        .find_one(doc! { "_id": oid }, None)
+        .await
+        .map_err(|_| AppError::Internal("查询失败".into()))?
+        .ok_or(AppError::NotFound("Material not found".into()))
+}
+
+// 组织者、讲者或已报名该演讲的听众可以访问私有课件
+async fn is_registered(client: &AppState, lecture_oid: ObjectId, user_id: &str) -> Result<bool, AppError> {
+    let lecture = lecture_collection(client)
+        .find_one(doc! { "_id": lecture_oid }, None)
+        .await
+        .map_err(|_| AppError::Internal("查询失败".into()))?
+        .ok_or(AppError::NotFound("Lecture not found".into()))?;
+    if lecture.get_str("organizer_id").ok() == Some(user_id)
+        || lecture.get_str("speaker_id").ok() == Some(user_id)
+    {
+        return Ok(true);
+    }
+    let Ok(user_oid) = ObjectId::parse_str(user_id) else { return Ok(false) };
+    let la = la_collection(client)
+        .find_one(doc! { "lecture_id": lecture_oid, "audience_id": user_oid }, None)
+        .await
+        .map_err(|_| AppError::Internal("查询失败".into()))?;
+    Ok(la.is_some())
+}
+
+fn material_to_json(doc: &Document) -> serde_json::Value {
+    let id = ids::oid_hex(doc, "_id");
+    let private = doc.get_bool("private").unwrap_or(true);
+    serde_json::json!({
+        "id": &id,
+        "lecture_id": ids::oid_hex(doc, "lecture_id"),
+        "title": doc.get_str("title").unwrap_or(""),
+        "filename": doc.get_str("filename").unwrap_or(""),
+        "kind": doc.get_str("kind").unwrap_or("material"),
+        "content_type": doc.get_str("content_type").unwrap_or("application/octet-stream"),
+        "size": doc.get_i64("size").unwrap_or(0),
+        "private": private,
+        "uploaded_at": doc.get_i64("uploaded_at").unwrap_or(0),
+        // 公开课件可直接下载；私有课件需先通过 /link 获取签名链接
+        "download_url": if private { serde_json::Value::Null } else { format!("/material/{}/download", id).into() },
+    })
+}
+
+// ==================== 路由 ====================
+
+// POST /material/upload/:lecture_id  (multipart: file, title, private, kind)，仅演讲的组织者或讲者
+async fn upload_material(
+    State(client): State<AppState>,
+    auth: RequireRole<Host>,
+    Path(lecture_id): Path<String>,
+    mut multipart: Multipart,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let lecture_oid = ObjectId::parse_str(&lecture_id)
+        .map_err(|_| AppError::BadRequest("无效的 lecture_id".into()))?;
+    let lecture = lecture_collection(&client)
+        .find_one(doc! { "_id": lecture_oid }, None)
+        .await
+        .map_err(|_| AppError::Internal("查询失败".into()))?
+        .ok_or(AppError::NotFound("Lecture not found".into()))?;
+    if !is_host(&lecture, &auth.id_hex()) {
+        return Err(AppError::Forbidden("仅演讲的组织者或讲者可上传课件".into()));
+    }
+
+    let mut title = String::new();
+    let mut private = true;
+    let mut kind = "material".to_string();
+    let mut stored: Option<(String, String, i64)> = None;
+
+    while let Some(mut field) = multipart.next_field().await.unwrap_or(None) {
+        let name = field.name().unwrap_or("").to_string();
+        match name.as_str() {
+            "title" => title = field.text().await.unwrap_or_default().trim().to_string(),
+            "private" => {
+                let v = field.text().await.unwrap_or_default();
+                private = !matches!(v.trim(), "false" | "0");
+            }
+            "kind" => {
+                let v = field.text().await.unwrap_or_default();
+                kind = if v.trim() == "recording" { "recording".into() } else { "material".into() };
+            }
+            "file" => {
+                let filename = field.file_name().unwrap_or("unknown").to_string();
+                let ext = std::path::Path::new(&filename)
+                    .extension()
+                    .and_then(|s| s.to_str())
+                    .map(|e| format!(".{}", e.to_lowercase()))
+                    .unwrap_or_default();
+                let stored_name = format!("{}{}", Uuid::new_v4().simple(), ext);
+                // 录像可能很大，逐块写入磁盘而不是整体读入内存
+                let path = format!("{}/{}", material_dir(), stored_name);
+                let mut file = tokio::fs::File::create(&path)
+                    .await
+                    .map_err(|_| AppError::Internal("写入文件失败".into()))?;
+                let mut size = 0_i64;
+                loop {
+                    let chunk = match field.chunk().await {
+                        Ok(Some(chunk)) => chunk,
+                        Ok(None) => break,
+                        Err(_) => {
+                            let _ = tokio::fs::remove_file(&path).await;
+                            return Err(AppError::BadRequest("读取文件失败".into()));
+                        }
+                    };
+                    file.write_all(&chunk)
+                        .await
+                        .map_err(|_| AppError::Internal("写入文件失败".into()))?;
+                    size += chunk.len() as i64;
+                }
+                file.flush().await.map_err(|_| AppError::Internal("写入文件失败".into()))?;
+                stored = Some((filename, stored_name, size));
+            }
+            _ => {}
+        }
+    }
+
+    let (filename, stored_name, size) = stored.ok_or(AppError::BadRequest("缺少 file 字段".into()))?;
+    if title.is_empty() {
+        title = filename.clone();
+    }
+
+    let mut material_doc = doc! {
+        "lecture_id": lecture_oid,
+        "title": &title,
+        "filename": &filename,
+        "stored_name": &stored_name,
+        "kind": &kind,
+        "content_type": content_type_for(&filename),
+        "size": size,
+        "private": private,
+        "uploaded_at": Utc::now().timestamp_millis(),
+    };
+    retry::insert_one(&material_collection(&client), &mut material_doc)
+        .await
+        .map_err(|e| retry::db_error(e, "保存课件失败"))?;
+    Ok(Json(material_to_json(&material_doc)))
+}
+
+// GET /material/lecture/:lecture_id
+async fn list_materials(
+    State(client): State<AppState>,
+    Path(lecture_id): Path<String>,
+) -> Result<Json<Vec<serde_json::Value>>, AppError> {
+    let lecture_oid = ObjectId::parse_str(&lecture_id)
+        .map_err(|_| AppError::BadRequest("无效的 lecture_id".into()))?;
+    let mut cursor = material_collection(&client)
+        .find(doc! { "lecture_id": lecture_oid }, None)
+        .await
+        .map_err(|_| AppError::Internal("查询失败".into()))?;
+
+    let mut items = Vec::new();
+    while let Some(doc) = cursor
+        .try_next()
+        .await
+        .map_err(|_| AppError::Internal("读取失败".into()))?
+    {
+        items.push(material_to_json(&doc));
+    }
+    Ok(Json(items))
+}
+
+// GET /material/:material_id/link -> 为当前已报名用户签发短期下载链接
+async fn create_signed_link(
+    State(client): State<AppState>,
+    auth: RequireRole<AnyRole>,
+    Path(material_id): Path<String>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let material = find_material(&client, &material_id).await?;
+    let lecture_oid = material.get_object_id("lecture_id")
+        .map_err(|_| AppError::Internal("字段缺失".into()))?;
+    let uid = auth.id_hex();
+    if !is_registered(&client, lecture_oid, &uid).await? {
+        return Err(AppError::Forbidden("未报名该演讲，无法下载课件".into()));
+    }
+
+    let expires = Utc::now().timestamp() + LINK_TTL_SECS;
+    let sig = signing::sign(&signed_payload(&material_id, &uid, expires));
+    Ok(Json(serde_json::json!({
+        "url": format!("/material/{}/download?uid={}&expires={}&sig={}", material_id, uid, expires, sig),
+        "expires_at": expires,
+    })))
+}
+
+// GET /material/:material_id/download -> 私有课件需校验签名、有效期与报名状态
+// 支持 Range 请求，录像可拖动播放、大文件可断点续传
+async fn download_material(
+    State(client): State<AppState>,
+    Path(material_id): Path<String>,
+    Query(query): Query<DownloadQuery>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let material = find_material(&client, &material_id).await?;
+
+    if material.get_bool("private").unwrap_or(true) {
+        let (Some(uid), Some(expires), Some(sig)) = (query.uid, query.expires, query.sig) else {
+            return Err(AppError::Forbidden("私有课件需使用签名链接下载".into()));
+        };
+        verify_signed_link(&material_id, &uid, expires, &sig, Utc::now().timestamp())?;
+        let lecture_oid = material.get_object_id("lecture_id")
+            .map_err(|_| AppError::Internal("字段缺失".into()))?;
+        if !is_registered(&client, lecture_oid, &uid).await? {
+            return Err(AppError::Forbidden("未报名该演讲，无法下载课件".into()));
+        }
+    }
+
+    let stored_name = material.get_str("stored_name")
+        .map_err(|_| AppError::Internal("字段缺失".into()))?;
+    let filename = material.get_str("filename").unwrap_or(stored_name);
+    let content_type = material
+        .get_str("content_type")
+        .unwrap_or_else(|_| content_type_for(filename))
+        .to_string();
+    let disposition = content_disposition(filename, query.inline.unwrap_or(false));
+
+    let mut file = tokio::fs::File::open(format!("{}/{}", material_dir(), stored_name))
+        .await
+        .map_err(|_| AppError::NotFound("文件不存在".into()))?;
+    let size = file
+        .metadata()
+        .await
+        .map_err(|_| AppError::Internal("读取文件失败".into()))?
+        .len();
+
+    let range = headers.get(header::RANGE).and_then(|v| v.to_str().ok());
+    let Some(range) = range else {
+        return Ok((
+            [
+                (header::CONTENT_TYPE, content_type),
+                (header::CONTENT_DISPOSITION, disposition),
+                (header::ACCEPT_RANGES, "bytes".to_string()),
+                (header::CONTENT_LENGTH, size.to_string()),
+            ],
+            Body::from_stream(ReaderStream::new(file)),
+        )
+            .into_response());
+    };
+
+    let Ok((start, end)) = parse_range(range, size) else {
+        return Ok((
+            StatusCode::RANGE_NOT_SATISFIABLE,
+            [(header::CONTENT_RANGE, format!("bytes */{}", size))],
+        )
+            .into_response());
+    };
+
+    file.seek(std::io::SeekFrom::Start(start))
+        .await
+        .map_err(|_| AppError::Internal("读取文件失败".into()))?;
+    let length = end - start + 1;
+
+    Ok((
+        StatusCode::PARTIAL_CONTENT,
+        [
+            (header::CONTENT_TYPE, content_type),
+            (header::CONTENT_DISPOSITION, disposition),
+            (header::ACCEPT_RANGES, "bytes".to_string()),
+            (header::CONTENT_RANGE, format!("bytes {}-{}/{}", start, end, size)),
+            (header::CONTENT_LENGTH, length.to_string()),
+        ],
+        Body::from_stream(ReaderStream::new(file.take(length))),
+    )
+        .into_response())
+}
+
+// ==================== Router ====================
+
+pub fn router() -> Router<AppState> {
+    std::fs::create_dir_all(material_dir()).expect("无法创建课件目录");
+
+    Router::new()
+        .route(
+            "/upload/:lecture_id",
+            post(upload_material).layer(DefaultBodyLimit::max(MAX_UPLOAD_BYTES)),
+        )
+        .route("/lecture/:lecture_id", get(list_materials))
+        .route("/:material_id/link", get(create_signed_link))
+}
+
+// 下载凭签名链接鉴权（浏览器直接打开，无法携带 Bearer 令牌），不经过 require_auth
+pub fn download_router() -> Router<AppState> {
+    Router::new().route("/:material_id/download", get(download_material))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::StatusCode;
+
+    #[test]
+    fn parse_range_accepts_closed_open_and_suffix_ranges() {
+        assert_eq!(parse_range("bytes=0-99", 1000), Ok((0, 99)));
+        assert_eq!(parse_range("bytes=500-", 1000), Ok((500, 999)));
+        assert_eq!(parse_range("bytes=-100", 1000), Ok((900, 999)));
+        assert_eq!(parse_range(" bytes=10-20 ", 1000), Ok((10, 20)));
+    }
+
+    #[test]
+    fn parse_range_clamps_to_file_size() {
+        assert_eq!(parse_range("bytes=900-5000", 1000), Ok((900, 999)));
+        assert_eq!(parse_range("bytes=-5000", 1000), Ok((0, 999)));
+    }
+
+    #[test]
+    fn parse_range_rejects_unsatisfiable_or_malformed_ranges() {
+        for value in ["bytes=1000-", "bytes=20-10", "bytes=-0", "bytes=0-1,5-6", "items=0-1", "bytes=a-b", "bytes=5"] {
+            assert_eq!(parse_range(value, 1000), Err(()), "{}", value);
+        }
+        assert_eq!(parse_range("bytes=0-0", 0), Err(()));
+    }
+
+    #[test]
+    fn signed_link_verifies_until_expiry() {
+        let sig = signing::sign(&signed_payload("m1", "u1", 1_000));
+        assert!(verify_signed_link("m1", "u1", 1_000, &sig, 999).is_ok());
+        assert!(verify_signed_link("m1", "u1", 1_000, &sig, 1_000).is_ok());
+        let expired = verify_signed_link("m1", "u1", 1_000, &sig, 1_001).unwrap_err();
+        assert_eq!(expired.status(), StatusCode::GONE);
+    }
+
+    #[test]
+    fn signed_link_rejects_tampered_fields() {
+        let sig = signing::sign(&signed_payload("m1", "u1", 1_000));
+        for (material_id, uid, expires) in [("m2", "u1", 1_000), ("m1", "u2", 1_000), ("m1", "u1", 2_000)] {
+            let err = verify_signed_link(material_id, uid, expires, &sig, 0).unwrap_err();
+            assert_eq!(err.status(), StatusCode::FORBIDDEN);
+        }
+        let err = verify_signed_link("m1", "u1", 1_000, "not-hex", 0).unwrap_err();
+        assert_eq!(err.status(), StatusCode::FORBIDDEN);
+    }
+}
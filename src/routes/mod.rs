@@ -1,7 +1,27 @@
 pub mod invitation;
+pub mod invitation_template;
 pub mod lecture;
 pub mod discussion;
+pub mod embed;
+pub mod faq;
 pub mod la;
+pub mod live;
 pub mod feedback;
+pub mod admin;
+pub mod banner;
+pub mod organization;
+pub mod apikey;
+pub mod material;
+pub mod moderation;
+pub mod notes;
+pub mod notification;
+pub mod reaction;
+pub mod time;
+pub mod transcript;
 
 pub mod user;
+pub mod waiting_room;
+pub mod ws;
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,334 @@
+// src/routes/moderation.rs
+// 讨论区管理：置顶、隐藏消息，以及在演讲结束前禁言或静默禁言用户。
+// 操作者需具备本场讨论管理员权限（组织者、讲者天然具备）；隐藏的消息保留在库中，
+// 以 moderated_by 记录最近一次处理人，公开列表不再返回。
+// 静默禁言的用户仍可正常发言，但其消息标记 shadowed，只有本人看得到，也不做实时推送
+use axum::{
+    extract::{Path, State},
+    response::Json,
+    routing::{delete, get, post},
+    Router,
+};
+use bson::{doc, oid::ObjectId, Document};
+use chrono::Utc;
+use futures_util::TryStreamExt;
+use mongodb::{options::FindOptions, Client};
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::auth::{require_lecture_role, AuthUser, LectureRole};
+use crate::db::{discussion_collection, lecture_collection, user_collection};
+use crate::error::AppError;
+use crate::lifecycle::LectureStatus;
+use crate::routes::lecture::is_host;
+use crate::{audit, ids, realtime};
+
+type AppState = Arc<Client>;
+
+// ==================== 模型 ====================
+
+#[derive(Deserialize)]
+struct PinRequest {
+    pinned: bool,
+}
+
+#[derive(Deserialize)]
+struct HideRequest {
+    hidden: bool,
+    reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct MuteRequest {
+    user_id: String,
+    reason: Option<String>,
+}
+
+// ==================== 工具函数 ====================
+
+// 禁言名单存在演讲的 muted_users 上，静默禁言名单存在 shadow_muted_users 上
+#[derive(Clone, Copy)]
+enum MuteKind {
+    Mute,
+    Shadow,
+}
+
+impl MuteKind {
+    fn field(self) -> &'static str {
+        match self {
+            MuteKind::Mute => "muted_users",
+            MuteKind::Shadow => "shadow_muted_users",
+        }
+    }
+
+    fn action(self) -> &'static str {
+        match self {
+            MuteKind::Mute => "discussion.mute",
+            MuteKind::Shadow => "discussion.shadow_mute",
+        }
+    }
+
+    fn lift_action(self) -> &'static str {
+        match self {
+            MuteKind::Mute => "discussion.unmute",
+            MuteKind::Shadow => "discussion.shadow_unmute",
+        }
+    }
+}
+
+fn listed(lecture: &Document, kind: MuteKind, user: ObjectId) -> bool {
+    lecture
+        .get_array(kind.field())
+        .map(|a| a.iter().any(|m| m.as_document().and_then(|d| d.get_object_id("user_id").ok()) == Some(user)))
+        .unwrap_or(false)
+}
+
+// 禁言只在演讲结束前有效，结束（或取消）后自动失效
+pub fn is_muted(lecture: &Document, user: ObjectId) -> bool {
+    mute_active(lecture) && listed(lecture, MuteKind::Mute, user)
+}
+
+pub fn is_shadow_muted(lecture: &Document, user: ObjectId) -> bool {
+    mute_active(lecture) && listed(lecture, MuteKind::Shadow, user)
+}
+
+// 对 viewer 可见的讨论：未隐藏，且不是静默消息或本人发的静默消息；
+// viewer 为 None 时（公开摘要、SSE 等）静默消息一律不返回
+pub fn visible_filter(viewer: Option<ObjectId>) -> Document {
+    let mut filter = doc! { "hidden": { "$ne": true } };
+    match viewer {
+        Some(viewer) => filter.insert("$or", vec![doc! { "shadowed": { "$ne": true } }, doc! { "user_id": viewer }]),
+        None => filter.insert("shadowed", doc! { "$ne": true }),
+    };
+    filter
+}
+
+fn mute_active(lecture: &Document) -> bool {
+    let status = LectureStatus::of(lecture);
+    status.is_upcoming() || status == LectureStatus::Live
+}
+
+// 取出讨论并校验当前用户对其所属演讲的管理权限
+async fn load_moderated(client: &AppState, discussion_id: &str, auth: &AuthUser) -> Result<(ObjectId, Document), AppError> {
+    let oid = ids::parse_oid(discussion_id, "discussion_id")?;
+    let discussion = discussion_collection(client)
+        .find_one(doc! { "_id": oid }, None)
+        .await?
+        .ok_or(AppError::NotFound("Discussion not found".into()))?;
+    let lecture_oid = discussion
+        .get_object_id("lecture_id")
+        .map_err(|_| AppError::Internal("讨论数据异常".into()))?;
+    require_lecture_role(client, lecture_oid, auth, LectureRole::Moderator).await?;
+    Ok((lecture_oid, discussion))
+}
+
+fn reason_of(raw: Option<String>) -> Option<String> {
+    raw.map(|r| r.trim().to_string()).filter(|r| !r.is_empty())
+}
+
+// ==================== 路由 ====================
+
+// POST /discussion/:id/pin {pinned} -> 置顶或取消置顶
+async fn pin_discussion(
+    State(client): State<AppState>,
+    auth: AuthUser,
+    Path(discussion_id): Path<String>,
+    Json(payload): Json<PinRequest>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let (lecture_oid, discussion) = load_moderated(&client, &discussion_id, &auth).await?;
+    if payload.pinned && discussion.get_bool("hidden").unwrap_or(false) {
+        return Err(AppError::Conflict("已隐藏的消息不能置顶".into()));
+    }
+    let oid = discussion.get_object_id("_id").map_err(|_| AppError::Internal("讨论数据异常".into()))?;
+    let update = if payload.pinned {
+        doc! { "$set": { "pinned": true, "pinned_by": auth.id, "pinned_at": Utc::now().timestamp_millis() } }
+    } else {
+        doc! { "$set": { "pinned": false }, "$unset": { "pinned_by": "", "pinned_at": "" } }
+    };
+    discussion_collection(&client).update_one(doc! { "_id": oid }, update, None).await?;
+    audit::record(
+        &client,
+        Some(auth.id),
+        if payload.pinned { "discussion.pin" } else { "discussion.unpin" },
+        &format!("discussion:{}", discussion_id),
+        doc! { "lecture_id": lecture_oid.to_hex() },
+    )
+    .await;
+    realtime::publish(lecture_oid, "discussion.pinned", serde_json::json!({ "id": discussion_id, "pinned": payload.pinned }));
+    Ok(Json(serde_json::json!({ "id": discussion_id, "pinned": payload.pinned })))
+}
+
+// POST /discussion/:id/hide {hidden, reason?} -> 隐藏或恢复；隐藏时一并取消置顶
+async fn hide_discussion(
+    State(client): State<AppState>,
+    auth: AuthUser,
+    Path(discussion_id): Path<String>,
+    Json(payload): Json<HideRequest>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let (lecture_oid, discussion) = load_moderated(&client, &discussion_id, &auth).await?;
+    let oid = discussion.get_object_id("_id").map_err(|_| AppError::Internal("讨论数据异常".into()))?;
+    let reason = reason_of(payload.reason);
+    let moderated_by = doc! {
+        "user_id": auth.id,
+        "action": if payload.hidden { "hide" } else { "unhide" },
+        "reason": reason.clone(),
+        "at": Utc::now().timestamp_millis(),
+    };
+    let mut set_doc = doc! { "hidden": payload.hidden, "moderated_by": moderated_by };
+    if payload.hidden {
+        set_doc.insert("pinned", false);
+    }
+    discussion_collection(&client).update_one(doc! { "_id": oid }, doc! { "$set": set_doc }, None).await?;
+    audit::record(
+        &client,
+        Some(auth.id),
+        if payload.hidden { "discussion.hide" } else { "discussion.unhide" },
+        &format!("discussion:{}", discussion_id),
+        doc! { "lecture_id": lecture_oid.to_hex(), "reason": reason },
+    )
+    .await;
+    realtime::publish(lecture_oid, "discussion.hidden", serde_json::json!({ "id": discussion_id, "hidden": payload.hidden }));
+    Ok(Json(serde_json::json!({ "id": discussion_id, "hidden": payload.hidden })))
+}
+
+// GET /discussion/lecture/:lecture_id/pinned -> 置顶消息，按置顶时间先后
+async fn list_pinned(
+    State(client): State<AppState>,
+    Path(lecture_id): Path<String>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let lecture_oid = ids::parse_oid(&lecture_id, "lecture_id")?;
+    let options = FindOptions::builder().sort(doc! { "pinned_at": 1 }).build();
+    let items: Vec<serde_json::Value> = discussion_collection(&client)
+        .find(doc! { "lecture_id": lecture_oid, "pinned": true, "shadowed": { "$ne": true }, "hidden": { "$ne": true } }, options)
+        .await?
+        .map_ok(ids::doc_to_json)
+        .try_collect()
+        .await?;
+    Ok(Json(serde_json::json!({ "items": items })))
+}
+
+async fn add_mute(client: AppState, auth: AuthUser, lecture_id: String, payload: MuteRequest, kind: MuteKind) -> Result<Json<serde_json::Value>, AppError> {
+    let lecture_oid = ids::parse_oid(&lecture_id, "lecture_id")?;
+    let user_oid = ids::parse_oid(&payload.user_id, "user_id")?;
+    let lecture = require_lecture_role(&client, lecture_oid, &auth, LectureRole::Moderator).await?;
+    if user_oid == auth.id || is_host(&lecture, &user_oid.to_hex()) {
+        return Err(AppError::BadRequest("不能禁言自己或本场的组织者、讲者".into()));
+    }
+    if !mute_active(&lecture) {
+        return Err(AppError::Conflict("演讲已结束，无需禁言".into()));
+    }
+    user_collection(&client)
+        .find_one(doc! { "_id": user_oid }, None)
+        .await?
+        .ok_or(AppError::NotFound("用户不存在".into()))?;
+
+    let reason = reason_of(payload.reason);
+    let entry = doc! { "user_id": user_oid, "by": auth.id, "reason": reason.clone(), "at": Utc::now().timestamp_millis() };
+    let result = lecture_collection(&client)
+        .update_one(
+            doc! { "_id": lecture_oid, format!("{}.user_id", kind.field()): { "$ne": user_oid } },
+            doc! { "$push": { kind.field(): entry } },
+            None,
+        )
+        .await?;
+    if result.modified_count > 0 {
+        audit::record(
+            &client,
+            Some(auth.id),
+            kind.action(),
+            &format!("lecture:{}", lecture_id),
+            doc! { "user_id": user_oid.to_hex(), "reason": reason },
+        )
+        .await;
+    }
+    Ok(Json(serde_json::json!({ "lecture_id": lecture_id, "user_id": payload.user_id, "muted": true })))
+}
+
+async fn remove_mute(client: AppState, auth: AuthUser, lecture_id: String, user_id: String, kind: MuteKind) -> Result<Json<serde_json::Value>, AppError> {
+    let lecture_oid = ids::parse_oid(&lecture_id, "lecture_id")?;
+    let user_oid = ids::parse_oid(&user_id, "user_id")?;
+    require_lecture_role(&client, lecture_oid, &auth, LectureRole::Moderator).await?;
+    let result = lecture_collection(&client)
+        .update_one(
+            doc! { "_id": lecture_oid },
+            doc! { "$pull": { kind.field(): { "user_id": user_oid } } },
+            None,
+        )
+        .await?;
+    if result.modified_count == 0 {
+        return Err(AppError::NotFound("该用户未被禁言".into()));
+    }
+    audit::record(&client, Some(auth.id), kind.lift_action(), &format!("lecture:{}", lecture_id), doc! { "user_id": &user_id }).await;
+    Ok(Json(serde_json::json!({ "lecture_id": lecture_id, "user_id": user_id, "muted": false })))
+}
+
+// POST /discussion/lecture/:lecture_id/mute {user_id, reason?} -> 禁言至演讲结束
+async fn mute_user(
+    State(client): State<AppState>,
+    auth: AuthUser,
+    Path(lecture_id): Path<String>,
+    Json(payload): Json<MuteRequest>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    add_mute(client, auth, lecture_id, payload, MuteKind::Mute).await
+}
+
+// DELETE /discussion/lecture/:lecture_id/mute/:user_id -> 解除禁言
+async fn unmute_user(
+    State(client): State<AppState>,
+    auth: AuthUser,
+    Path((lecture_id, user_id)): Path<(String, String)>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    remove_mute(client, auth, lecture_id, user_id, MuteKind::Mute).await
+}
+
+// POST /discussion/lecture/:lecture_id/shadow_mute {user_id, reason?} -> 静默禁言至演讲结束：
+// 本人照常发言、看得到自己的消息，其他人看不到
+async fn shadow_mute_user(
+    State(client): State<AppState>,
+    auth: AuthUser,
+    Path(lecture_id): Path<String>,
+    Json(payload): Json<MuteRequest>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    add_mute(client, auth, lecture_id, payload, MuteKind::Shadow).await
+}
+
+// DELETE /discussion/lecture/:lecture_id/shadow_mute/:user_id -> 解除静默禁言；
+// 已发出的静默消息仍不公开，管理者可带 include_hidden 查看
+async fn shadow_unmute_user(
+    State(client): State<AppState>,
+    auth: AuthUser,
+    Path((lecture_id, user_id)): Path<(String, String)>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    remove_mute(client, auth, lecture_id, user_id, MuteKind::Shadow).await
+}
+
+// GET /discussion/lecture/:lecture_id/mutes -> 当前禁言及静默禁言名单（仅管理者）
+async fn list_mutes(
+    State(client): State<AppState>,
+    auth: AuthUser,
+    Path(lecture_id): Path<String>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let lecture_oid = ids::parse_oid(&lecture_id, "lecture_id")?;
+    let lecture = require_lecture_role(&client, lecture_oid, &auth, LectureRole::Moderator).await?;
+    let entries = |kind: MuteKind| -> Vec<serde_json::Value> {
+        lecture
+            .get_array(kind.field())
+            .map(|a| a.iter().filter_map(|m| m.as_document().cloned()).map(ids::doc_to_json).collect())
+            .unwrap_or_default()
+    };
+    Ok(Json(serde_json::json!({ "items": entries(MuteKind::Mute), "shadow_items": entries(MuteKind::Shadow) })))
+}
+
+// ==================== Router ====================
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/:discussion_id/pin", post(pin_discussion))
+        .route("/:discussion_id/hide", post(hide_discussion))
+        .route("/lecture/:lecture_id/pinned", get(list_pinned))
+        .route("/lecture/:lecture_id/mute", post(mute_user))
+        .route("/lecture/:lecture_id/mute/:user_id", delete(unmute_user))
+        .route("/lecture/:lecture_id/shadow_mute", post(shadow_mute_user))
+        .route("/lecture/:lecture_id/shadow_mute/:user_id", delete(shadow_unmute_user))
+        .route("/lecture/:lecture_id/mutes", get(list_mutes))
+}
@@ -0,0 +1,164 @@
+// src/routes/notes.rs
+// 讲者备注：仅组织者与讲者可读写的演讲备注，用于沟通场地、设备等事务。
+// 每次保存追加一条修订（lecture_id + revision 唯一），最新修订即当前内容
+use axum::{
+    extract::{Path, State},
+    response::{Json, Response},
+    routing::get,
+    Router,
+};
+use bson::{doc, oid::ObjectId, Document};
+use chrono::Utc;
+use futures_util::TryStreamExt;
+use mongodb::{options::FindOneOptions, Client};
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::auth::AuthUser;
+use crate::db::{duplicate_key_index, lecture_note_collection, user_collection};
+use crate::error::AppError;
+use crate::{ids, retry};
+use crate::pagination::PageParams;
+use crate::routes::lecture::{is_host, load_lecture};
+
+type AppState = Arc<Client>;
+
+const MAX_NOTE_CHARS: usize = 20_000;
+
+// ==================== 模型 ====================
+
+#[derive(Deserialize)]
+struct NoteUpdate {
+    content: String,
+    // 编辑时所基于的修订号；与当前修订不一致说明期间有人保存过，返回 409 避免覆盖
+    base_revision: Option<i32>,
+}
+
+// ==================== 工具函数 ====================
+
+async fn load_host_lecture(client: &AppState, lecture_id: &str, auth: &AuthUser) -> Result<ObjectId, AppError> {
+    let lecture = load_lecture(client, lecture_id).await?;
+    if !is_host(&lecture, &auth.id_hex()) {
+        return Err(AppError::Forbidden("只有组织者或讲者可以查看和编辑备注".into()));
+    }
+    lecture.get_object_id("_id").map_err(|_| AppError::Internal("演讲ID无效".into()))
+}
+
+async fn latest_revision(client: &AppState, lecture_oid: ObjectId) -> Result<Option<Document>, AppError> {
+    let options = FindOneOptions::builder().sort(doc! { "revision": -1 }).build();
+    Ok(lecture_note_collection(client)
+        .find_one(doc! { "lecture_id": lecture_oid }, options)
+        .await?)
+}
+
+async fn revision_json(client: &AppState, revision: Document) -> serde_json::Value {
+    let author = match revision.get_object_id("author_id") {
+        Ok(oid) => user_collection(client).find_one(doc! { "_id": oid }, None).await.ok().flatten(),
+        Err(_) => None,
+    };
+    let mut v = ids::doc_to_json(revision);
+    if let Some(obj) = v.as_object_mut() {
+        let username = author.as_ref().and_then(|u| u.get_str("username").ok()).unwrap_or("");
+        obj.insert("author_name".to_string(), serde_json::json!(username));
+    }
+    v
+}
+
+// ==================== 路由 ====================
+
+// GET /lecture/:id/notes -> 当前备注；尚未编辑过时 revision 为 0
+async fn get_notes(
+    State(client): State<AppState>,
+    auth: AuthUser,
+    Path(lecture_id): Path<String>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let lecture_oid = load_host_lecture(&client, &lecture_id, &auth).await?;
+    Ok(Json(match latest_revision(&client, lecture_oid).await? {
+        Some(revision) => revision_json(&client, revision).await,
+        None => serde_json::json!({ "lecture_id": lecture_id, "revision": 0, "content": "" }),
+    }))
+}
+
+// PUT /lecture/:id/notes {content, base_revision?} -> 保存为新修订
+async fn update_notes(
+    State(client): State<AppState>,
+    auth: AuthUser,
+    Path(lecture_id): Path<String>,
+    Json(payload): Json<NoteUpdate>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let lecture_oid = load_host_lecture(&client, &lecture_id, &auth).await?;
+    if payload.content.chars().count() > MAX_NOTE_CHARS {
+        return Err(AppError::BadRequest(format!("备注不能超过 {} 字", MAX_NOTE_CHARS)));
+    }
+
+    let current = latest_revision(&client, lecture_oid).await?;
+    let current_revision = current.as_ref().and_then(|r| r.get_i32("revision").ok()).unwrap_or(0);
+    if payload.base_revision.is_some_and(|base| base != current_revision) {
+        return Err(AppError::Conflict("备注已被他人修改，请刷新后再保存".into())
+            .with_details(serde_json::json!({ "current_revision": current_revision })));
+    }
+    if current.as_ref().and_then(|r| r.get_str("content").ok()) == Some(payload.content.as_str()) {
+        return Ok(Json(revision_json(&client, current.unwrap_or_default()).await));
+    }
+
+    let mut revision = doc! {
+        "lecture_id": lecture_oid,
+        "revision": current_revision + 1,
+        "content": &payload.content,
+        "author_id": auth.id,
+        "created_at": Utc::now().timestamp_millis(),
+    };
+    // 两人同时保存时唯一索引只放行一个
+    match retry::insert_one(&lecture_note_collection(&client), &mut revision).await {
+        Ok(_) => Ok(Json(revision_json(&client, revision).await)),
+        Err(e) if duplicate_key_index(&e).is_some() => Err(AppError::Conflict("备注已被他人修改，请刷新后再保存".into())
+            .with_details(serde_json::json!({ "current_revision": current_revision + 1 }))),
+        Err(e) => Err(retry::db_error(e, "保存备注失败")),
+    }
+}
+
+// GET /lecture/:id/notes/revisions -> 修订历史，最新在前
+async fn list_revisions(
+    State(client): State<AppState>,
+    auth: AuthUser,
+    Path(lecture_id): Path<String>,
+    paging: PageParams,
+) -> Result<Response, AppError> {
+    let lecture_oid = load_host_lecture(&client, &lecture_id, &auth).await?;
+    let coll = lecture_note_collection(&client);
+    let filter = doc! { "lecture_id": lecture_oid };
+    let total = coll.count_documents(filter.clone(), None).await?;
+    let revisions: Vec<Document> = coll
+        .find(filter, paging.find_options(doc! { "revision": -1 }))
+        .await?
+        .try_collect()
+        .await?;
+    let mut items = Vec::with_capacity(revisions.len());
+    for revision in revisions {
+        items.push(revision_json(&client, revision).await);
+    }
+    Ok(paging.respond(items, total))
+}
+
+// GET /lecture/:id/notes/revisions/:revision
+async fn get_revision(
+    State(client): State<AppState>,
+    auth: AuthUser,
+    Path((lecture_id, revision)): Path<(String, i32)>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let lecture_oid = load_host_lecture(&client, &lecture_id, &auth).await?;
+    let found = lecture_note_collection(&client)
+        .find_one(doc! { "lecture_id": lecture_oid, "revision": revision }, None)
+        .await?
+        .ok_or(AppError::NotFound("修订不存在".into()))?;
+    Ok(Json(revision_json(&client, found).await))
+}
+
+// ==================== Router ====================
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/:lecture_id/notes", get(get_notes).put(update_notes))
+        .route("/:lecture_id/notes/revisions", get(list_revisions))
+        .route("/:lecture_id/notes/revisions/:revision", get(get_revision))
+}
@@ -0,0 +1,149 @@
+// src/routes/notification.rs
+use axum::{
+    extract::{Path, Query, State},
+    response::{Json, Response},
+    routing::{get, post},
+    Router,
+};
+use bson::{doc, oid::ObjectId, Document};
+use futures_util::TryStreamExt;
+use mongodb::Client;
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::auth::AuthUser;
+use crate::db::notification_collection;
+use crate::error::AppError;
+use crate::{ids, retry};
+use crate::pagination::PageParams;
+
+type AppState = Arc<Client>;
+
+// ==================== 模型 ====================
+
+#[derive(Deserialize, Default)]
+struct ListQuery {
+    // 只返回未读通知
+    #[serde(default)]
+    unread_only: bool,
+    kind: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct MarkReadRequest {
+    // 指定通知 ID；为空且 all 为 true 时标记全部
+    #[serde(default)]
+    ids: Vec<String>,
+    #[serde(default)]
+    all: bool,
+}
+
+// ==================== 工具函数 ====================
+
+// 通知只对本人可见
+fn own_user(auth: &AuthUser, user_id: &str) -> Result<ObjectId, AppError> {
+    auth.ensure_self(user_id)?;
+    Ok(auth.id)
+}
+
+fn notification_json(doc: Document) -> serde_json::Value {
+    let mut v = ids::doc_to_json(doc);
+    if let Some(obj) = v.as_object_mut() {
+        // 旧通知没有 count 字段，按单条计
+        obj.entry("count").or_insert(serde_json::json!(1));
+    }
+    v
+}
+
+// ==================== 路由 ====================
+
+// GET /notification/:user_id?unread_only=&kind= -> 最近更新的在前
+async fn list_notifications(
+    State(client): State<AppState>,
+    auth: AuthUser,
+    Path(user_id): Path<String>,
+    Query(query): Query<ListQuery>,
+    paging: PageParams,
+) -> Result<Response, AppError> {
+    let user_oid = own_user(&auth, &user_id)?;
+    let mut filter = doc! { "user_id": user_oid };
+    if query.unread_only {
+        filter.insert("read", false);
+    }
+    if let Some(kind) = query.kind.as_deref().map(str::trim).filter(|s| !s.is_empty()) {
+        filter.insert("kind", kind);
+    }
+    let coll = notification_collection(&client);
+    let total = coll.count_documents(filter.clone(), None).await?;
+    let items: Vec<serde_json::Value> = coll
+        .find(filter, paging.find_options(doc! { "updated_at": -1, "_id": -1 }))
+        .await?
+        .map_ok(notification_json)
+        .try_collect()
+        .await?;
+    Ok(paging.respond(items, total))
+}
+
+// GET /notification/:user_id/unread_count -> 铃铛角标：未读通知条数及其合并的事件总数
+async fn unread_count(
+    State(client): State<AppState>,
+    auth: AuthUser,
+    Path(user_id): Path<String>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let user_oid = own_user(&auth, &user_id)?;
+    let pipeline = vec![
+        doc! { "$match": { "user_id": user_oid, "read": false } },
+        doc! { "$group": {
+            "_id": null,
+            "unread": { "$sum": 1 },
+            "events": { "$sum": { "$ifNull": ["$count", 1] } },
+        } },
+    ];
+    let row = notification_collection(&client)
+        .aggregate(pipeline, None)
+        .await?
+        .try_next()
+        .await?
+        .unwrap_or_default();
+    let number = |field: &str| match row.get(field) {
+        Some(bson::Bson::Int32(n)) => *n as i64,
+        Some(bson::Bson::Int64(n)) => *n,
+        _ => 0,
+    };
+    Ok(Json(serde_json::json!({ "unread": number("unread"), "events": number("events") })))
+}
+
+// POST /notification/mark_read {ids: [..]} 或 {all: true} -> 标记当前用户的通知为已读
+async fn mark_read(
+    State(client): State<AppState>,
+    auth: AuthUser,
+    Json(payload): Json<MarkReadRequest>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let mut filter = doc! { "user_id": auth.id, "read": false };
+    if !payload.ids.is_empty() {
+        let oids = payload
+            .ids
+            .iter()
+            .map(|id| ids::parse_oid(id, "ids"))
+            .collect::<Result<Vec<_>, _>>()?;
+        filter.insert("_id", doc! { "$in": oids });
+    } else if !payload.all {
+        return Err(AppError::BadRequest("请提供 ids 或 all: true".into()));
+    }
+    let now = chrono::Utc::now().timestamp_millis();
+    let coll = notification_collection(&client);
+    let update = doc! { "$set": { "read": true, "read_at": now } };
+    let result = retry::with_retry("notification.mark_read", || coll.update_many(filter.clone(), update.clone(), None))
+        .await
+        .map_err(|e| retry::db_error(e, "标记已读失败"))?;
+    Ok(Json(serde_json::json!({ "marked": result.modified_count })))
+}
+
+// ==================== Router ====================
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/mark_read", post(mark_read))
+        .route("/:user_id", get(list_notifications))
+        .route("/:user_id/unread_count", get(unread_count))
+}
@@ -0,0 +1,402 @@
+// src/routes/organization.rs
+use axum::{
+    extract::{Path, State},
+    response::{Json, Response},
+    routing::{get, post, put},
+    Router,
+};
+use bson::{doc, oid::ObjectId, Bson, Document};
+use chrono::Utc;
+use futures_util::TryStreamExt;
+use mongodb::Client;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::auth::{AuthUser, Organizer, RequireRole};
+use crate::db::{lecture_collection, organization_collection};
+use crate::routes::embed;
+use crate::{ids, lecturecode, retry};
+use crate::error::AppError;
+use crate::pagination::PageParams;
+
+type AppState = Arc<Client>;
+
+// ==================== 模型 ====================
+
+#[derive(Deserialize)]
+struct OrganizationCreate {
+    name: String,
+    #[serde(default)]
+    settings: SettingsUpdate,
+}
+
+#[derive(Deserialize, Default)]
+struct SettingsUpdate {
+    logo: Option<String>,
+    accent_color: Option<String>,
+    // 默认提醒时间（开讲前多少分钟），如 [1440, 30]，组织内演讲按此提醒
+    default_reminder_minutes: Option<Vec<i32>>,
+    // 嵌入页面允许的来源，如 https://cs.example.edu；演讲未单独设置 embed_origins 时沿用
+    embed_origins: Option<Vec<String>>,
+    // 演讲结束多少天后自动归档，0 表示不自动归档
+    archive_after_days: Option<i32>,
+    // 演讲码前缀（1~4 位字母数字），空字符串表示取消
+    code_prefix: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct CoListDecision {
+    approve: bool,
+}
+
+// ==================== 工具函数 ====================
+
+static COLOR_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^#[0-9a-fA-F]{6}$").unwrap());
+
+fn default_settings() -> Document {
+    doc! {
+        "logo": "",
+        "accent_color": "#409eff",
+        "default_reminder_minutes": [1440, 30],
+        "embed_origins": [],
+    }
+}
+
+// 校验并转换设置项，只包含请求中出现的字段
+fn settings_to_set_doc(s: SettingsUpdate) -> Result<Document, AppError> {
+    let mut set = doc! {};
+    if let Some(logo) = s.logo {
+        set.insert("settings.logo", logo.trim());
+    }
+    if let Some(color) = s.accent_color {
+        if !COLOR_RE.is_match(&color) {
+            return Err(AppError::BadRequest("accent_color 需为 #RRGGBB 格式".into()));
+        }
+        set.insert("settings.accent_color", color.to_lowercase());
+    }
+    if let Some(mut minutes) = s.default_reminder_minutes {
+        if minutes.iter().any(|m| !(0..=10080).contains(m)) {
+            return Err(AppError::BadRequest("提醒时间需在 0~10080 分钟之间".into()));
+        }
+        minutes.sort_unstable_by(|a, b| b.cmp(a));
+        minutes.dedup();
+        set.insert("settings.default_reminder_minutes", minutes);
+    }
+    if let Some(origins) = s.embed_origins {
+        set.insert("settings.embed_origins", embed::normalize_origins(origins, "embed_origins")?);
+    }
+    if let Some(days) = s.archive_after_days {
+        if !(0..=3650).contains(&days) {
+            return Err(AppError::BadRequest("archive_after_days 需在 0~3650 之间".into()));
+        }
+        set.insert("settings.archive_after_days", days);
+    }
+    if let Some(prefix) = s.code_prefix {
+        let prefix = prefix.trim().to_uppercase();
+        if !prefix.is_empty() && !lecturecode::is_valid_prefix(&prefix) {
+            return Err(AppError::BadRequest("code_prefix 需为 1~4 位字母或数字".into()));
+        }
+        set.insert("settings.code_prefix", prefix);
+    }
+    Ok(set)
+}
+
+// 只有组织成员（含所有者）可以管理组织
+fn ensure_member(org: &Document, auth: &AuthUser) -> Result<(), AppError> {
+    let me = auth.id_hex();
+    let member = org.get_str("owner_id").ok() == Some(me.as_str())
+        || org
+            .get_array("members")
+            .is_ok_and(|m| m.iter().any(|v| v.as_str() == Some(me.as_str())));
+    if !member {
+        return Err(AppError::Forbidden("只有组织成员可以执行该操作".into()));
+    }
+    Ok(())
+}
+
+fn doc_to_json(doc: Document) -> Result<serde_json::Value, AppError> {
+    Ok(ids::doc_to_json(doc))
+}
+
+// 供公开的演讲接口使用：只返回品牌相关字段
+pub async fn branding_for(client: &Arc<Client>, org_id: &str) -> Option<serde_json::Value> {
+    let oid = ObjectId::parse_str(org_id).ok()?;
+    let org = organization_collection(client)
+        .find_one(doc! { "_id": oid }, None)
+        .await
+        .ok()??;
+    let settings = org.get_document("settings").ok();
+    Some(serde_json::json!({
+        "org_id": org_id,
+        "name": org.get_str("name").unwrap_or(""),
+        "logo": settings.and_then(|s| s.get_str("logo").ok()).unwrap_or(""),
+        "accent_color": settings.and_then(|s| s.get_str("accent_color").ok()).unwrap_or("#409eff"),
+    }))
+}
+
+// ==================== 路由 ====================
+
+// POST /org/create -> 当前组织者成为所有者
+async fn create_organization(
+    State(client): State<AppState>,
+    auth: RequireRole<Organizer>,
+    Json(payload): Json<OrganizationCreate>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let coll = organization_collection(&client);
+
+    let name = payload.name.trim().to_string();
+    if name.is_empty() {
+        return Err(AppError::BadRequest("组织名称不能为空".into()));
+    }
+    let owner_id = auth.id_hex();
+
+    // 先铺默认设置，再用请求中的设置覆盖
+    let mut settings = default_settings();
+    for (key, value) in settings_to_set_doc(payload.settings)? {
+        settings.insert(key.trim_start_matches("settings."), value);
+    }
+
+    let mut org_doc = doc! {
+        "name": &name,
+        "owner_id": &owner_id,
+        "members": [&owner_id],
+        "settings": settings,
+        "created_at": Utc::now().timestamp_millis(),
+    };
+
+    let id = retry::insert_one(&coll, &mut org_doc)
+        .await
+        .map_err(|e| retry::db_error(e, "创建组织失败"))?;
+
+    let created = coll
+        .find_one(doc! { "_id": id }, None)
+        .await
+        .map_err(|_| AppError::Internal("查询失败".into()))?
+        .ok_or(AppError::Internal("组织创建后未找到".into()))?;
+    Ok(Json(doc_to_json(created)?))
+}
+
+// GET /org/
+async fn list_organizations(
+    State(client): State<AppState>,
+    paging: PageParams,
+) -> Result<Response, AppError> {
+    let coll = organization_collection(&client);
+    let total = coll
+        .count_documents(doc! {}, None)
+        .await
+        .map_err(|_| AppError::Internal("查询失败".into()))?;
+    let mut cursor = coll
+        .find(doc! {}, paging.find_options(doc! { "_id": 1 }))
+        .await
+        .map_err(|_| AppError::Internal("查询失败".into()))?;
+
+    let mut items = Vec::new();
+    while let Some(doc) = cursor
+        .try_next()
+        .await
+        .map_err(|_| AppError::Internal("读取失败".into()))?
+    {
+        items.push(doc_to_json(doc)?);
+    }
+    Ok(paging.respond(items, total))
+}
+
+// GET /org/:org_id
+async fn get_organization(
+    State(client): State<AppState>,
+    Path(org_id): Path<String>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let coll = organization_collection(&client);
+    let oid = ObjectId::parse_str(&org_id)
+        .map_err(|_| AppError::BadRequest("无效的 org_id".into()))?;
+    let doc = coll
+        .find_one(doc! { "_id": oid }, None)
+        .await
+        .map_err(|_| AppError::Internal("查询失败".into()))?
+        .ok_or(AppError::NotFound("Organization not found".into()))?;
+    Ok(Json(doc_to_json(doc)?))
+}
+
+// PUT /org/:org_id/settings -> 仅组织成员中的组织者
+async fn update_settings(
+    State(client): State<AppState>,
+    auth: RequireRole<Organizer>,
+    Path(org_id): Path<String>,
+    Json(payload): Json<SettingsUpdate>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let coll = organization_collection(&client);
+    let oid = ObjectId::parse_str(&org_id)
+        .map_err(|_| AppError::BadRequest("无效的 org_id".into()))?;
+    let org = coll
+        .find_one(doc! { "_id": oid }, None)
+        .await
+        .map_err(|_| AppError::Internal("查询失败".into()))?
+        .ok_or(AppError::NotFound("Organization not found".into()))?;
+    ensure_member(&org, &auth)?;
+
+    let set_doc = settings_to_set_doc(payload)?;
+    if set_doc.is_empty() {
+        return Err(AppError::BadRequest("无可更新字段".into()));
+    }
+
+    let result = coll
+        .update_one(doc! { "_id": oid }, doc! { "$set": set_doc }, None)
+        .await
+        .map_err(|_| AppError::Internal("更新失败".into()))?;
+    if result.matched_count == 0 {
+        return Err(AppError::NotFound("Organization not found".into()));
+    }
+
+    let doc = coll
+        .find_one(doc! { "_id": oid }, None)
+        .await
+        .map_err(|_| AppError::Internal("查询失败".into()))?
+        .ok_or(AppError::NotFound("Organization not found".into()))?;
+    let settings = doc.get("settings").cloned().unwrap_or(Bson::Document(default_settings()));
+    Ok(Json(serde_json::json!({
+        "message": "组织设置已更新",
+        "settings": settings,
+    })))
+}
+
+// GET /org/:org_id/lectures -> 组织日历：自有演讲 + 已批准的联合发布演讲
+async fn list_org_lectures(
+    State(client): State<AppState>,
+    Path(org_id): Path<String>,
+    paging: PageParams,
+) -> Result<Response, AppError> {
+    let org_hex = ObjectId::parse_str(&org_id)
+        .map_err(|_| AppError::BadRequest("无效的 org_id".into()))?
+        .to_hex();
+    let filter = doc! { "$or": [
+        { "org_id": &org_hex },
+        { "co_listings": { "$elemMatch": { "org_id": &org_hex, "status": "approved" } } },
+    ] };
+    let coll = lecture_collection(&client);
+    let total = coll
+        .count_documents(filter.clone(), None)
+        .await
+        .map_err(|_| AppError::Internal("查询失败".into()))?;
+    let mut cursor = coll
+        .find(filter, paging.find_options(doc! { "start_time": 1, "_id": 1 }))
+        .await
+        .map_err(|_| AppError::Internal("查询失败".into()))?;
+
+    let mut items = Vec::new();
+    while let Some(doc) = cursor
+        .try_next()
+        .await
+        .map_err(|_| AppError::Internal("读取失败".into()))?
+    {
+        let co_listed = doc.get_str("org_id").ok() != Some(org_hex.as_str());
+        let mut v = doc_to_json(doc)?;
+        if let Some(obj) = v.as_object_mut() {
+            obj.insert("co_listed".to_string(), serde_json::Value::Bool(co_listed));
+        }
+        items.push(v);
+    }
+    Ok(paging.respond(items, total))
+}
+
+// GET /org/:org_id/colist/pending -> 待本组织审批的联合发布申请，仅组织成员可见
+async fn list_pending_colistings(
+    State(client): State<AppState>,
+    auth: RequireRole<Organizer>,
+    Path(org_id): Path<String>,
+) -> Result<Json<Vec<serde_json::Value>>, AppError> {
+    let org_oid = ObjectId::parse_str(&org_id)
+        .map_err(|_| AppError::BadRequest("无效的 org_id".into()))?;
+    let org = organization_collection(&client)
+        .find_one(doc! { "_id": org_oid }, None)
+        .await
+        .map_err(|_| AppError::Internal("查询失败".into()))?
+        .ok_or(AppError::NotFound("Organization not found".into()))?;
+    ensure_member(&org, &auth)?;
+    let org_hex = org_oid.to_hex();
+    let filter = doc! {
+        "co_listings": { "$elemMatch": { "org_id": &org_hex, "status": "pending" } },
+    };
+    let mut cursor = lecture_collection(&client)
+        .find(filter, None)
+        .await
+        .map_err(|_| AppError::Internal("查询失败".into()))?;
+
+    let mut items = Vec::new();
+    while let Some(doc) = cursor
+        .try_next()
+        .await
+        .map_err(|_| AppError::Internal("读取失败".into()))?
+    {
+        items.push(serde_json::json!({
+            "lecture_id": ids::oid_hex(&doc, "_id"),
+            "topic": doc.get_str("topic").unwrap_or(""),
+            "start_time": doc.get_i64("start_time").unwrap_or(0),
+            "from_org_id": doc.get_str("org_id").ok(),
+        }));
+    }
+    Ok(Json(items))
+}
+
+// POST /org/:org_id/colist/:lecture_id -> 审批联合发布申请，审批人为当前登录的本组织成员
+async fn decide_colisting(
+    State(client): State<AppState>,
+    auth: RequireRole<Organizer>,
+    Path((org_id, lecture_id)): Path<(String, String)>,
+    Json(payload): Json<CoListDecision>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let org_oid = ObjectId::parse_str(&org_id)
+        .map_err(|_| AppError::BadRequest("无效的 org_id".into()))?;
+    let lecture_oid = ObjectId::parse_str(&lecture_id)
+        .map_err(|_| AppError::BadRequest("无效的 lecture_id".into()))?;
+    let org_hex = org_oid.to_hex();
+
+    let org = organization_collection(&client)
+        .find_one(doc! { "_id": org_oid }, None)
+        .await
+        .map_err(|_| AppError::Internal("查询失败".into()))?
+        .ok_or(AppError::NotFound("Organization not found".into()))?;
+    ensure_member(&org, &auth)?;
+
+    let status = if payload.approve { "approved" } else { "rejected" };
+    let result = lecture_collection(&client)
+        .update_one(
+            doc! {
+                "_id": lecture_oid,
+                "co_listings": { "$elemMatch": { "org_id": &org_hex, "status": "pending" } },
+            },
+            doc! { "$set": {
+                "co_listings.$.status": status,
+                "co_listings.$.decided_at": Utc::now().timestamp_millis(),
+                "co_listings.$.decided_by": auth.id_hex(),
+            } },
+            None,
+        )
+        .await
+        .map_err(|_| AppError::Internal("更新失败".into()))?;
+    if result.matched_count == 0 {
+        return Err(AppError::NotFound("未找到待审批的联合发布申请".into()));
+    }
+
+    Ok(Json(serde_json::json!({
+        "message": if payload.approve { "已同意联合发布" } else { "已拒绝联合发布" },
+        "lecture_id": lecture_id,
+        "org_id": org_hex,
+        "status": status,
+    })))
+}
+
+// ==================== Router ====================
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/create", post(create_organization))
+        .route("/", get(list_organizations))
+        .route("/:org_id", get(get_organization))
+        .route("/:org_id/settings", put(update_settings))
+        .route("/:org_id/lectures", get(list_org_lectures))
+        .route("/:org_id/colist/pending", get(list_pending_colistings))
+        .route("/:org_id/colist/:lecture_id", post(decide_colisting))
+}
@@ -0,0 +1,154 @@
+// src/routes/reaction.rs
+// 讨论消息的点赞与表情回应：每人对同一消息的同一种回应只记一次（唯一索引兜底），
+// 各类计数冗余在讨论文档的 reactions 字段上，列表可直接返回并按点赞数排序
+use axum::{
+    extract::{Path, State},
+    response::Json,
+    routing::{delete, post},
+    Router,
+};
+use bson::{doc, oid::ObjectId, Document};
+use chrono::Utc;
+use futures_util::TryStreamExt;
+use mongodb::Client;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::auth::AuthUser;
+use crate::db::{discussion_collection, duplicate_key_index, reaction_collection};
+use crate::error::AppError;
+use crate::routes::moderation;
+use crate::{ids, realtime};
+
+type AppState = Arc<Client>;
+
+pub const UPVOTE: &str = "upvote";
+// 除点赞外可用的表情
+const EMOJIS: [&str; 6] = ["👍", "❤️", "😂", "🎉", "🤔", "👀"];
+
+#[derive(Deserialize)]
+struct ReactRequest {
+    kind: String,
+}
+
+fn parse_kind(raw: &str) -> Result<String, AppError> {
+    let kind = raw.trim();
+    if kind == UPVOTE || EMOJIS.contains(&kind) {
+        Ok(kind.to_string())
+    } else {
+        Err(AppError::BadRequest(format!("kind 仅支持 {} 或 {}", UPVOTE, EMOJIS.join(" "))))
+    }
+}
+
+// 讨论文档上的各类回应计数，计数为 0 的不返回
+pub fn counts(discussion: &Document) -> HashMap<String, i64> {
+    discussion
+        .get_document("reactions")
+        .map(|r| {
+            r.iter()
+                .filter_map(|(k, v)| match v {
+                    bson::Bson::Int32(n) => Some((k.clone(), *n as i64)),
+                    bson::Bson::Int64(n) => Some((k.clone(), *n)),
+                    _ => None,
+                })
+                .filter(|(_, n)| *n > 0)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// 当前用户在给定消息上做过的回应：discussion_id(hex) -> kinds
+pub async fn mine(client: &AppState, user: ObjectId, discussions: &[ObjectId]) -> Result<HashMap<String, Vec<String>>, AppError> {
+    let mut out: HashMap<String, Vec<String>> = HashMap::new();
+    if discussions.is_empty() {
+        return Ok(out);
+    }
+    let mut cursor = reaction_collection(client)
+        .find(doc! { "user_id": user, "discussion_id": { "$in": discussions } }, None)
+        .await?;
+    while let Some(r) = cursor.try_next().await? {
+        if let (Ok(d), Ok(kind)) = (r.get_object_id("discussion_id"), r.get_str("kind")) {
+            out.entry(d.to_hex()).or_default().push(kind.to_string());
+        }
+    }
+    Ok(out)
+}
+
+async fn load_visible(client: &AppState, discussion_id: &str, viewer: ObjectId) -> Result<Document, AppError> {
+    let oid = ids::parse_oid(discussion_id, "discussion_id")?;
+    let mut filter = doc! { "_id": oid };
+    filter.extend(moderation::visible_filter(Some(viewer)));
+    discussion_collection(client)
+        .find_one(filter, None)
+        .await?
+        .ok_or(AppError::NotFound("Discussion not found".into()))
+}
+
+// 写入计数后读取最新文档并推送
+async fn respond(client: &AppState, discussion: &Document, kind: &str, delta: i32) -> Result<Json<serde_json::Value>, AppError> {
+    let oid = discussion.get_object_id("_id").map_err(|_| AppError::Internal("讨论数据异常".into()))?;
+    let updated = discussion_collection(client)
+        .find_one_and_update(
+            doc! { "_id": oid },
+            doc! { "$inc": { format!("reactions.{}", kind): delta } },
+            mongodb::options::FindOneAndUpdateOptions::builder()
+                .return_document(mongodb::options::ReturnDocument::After)
+                .build(),
+        )
+        .await?
+        .unwrap_or_else(|| discussion.clone());
+    let reactions = counts(&updated);
+    // 静默消息只有作者本人看得到，不推送
+    if let (Ok(lecture_oid), false) = (discussion.get_object_id("lecture_id"), discussion.get_bool("shadowed").unwrap_or(false)) {
+        realtime::publish(lecture_oid, "discussion.reacted", serde_json::json!({ "id": oid.to_hex(), "reactions": reactions }));
+    }
+    Ok(Json(serde_json::json!({ "id": oid.to_hex(), "reactions": reactions })))
+}
+
+// POST /discussion/:id/react {kind} -> 点赞或表情回应；重复回应返回 409
+async fn react(
+    State(client): State<AppState>,
+    auth: AuthUser,
+    Path(discussion_id): Path<String>,
+    Json(payload): Json<ReactRequest>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let kind = parse_kind(&payload.kind)?;
+    let discussion = load_visible(&client, &discussion_id, auth.id).await?;
+    let reaction = doc! {
+        "discussion_id": discussion.get_object_id("_id").ok(),
+        "lecture_id": discussion.get_object_id("lecture_id").ok(),
+        "user_id": auth.id,
+        "kind": &kind,
+        "rehearsal": discussion.get_bool("rehearsal").unwrap_or(false),
+        "created_at": Utc::now().timestamp_millis(),
+    };
+    match reaction_collection(&client).insert_one(reaction, None).await {
+        Ok(_) => respond(&client, &discussion, &kind, 1).await,
+        Err(e) if duplicate_key_index(&e).is_some() => Err(AppError::Conflict("已经回应过了".into())),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// DELETE /discussion/:id/react/:kind -> 撤销自己的回应
+async fn unreact(
+    State(client): State<AppState>,
+    auth: AuthUser,
+    Path((discussion_id, kind)): Path<(String, String)>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let kind = parse_kind(&kind)?;
+    let discussion = load_visible(&client, &discussion_id, auth.id).await?;
+    let result = reaction_collection(&client)
+        .delete_one(doc! { "discussion_id": discussion.get_object_id("_id").ok(), "user_id": auth.id, "kind": &kind }, None)
+        .await?;
+    if result.deleted_count == 0 {
+        return Err(AppError::NotFound("尚未回应".into()));
+    }
+    respond(&client, &discussion, &kind, -1).await
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/:discussion_id/react", post(react))
+        .route("/:discussion_id/react/:kind", delete(unreact))
+}
@@ -0,0 +1,87 @@
+// 路由级鉴权：未登录返回 401，角色不符或代他人操作返回 403。
+// 这些请求都在访问数据库之前被拒绝，测试不需要运行中的 MongoDB
+use axum::body::Body;
+use axum::extract::ConnectInfo;
+use axum::http::{Method, Request, StatusCode};
+use axum::Router;
+use bson::oid::ObjectId;
+use std::net::SocketAddr;
+use tower::Service;
+
+use crate::auth::tests::{offline_client, user};
+use crate::auth::{AuthUser, ROLE_AUDIENCE, ROLE_ORGANIZER, ROLE_SPEAKER};
+use crate::routes::{la, lecture};
+
+async fn app() -> Router {
+    Router::new()
+        .nest("/LA", la::router())
+        .nest("/lecture", lecture::router())
+        .with_state(offline_client().await)
+}
+
+async fn status(method: Method, uri: &str, body: serde_json::Value, as_user: Option<AuthUser>) -> StatusCode {
+    let mut req = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    req.extensions_mut().insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 40000))));
+    if let Some(u) = as_user {
+        req.extensions_mut().insert(u);
+    }
+    app().await.call(req).await.unwrap().status()
+}
+
+fn la_body(audience_id: &str) -> serde_json::Value {
+    serde_json::json!({ "lecture_id": ObjectId::new().to_hex(), "audience_id": audience_id })
+}
+
+#[tokio::test]
+async fn mutation_routes_require_login() {
+    let lid = ObjectId::new().to_hex();
+    let aid = ObjectId::new().to_hex();
+    let cases = [
+        (Method::POST, "/LA/add".to_string()),
+        (Method::POST, "/LA/create".to_string()),
+        (Method::DELETE, format!("/LA/delete?lecture_id={}&audience_id={}", lid, aid)),
+        (Method::GET, format!("/LA/by-lecture?lecture_id={}", lid)),
+        (Method::GET, format!("/LA/by-audience?audience_id={}", aid)),
+        (Method::POST, "/LA/update_is_present".to_string()),
+        (Method::POST, "/lecture/create".to_string()),
+        (Method::PUT, format!("/lecture/{}", lid)),
+        (Method::DELETE, format!("/lecture/{}", lid)),
+        (Method::POST, format!("/lecture/{}/colist", lid)),
+        (Method::POST, format!("/lecture/{}/cancel", lid)),
+        (Method::POST, format!("/lecture/{}/reschedule", lid)),
+    ];
+    for (method, uri) in cases {
+        let got = status(method.clone(), &uri, la_body(&aid), None).await;
+        assert_eq!(got, StatusCode::UNAUTHORIZED, "{} {}", method, uri);
+    }
+}
+
+#[tokio::test]
+async fn mutation_routes_reject_wrong_roles() {
+    let lid = ObjectId::new().to_hex();
+    let organizer = user(ROLE_ORGANIZER);
+    let audience = user(ROLE_AUDIENCE);
+    let cases = [
+        // 报名只能由听众为自己操作
+        (Method::POST, "/LA/add".to_string(), la_body(&organizer.id_hex()), organizer.clone()),
+        (Method::POST, "/LA/create".to_string(), la_body(&organizer.id_hex()), organizer.clone()),
+        (Method::POST, "/LA/add".to_string(), la_body(&ObjectId::new().to_hex()), audience.clone()),
+        (Method::GET, format!("/LA/by-audience?audience_id={}", ObjectId::new().to_hex()), serde_json::json!({}), audience.clone()),
+        // 名单与组织者操作
+        (Method::GET, format!("/LA/by-lecture?lecture_id={}", lid), serde_json::json!({}), audience.clone()),
+        (Method::GET, format!("/LA/present?lecture_id={}", lid), serde_json::json!({}), audience.clone()),
+        (Method::POST, "/lecture/create".to_string(), serde_json::json!({}), audience.clone()),
+        (Method::DELETE, format!("/lecture/{}", lid), serde_json::json!({}), user(ROLE_SPEAKER)),
+        (Method::POST, format!("/lecture/{}/colist", lid), serde_json::json!({}), user(ROLE_SPEAKER)),
+        (Method::POST, format!("/lecture/{}/cancel", lid), serde_json::json!({}), audience.clone()),
+    ];
+    for (method, uri, body, as_user) in cases {
+        let got = status(method.clone(), &uri, body, Some(as_user)).await;
+        assert_eq!(got, StatusCode::FORBIDDEN, "{} {}", method, uri);
+    }
+}
@@ -0,0 +1,36 @@
+// src/routes/time.rs
+// 服务器时间：前端倒计时以服务器时钟为准，避免本机时钟偏差导致签到窗口前后误差
+use axum::{
+    http::header,
+    response::{IntoResponse, Json},
+    routing::get,
+    Router,
+};
+use bson::Document;
+use chrono::{SecondsFormat, Utc};
+use mongodb::Client;
+use std::sync::Arc;
+
+type AppState = Arc<Client>;
+
+// 距开始的整秒数（向上取整），已开始时为 0
+pub fn seconds_until_start(lecture: &Document, now_ms: i64) -> i64 {
+    let start = lecture.get_i64("start_time").unwrap_or(0);
+    ((start - now_ms).max(0) + 999) / 1000
+}
+
+// GET /time -> 当前服务器时间；不可缓存，客户端可按往返耗时的一半校正
+async fn server_time() -> impl IntoResponse {
+    let now = Utc::now();
+    (
+        [(header::CACHE_CONTROL, "no-store")],
+        Json(serde_json::json!({
+            "now": now.timestamp_millis(),
+            "iso": now.to_rfc3339_opts(SecondsFormat::Millis, true),
+        })),
+    )
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/", get(server_time))
+}
@@ -0,0 +1,279 @@
+// src/routes/transcript.rs
+// 现场记录：演讲进行中，组织者、讲者及被授予记录员角色的用户可按分节追加带时间的记录，
+// 多人同时记录互不覆盖；演讲结束后整理为按分节排列的文字记录，并计入演讲报告
+use axum::{
+    extract::{Path, State},
+    response::Json,
+    routing::{get, put},
+    Router,
+};
+use bson::{doc, oid::ObjectId, Document};
+use chrono::Utc;
+use futures_util::TryStreamExt;
+use mongodb::{options::FindOptions, Client};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::auth::{require_lecture_role, AuthUser, LectureRole};
+use crate::db::{transcript_collection, user_collection};
+use crate::error::AppError;
+use crate::lifecycle::LectureStatus;
+use crate::routes::lecture::{is_host, load_lecture};
+use crate::{ids, realtime, retry};
+
+type AppState = Arc<Client>;
+
+const MAX_CONTENT_CHARS: usize = 2000;
+const MAX_SECTION_CHARS: usize = 50;
+const MAX_SECTIONS: usize = 30;
+// 未指定分节的记录归入此节
+pub const DEFAULT_SECTION: &str = "记录";
+
+// ==================== 模型 ====================
+
+#[derive(Deserialize)]
+struct EntryCreate {
+    content: String,
+    section: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct EntryUpdate {
+    content: Option<String>,
+    section: Option<String>,
+}
+
+// ==================== 工具函数 ====================
+
+fn check_content(raw: &str) -> Result<String, AppError> {
+    let content = raw.trim();
+    if content.is_empty() {
+        return Err(AppError::BadRequest("记录内容不能为空".into()));
+    }
+    if content.chars().count() > MAX_CONTENT_CHARS {
+        return Err(AppError::BadRequest(format!("单条记录不能超过 {} 字", MAX_CONTENT_CHARS)));
+    }
+    Ok(content.to_string())
+}
+
+fn check_section(raw: Option<&str>) -> Result<String, AppError> {
+    let section = raw.map(str::trim).filter(|s| !s.is_empty()).unwrap_or(DEFAULT_SECTION);
+    if section.chars().count() > MAX_SECTION_CHARS {
+        return Err(AppError::BadRequest(format!("分节名称不能超过 {} 字", MAX_SECTION_CHARS)));
+    }
+    Ok(section.to_string())
+}
+
+// 新分节数量有上限，已有分节可继续追加
+async fn ensure_section_allowed(client: &AppState, lecture_oid: ObjectId, section: &str) -> Result<(), AppError> {
+    let sections = transcript_collection(client).distinct("section", doc! { "lecture_id": lecture_oid }, None).await?;
+    if sections.len() >= MAX_SECTIONS && !sections.iter().any(|s| s.as_str() == Some(section)) {
+        return Err(AppError::BadRequest(format!("每场演讲最多 {} 个分节", MAX_SECTIONS)));
+    }
+    Ok(())
+}
+
+fn lecture_oid_of(lecture: &Document) -> Result<ObjectId, AppError> {
+    lecture.get_object_id("_id").map_err(|_| AppError::Internal("演讲ID无效".into()))
+}
+
+// 记录时间相对演讲实际开始的偏移，没有实际开始时间时按计划开始时间
+fn started_at(lecture: &Document) -> i64 {
+    lecture.get_i64("started_at").or_else(|_| lecture.get_i64("start_time")).unwrap_or(0)
+}
+
+async fn usernames(client: &AppState, entries: &[Document]) -> Result<HashMap<ObjectId, String>, AppError> {
+    let mut authors: Vec<ObjectId> = entries.iter().filter_map(|e| e.get_object_id("author_id").ok()).collect();
+    authors.sort();
+    authors.dedup();
+    if authors.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let users: Vec<Document> = user_collection(client)
+        .find(doc! { "_id": { "$in": authors } }, None)
+        .await?
+        .try_collect()
+        .await?;
+    Ok(users
+        .iter()
+        .filter_map(|u| Some((u.get_object_id("_id").ok()?, u.get_str("username").unwrap_or("").to_string())))
+        .collect())
+}
+
+fn entry_json(entry: &Document, names: &HashMap<ObjectId, String>) -> serde_json::Value {
+    let author = entry.get_object_id("author_id").ok();
+    serde_json::json!({
+        "id": ids::oid_hex(entry, "_id"),
+        "section": entry.get_str("section").unwrap_or(DEFAULT_SECTION),
+        "content": entry.get_str("content").unwrap_or(""),
+        "author_id": author.map(|a| a.to_hex()).unwrap_or_default(),
+        "author_name": author.and_then(|a| names.get(&a)).map(String::as_str).unwrap_or(""),
+        "created_at": entry.get_i64("created_at").unwrap_or(0),
+        "offset_ms": entry.get_i64("offset_ms").unwrap_or(0),
+        "edited_at": entry.get_i64("edited_at").ok(),
+    })
+}
+
+async fn load_entry(client: &AppState, lecture_oid: ObjectId, entry_id: &str) -> Result<Document, AppError> {
+    let oid = ids::parse_oid(entry_id, "entry_id")?;
+    transcript_collection(client)
+        .find_one(doc! { "_id": oid, "lecture_id": lecture_oid }, None)
+        .await?
+        .ok_or(AppError::NotFound("记录不存在".into()))
+}
+
+// 按分节整理：分节按第一条记录的时间排列，节内按记录时间排列
+async fn sections(client: &AppState, lecture_oid: ObjectId) -> Result<Vec<(String, Vec<Document>)>, AppError> {
+    let options = FindOptions::builder().sort(doc! { "created_at": 1, "_id": 1 }).build();
+    let entries: Vec<Document> = transcript_collection(client)
+        .find(doc! { "lecture_id": lecture_oid }, options)
+        .await?
+        .try_collect()
+        .await?;
+    let mut grouped: Vec<(String, Vec<Document>)> = Vec::new();
+    for entry in entries {
+        let section = entry.get_str("section").unwrap_or(DEFAULT_SECTION).to_string();
+        match grouped.iter_mut().find(|(title, _)| *title == section) {
+            Some((_, list)) => list.push(entry),
+            None => grouped.push((section, vec![entry])),
+        }
+    }
+    Ok(grouped)
+}
+
+// ==================== 路由 ====================
+
+// POST /lecture/:id/transcript {content, section?} -> 演讲进行中追加一条记录（组织者、讲者或本场记录员）
+async fn append_entry(
+    State(client): State<AppState>,
+    auth: AuthUser,
+    Path(lecture_id): Path<String>,
+    Json(payload): Json<EntryCreate>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let lecture_oid = ids::parse_oid(&lecture_id, "lecture_id")?;
+    let lecture = require_lecture_role(&client, lecture_oid, &auth, LectureRole::NoteTaker).await?;
+    if LectureStatus::of(&lecture) != LectureStatus::Live {
+        return Err(AppError::Conflict("只有进行中的演讲可以追加记录".into()));
+    }
+    let content = check_content(&payload.content)?;
+    let section = check_section(payload.section.as_deref())?;
+    ensure_section_allowed(&client, lecture_oid, &section).await?;
+
+    let now = Utc::now().timestamp_millis();
+    let mut entry = doc! {
+        "lecture_id": lecture_oid,
+        "section": &section,
+        "content": &content,
+        "author_id": auth.id,
+        "created_at": now,
+        "offset_ms": (now - started_at(&lecture)).max(0),
+    };
+    retry::insert_one(&transcript_collection(&client), &mut entry)
+        .await
+        .map_err(|e| retry::db_error(e, "保存记录失败"))?;
+    let names = usernames(&client, std::slice::from_ref(&entry)).await?;
+    let body = entry_json(&entry, &names);
+    realtime::publish(lecture_oid, "transcript.appended", body.clone());
+    Ok(Json(body))
+}
+
+// PUT /lecture/:id/transcript/:entry_id {content?, section?} -> 修改自己的记录，演讲结束后仍可订正
+async fn update_entry(
+    State(client): State<AppState>,
+    auth: AuthUser,
+    Path((lecture_id, entry_id)): Path<(String, String)>,
+    Json(payload): Json<EntryUpdate>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let lecture_oid = ids::parse_oid(&lecture_id, "lecture_id")?;
+    let lecture = require_lecture_role(&client, lecture_oid, &auth, LectureRole::NoteTaker).await?;
+    if !matches!(LectureStatus::of(&lecture), LectureStatus::Live | LectureStatus::Ended) {
+        return Err(AppError::Conflict("演讲未在进行或已取消，无法修改记录".into()));
+    }
+    let entry = load_entry(&client, lecture_oid, &entry_id).await?;
+    if entry.get_object_id("author_id").ok() != Some(auth.id) {
+        return Err(AppError::Forbidden("只能修改自己的记录".into()));
+    }
+
+    let mut set_doc = doc! { "edited_at": Utc::now().timestamp_millis() };
+    if let Some(content) = payload.content.as_deref() {
+        set_doc.insert("content", check_content(content)?);
+    }
+    if payload.section.is_some() {
+        let section = check_section(payload.section.as_deref())?;
+        ensure_section_allowed(&client, lecture_oid, &section).await?;
+        set_doc.insert("section", section);
+    }
+    let oid = entry.get_object_id("_id").map_err(|_| AppError::Internal("记录ID无效".into()))?;
+    transcript_collection(&client)
+        .update_one(doc! { "_id": oid }, doc! { "$set": set_doc.clone() }, None)
+        .await?;
+    let mut entry = entry;
+    entry.extend(set_doc);
+    let names = usernames(&client, std::slice::from_ref(&entry)).await?;
+    let body = entry_json(&entry, &names);
+    realtime::publish(lecture_oid, "transcript.updated", body.clone());
+    Ok(Json(body))
+}
+
+// DELETE /lecture/:id/transcript/:entry_id -> 删除记录（作者本人、组织者或讲者）
+async fn delete_entry(
+    State(client): State<AppState>,
+    auth: AuthUser,
+    Path((lecture_id, entry_id)): Path<(String, String)>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let lecture_oid = ids::parse_oid(&lecture_id, "lecture_id")?;
+    let lecture = require_lecture_role(&client, lecture_oid, &auth, LectureRole::NoteTaker).await?;
+    let entry = load_entry(&client, lecture_oid, &entry_id).await?;
+    if entry.get_object_id("author_id").ok() != Some(auth.id) && !is_host(&lecture, &auth.id_hex()) {
+        return Err(AppError::Forbidden("只能删除自己的记录".into()));
+    }
+    transcript_collection(&client).delete_one(doc! { "_id": entry.get_object_id("_id").ok() }, None).await?;
+    realtime::publish(lecture_oid, "transcript.deleted", serde_json::json!({ "id": entry_id }));
+    Ok(Json(serde_json::json!({ "message": "记录已删除" })))
+}
+
+// GET /lecture/:id/transcript -> 按分节整理的文字记录。演讲结束后对所有登录用户开放，
+// 进行中只有组织者、讲者与记录员可见
+async fn get_transcript(
+    State(client): State<AppState>,
+    auth: AuthUser,
+    Path(lecture_id): Path<String>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let lecture = load_lecture(&client, &lecture_id).await?;
+    let lecture_oid = lecture_oid_of(&lecture)?;
+    let status = LectureStatus::of(&lecture);
+    if status != LectureStatus::Ended {
+        require_lecture_role(&client, lecture_oid, &auth, LectureRole::NoteTaker).await?;
+    }
+
+    let grouped = sections(&client, lecture_oid).await?;
+    let all: Vec<Document> = grouped.iter().flat_map(|(_, list)| list.iter().cloned()).collect();
+    let names = usernames(&client, &all).await?;
+    let mut contributors: Vec<&str> = names.values().map(String::as_str).collect();
+    contributors.sort_unstable();
+    Ok(Json(serde_json::json!({
+        "lecture_id": lecture_id,
+        "topic": lecture.get_str("topic").unwrap_or(""),
+        "status": status.name(),
+        "started_at": started_at(&lecture),
+        "ended_at": lecture.get_i64("ended_at").ok(),
+        "entry_count": all.len(),
+        "contributors": contributors,
+        "sections": grouped
+            .iter()
+            .map(|(title, list)| serde_json::json!({
+                "title": title,
+                "entries": list.iter().map(|e| entry_json(e, &names)).collect::<Vec<_>>(),
+            }))
+            .collect::<Vec<_>>(),
+    })))
+}
+
+// ==================== Router ====================
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/:lecture_id/transcript", get(get_transcript).post(append_entry))
+        .route("/:lecture_id/transcript/:entry_id", put(update_entry).delete(delete_entry))
+}
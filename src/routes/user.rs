@@ -1,22 +1,35 @@
 // src/routes/user.rs
 use axum::{
-    extract::{Multipart, Path, State},
-    http::StatusCode,
-    response::Json,
+    extract::{ConnectInfo, Multipart, Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Json, Response},
     routing::{get, post, put},
     Router,
 };
 use bcrypt::{hash, verify, DEFAULT_COST};
 use bson::{doc, oid::ObjectId, Document};
-use futures_util::stream::StreamExt;
+use futures_util::stream::{StreamExt, TryStreamExt};
 use mongodb::Client;
 use regex::Regex;
-use serde::{Deserialize, Serialize};
+use serde::Deserialize;
 use std::sync::Arc;
+use std::net::SocketAddr;
 use uuid::Uuid;
 
 // use crate::db::USER_COLLECTION;
-use crate::db::user_collection;
+use crate::client_info::client_ip;
+use crate::db::{
+    self, la_collection, lecture_collection, magic_link_collection, password_reset_collection, session_collection,
+    user_collection,
+};
+use crate::envelope::Pagination;
+use crate::mailer::MAILER;
+use crate::quota::hash_key;
+use crate::{audit, auth, config, ics, ids, pdf, retry, signing, tags};
+use crate::error::AppError;
+use crate::pagination::PageParams;
+use crate::lifecycle::LectureStatus;
+use crate::timefmt::{self, Locale, UserTime};
 
 // 共享状态
 type AppState = Arc<Client>;
@@ -31,18 +44,80 @@ struct UserCreate {
     role: i32,
 }
 
+#[derive(Deserialize, Default)]
+struct TranscriptQuery {
+    // json（默认）或 pdf
+    format: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ForgotPasswordRequest {
+    email: String,
+}
+
+#[derive(Deserialize)]
+struct ResetPasswordRequest {
+    token: String,
+    new_password: String,
+}
+
+#[derive(Deserialize)]
+struct MagicLinkRequest {
+    email: String,
+}
+
+#[derive(Deserialize)]
+struct MagicLinkCallback {
+    token: String,
+    #[serde(default)]
+    cookie: bool,
+}
+
+// 重置链接有效期
+const RESET_TOKEN_TTL_MINUTES: i64 = 30;
+// 免密登录链接有效期
+const MAGIC_LINK_TTL_MINUTES: i64 = 15;
+const MIN_PASSWORD_LEN: usize = 6;
+
+// 通知偏好，未提供的字段保持不变
+#[derive(Deserialize)]
+struct PreferencesUpdate {
+    // 组织者在演讲结束后是否接收汇总报告邮件（默认接收）
+    lecture_report_email: Option<bool>,
+    // 导出、证明与邮件中的时间展示：IANA 时区名与语言（zh-CN / en-US）
+    timezone: Option<String>,
+    locale: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RefreshRequest {
+    refresh_token: String,
+}
+
 #[derive(Deserialize)]
 struct UserLogin {
     email: String,
     password: String,
+    // 为 true 时同时签发 HttpOnly 会话 Cookie，供自带前端免于在脚本中保存令牌
+    #[serde(default)]
+    cookie: bool,
 }
 
-#[derive(Deserialize, Default)]
-struct UserUpdate {
-    username: Option<String>,
-    gender: Option<i32>,
-    age: Option<i32>,
-    motto: Option<String>,
+#[derive(Deserialize)]
+struct SpeakerQuery {
+    tag: Option<String>,
+    q: Option<String>,
+    page: Option<u64>,
+    limit: Option<u64>,
+}
+
+const MAX_BIO_CHARS: usize = 500;
+const MAX_EXPERTISE_TAGS: usize = 20;
+const MAX_SUBSCRIBED_TAGS: usize = 50;
+
+#[derive(Deserialize)]
+struct SubscriptionUpdate {
+    tags: Vec<String>,
 }
 
 // ==================== 工具函数 ====================
@@ -60,32 +135,38 @@ fn validate_email(email: &str) -> bool {
     re.is_match(email)
 }
 
+// 专长标签：支持逗号分隔或 JSON 数组，统一小写去重
+fn parse_expertise(raw: &str) -> Vec<String> {
+    let items: Vec<String> = serde_json::from_str::<Vec<String>>(raw)
+        .unwrap_or_else(|_| raw.split([',', '，']).map(|s| s.to_string()).collect());
+    let mut tags: Vec<String> = items
+        .iter()
+        .map(|t| t.trim().to_lowercase())
+        .filter(|t| !t.is_empty())
+        .collect();
+    tags.sort();
+    tags.dedup();
+    tags
+}
+
 // ==================== 路由函数 ====================
 
 async fn register(
     State(client): State<AppState>,
     Json(payload): Json<UserCreate>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, AppError> {
     let collection = user_collection(&client);
 
     // 校验邮箱格式
     if !validate_email(&payload.email) {
-        return Err((StatusCode::BAD_REQUEST, "Invalid email format".to_string()));
-    }
-
-    // 校验用户名/邮箱是否重复
-    if collection.find_one(doc! { "username": &payload.username }, None).await.unwrap().is_some() {
-        return Err((StatusCode::BAD_REQUEST, "用户名已被使用".to_string()));
-    }
-    if collection.find_one(doc! { "email": &payload.email }, None).await.unwrap().is_some() {
-        return Err((StatusCode::BAD_REQUEST, "邮箱已被注册".to_string()));
+        return Err(AppError::BadRequest("Invalid email format".to_string()));
     }
 
     let hashed = hash_password(&payload.password).map_err(|_| {
-        (StatusCode::INTERNAL_SERVER_ERROR, "密码加密失败".to_string())
+        AppError::Internal("密码加密失败".to_string())
     })?;
 
-    let user_doc = doc! {
+    let mut user_doc = doc! {
         "username": &payload.username,
         "email": &payload.email,
         "password": hashed,
@@ -94,8 +175,17 @@ async fn register(
         "background": "/static/uploads/aa486fc11bd94ab3bd9ef02baa48e357.jpg",
     };
 
-    collection.insert_one(user_doc, None).await
-        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "数据库错误".to_string()))?;
+    // 用户名/邮箱的唯一性由唯一索引保证，并发注册时只有一个能写入成功；
+    // 不再先查后插，避免两个请求同时通过检查
+    retry::insert_one(&collection, &mut user_doc).await.map_err(|e| {
+        match db::duplicate_key_index(&e).as_deref() {
+            Some("uniq_username") => AppError::Conflict("用户名已被使用".to_string())
+                .with_details(serde_json::json!({ "field": "username" })),
+            Some("uniq_email") => AppError::Conflict("邮箱已被注册".to_string())
+                .with_details(serde_json::json!({ "field": "email" })),
+            _ => retry::db_error(e, "注册失败"),
+        }
+    })?;
 
     Ok(Json(serde_json::json!({
         "message": "User successfully created",
@@ -105,109 +195,834 @@ async fn register(
 
 async fn login(
     State(client): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
     Json(payload): Json<UserLogin>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<auth::LoginResponse, AppError> {
     let collection = user_collection(&client);
 
     let user = collection.find_one(doc! { "email": &payload.email }, None).await
-        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "数据库错误".to_string()))?
-        .ok_or((StatusCode::UNAUTHORIZED, "Invalid credentials".to_string()))?;
+        .map_err(|_| AppError::Internal("数据库错误".to_string()))?
+        .ok_or(AppError::Unauthorized("Invalid credentials".to_string()))?;
 
     let hashed = user.get_str("password").map_err(|_| {
-        (StatusCode::INTERNAL_SERVER_ERROR, "密码字段缺失".to_string())
+        AppError::Internal("密码字段缺失".to_string())
     })?;
 
     if !verify_password(&payload.password, hashed).map_err(|_| {
-        (StatusCode::INTERNAL_SERVER_ERROR, "密码验证失败".to_string())
+        AppError::Internal("密码验证失败".to_string())
     })? {
-        return Err((StatusCode::UNAUTHORIZED, "Invalid credentials".to_string()));
+        return Err(AppError::Unauthorized("Invalid credentials".to_string()));
     }
 
-    let id = user.get_object_id("_id").unwrap().to_hex();
+    let oid = user.get_object_id("_id").unwrap();
+    let id = oid.to_hex();
+    let role = user.get_i32("role").unwrap_or(0);
+    let user_agent = headers
+        .get(header::USER_AGENT)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+    let ip = client_ip(&headers, Some(peer));
+    let mut resp = auth::start_session(&client, oid, role, user_agent, &ip, payload.cookie).await?;
 
-    Ok(Json(serde_json::json!({
-        "message": "Login successful",
-        "user": {
-            "id": id,
-            "email": payload.email,
-            "username": user.get_str("username").unwrap_or(""),
-            "role": user.get_i32("role").unwrap_or(0),
+    resp.body["message"] = "Login successful".into();
+    resp.body["user"] = serde_json::json!({
+        "id": id,
+        "email": payload.email,
+        "username": user.get_str("username").unwrap_or(""),
+        "role": role,
+    });
+    Ok(resp)
+}
+
+// POST /user/forgot_password -> 发送重置邮件；无论邮箱是否存在都返回相同结果，避免探测已注册邮箱
+async fn forgot_password(
+    State(client): State<AppState>,
+    Json(payload): Json<ForgotPasswordRequest>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let email = payload.email.trim();
+    let user = user_collection(&client)
+        .find_one(doc! { "email": email }, None)
+        .await
+        .map_err(|_| AppError::Internal("数据库错误".to_string()))?;
+
+    if let Some(user) = user {
+        let user_id = user.get_object_id("_id").map_err(|_| AppError::Internal("用户数据异常".to_string()))?;
+        let token = hex::encode(rand::random::<[u8; 32]>());
+        let now = chrono::Utc::now();
+        let resets = password_reset_collection(&client);
+        // 新链接生成后，之前未使用的链接一并作废
+        resets
+            .update_many(doc! { "user_id": user_id, "used": false }, doc! { "$set": { "used": true } }, None)
+            .await
+            .map_err(|_| AppError::Internal("数据库错误".to_string()))?;
+        resets
+            .insert_one(
+                doc! {
+                    "user_id": user_id,
+                    "token_hash": hash_key(&token),
+                    "used": false,
+                    "created_at": now.timestamp_millis(),
+                    "expires_at": (now + chrono::Duration::minutes(RESET_TOKEN_TTL_MINUTES)).timestamp_millis(),
+                },
+                None,
+            )
+            .await
+            .map_err(|_| AppError::Internal("数据库错误".to_string()))?;
+
+        let base = &config::get().public_base_url;
+        let body = format!(
+            "您好 {}，\n\n请在 {} 分钟内打开以下链接重置密码：\n{}/static/reset_password.html?token={}\n\n如非本人操作，请忽略本邮件。",
+            user.get_str("username").unwrap_or(""),
+            RESET_TOKEN_TTL_MINUTES,
+            base.trim_end_matches('/'),
+            token
+        );
+        if let Err(e) = MAILER.send(email, "重置密码", &body).await {
+            println!("发送重置密码邮件失败 {}: {}", email, e);
         }
+    }
+
+    Ok(Json(serde_json::json!({ "message": "如果该邮箱已注册，重置链接已发送" })))
+}
+
+fn magic_link_payload(user_hex: &str, nonce: &str, expires_at: i64) -> String {
+    format!("magic_link:{}:{}:{}", user_hex, nonce, expires_at)
+}
+
+// POST /user/magic_link {email} -> 发送一次性免密登录链接；与找回密码一样，不暴露邮箱是否注册
+async fn request_magic_link(
+    State(client): State<AppState>,
+    Json(payload): Json<MagicLinkRequest>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let email = payload.email.trim();
+    let user = user_collection(&client).find_one(doc! { "email": email }, None).await?;
+
+    if let Some(user) = user {
+        let user_id = user.get_object_id("_id").map_err(|_| AppError::Internal("用户数据异常".to_string()))?;
+        let nonce = hex::encode(rand::random::<[u8; 16]>());
+        let now = chrono::Utc::now();
+        let expires_at = (now + chrono::Duration::minutes(MAGIC_LINK_TTL_MINUTES)).timestamp_millis();
+        let links = magic_link_collection(&client);
+        // 只保留最新的一条链接
+        links
+            .update_many(doc! { "user_id": user_id, "used": false }, doc! { "$set": { "used": true } }, None)
+            .await?;
+        links
+            .insert_one(
+                doc! {
+                    "user_id": user_id,
+                    "nonce_hash": hash_key(&nonce),
+                    "used": false,
+                    "created_at": now.timestamp_millis(),
+                    "expires_at": expires_at,
+                },
+                None,
+            )
+            .await?;
+
+        // 令牌自带签名与过期时间，伪造或过期的链接无需查库即可拒绝
+        let user_hex = user_id.to_hex();
+        let sig = signing::sign(&magic_link_payload(&user_hex, &nonce, expires_at));
+        let token = format!("{}.{}.{}.{}", user_hex, nonce, expires_at, sig);
+        let body = format!(
+            "您好 {}，\n\n请在 {} 分钟内打开以下链接直接登录（链接仅可使用一次）：\n{}/static/magic_login.html?token={}\n\n如非本人操作，请忽略本邮件。",
+            user.get_str("username").unwrap_or(""),
+            MAGIC_LINK_TTL_MINUTES,
+            config::get().public_base_url.trim_end_matches('/'),
+            token
+        );
+        if let Err(e) = MAILER.send(email, "登录链接", &body).await {
+            println!("发送登录链接邮件失败 {}: {}", email, e);
+        }
+    }
+
+    Ok(Json(serde_json::json!({ "message": "如果该邮箱已注册，登录链接已发送" })))
+}
+
+// POST /user/magic_link/callback {token} -> 校验签名并作废链接，建立与密码登录相同的会话
+async fn magic_link_callback(
+    State(client): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(payload): Json<MagicLinkCallback>,
+) -> Result<auth::LoginResponse, AppError> {
+    let invalid = || AppError::Unauthorized("登录链接无效或已过期".to_string());
+    let parts: Vec<&str> = payload.token.trim().split('.').collect();
+    let [user_hex, nonce, expires_at, sig] = parts[..] else { return Err(invalid()) };
+    let expires_at: i64 = expires_at.parse().map_err(|_| invalid())?;
+    let now = chrono::Utc::now().timestamp_millis();
+    if expires_at <= now || !signing::verify(&magic_link_payload(user_hex, nonce, expires_at), sig) {
+        return Err(invalid());
+    }
+    let user_id = ObjectId::parse_str(user_hex).map_err(|_| invalid())?;
+
+    // 原子地标记为已使用，保证链接只能用一次
+    magic_link_collection(&client)
+        .find_one_and_update(
+            doc! { "user_id": user_id, "nonce_hash": hash_key(nonce), "used": false, "expires_at": { "$gt": now } },
+            doc! { "$set": { "used": true, "used_at": now } },
+            None,
+        )
+        .await?
+        .ok_or_else(invalid)?;
+    let user = user_collection(&client).find_one(doc! { "_id": user_id }, None).await?.ok_or_else(invalid)?;
+
+    let role = user.get_i32("role").unwrap_or(0);
+    let user_agent = headers
+        .get(header::USER_AGENT)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+    let ip = client_ip(&headers, Some(peer));
+    let mut resp = auth::start_session(&client, user_id, role, user_agent, &ip, payload.cookie).await?;
+
+    resp.body["message"] = "Login successful".into();
+    resp.body["user"] = serde_json::json!({
+        "id": user_hex,
+        "email": user.get_str("email").unwrap_or(""),
+        "username": user.get_str("username").unwrap_or(""),
+        "role": role,
+    });
+    Ok(resp)
+}
+
+// POST /user/reset_password -> 凭一次性令牌设置新密码，并使该用户所有会话失效
+async fn reset_password(
+    State(client): State<AppState>,
+    Json(payload): Json<ResetPasswordRequest>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    if payload.new_password.chars().count() < MIN_PASSWORD_LEN {
+        return Err(AppError::BadRequest(format!("密码至少 {} 位", MIN_PASSWORD_LEN)));
+    }
+    let now = chrono::Utc::now().timestamp_millis();
+    // 原子地标记为已使用，保证令牌只能用一次
+    let reset = password_reset_collection(&client)
+        .find_one_and_update(
+            doc! { "token_hash": hash_key(payload.token.trim()), "used": false, "expires_at": { "$gt": now } },
+            doc! { "$set": { "used": true, "used_at": now } },
+            None,
+        )
+        .await
+        .map_err(|_| AppError::Internal("数据库错误".to_string()))?
+        .ok_or(AppError::BadRequest("重置链接无效或已过期".to_string()))?;
+    let user_id = reset.get_object_id("user_id").map_err(|_| AppError::Internal("数据异常".to_string()))?;
+
+    let hashed = hash_password(&payload.new_password).map_err(|_| {
+        AppError::Internal("密码加密失败".to_string())
+    })?;
+    user_collection(&client)
+        .update_one(doc! { "_id": user_id }, doc! { "$set": { "password": hashed } }, None)
+        .await
+        .map_err(|_| AppError::Internal("数据库错误".to_string()))?;
+    auth::revoke_all_sessions(&client, user_id).await?;
+    audit::record(&client, Some(user_id), "user.password_reset", &format!("user:{}", user_id.to_hex()), doc! {}).await;
+
+    Ok(Json(serde_json::json!({ "message": "密码已重置，请重新登录" })))
+}
+
+// POST /user/refresh -> 用刷新令牌换取新的访问令牌（刷新令牌同时轮换）
+async fn refresh(
+    State(client): State<AppState>,
+    Json(payload): Json<RefreshRequest>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    Ok(Json(auth::refresh_session(&client, &payload.refresh_token).await?))
+}
+
+// POST /user/logout -> 吊销当前会话，刷新令牌与会话 Cookie 随之失效
+async fn logout(
+    State(client): State<AppState>,
+    auth: auth::AuthUser,
+) -> Result<Response, AppError> {
+    let session_id = auth.session_id.ok_or(AppError::BadRequest("当前请求不属于任何会话".to_string()))?;
+    auth::revoke_session(&client, auth.id, session_id).await?;
+    Ok((auth::clear_cookies(), Json(serde_json::json!({ "message": "已退出登录" }))).into_response())
+}
+
+// GET /user/sessions -> 本人的有效会话列表
+async fn list_sessions(
+    State(client): State<AppState>,
+    auth: auth::AuthUser,
+) -> Result<Json<Vec<serde_json::Value>>, AppError> {
+    let now = chrono::Utc::now().timestamp_millis();
+    let options = mongodb::options::FindOptions::builder().sort(doc! { "last_used_at": -1 }).build();
+    let mut cursor = session_collection(&client)
+        .find(doc! { "user_id": auth.id, "revoked": false, "expires_at": { "$gt": now } }, options)
+        .await
+        .map_err(|_| AppError::Internal("查询失败".to_string()))?;
+    let mut sessions = Vec::new();
+    while let Some(s) = cursor.next().await {
+        let s = s.map_err(|_| AppError::Internal("读取错误".to_string()))?;
+        let id = s.get_object_id("_id").ok();
+        sessions.push(serde_json::json!({
+            "id": id.map(|o| o.to_hex()).unwrap_or_default(),
+            "user_agent": s.get_str("user_agent").unwrap_or(""),
+            "ip": s.get_str("ip").unwrap_or(""),
+            "created_at": s.get_i64("created_at").unwrap_or(0),
+            "last_used_at": s.get_i64("last_used_at").unwrap_or(0),
+            "expires_at": s.get_i64("expires_at").unwrap_or(0),
+            "current": id.is_some() && id == auth.session_id,
+        }));
+    }
+    Ok(Json(sessions))
+}
+
+// DELETE /user/sessions/:session_id -> 吊销本人的某个会话（如在其他设备上登出）
+async fn revoke_session(
+    State(client): State<AppState>,
+    auth: auth::AuthUser,
+    Path(session_id): Path<String>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let sid = ObjectId::parse_str(&session_id)
+        .map_err(|_| AppError::BadRequest("无效的会话ID".to_string()))?;
+    if !auth::revoke_session(&client, auth.id, sid).await? {
+        return Err(AppError::NotFound("会话不存在或已失效".to_string()));
+    }
+    Ok(Json(serde_json::json!({ "message": "会话已吊销", "id": session_id })))
+}
+
+// GET /user/me -> 当前令牌对应的用户
+async fn get_me(
+    State(client): State<AppState>,
+    auth: auth::AuthUser,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let user = user_collection(&client)
+        .find_one(doc! { "_id": auth.id }, None)
+        .await
+        .map_err(|_| AppError::Internal("数据库错误".to_string()))?
+        .ok_or(AppError::Unauthorized("用户不存在".to_string()))?;
+    Ok(Json(serde_json::json!({
+        "id": auth.id_hex(),
+        "email": user.get_str("email").unwrap_or(""),
+        "username": user.get_str("username").unwrap_or(""),
+        "role": auth.role,
     })))
 }
 
+fn preferences_json(user: &Document) -> serde_json::Value {
+    let prefs = user.get_document("preferences").ok();
+    let time = UserTime::for_user(user);
+    serde_json::json!({
+        "lecture_report_email": prefs
+            .and_then(|p| p.get_bool("lecture_report_email").ok())
+            .unwrap_or(true),
+        "timezone": time.tz.name(),
+        "locale": time.locale.code(),
+    })
+}
+
+// GET /user/me/preferences
+async fn get_preferences(
+    State(client): State<AppState>,
+    auth: auth::AuthUser,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let user = user_collection(&client)
+        .find_one(doc! { "_id": auth.id }, None)
+        .await
+        .map_err(|_| AppError::Internal("数据库错误".to_string()))?
+        .ok_or(AppError::Unauthorized("用户不存在".to_string()))?;
+    Ok(Json(preferences_json(&user)))
+}
+
+// PUT /user/me/preferences
+async fn update_preferences(
+    State(client): State<AppState>,
+    auth: auth::AuthUser,
+    Json(payload): Json<PreferencesUpdate>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let mut set = Document::new();
+    if let Some(v) = payload.lecture_report_email {
+        set.insert("preferences.lecture_report_email", v);
+    }
+    if let Some(tz) = payload.timezone {
+        let tz = timefmt::parse_timezone(&tz)
+            .ok_or(AppError::BadRequest("timezone 无效，应为 IANA 时区名，如 Asia/Shanghai".to_string()))?;
+        set.insert("preferences.timezone", tz.name());
+    }
+    if let Some(locale) = payload.locale {
+        let locale = Locale::parse(&locale)
+            .ok_or(AppError::BadRequest("locale 仅支持 zh-CN 或 en-US".to_string()))?;
+        set.insert("preferences.locale", locale.code());
+    }
+    let coll = user_collection(&client);
+    if !set.is_empty() {
+        coll.update_one(doc! { "_id": auth.id }, doc! { "$set": set }, None)
+            .await
+            .map_err(|_| AppError::Internal("更新失败".to_string()))?;
+    }
+    let user = coll
+        .find_one(doc! { "_id": auth.id }, None)
+        .await
+        .map_err(|_| AppError::Internal("数据库错误".to_string()))?
+        .ok_or(AppError::Unauthorized("用户不存在".to_string()))?;
+    Ok(Json(preferences_json(&user)))
+}
+
+// ==================== 标签订阅 ====================
+
+async fn subscriptions_json(client: &AppState, user_id: ObjectId) -> Result<Json<serde_json::Value>, AppError> {
+    let user = user_collection(client)
+        .find_one(doc! { "_id": user_id }, None)
+        .await?
+        .ok_or(AppError::Unauthorized("用户不存在".to_string()))?;
+    Ok(Json(serde_json::json!({ "tags": tags::string_array(&user, "subscribed_tags") })))
+}
+
+// GET /user/me/subscriptions -> 已订阅的标签
+async fn get_subscriptions(
+    State(client): State<AppState>,
+    auth: auth::AuthUser,
+) -> Result<Json<serde_json::Value>, AppError> {
+    subscriptions_json(&client, auth.id).await
+}
+
+// PUT /user/me/subscriptions {tags} -> 整体替换订阅
+async fn set_subscriptions(
+    State(client): State<AppState>,
+    auth: auth::AuthUser,
+    Json(payload): Json<SubscriptionUpdate>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let tags = tags::validate(&payload.tags, MAX_SUBSCRIBED_TAGS)?;
+    user_collection(&client)
+        .update_one(doc! { "_id": auth.id }, doc! { "$set": { "subscribed_tags": tags } }, None)
+        .await?;
+    subscriptions_json(&client, auth.id).await
+}
+
+// POST /user/me/subscriptions/:tag -> 订阅单个标签（重复订阅无副作用）
+async fn subscribe_tag(
+    State(client): State<AppState>,
+    auth: auth::AuthUser,
+    Path(tag): Path<String>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let tag = tags::validate_tag(&tag)?;
+    // 数量上限作为更新条件，避免并发订阅时越过上限
+    let result = user_collection(&client)
+        .update_one(
+            doc! {
+                "_id": auth.id,
+                format!("subscribed_tags.{}", MAX_SUBSCRIBED_TAGS - 1): { "$exists": false },
+            },
+            doc! { "$addToSet": { "subscribed_tags": &tag } },
+            None,
+        )
+        .await?;
+    if result.matched_count == 0 {
+        let current = subscriptions_json(&client, auth.id).await?;
+        let already = current.0["tags"].as_array().is_some_and(|t| t.iter().any(|v| v == tag.as_str()));
+        if !already {
+            return Err(AppError::BadRequest(format!("最多订阅 {} 个标签", MAX_SUBSCRIBED_TAGS)));
+        }
+        return Ok(current);
+    }
+    subscriptions_json(&client, auth.id).await
+}
+
+// DELETE /user/me/subscriptions/:tag
+async fn unsubscribe_tag(
+    State(client): State<AppState>,
+    auth: auth::AuthUser,
+    Path(tag): Path<String>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let tag = tag.trim().to_lowercase();
+    user_collection(&client)
+        .update_one(doc! { "_id": auth.id }, doc! { "$pull": { "subscribed_tags": &tag } }, None)
+        .await?;
+    subscriptions_json(&client, auth.id).await
+}
+
+// GET /user/me/feed -> 个性化推荐：带有已订阅标签、尚未结束的公开演讲，按开始时间排序
+async fn get_feed(
+    State(client): State<AppState>,
+    auth: auth::AuthUser,
+    paging: PageParams,
+) -> Result<Response, AppError> {
+    let user = user_collection(&client)
+        .find_one(doc! { "_id": auth.id }, None)
+        .await?
+        .ok_or(AppError::Unauthorized("用户不存在".to_string()))?;
+    let subscribed = tags::string_array(&user, "subscribed_tags");
+    if subscribed.is_empty() {
+        return Ok(paging.respond(Vec::<serde_json::Value>::new(), 0));
+    }
+
+    let filter = doc! {
+        "tags": { "$in": &subscribed },
+        "status": { "$in": [LectureStatus::Scheduled.as_i32(), LectureStatus::Live.as_i32()] },
+        "archived": { "$ne": true },
+    };
+    let coll = lecture_collection(&client);
+    let total = coll.count_documents(filter.clone(), None).await?;
+    let lectures: Vec<Document> = coll
+        .find(filter, paging.find_options(doc! { "start_time": 1, "_id": 1 }))
+        .await?
+        .try_collect()
+        .await?;
+    let items: Vec<serde_json::Value> = lectures
+        .into_iter()
+        .map(|lecture| {
+            let matched: Vec<String> = tags::string_array(&lecture, "tags")
+                .into_iter()
+                .filter(|t| subscribed.contains(t))
+                .collect();
+            let mut v = ids::doc_to_json(lecture);
+            if let Some(obj) = v.as_object_mut() {
+                obj.insert("matched_tags".to_string(), serde_json::json!(matched));
+            }
+            v
+        })
+        .collect();
+    Ok(paging.respond(items, total))
+}
+
+#[derive(Deserialize)]
+struct CalendarQuery {
+    token: Option<String>,
+}
+
+fn calendar_feed_url(user_hex: &str, token: &str) -> String {
+    format!("{}/user/{}/calendar.ics?token={}", config::get().public_base_url.trim_end_matches('/'), user_hex, token)
+}
+
+// GET /user/me/calendar -> 本人日历订阅地址（首次访问时生成令牌）
+async fn get_calendar_feed(
+    State(client): State<AppState>,
+    auth: auth::AuthUser,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let user = user_collection(&client)
+        .find_one(doc! { "_id": auth.id }, None)
+        .await?
+        .ok_or(AppError::NotFound("用户未找到".to_string()))?;
+    let token = match user.get_str("calendar_token") {
+        Ok(token) => token.to_string(),
+        Err(_) => rotate_calendar_token(&client, auth.id).await?,
+    };
+    Ok(Json(serde_json::json!({ "url": calendar_feed_url(&auth.id_hex(), &token) })))
+}
+
+// POST /user/me/calendar/rotate -> 订阅地址泄露时更换令牌，旧地址立即失效
+async fn rotate_calendar_feed(
+    State(client): State<AppState>,
+    auth: auth::AuthUser,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let token = rotate_calendar_token(&client, auth.id).await?;
+    Ok(Json(serde_json::json!({ "url": calendar_feed_url(&auth.id_hex(), &token) })))
+}
+
+async fn rotate_calendar_token(client: &AppState, user_id: ObjectId) -> Result<String, AppError> {
+    let token = hex::encode(rand::random::<[u8; 24]>());
+    user_collection(client)
+        .update_one(doc! { "_id": user_id }, doc! { "$set": { "calendar_token": &token } }, None)
+        .await?;
+    Ok(token)
+}
+
+// GET /user/:user_id/calendar.ics?token=.. -> 日历订阅源：本人组织、主讲或已报名的演讲。
+// 日历客户端无法携带登录凭据，以订阅令牌认证；已登录本人也可直接访问
+async fn get_calendar(
+    State(client): State<AppState>,
+    auth: Option<auth::AuthUser>,
+    Path(user_id): Path<String>,
+    Query(query): Query<CalendarQuery>,
+) -> Result<Response, AppError> {
+    let user_oid = ids::parse_oid(&user_id, "user_id")?;
+    let user = user_collection(&client)
+        .find_one(doc! { "_id": user_oid }, None)
+        .await?
+        .ok_or(AppError::NotFound("用户未找到".to_string()))?;
+    let token_ok = matches!(
+        (query.token.as_deref(), user.get_str("calendar_token").ok()),
+        (Some(given), Some(expected)) if !given.is_empty() && given == expected
+    );
+    if !token_ok && auth.as_ref().map(|a| a.id) != Some(user_oid) {
+        return Err(AppError::Unauthorized("订阅令牌无效".to_string()));
+    }
+
+    let user_hex = user_oid.to_hex();
+    let joined: Vec<ObjectId> = la_collection(&client)
+        .find(doc! { "audience_id": user_oid }, None)
+        .await?
+        .try_collect::<Vec<Document>>()
+        .await?
+        .iter()
+        .filter_map(|r| r.get_object_id("lecture_id").ok())
+        .collect();
+    // 草稿只出现在组织者本人的日历中
+    let filter = doc! { "$or": [
+        { "organizer_id": &user_hex },
+        { "speaker_id": &user_hex, "status": { "$ne": LectureStatus::Draft.as_i32() } },
+        { "_id": { "$in": joined }, "status": { "$ne": LectureStatus::Draft.as_i32() } },
+    ] };
+    let options = mongodb::options::FindOptions::builder().sort(doc! { "start_time": 1 }).build();
+    let lectures: Vec<Document> = lecture_collection(&client).find(filter, options).await?.try_collect().await?;
+
+    let name = format!("{} 的演讲日程", user.get_str("username").unwrap_or(""));
+    Ok(ics::response(ics::calendar(&name, &lectures), "calendar.ics"))
+}
+
+// GET /user/:user_id/transcript?format=json|pdf -> 本人的出勤证明：所有计为到场的演讲及时长
+async fn get_transcript(
+    State(client): State<AppState>,
+    auth: auth::AuthUser,
+    Path(user_id): Path<String>,
+    Query(query): Query<TranscriptQuery>,
+) -> Result<Response, AppError> {
+    auth.ensure_self(&user_id)?;
+    let user = user_collection(&client)
+        .find_one(doc! { "_id": auth.id }, None)
+        .await
+        .map_err(|_| AppError::Internal("查询失败".to_string()))?
+        .ok_or(AppError::NotFound("用户未找到".to_string()))?;
+
+    let mut lecture_ids = Vec::new();
+    let mut cursor = la_collection(&client)
+        .find(doc! { "audience_id": auth.id, "is_present": true }, None)
+        .await
+        .map_err(|_| AppError::Internal("查询失败".to_string()))?;
+    while let Some(record) = cursor.next().await {
+        let record = record.map_err(|_| AppError::Internal("读取错误".to_string()))?;
+        if let Ok(oid) = record.get_object_id("lecture_id") {
+            lecture_ids.push(oid);
+        }
+    }
+
+    let options = mongodb::options::FindOptions::builder().sort(doc! { "start_time": 1 }).build();
+    let mut cursor = lecture_collection(&client)
+        .find(doc! { "_id": { "$in": &lecture_ids } }, options)
+        .await
+        .map_err(|_| AppError::Internal("查询失败".to_string()))?;
+    let time = UserTime::for_user(&user);
+    let mut items = Vec::new();
+    let mut total_minutes = 0;
+    while let Some(lecture) = cursor.next().await {
+        let lecture = lecture.map_err(|_| AppError::Internal("读取错误".to_string()))?;
+        let start_time = lecture.get_i64("start_time").unwrap_or(0);
+        let duration = lecture.get_i32("duration").unwrap_or(0).max(0);
+        total_minutes += duration;
+        items.push(serde_json::json!({
+            "lecture_id": ids::oid_hex(&lecture, "_id"),
+            "topic": lecture.get_str("topic").unwrap_or(""),
+            "start_time": start_time,
+            "date": time.date(start_time),
+            "duration": duration,
+            "hours": (duration as f64 / 60.0 * 100.0).round() / 100.0,
+        }));
+    }
+    let total_hours = (total_minutes as f64 / 60.0 * 100.0).round() / 100.0;
+    let username = user.get_str("username").unwrap_or("").to_string();
+
+    if query.format.as_deref() != Some("pdf") {
+        return Ok(Json(serde_json::json!({
+            "user_id": user_id,
+            "username": username,
+            "lecture_count": items.len(),
+            "total_hours": total_hours,
+            "lectures": items,
+        }))
+        .into_response());
+    }
+
+    let mut lines = vec![
+        pdf::Line::new("出勤证明 Attendance Transcript", 18.0),
+        pdf::Line::new(format!("姓名: {}    用户ID: {}", username, user_id), 11.0),
+        pdf::Line::new(
+            format!("共 {} 场演讲，合计 {} 小时", items.len(), total_hours),
+            11.0,
+        ),
+        pdf::Line::new(
+            format!("生成时间: {}", time.now()),
+            11.0,
+        ),
+        pdf::Line::new("", 11.0),
+    ];
+    for item in &items {
+        lines.push(pdf::Line::new(
+            format!(
+                "{}  {}h  {}",
+                item["date"].as_str().unwrap_or(""),
+                item["hours"],
+                item["topic"].as_str().unwrap_or("")
+            ),
+            11.0,
+        ));
+    }
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/pdf".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"transcript-{}.pdf\"", user_id),
+            ),
+        ],
+        pdf::render(lines),
+    )
+        .into_response())
+}
+
 async fn get_all_users(
     State(client): State<AppState>,
-) -> Result<Json<Vec<serde_json::Value>>, (StatusCode, String)> {
+    paging: PageParams,
+) -> Result<Response, AppError> {
     let collection = user_collection(&client);
 
-    let mut cursor = collection.find(doc! {}, None).await
-        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "查询失败".to_string()))?;
+    let total = collection.count_documents(doc! {}, None).await
+        .map_err(|_| AppError::Internal("查询失败".to_string()))?;
+    let mut cursor = collection.find(doc! {}, paging.find_options(doc! { "_id": 1 })).await
+        .map_err(|_| AppError::Internal("查询失败".to_string()))?;
 
     let mut users = Vec::new();
     while let Some(result) = cursor.next().await {
-        let mut doc = result.map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "读取错误".to_string()))?;
-        doc.remove("password");
-        let id = doc.get_object_id("_id").unwrap().to_hex();
-        doc.insert("id", id);
-        doc.remove("_id");
-        users.push(serde_json::to_value(doc).unwrap());
+        let doc = result.map_err(|_| AppError::Internal("读取错误".to_string()))?;
+        users.push(ids::user_to_json(doc));
     }
 
-    Ok(Json(users))
+    Ok(paging.respond(users, total))
 }
 
 async fn get_user(
     State(client): State<AppState>,
     Path(user_id): Path<String>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, AppError> {
     let collection = user_collection(&client);
 
     let obj_id = ObjectId::parse_str(&user_id)
-        .map_err(|_| (StatusCode::BAD_REQUEST, "无效的用户ID".to_string()))?;
+        .map_err(|_| AppError::BadRequest("无效的用户ID".to_string()))?;
 
     let user = collection.find_one(doc! { "_id": obj_id }, None).await
-        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "查询失败".to_string()))?
-        .ok_or((StatusCode::NOT_FOUND, "用户未找到".to_string()))?;
-
-    let mut user: serde_json::Value = bson::from_document(user)
-        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "序列化错误".to_string()))?;
-
-    let obj = user.as_object_mut().unwrap();
-    obj.remove("password");
-    
-    
-    // let id = obj.get("_id").unwrap().as_str().unwrap().to_string(); // _id 已经是 hex 字符串
-    // obj.insert("id".to_string(), serde_json::Value::String(id));
-    // obj.remove("_id");
-    
-    // 替换有问题的部分：
-    let id = match obj.get("_id") {
-        Some(serde_json::Value::String(s)) => s.clone(),
-        Some(other_value) => other_value.to_string(),
-        None => user_id.clone(), // 如果没有 _id，使用传入的 user_id
+        .map_err(|_| AppError::Internal("查询失败".to_string()))?
+        .ok_or(AppError::NotFound("用户未找到".to_string()))?;
+
+    Ok(Json(ids::user_to_json(user)))
+}
+
+// GET /user/speakers?tag=&q=&page=&limit= -> 讲者目录（含历史演讲数与平均评分）
+async fn list_speakers(
+    State(client): State<AppState>,
+    Query(query): Query<SpeakerQuery>,
+) -> Result<Response, AppError> {
+    let collection = user_collection(&client);
+
+    let page = query.page.unwrap_or(1).max(1);
+    let limit = query.limit.unwrap_or(20).clamp(1, 100);
+
+    let mut filter = doc! { "role": 2 };
+    if let Some(tag) = query.tag.as_ref().map(|t| t.trim().to_lowercase()).filter(|t| !t.is_empty()) {
+        filter.insert("expertise", tag);
+    }
+    if let Some(q) = query.q.as_ref().map(|q| q.trim()).filter(|q| !q.is_empty()) {
+        let pattern = regex::escape(q);
+        filter.insert("$or", vec![
+            doc! { "username": { "$regex": &pattern, "$options": "i" } },
+            doc! { "bio": { "$regex": &pattern, "$options": "i" } },
+        ]);
+    }
+
+    // lecture.speaker_id 以 hex 字符串存储，因此按字符串关联；评分取自已结束演讲的反馈
+    let pipeline = vec![
+        doc! { "$match": filter },
+        doc! { "$lookup": {
+            "from": "lecture",
+            "let": { "sid": { "$toString": "$_id" } },
+            "pipeline": [
+                { "$match": { "$expr": { "$and": [
+                    { "$eq": ["$speaker_id", "$$sid"] },
+                    { "$eq": ["$status", -1] },
+                ] } } },
+                { "$lookup": {
+                    "from": "feedback",
+                    "localField": "_id",
+                    "foreignField": "lecture_id",
+                    "as": "fb",
+                } },
+                { "$project": { "ratings": "$fb.rating" } },
+            ],
+            "as": "past",
+        } },
+        doc! { "$addFields": {
+            "past_lecture_count": { "$size": "$past" },
+            "ratings": { "$reduce": {
+                "input": "$past.ratings",
+                "initialValue": [],
+                "in": { "$concatArrays": ["$$value", "$$this"] },
+            } },
+        } },
+        doc! { "$addFields": { "rating": { "$avg": "$ratings" } } },
+        doc! { "$sort": { "past_lecture_count": -1, "username": 1 } },
+        doc! { "$facet": {
+            "items": [
+                { "$skip": ((page - 1) * limit) as i64 },
+                { "$limit": limit as i64 },
+                { "$project": {
+                    "username": 1, "avatar": 1, "bio": 1, "expertise": 1,
+                    "past_lecture_count": 1, "rating": 1,
+                } },
+            ],
+            "total": [{ "$count": "count" }],
+        } },
+    ];
+
+    let mut cursor = collection.aggregate(pipeline, None).await
+        .map_err(|_| AppError::Internal("查询失败".to_string()))?;
+    let result = match cursor.next().await {
+        Some(r) => r.map_err(|_| AppError::Internal("读取错误".to_string()))?,
+        None => doc! {},
     };
-    obj.insert("id".to_string(), serde_json::Value::String(id));
-    obj.remove("_id");
 
-    Ok(Json(user))
+    let total = result
+        .get_array("total")
+        .ok()
+        .and_then(|a| a.first())
+        .and_then(|d| d.as_document())
+        .and_then(|d| d.get_i32("count").ok())
+        .unwrap_or(0) as u64;
+
+    let mut speakers = Vec::new();
+    for item in result.get_array("items").cloned().unwrap_or_default() {
+        let Some(doc) = item.as_document() else { continue };
+        let rating = doc
+            .get_f64("rating")
+            .ok()
+            .map(|r| (r * 100.0).round() / 100.0);
+        speakers.push(serde_json::json!({
+            "id": ids::oid_hex(doc, "_id"),
+            "username": doc.get_str("username").unwrap_or(""),
+            "avatar": doc.get_str("avatar").unwrap_or(""),
+            "bio": doc.get_str("bio").unwrap_or(""),
+            "expertise": doc.get_array("expertise").cloned().unwrap_or_default(),
+            "past_lecture_count": doc.get_i32("past_lecture_count").unwrap_or(0),
+            "rating": rating,
+        }));
+    }
+
+    let mut resp = Json(serde_json::json!({
+        "items": speakers,
+        "total": total,
+        "page": page,
+        "limit": limit,
+        "has_more": page * limit < total,
+    }))
+    .into_response();
+    resp.extensions_mut().insert(Pagination { page, limit, total });
+    Ok(resp)
 }
 
-const UPLOAD_DIR: &str = "static/uploads";
+// 头像等上传目录由 config.upload_dir 指定，对外统一以 /static/uploads/ 访问
+fn upload_dir() -> &'static str {
+    &crate::config::get().upload_dir
+}
 
 async fn update_user_with_files(
     State(client): State<AppState>,
     Path(user_id): Path<String>,
     mut multipart: Multipart,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, AppError> {
     let collection = user_collection(&client);
 
     let obj_id = ObjectId::parse_str(&user_id)
-        .map_err(|_| (StatusCode::BAD_REQUEST, "无效的用户ID".to_string()))?;
+        .map_err(|_| AppError::BadRequest("无效的用户ID".to_string()))?;
 
     let db_user = collection.find_one(doc! { "_id": obj_id }, None).await
-        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "查询失败".to_string()))?
-        .ok_or((StatusCode::NOT_FOUND, "用户未找到".to_string()))?;
+        .map_err(|_| AppError::Internal("查询失败".to_string()))?
+        .ok_or(AppError::NotFound("用户未找到".to_string()))?;
 
     let mut update_data = doc! {};
     let mut paths = doc! { "avatar": null, "background": null };
@@ -221,12 +1036,12 @@ async fn update_user_with_files(
             "username" => {
                 let username = field.text().await.unwrap_or_default();
                 if username.is_empty() {
-                    return Err((StatusCode::BAD_REQUEST, "用户名不能为空".to_string()));
+                    return Err(AppError::BadRequest("用户名不能为空".to_string()));
                 }
-                if Some(&username) != current_username.as_ref() {
-                    if collection.find_one(doc! { "username": &username }, None).await.unwrap().is_some() {
-                        return Err((StatusCode::BAD_REQUEST, "用户名已被使用".to_string()));
-                    }
+                if Some(&username) != current_username.as_ref()
+                    && collection.find_one(doc! { "username": &username }, None).await.unwrap().is_some()
+                {
+                    return Err(AppError::BadRequest("用户名已被使用".to_string()));
                 }
                 update_data.insert("username", username);
             }
@@ -250,21 +1065,35 @@ async fn update_user_with_files(
                     update_data.insert("motto", motto);
                 }
             }
+            "bio" => {
+                let bio = field.text().await.unwrap_or_default().trim().to_string();
+                if bio.chars().count() > MAX_BIO_CHARS {
+                    return Err(AppError::BadRequest(format!("简介不能超过 {} 字", MAX_BIO_CHARS)));
+                }
+                update_data.insert("bio", bio);
+            }
+            "expertise" => {
+                let tags = parse_expertise(&field.text().await.unwrap_or_default());
+                if tags.len() > MAX_EXPERTISE_TAGS {
+                    return Err(AppError::BadRequest(format!("专长标签最多 {} 个", MAX_EXPERTISE_TAGS)));
+                }
+                update_data.insert("expertise", tags);
+            }
             "avatar" | "background" => {
                 let filename = field.file_name().unwrap_or("unknown").to_string();
                 let ext = std::path::Path::new(&filename)
                     .extension()
                     .and_then(|s| s.to_str())
                     .unwrap_or("");
-                let new_filename = format!("{}{}", Uuid::new_v4().to_string(), ext);
-                let path = format!("{}/{}", UPLOAD_DIR, new_filename);
+                let new_filename = format!("{}{}", Uuid::new_v4(), ext);
+                let path = format!("{}/{}", upload_dir(), new_filename);
 
                 let mut file = std::fs::File::create(&path)
-                    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "无法保存文件".to_string()))?;
+                    .map_err(|_| AppError::Internal("无法保存文件".to_string()))?;
                 let bytes = field.bytes().await
-                    .map_err(|_| (StatusCode::BAD_REQUEST, "读取文件失败".to_string()))?;
+                    .map_err(|_| AppError::BadRequest("读取文件失败".to_string()))?;
                 std::io::copy(&mut bytes.as_ref(), &mut file)
-                    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "写入文件失败".to_string()))?;
+                    .map_err(|_| AppError::Internal("写入文件失败".to_string()))?;
 
                 let url = format!("/static/uploads/{}", new_filename);
                 if name == "avatar" {
@@ -280,11 +1109,11 @@ async fn update_user_with_files(
     }
 
     if update_data.is_empty() {
-        return Err((StatusCode::BAD_REQUEST, "没有可更新的字段".to_string()));
+        return Err(AppError::BadRequest("没有可更新的字段".to_string()));
     }
 
     collection.update_one(doc! { "_id": obj_id }, doc! { "$set": update_data.clone() }, None).await
-        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "更新失败".to_string()))?;
+        .map_err(|_| AppError::Internal("更新失败".to_string()))?;
 
     Ok(Json(serde_json::json!({
         "message": "用户信息已更新",
@@ -296,13 +1125,31 @@ async fn update_user_with_files(
 // ==================== Router ====================
 
 pub fn router() -> Router<AppState> {
-    std::fs::create_dir_all(UPLOAD_DIR).expect("无法创建上传目录");
+    std::fs::create_dir_all(upload_dir()).expect("无法创建上传目录");
 
     Router::new()
         .route("/register", post(register))
         .route("/login", post(login))
         .route("/", get(get_all_users))
+        .route("/me", get(get_me))
+        .route("/me/preferences", get(get_preferences).put(update_preferences))
+        .route("/me/subscriptions", get(get_subscriptions).put(set_subscriptions))
+        .route("/me/subscriptions/:tag", post(subscribe_tag).delete(unsubscribe_tag))
+        .route("/me/feed", get(get_feed))
+        .route("/me/calendar", get(get_calendar_feed))
+        .route("/me/calendar/rotate", post(rotate_calendar_feed))
+        .route("/refresh", post(refresh))
+        .route("/forgot_password", post(forgot_password))
+        .route("/reset_password", post(reset_password))
+        .route("/magic_link", post(request_magic_link))
+        .route("/magic_link/callback", post(magic_link_callback))
+        .route("/logout", post(logout))
+        .route("/sessions", get(list_sessions))
+        .route("/sessions/:session_id", axum::routing::delete(revoke_session))
+        .route("/speakers", get(list_speakers))
         .route("/:user_id", get(get_user))
+        .route("/:user_id/transcript", get(get_transcript))
+        .route("/:user_id/calendar.ics", get(get_calendar))
         .route("/update/:user_id", put(update_user_with_files))
 }
 
@@ -0,0 +1,192 @@
+// src/routes/waiting_room.rs
+// 候场：组织者为未开始的演讲开启候场后，已报名的听众可提前进入等候，组织者可看到等候人数；
+// 候场期间讨论只对组织者、讲者开放，演讲开始时实时通道广播 lecture.started，讨论随之开放。
+// 等候状态记在报名记录的 waiting_since 上
+use axum::{
+    extract::{Path, State},
+    response::Json,
+    routing::{get, post},
+    Router,
+};
+use bson::{doc, oid::ObjectId, Document};
+use chrono::Utc;
+use futures_util::TryStreamExt;
+use mongodb::{options::FindOptions, Client};
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::auth::AuthUser;
+use crate::db::{la_collection, lecture_collection, user_collection};
+use crate::error::AppError;
+use crate::lifecycle::LectureStatus;
+use crate::routes::lecture::{is_host, load_lecture};
+use crate::{ids, realtime};
+
+type AppState = Arc<Client>;
+
+// 组织者视图最多列出的等候者
+const MAX_LISTED: i64 = 200;
+
+#[derive(Deserialize)]
+struct WaitingRoomToggle {
+    enabled: bool,
+}
+
+// 候场中：开启了候场且尚未开始。此时讨论仅对组织者、讲者开放
+pub fn is_holding(lecture: &Document) -> bool {
+    lecture.get_bool("waiting_room").unwrap_or(false) && LectureStatus::of(lecture) == LectureStatus::Scheduled
+}
+
+async fn waiting_count(client: &AppState, lecture_oid: ObjectId) -> Result<u64, AppError> {
+    Ok(la_collection(client)
+        .count_documents(doc! { "lecture_id": lecture_oid, "waiting_since": { "$exists": true } }, None)
+        .await?)
+}
+
+async fn publish_count(client: &AppState, lecture_oid: ObjectId) -> Result<u64, AppError> {
+    let waiting = waiting_count(client, lecture_oid).await?;
+    realtime::publish(lecture_oid, "waiting_room.updated", serde_json::json!({ "waiting": waiting }));
+    Ok(waiting)
+}
+
+fn lecture_oid_of(lecture: &Document) -> Result<ObjectId, AppError> {
+    lecture.get_object_id("_id").map_err(|_| AppError::Internal("演讲ID无效".into()))
+}
+
+// PUT /lecture/:id/waiting_room {enabled} -> 开启/关闭候场（组织者或讲者，开播前）
+async fn set_waiting_room(
+    State(client): State<AppState>,
+    auth: AuthUser,
+    Path(lecture_id): Path<String>,
+    Json(payload): Json<WaitingRoomToggle>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let lecture = load_lecture(&client, &lecture_id).await?;
+    if !is_host(&lecture, &auth.id_hex()) {
+        return Err(AppError::Forbidden("只有组织者或讲者可以设置候场".into()));
+    }
+    if !LectureStatus::of(&lecture).is_upcoming() {
+        return Err(AppError::Conflict("演讲已开始或已结束，无法设置候场".into()));
+    }
+    let oid = lecture_oid_of(&lecture)?;
+    lecture_collection(&client)
+        .update_one(doc! { "_id": oid }, doc! { "$set": { "waiting_room": payload.enabled } }, None)
+        .await?;
+    // 关闭候场时清空等候名单
+    if !payload.enabled {
+        la_collection(&client)
+            .update_many(doc! { "lecture_id": oid }, doc! { "$unset": { "waiting_since": "" } }, None)
+            .await?;
+        publish_count(&client, oid).await?;
+    }
+    Ok(Json(serde_json::json!({ "lecture_id": lecture_id, "waiting_room": payload.enabled })))
+}
+
+// GET /lecture/:id/waiting_room -> 等候人数及名单，先到在前（组织者或讲者）
+async fn get_waiting_room(
+    State(client): State<AppState>,
+    auth: AuthUser,
+    Path(lecture_id): Path<String>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let lecture = load_lecture(&client, &lecture_id).await?;
+    if !is_host(&lecture, &auth.id_hex()) {
+        return Err(AppError::Forbidden("只有组织者或讲者可以查看候场名单".into()));
+    }
+    let oid = lecture_oid_of(&lecture)?;
+    let options = FindOptions::builder().sort(doc! { "waiting_since": 1 }).limit(MAX_LISTED).build();
+    let entries: Vec<Document> = la_collection(&client)
+        .find(doc! { "lecture_id": oid, "waiting_since": { "$exists": true } }, options)
+        .await?
+        .try_collect()
+        .await?;
+    let mut attendees = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let Ok(audience_oid) = entry.get_object_id("audience_id") else { continue };
+        let user = user_collection(&client).find_one(doc! { "_id": audience_oid }, None).await?;
+        attendees.push(serde_json::json!({
+            "user_id": audience_oid.to_hex(),
+            "username": user.as_ref().and_then(|u| u.get_str("username").ok()).unwrap_or(""),
+            "waiting_since": entry.get_i64("waiting_since").unwrap_or(0),
+        }));
+    }
+    Ok(Json(serde_json::json!({
+        "lecture_id": lecture_id,
+        "enabled": lecture.get_bool("waiting_room").unwrap_or(false),
+        "status": LectureStatus::of(&lecture).name(),
+        "waiting": waiting_count(&client, oid).await?,
+        "attendees": attendees,
+    })))
+}
+
+// POST /lecture/:id/waiting_room/join -> 已报名听众进入候场；演讲已开始时直接返回 live
+async fn join_waiting_room(
+    State(client): State<AppState>,
+    auth: AuthUser,
+    Path(lecture_id): Path<String>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let lecture = load_lecture(&client, &lecture_id).await?;
+    let oid = lecture_oid_of(&lecture)?;
+    let status = LectureStatus::of(&lecture);
+    if status == LectureStatus::Live {
+        return Ok(Json(serde_json::json!({ "lecture_id": lecture_id, "status": "live" })));
+    }
+    if !is_holding(&lecture) {
+        return Err(AppError::Conflict("该演讲未开启候场".into()));
+    }
+    let result = la_collection(&client)
+        .update_one(
+            doc! { "lecture_id": oid, "audience_id": auth.id, "waiting_since": { "$exists": false } },
+            doc! { "$set": { "waiting_since": Utc::now().timestamp_millis() } },
+            None,
+        )
+        .await?;
+    if result.matched_count == 0
+        && la_collection(&client)
+            .find_one(doc! { "lecture_id": oid, "audience_id": auth.id }, None)
+            .await?
+            .is_none()
+    {
+        return Err(AppError::Forbidden("请先报名该演讲".into()));
+    }
+    let waiting = publish_count(&client, oid).await?;
+    Ok(Json(serde_json::json!({ "lecture_id": lecture_id, "status": "waiting", "waiting": waiting })))
+}
+
+// POST /lecture/:id/waiting_room/leave -> 离开候场
+async fn leave_waiting_room(
+    State(client): State<AppState>,
+    auth: AuthUser,
+    Path(lecture_id): Path<String>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let oid = ids::parse_oid(&lecture_id, "lecture_id")?;
+    let result = la_collection(&client)
+        .update_one(
+            doc! { "lecture_id": oid, "audience_id": auth.id, "waiting_since": { "$exists": true } },
+            doc! { "$unset": { "waiting_since": "" } },
+            None,
+        )
+        .await?;
+    let waiting = if result.modified_count > 0 { publish_count(&client, oid).await? } else { waiting_count(&client, oid).await? };
+    Ok(Json(serde_json::json!({ "lecture_id": lecture_id, "status": "left", "waiting": waiting })))
+}
+
+// 演讲开始时调用：广播 lecture.started，等候中的听众据此进入直播间
+pub async fn open(client: &AppState, lecture: &Document) {
+    let Ok(oid) = lecture.get_object_id("_id") else { return };
+    let admitted = waiting_count(client, oid).await.unwrap_or(0);
+    realtime::publish(
+        oid,
+        "lecture.started",
+        serde_json::json!({
+            "lecture_id": oid.to_hex(),
+            "started_at": lecture.get_i64("started_at").ok(),
+            "admitted": admitted,
+        }),
+    );
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/:lecture_id/waiting_room", get(get_waiting_room).put(set_waiting_room))
+        .route("/:lecture_id/waiting_room/join", post(join_waiting_room))
+        .route("/:lecture_id/waiting_room/leave", post(leave_waiting_room))
+}
@@ -0,0 +1,93 @@
+// src/routes/ws.rs
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Path, Query, State,
+    },
+    response::Response,
+    routing::get,
+    Router,
+};
+use bson::oid::ObjectId;
+use futures_util::{SinkExt, StreamExt};
+use mongodb::Client;
+use serde::Deserialize;
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::auth::{self, AuthUser};
+use crate::error::AppError;
+use crate::realtime;
+use crate::routes::lecture::load_lecture;
+
+type AppState = Arc<Client>;
+
+#[derive(Deserialize, Default)]
+struct SocketQuery {
+    // 浏览器发起 WebSocket 时无法设置 Authorization 头，可改用查询参数携带访问令牌
+    token: Option<String>,
+}
+
+// GET /ws/lecture/:lecture_id -> 演讲房间实时通道。
+// 服务端推送 {type, data, at}：discussion.created / feedback.updated / attendance.updated / presence，
+// 以及 lecture.started、waiting_room.updated 等房间状态变化；
+// 客户端发送 "ping" 会收到 "pong"，其余消息忽略
+async fn lecture_socket(
+    State(client): State<AppState>,
+    ws: WebSocketUpgrade,
+    auth: Option<AuthUser>,
+    Path(lecture_id): Path<String>,
+    Query(query): Query<SocketQuery>,
+) -> Result<Response, AppError> {
+    let user = match (auth, query.token.as_deref().filter(|t| !t.is_empty())) {
+        (Some(user), _) => Some(user),
+        (None, Some(token)) => auth::verify_token(&client, token).await,
+        (None, None) => None,
+    }
+    .ok_or(AppError::Unauthorized("未登录或登录已过期".to_string()))?;
+    let lecture = load_lecture(&client, &lecture_id).await?;
+    let lecture_oid = lecture.get_object_id("_id").map_err(|_| AppError::Internal("演讲ID无效".into()))?;
+    Ok(ws.on_upgrade(move |socket| run(socket, lecture_oid, user.id)))
+}
+
+async fn run(socket: WebSocket, lecture: ObjectId, user: ObjectId) {
+    let (mut sink, mut stream) = socket.split();
+    let (mut rx, presence) = realtime::join(lecture, user);
+    let hello = serde_json::json!({ "type": "presence", "data": presence }).to_string();
+
+    if sink.send(Message::Text(hello)).await.is_ok() {
+        loop {
+            tokio::select! {
+                event = rx.recv() => match event {
+                    Ok(text) => {
+                        if sink.send(Message::Text(text)).await.is_err() {
+                            break;
+                        }
+                    }
+                    // 客户端消费太慢时跳过积压的事件，前端可据此重新拉取
+                    Err(RecvError::Lagged(skipped)) => {
+                        let notice = serde_json::json!({ "type": "lagged", "data": { "skipped": skipped } });
+                        if sink.send(Message::Text(notice.to_string())).await.is_err() {
+                            break;
+                        }
+                    }
+                    Err(RecvError::Closed) => break,
+                },
+                incoming = stream.next() => match incoming {
+                    Some(Ok(Message::Text(text))) if text.trim() == "ping" => {
+                        if sink.send(Message::Text("pong".to_string())).await.is_err() {
+                            break;
+                        }
+                    }
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => {}
+                },
+            }
+        }
+    }
+    realtime::leave(lecture, user, rx);
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/lecture/:lecture_id", get(lecture_socket))
+}
@@ -0,0 +1,42 @@
+use mongodb::Client;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+// 周期性后台任务：每隔 period 执行一次 job，失败只记录日志，不影响后续轮次
+pub fn spawn_every<F, Fut>(name: &'static str, period: Duration, client: Arc<Client>, job: F)
+where
+    F: Fn(Arc<Client>) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<String, String>> + Send + 'static,
+{
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(period);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            match job(client.clone()).await {
+                Ok(summary) if !summary.is_empty() => println!("[job:{}] {}", name, summary),
+                Ok(_) => {}
+                Err(e) => println!("[job:{}] 执行失败: {}", name, e),
+            }
+        }
+    });
+}
+
+// 按 config.jobs.<name> 注册：enabled = false 时不启动，interval_secs 覆盖默认间隔
+pub fn spawn_job<F, Fut>(name: &'static str, default_period: Duration, client: Arc<Client>, job: F)
+where
+    F: Fn(Arc<Client>) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<String, String>> + Send + 'static,
+{
+    let cfg = crate::config::get().job(name);
+    if cfg.is_some_and(|c| !c.enabled) {
+        println!("[job:{}] 已在配置中停用", name);
+        return;
+    }
+    let period = cfg
+        .and_then(|c| c.interval_secs)
+        .map(Duration::from_secs)
+        .unwrap_or(default_period);
+    spawn_every(name, period, client, job);
+}
@@ -0,0 +1,172 @@
+use bson::doc;
+use chrono::Utc;
+use mongodb::Client;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::{breaker, config, db, ratelimit};
+
+// 部署自检：数据库连通性、索引是否齐全、上传目录可写、配置是否合理。
+// 启动后在后台执行一次并逐项输出到日志，GET /admin/selfcheck 可随时重跑
+const DB_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Level {
+    Ok,
+    Warn,
+    Fail,
+}
+
+impl Level {
+    fn name(self) -> &'static str {
+        match self {
+            Level::Ok => "ok",
+            Level::Warn => "warn",
+            Level::Fail => "fail",
+        }
+    }
+}
+
+struct Check {
+    name: &'static str,
+    level: Level,
+    detail: String,
+    elapsed_ms: u128,
+}
+
+impl Check {
+    fn json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "name": self.name,
+            "status": self.level.name(),
+            "detail": self.detail,
+            "elapsed_ms": self.elapsed_ms,
+        })
+    }
+}
+
+fn check(name: &'static str, started: Instant, (level, detail): (Level, String)) -> Check {
+    Check { name, level, detail, elapsed_ms: started.elapsed().as_millis() }
+}
+
+async fn database(client: &Arc<Client>) -> (Level, String) {
+    match tokio::time::timeout(DB_TIMEOUT, db::database(client).run_command(doc! { "ping": 1 }, None)).await {
+        Ok(Ok(_)) => (Level::Ok, format!("已连接 {}", config::get().database_name())),
+        Ok(Err(e)) => (Level::Fail, format!("ping 失败: {}", e)),
+        Err(_) => (Level::Fail, format!("ping 超过 {} 秒未响应", DB_TIMEOUT.as_secs())),
+    }
+}
+
+async fn indexes(client: &Arc<Client>) -> (Level, String) {
+    match tokio::time::timeout(DB_TIMEOUT, db::missing_indexes(client)).await {
+        Ok(Ok(missing)) if missing.is_empty() => (Level::Ok, "索引齐全".to_string()),
+        // 缺索引时唯一性只能靠应用层检查，并发下可能写入重复数据
+        Ok(Ok(missing)) => (Level::Fail, format!("缺少索引: {}", missing.join(", "))),
+        Ok(Err(e)) => (Level::Fail, format!("读取索引失败: {}", e)),
+        Err(_) => (Level::Fail, "读取索引超时".to_string()),
+    }
+}
+
+async fn privileges(client: &Arc<Client>) -> (Level, String) {
+    match tokio::time::timeout(DB_TIMEOUT, db::missing_privileges(client)).await {
+        Ok(Ok(None)) => (Level::Warn, "连接未使用账号认证，无法核对权限".to_string()),
+        Ok(Ok(Some(missing))) if missing.is_empty() => (Level::Ok, "账号权限齐全".to_string()),
+        Ok(Ok(Some(missing))) => (Level::Fail, format!("缺少权限: {}", missing.join("; "))),
+        Ok(Err(e)) => (Level::Fail, format!("读取权限失败: {}", e)),
+        Err(_) => (Level::Fail, "读取权限超时".to_string()),
+    }
+}
+
+// 写入并删除一个探测文件
+fn writable(dir: &str) -> Result<(), String> {
+    std::fs::create_dir_all(dir).map_err(|e| format!("{} 无法创建: {}", dir, e))?;
+    let probe = Path::new(dir).join(format!(".selfcheck-{}", std::process::id()));
+    std::fs::write(&probe, b"ok").map_err(|e| format!("{} 不可写: {}", dir, e))?;
+    let _ = std::fs::remove_file(&probe);
+    Ok(())
+}
+
+fn upload_dirs() -> (Level, String) {
+    let cfg = config::get();
+    let errors: Vec<String> = [cfg.upload_dir.as_str(), cfg.material_dir.as_str(), cfg.export_dir.as_str()]
+        .iter()
+        .filter_map(|dir| writable(dir).err())
+        .collect();
+    if errors.is_empty() {
+        (Level::Ok, format!("{}、{} 与 {} 可写", cfg.upload_dir, cfg.material_dir, cfg.export_dir))
+    } else {
+        (Level::Fail, errors.join("; "))
+    }
+}
+
+// 配置在启动时已做格式校验，这里只提示生产环境下不合适的取值
+fn config_sanity() -> (Level, String) {
+    let cfg = config::get();
+    let mut warnings = Vec::new();
+    if cfg.jwt_secret.is_empty() {
+        warnings.push("未配置 jwt_secret，重启后登录全部失效");
+    }
+    if cfg.signing_secret.is_empty() {
+        warnings.push("未配置 signing_secret，重启后签名链接全部失效");
+    }
+    if cfg.admin_token.is_empty() {
+        warnings.push("未配置 admin_token，管理接口不可用");
+    }
+    if cfg.cors_origins.is_empty() {
+        warnings.push("cors_origins 为空，允许任意来源跨域");
+    }
+    if cfg.public_base_url == config::Config::default().public_base_url {
+        warnings.push("未配置 public_base_url，邮件与日历链接指向 127.0.0.1");
+    }
+    if warnings.is_empty() {
+        (Level::Ok, "配置正常".to_string())
+    } else {
+        (Level::Warn, warnings.join("; "))
+    }
+}
+
+async fn redis() -> (Level, String) {
+    match ratelimit::ping().await {
+        None => (Level::Ok, "未配置，限流按进程计数".to_string()),
+        Some(Ok(())) => (Level::Ok, "已连接".to_string()),
+        // Redis 不可用时限流退化为进程内计数，服务仍可用
+        Some(Err(e)) => (Level::Warn, format!("PING 失败，限流暂按进程计数: {}", e)),
+    }
+}
+
+pub async fn run(client: &Arc<Client>) -> serde_json::Value {
+    let mut checks = Vec::new();
+    let t = Instant::now();
+    checks.push(check("database", t, database(client).await));
+    let t = Instant::now();
+    let db_ok = checks[0].level == Level::Ok;
+    let index_result = if db_ok { indexes(client).await } else { (Level::Fail, "数据库不可用，未检查".to_string()) };
+    checks.push(check("indexes", t, index_result));
+    let t = Instant::now();
+    let privilege_result = if db_ok { privileges(client).await } else { (Level::Fail, "数据库不可用，未检查".to_string()) };
+    checks.push(check("privileges", t, privilege_result));
+    let t = Instant::now();
+    checks.push(check("upload_dirs", t, upload_dirs()));
+    let t = Instant::now();
+    checks.push(check("config", t, config_sanity()));
+    let t = Instant::now();
+    checks.push(check("redis", t, redis().await));
+
+    let worst = checks.iter().map(|c| c.level).max_by_key(|l| *l as u8).unwrap_or(Level::Ok);
+    serde_json::json!({
+        "status": worst.name(),
+        "checked_at": Utc::now().timestamp_millis(),
+        "version": env!("CARGO_PKG_VERSION"),
+        "db_breaker": breaker::status(),
+        "checks": checks.iter().map(Check::json).collect::<Vec<_>>(),
+    })
+}
+
+// 每项一行 JSON，便于日志系统按字段检索
+pub fn log(report: &serde_json::Value) {
+    for item in report["checks"].as_array().into_iter().flatten() {
+        println!("[selfcheck] {}", item);
+    }
+    println!("[selfcheck] 自检完成: {}", report["status"].as_str().unwrap_or("unknown"));
+}
@@ -0,0 +1,50 @@
+use hmac::{Hmac, Mac};
+use once_cell::sync::Lazy;
+use rand::Rng;
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+// 签名密钥：取配置中的 signing_secret；未配置时每次启动随机生成（重启后旧链接全部失效，prod 环境启动时已拒绝）
+static SECRET: Lazy<Vec<u8>> = Lazy::new(|| match crate::config::get().signing_secret.as_str() {
+    s if !s.is_empty() => s.as_bytes().to_vec(),
+    _ => {
+        println!("警告: 未配置 SIGNING_SECRET，使用随机密钥，重启后签名链接将失效");
+        rand::thread_rng().gen::<[u8; 32]>().to_vec()
+    }
+});
+
+pub fn sign(payload: &str) -> String {
+    let mut mac = HmacSha256::new_from_slice(&SECRET).expect("HMAC 接受任意长度密钥");
+    mac.update(payload.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+// 常量时间比较，避免通过响应时间猜测签名
+pub fn verify(payload: &str, signature: &str) -> bool {
+    let Ok(sig) = hex::decode(signature) else { return false };
+    let mut mac = HmacSha256::new_from_slice(&SECRET).expect("HMAC 接受任意长度密钥");
+    mac.update(payload.as_bytes());
+    mac.verify_slice(&sig).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signature_round_trips() {
+        let sig = sign("lecture:1");
+        assert_eq!(sig.len(), 64);
+        assert!(verify("lecture:1", &sig));
+    }
+
+    #[test]
+    fn rejects_other_payloads_and_malformed_signatures() {
+        let sig = sign("lecture:1");
+        assert!(!verify("lecture:2", &sig));
+        assert!(!verify("lecture:1", &sig[..62]));
+        assert!(!verify("lecture:1", "zz"));
+        assert!(!verify("lecture:1", ""));
+    }
+}
@@ -0,0 +1,139 @@
+use once_cell::sync::Lazy;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::pin::Pin;
+
+// 讨论摘要生成器：默认为抽取式实现，后续可接入 LLM 等外部服务
+pub trait Summarizer: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn summarize<'a>(
+        &'a self,
+        messages: &'a [String],
+        keywords: &'a [(String, usize)],
+    ) -> Pin<Box<dyn Future<Output = Option<String>> + Send + 'a>>;
+}
+
+// 抽取式摘要：挑出覆盖高频关键词最多的几条原始消息
+pub struct ExtractiveSummarizer {
+    pub max_sentences: usize,
+}
+
+impl Summarizer for ExtractiveSummarizer {
+    fn name(&self) -> &'static str {
+        "extractive"
+    }
+
+    fn summarize<'a>(
+        &'a self,
+        messages: &'a [String],
+        keywords: &'a [(String, usize)],
+    ) -> Pin<Box<dyn Future<Output = Option<String>> + Send + 'a>> {
+        Box::pin(async move {
+            let weights: HashMap<&str, usize> =
+                keywords.iter().map(|(k, c)| (k.as_str(), *c)).collect();
+            let mut scored: Vec<(usize, usize)> = messages
+                .iter()
+                .enumerate()
+                .map(|(idx, m)| {
+                    let tokens: HashSet<String> = tokenize(m).into_iter().collect();
+                    let score = tokens.iter().filter_map(|t| weights.get(t.as_str())).sum();
+                    (idx, score)
+                })
+                .filter(|(_, score)| *score > 0)
+                .collect();
+            scored.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+            let mut picked: Vec<usize> = scored.iter().take(self.max_sentences).map(|(i, _)| *i).collect();
+            if picked.is_empty() {
+                return None;
+            }
+            // 按原始顺序输出，读起来更连贯
+            picked.sort_unstable();
+            Some(
+                picked
+                    .into_iter()
+                    .map(|i| messages[i].trim().to_string())
+                    .collect::<Vec<_>>()
+                    .join(" / "),
+            )
+        })
+    }
+}
+
+pub static SUMMARIZER: Lazy<Box<dyn Summarizer>> =
+    Lazy::new(|| Box::new(ExtractiveSummarizer { max_sentences: 3 }));
+
+static STOPWORDS: Lazy<HashSet<&'static str>> = Lazy::new(|| {
+    [
+        "the", "and", "for", "are", "but", "not", "you", "all", "can", "was", "this", "that",
+        "with", "have", "what", "how", "why", "when", "who", "is", "it", "to", "of", "in", "on",
+        "a", "an", "do", "does", "be", "or", "if", "so", "we", "i", "my", "me", "your", "at",
+        "我们", "你们", "他们", "这个", "那个", "什么", "怎么", "可以", "就是",
+        "一个", "没有", "因为", "所以", "但是", "如果", "还是", "老师", "请问", "谢谢",
+    ]
+    .into_iter()
+    .collect()
+});
+
+fn is_cjk(c: char) -> bool {
+    matches!(c as u32, 0x4E00..=0x9FFF | 0x3400..=0x4DBF)
+}
+
+// 英文按单词切分；中文无分词器，按相邻二字组（bigram）近似提取词语
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+    let mut cjk_run: Vec<char> = Vec::new();
+
+    let flush_word = |word: &mut String, tokens: &mut Vec<String>| {
+        if word.chars().count() >= 2 && !STOPWORDS.contains(word.as_str()) {
+            tokens.push(word.clone());
+        }
+        word.clear();
+    };
+    let flush_cjk = |run: &mut Vec<char>, tokens: &mut Vec<String>| {
+        for pair in run.windows(2) {
+            let gram: String = pair.iter().collect();
+            if !STOPWORDS.contains(gram.as_str()) {
+                tokens.push(gram);
+            }
+        }
+        run.clear();
+    };
+
+    for c in text.chars() {
+        if is_cjk(c) {
+            flush_word(&mut word, &mut tokens);
+            cjk_run.push(c);
+        } else if c.is_alphanumeric() || c == '_' || c == '+' || c == '#' {
+            flush_cjk(&mut cjk_run, &mut tokens);
+            word.extend(c.to_lowercase());
+        } else {
+            flush_word(&mut word, &mut tokens);
+            flush_cjk(&mut cjk_run, &mut tokens);
+        }
+    }
+    flush_word(&mut word, &mut tokens);
+    flush_cjk(&mut cjk_run, &mut tokens);
+    tokens
+}
+
+// 统计关键词出现次数（同一条消息内重复只计一次），按频次降序取前 limit 个
+pub fn top_keywords(messages: &[String], limit: usize) -> Vec<(String, usize)> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for m in messages {
+        let unique: HashSet<String> = tokenize(m).into_iter().collect();
+        for t in unique {
+            *counts.entry(t).or_default() += 1;
+        }
+    }
+    let mut list: Vec<(String, usize)> = counts.into_iter().filter(|(_, c)| *c >= 2).collect();
+    list.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    list.truncate(limit);
+    list
+}
+
+pub fn looks_like_question(text: &str) -> bool {
+    let t = text.trim_end();
+    t.ends_with('?') || t.ends_with('？')
+}
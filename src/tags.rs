@@ -0,0 +1,121 @@
+use bson::{doc, oid::ObjectId, Document};
+use futures_util::TryStreamExt;
+use mongodb::Client;
+use std::sync::Arc;
+
+use crate::db::{lecture_collection, user_collection};
+use crate::error::AppError;
+use crate::lifecycle::LectureStatus;
+use crate::notify;
+
+// 演讲标签与用户订阅共用同一套规范化：去空白、小写、去重
+pub fn normalize(items: &[String]) -> Vec<String> {
+    let mut tags: Vec<String> = items
+        .iter()
+        .map(|t| t.trim().to_lowercase())
+        .filter(|t| !t.is_empty())
+        .collect();
+    tags.sort();
+    tags.dedup();
+    tags
+}
+
+pub const MAX_LECTURE_TAGS: usize = 10;
+const MAX_TAG_CHARS: usize = 32;
+
+// 单个标签：规范化后 1~32 个字符，只允许文字、数字及 - _ + . #（如 c++、c#、node.js）
+pub fn validate_tag(raw: &str) -> Result<String, AppError> {
+    let tag = raw.trim().to_lowercase();
+    if tag.is_empty() {
+        return Err(AppError::BadRequest("标签不能为空".into()));
+    }
+    if tag.chars().count() > MAX_TAG_CHARS {
+        return Err(AppError::BadRequest(format!("标签不能超过 {} 个字符: {}", MAX_TAG_CHARS, tag)));
+    }
+    if !tag.chars().all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '+' | '.' | '#')) {
+        return Err(AppError::BadRequest(format!("标签只能包含文字、数字及 - _ + . #: {}", tag)));
+    }
+    Ok(tag)
+}
+
+// 逐个校验后去重，数量超过 max 时拒绝
+pub fn validate(items: &[String], max: usize) -> Result<Vec<String>, AppError> {
+    let mut tags = Vec::with_capacity(items.len());
+    for item in items {
+        if !item.trim().is_empty() {
+            tags.push(validate_tag(item)?);
+        }
+    }
+    let tags = normalize(&tags);
+    if tags.len() > max {
+        return Err(AppError::BadRequest(format!("标签最多 {} 个", max)));
+    }
+    Ok(tags)
+}
+
+pub fn string_array(d: &Document, key: &str) -> Vec<String> {
+    d.get_array(key)
+        .map(|a| a.iter().filter_map(|t| t.as_str().map(|s| s.to_string())).collect())
+        .unwrap_or_default()
+}
+
+// 演讲公开（非草稿）且带标签时，通知订阅了其中任一标签的用户；
+// 每场演讲只通知一次，以 tag_notified 标记原子占位，草稿反复发布也不会重复打扰
+pub async fn notify_subscribers(client: &Arc<Client>, lecture: &Document) -> mongodb::error::Result<u64> {
+    let status = LectureStatus::of(lecture);
+    let tags = string_array(lecture, "tags");
+    if !matches!(status, LectureStatus::Scheduled | LectureStatus::Live) || tags.is_empty() {
+        return Ok(0);
+    }
+    let Ok(lecture_oid) = lecture.get_object_id("_id") else {
+        return Ok(0);
+    };
+    let claimed = lecture_collection(client)
+        .update_one(
+            doc! { "_id": lecture_oid, "tag_notified": { "$ne": true } },
+            doc! { "$set": { "tag_notified": true } },
+            None,
+        )
+        .await?;
+    if claimed.modified_count == 0 {
+        return Ok(0);
+    }
+
+    // 组织者与讲者不需要收到自己演讲的推荐
+    let hosts: Vec<ObjectId> = ["organizer_id", "speaker_id"]
+        .iter()
+        .filter_map(|k| lecture.get_str(k).ok())
+        .filter_map(|s| ObjectId::parse_str(s).ok())
+        .collect();
+    let mut cursor = user_collection(client)
+        .find(doc! { "subscribed_tags": { "$in": &tags }, "_id": { "$nin": &hosts } }, None)
+        .await?;
+    let mut sent = 0;
+    while let Some(user) = cursor.try_next().await? {
+        let Ok(user_id) = user.get_object_id("_id") else { continue };
+        let matched: Vec<String> = string_array(&user, "subscribed_tags")
+            .into_iter()
+            .filter(|t| tags.contains(t))
+            .collect();
+        let payload = doc! {
+            "lecture_id": lecture_oid.to_hex(),
+            "topic": lecture.get_str("topic").unwrap_or(""),
+            "start_time": lecture.get_i64("start_time").unwrap_or(0),
+            "matched_tags": matched,
+        };
+        if notify::push(client, user_id, "tag_lecture", payload).await.is_ok() {
+            sent += 1;
+        }
+    }
+    Ok(sent)
+}
+
+// 在后台推送，不阻塞创建/发布请求
+pub fn spawn_notify(client: &Arc<Client>, lecture: Document) {
+    let client = client.clone();
+    tokio::spawn(async move {
+        if let Err(e) = notify_subscribers(&client, &lecture).await {
+            println!("[tags] 推送订阅通知失败: {}", e);
+        }
+    });
+}
@@ -0,0 +1,108 @@
+use bson::Document;
+use chrono::{DateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+
+use crate::error::AppError;
+
+// 面向用户的时间展示：按接收者的时区与语言格式化（导出、证明、邮件共用）。
+// 用户在 preferences.timezone / preferences.locale 中设置，未设置时使用配置中的默认值
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Locale {
+    ZhCn,
+    EnUs,
+}
+
+impl Locale {
+    pub fn parse(raw: &str) -> Option<Locale> {
+        match raw.trim().to_ascii_lowercase().replace('_', "-").as_str() {
+            "zh" | "zh-cn" | "zh-hans" => Some(Locale::ZhCn),
+            "en" | "en-us" => Some(Locale::EnUs),
+            _ => None,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Locale::ZhCn => "zh-CN",
+            Locale::EnUs => "en-US",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct UserTime {
+    pub tz: Tz,
+    pub locale: Locale,
+}
+
+pub fn parse_timezone(raw: &str) -> Option<Tz> {
+    raw.trim().parse::<Tz>().ok()
+}
+
+impl Default for UserTime {
+    fn default() -> Self {
+        let cfg = crate::config::get();
+        UserTime {
+            tz: parse_timezone(&cfg.default_timezone).unwrap_or(Tz::UTC),
+            locale: Locale::parse(&cfg.default_locale).unwrap_or(Locale::ZhCn),
+        }
+    }
+}
+
+impl UserTime {
+    pub fn for_user(user: &Document) -> UserTime {
+        let default = UserTime::default();
+        let prefs = user.get_document("preferences").ok();
+        let pref = |key: &str| prefs.and_then(|p| p.get_str(key).ok());
+        UserTime {
+            tz: pref("timezone").and_then(parse_timezone).unwrap_or(default.tz),
+            locale: pref("locale").and_then(Locale::parse).unwrap_or(default.locale),
+        }
+    }
+
+    fn local(&self, ms: i64) -> Option<DateTime<Tz>> {
+        Utc.timestamp_millis_opt(ms).single().map(|t| t.with_timezone(&self.tz))
+    }
+
+    // 日期 + 时间 + 时区缩写，如 2025年03月01日 14:00 CST / Mar 1, 2025 2:00 PM EST
+    pub fn datetime(&self, ms: i64) -> String {
+        let Some(t) = self.local(ms) else { return String::new() };
+        match self.locale {
+            Locale::ZhCn => t.format("%Y年%m月%d日 %H:%M %Z").to_string(),
+            Locale::EnUs => t.format("%b %-d, %Y %-I:%M %p %Z").to_string(),
+        }
+    }
+
+    pub fn date(&self, ms: i64) -> String {
+        let Some(t) = self.local(ms) else { return String::new() };
+        match self.locale {
+            Locale::ZhCn => t.format("%Y年%m月%d日").to_string(),
+            Locale::EnUs => t.format("%b %-d, %Y").to_string(),
+        }
+    }
+
+    // 导出表格用的本地时间，如 2025-03-01 14:00:00 +08:00，便于排序与再处理
+    pub fn iso(&self, ms: i64) -> String {
+        self.local(ms).map(|t| t.format("%Y-%m-%d %H:%M:%S %:z").to_string()).unwrap_or_default()
+    }
+
+    // 按月统计的分组键，如 2025-03
+    pub fn month(&self, ms: i64) -> String {
+        self.local(ms).map(|t| t.format("%Y-%m").to_string()).unwrap_or_default()
+    }
+
+    pub fn now(&self) -> String {
+        self.datetime(Utc::now().timestamp_millis())
+    }
+}
+
+// 查询参数中的时间：毫秒时间戳或 RFC3339 字符串
+pub fn parse_time_param(raw: &str, field: &str) -> Result<i64, AppError> {
+    let raw = raw.trim();
+    if let Ok(ms) = raw.parse::<i64>() {
+        return Ok(ms);
+    }
+    chrono::DateTime::parse_from_rfc3339(raw)
+        .map(|t| t.timestamp_millis())
+        .map_err(|_| AppError::BadRequest(format!("{} 应为毫秒时间戳或 RFC3339 时间", field)))
+}
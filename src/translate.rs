@@ -0,0 +1,34 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::future::Future;
+use std::pin::Pin;
+
+// 讨论消息翻译：默认不启用，部署时接入第三方机器翻译服务后替换 TRANSLATOR。
+// 译文按目标语言缓存在讨论文档上，同一条消息同一语言只翻译一次
+pub trait Translator: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn translate<'a>(
+        &'a self,
+        text: &'a str,
+        to: &'a str,
+    ) -> Pin<Box<dyn Future<Output = Result<String, String>> + Send + 'a>>;
+}
+
+pub static TRANSLATOR: Lazy<Option<Box<dyn Translator>>> = Lazy::new(|| None);
+
+// 语言代码如 en、zh-CN、pt-BR；主语言统一小写，地区统一大写
+static LANG_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^([A-Za-z]{2,3})(?:-([A-Za-z]{2}|[A-Za-z0-9]{4}))?$").unwrap());
+
+pub fn normalize_lang(raw: &str) -> Option<String> {
+    let caps = LANG_RE.captures(raw.trim())?;
+    let primary = caps[1].to_lowercase();
+    Some(match caps.get(2) {
+        Some(region) if region.len() == 2 => format!("{}-{}", primary, region.as_str().to_uppercase()),
+        Some(script) => {
+            let s = script.as_str();
+            format!("{}-{}{}", primary, s[..1].to_uppercase(), s[1..].to_lowercase())
+        }
+        None => primary,
+    })
+}
@@ -0,0 +1,131 @@
+// 极简 ZIP 打包：条目不压缩（stored），文件名按 UTF-8 标记，系统自带的解压工具均可打开。
+// 导出内容为 CSV/JSON 文本，体积不大，不值得为压缩引入依赖
+
+use chrono::{Datelike, Timelike, Utc};
+
+const CRC_TABLE: [u32; 256] = crc_table();
+
+const fn crc_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut c = i as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 { 0xEDB8_8320 ^ (c >> 1) } else { c >> 1 };
+            k += 1;
+        }
+        table[i] = c;
+        i += 1;
+    }
+    table
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |c, &b| CRC_TABLE[((c ^ b as u32) & 0xFF) as usize] ^ (c >> 8))
+}
+
+// MS-DOS 格式的修改时间与日期（UTC，精度 2 秒）
+fn dos_datetime() -> (u16, u16) {
+    let now = Utc::now();
+    let time = ((now.hour() << 11) | (now.minute() << 5) | (now.second() / 2)) as u16;
+    let date = ((((now.year() - 1980).max(0) as u32) << 9) | (now.month() << 5) | now.day()) as u16;
+    (time, date)
+}
+
+struct Entry {
+    name: String,
+    crc: u32,
+    size: u32,
+    offset: u32,
+}
+
+pub struct ZipWriter {
+    buf: Vec<u8>,
+    entries: Vec<Entry>,
+    time: u16,
+    date: u16,
+}
+
+// 通用标志位 11：文件名为 UTF-8
+const FLAG_UTF8: u16 = 0x0800;
+const VERSION: u16 = 20;
+
+impl Default for ZipWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ZipWriter {
+    pub fn new() -> Self {
+        let (time, date) = dos_datetime();
+        ZipWriter { buf: Vec::new(), entries: Vec::new(), time, date }
+    }
+
+    fn u16(&mut self, v: u16) {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+    }
+
+    fn u32(&mut self, v: u32) {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+    }
+
+    // 单个条目与整个包都不能超过 4GB（未使用 ZIP64）
+    pub fn add(&mut self, name: &str, data: &[u8]) -> Result<(), String> {
+        let size = u32::try_from(data.len()).map_err(|_| format!("{} 超过 4GB", name))?;
+        let offset = u32::try_from(self.buf.len()).map_err(|_| "压缩包超过 4GB".to_string())?;
+        let crc = crc32(data);
+        self.u32(0x0403_4b50);
+        self.u16(VERSION);
+        self.u16(FLAG_UTF8);
+        self.u16(0);
+        self.u16(self.time);
+        self.u16(self.date);
+        self.u32(crc);
+        self.u32(size);
+        self.u32(size);
+        self.u16(name.len() as u16);
+        self.u16(0);
+        self.buf.extend_from_slice(name.as_bytes());
+        self.buf.extend_from_slice(data);
+        self.entries.push(Entry { name: name.to_string(), crc, size, offset });
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Vec<u8> {
+        let cd_offset = self.buf.len() as u32;
+        let entries = std::mem::take(&mut self.entries);
+        for entry in &entries {
+            self.u32(0x0201_4b50);
+            self.u16(VERSION);
+            self.u16(VERSION);
+            self.u16(FLAG_UTF8);
+            self.u16(0);
+            self.u16(self.time);
+            self.u16(self.date);
+            self.u32(entry.crc);
+            self.u32(entry.size);
+            self.u32(entry.size);
+            self.u16(entry.name.len() as u16);
+            // 扩展字段、注释长度，磁盘号，内部、外部属性
+            self.u16(0);
+            self.u16(0);
+            self.u16(0);
+            self.u16(0);
+            self.u32(0);
+            self.u32(entry.offset);
+            self.buf.extend_from_slice(entry.name.as_bytes());
+        }
+        let cd_size = self.buf.len() as u32 - cd_offset;
+        self.u32(0x0605_4b50);
+        self.u16(0);
+        self.u16(0);
+        self.u16(entries.len() as u16);
+        self.u16(entries.len() as u16);
+        self.u32(cd_size);
+        self.u32(cd_offset);
+        self.u16(0);
+        self.buf
+    }
+}
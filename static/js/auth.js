@@ -0,0 +1,63 @@
+// 为同源接口请求自动附带登录令牌；访问令牌过期时先用刷新令牌续期，仍失败则跳回登录页
+(function () {
+  const originalFetch = window.fetch.bind(window);
+  let refreshing = null;
+
+  function refreshToken() {
+    const refresh = sessionStorage.getItem("refreshToken");
+    if (!refresh) return Promise.resolve(false);
+    // 并发请求共用同一次刷新
+    if (!refreshing) {
+      refreshing = originalFetch("/user/refresh", {
+        method: "POST",
+        headers: { "Content-Type": "application/json" },
+        body: JSON.stringify({ refresh_token: refresh })
+      })
+        .then(res => (res.ok ? res.json() : null))
+        .then(data => {
+          if (!data) return false;
+          sessionStorage.setItem("token", data.token);
+          sessionStorage.setItem("refreshToken", data.refresh_token);
+          return true;
+        })
+        .catch(() => false)
+        .finally(() => { refreshing = null; });
+    }
+    return refreshing;
+  }
+
+  function withToken(input, init) {
+    const token = sessionStorage.getItem("token");
+    init = Object.assign({}, init);
+    const headers = new Headers(init.headers || (typeof input === "string" ? undefined : input.headers));
+    if (token) headers.set("Authorization", `Bearer ${token}`);
+    init.headers = headers;
+    return init;
+  }
+
+  function toLogin() {
+    sessionStorage.removeItem("token");
+    sessionStorage.removeItem("refreshToken");
+    location.href = "/static/login.html";
+  }
+
+  window.fetch = function (input, init) {
+    const url = new URL(typeof input === "string" ? input : input.url, location.href);
+    if (!sessionStorage.getItem("token") || url.origin !== location.origin) {
+      return originalFetch(input, init);
+    }
+    return originalFetch(input, withToken(input, init)).then(res => {
+      if (res.status !== 401) return res;
+      return refreshToken().then(ok => {
+        if (!ok) {
+          toLogin();
+          return res;
+        }
+        return originalFetch(input, withToken(input, init)).then(retry => {
+          if (retry.status === 401) toLogin();
+          return retry;
+        });
+      });
+    });
+  };
+})();
@@ -0,0 +1,47 @@
+// 演讲房间实时通道：优先用 WebSocket 接收推送，连接断开时退回轮询并定时重连
+(function () {
+  window.connectLectureRoom = function (lectureId, handlers, poll, interval) {
+    let timer = null;
+
+    function startPolling() {
+      if (timer) return;
+      poll();
+      timer = setInterval(poll, interval || 2000);
+    }
+
+    function stopPolling() {
+      clearInterval(timer);
+      timer = null;
+    }
+
+    function connect() {
+      if (!("WebSocket" in window)) return startPolling();
+      const proto = location.protocol === "https:" ? "wss:" : "ws:";
+      const token = sessionStorage.getItem("token") || "";
+      const ws = new WebSocket(`${proto}//${location.host}/ws/lecture/${encodeURIComponent(lectureId)}?token=${encodeURIComponent(token)}`);
+      let keepalive = null;
+
+      ws.onopen = () => {
+        stopPolling();
+        poll();
+        keepalive = setInterval(() => ws.send("ping"), 30000);
+      };
+      ws.onmessage = (e) => {
+        if (e.data === "pong") return;
+        let msg;
+        try { msg = JSON.parse(e.data); } catch (_) { return; }
+        // 推送有积压被跳过时整体刷新一次
+        if (msg.type === "lagged") return poll();
+        const handler = handlers[msg.type];
+        if (handler) handler(msg.data);
+      };
+      ws.onclose = () => {
+        clearInterval(keepalive);
+        startPolling();
+        setTimeout(connect, 5000);
+      };
+    }
+
+    connect();
+  };
+})();